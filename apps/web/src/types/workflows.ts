@@ -37,8 +37,18 @@ export interface Action {
   plugin_config_schema_locked: boolean;
   presentation?: NodePresentation;
   handles?: HandleProps[];
+  retry?: RetryPolicy;
 }
 
+// Retries a failing action with exponential backoff. Empty retry_on retries every error type
+export type RetryPolicy = {
+  max_attempts: number;
+  initial_delay_ms: number;
+  backoff_multiplier: number;
+  max_delay_ms: number;
+  retry_on: string[]; // e.g. "execution_timeout", "plugin_execution_error", "http_5xx"
+};
+
 // Presentation data only needed for react flow but we need all of it
 interface NodePresentation {
 
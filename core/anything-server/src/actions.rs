@@ -175,13 +175,14 @@ pub async fn get_actions(
         if let Some(json_array) = json_items.as_array() {
             db_array.extend(json_array.clone());
         }
+        db_array.extend(state.plugin_host.action_templates("action"));
     }
 
     Json(db_items).into_response()
 }
 
 // Actions
-pub async fn get_triggers() -> impl IntoResponse {
+pub async fn get_triggers(State(state): State<Arc<AppState>>) -> impl IntoResponse {
     println!("Handling a get_actions");
 
     // Load schema templates from the registry
@@ -214,7 +215,10 @@ pub async fn get_triggers() -> impl IntoResponse {
                 .cloned()
                 .collect::<Vec<_>>()
         })
-        .map(|filtered| Value::Array(filtered))
+        .map(|mut filtered| {
+            filtered.extend(state.plugin_host.action_templates("trigger"));
+            Value::Array(filtered)
+        })
         .unwrap_or(Value::Array(vec![]));
 
     Json(filtered_items).into_response()
@@ -1,7 +1,9 @@
+use crate::actor_processor::cancellation::CancelSignal;
 use crate::actor_processor::messages::ActorMessage;
 use crate::actor_processor::task_actor::TaskActor;
 use crate::processor::components::{EnhancedSpanFactory, WorkflowExecutionContext};
 use crate::processor::execute_task::TaskResult;
+use crate::types::action_types::RetryPolicy;
 use crate::types::task_types::Task;
 use crate::AppState;
 
@@ -63,6 +65,8 @@ impl TaskActorPool {
         task: Task,
         context: WorkflowExecutionContext,
         in_memory_tasks: Option<&std::collections::HashMap<Uuid, Task>>,
+        retry_policy: Option<RetryPolicy>,
+        cancel: CancelSignal,
     ) -> Result<TaskResult, Box<dyn std::error::Error + Send + Sync>> {
         // Round-robin load balancing
         let mut index = self.current_index.write().await;
@@ -79,6 +83,8 @@ impl TaskActorPool {
                 respond_to: tx,
                 context,
                 in_memory_tasks: in_memory_tasks.cloned(),
+                retry_policy,
+                cancel,
             })
             .await
             .map_err(|e| format!("Failed to send task to actor: {}", e))?;
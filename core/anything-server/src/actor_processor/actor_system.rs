@@ -146,12 +146,23 @@ impl ActorProcessor {
                 ))
             })?;
 
-        rx.await.map_err(|e| {
-            ProcessorError::WorkflowExecutionError(format!(
-                "Failed to receive workflow result: {}",
-                e
-            ))
-        })?
+        // Wait for the result on the side so the next workflow is dispatched right away,
+        // a Call Workflow action waiting on a session it started would otherwise never see it run
+        tokio::spawn(async move {
+            let result = rx.await.map_err(|e| {
+                ProcessorError::WorkflowExecutionError(format!(
+                    "Failed to receive workflow result: {}",
+                    e
+                ))
+            });
+
+            if let Err(e) | Ok(Err(e)) = result {
+                error!("[ACTOR_PROCESSOR] Error processing message: {}", e);
+                METRICS.record_workflow_error(&e.to_string());
+            }
+        });
+
+        Ok(())
     }
 
     async fn shutdown(&self) {
@@ -0,0 +1,164 @@
+use crate::processor::execute_task::TaskError;
+use crate::processor::run_queue::get_cancel_requested_runs;
+use crate::system_plugins::call_workflow::finish_workflow_call;
+use crate::AppState;
+
+use serde_json::{json, Value};
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::watch;
+use tokio::time::sleep;
+use tracing::{error, info};
+use uuid::Uuid;
+
+pub const CANCELED_ERROR_TYPE: &str = "canceled";
+
+const CANCEL_REQUEST_CHECK_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Tells the workflow actor and task actors running a flow session that it was canceled
+#[derive(Debug, Clone)]
+pub struct CancelSignal {
+    receiver: watch::Receiver<bool>,
+}
+
+impl CancelSignal {
+    pub fn new() -> (watch::Sender<bool>, Self) {
+        let (sender, receiver) = watch::channel(false);
+        (sender, Self { receiver })
+    }
+
+    pub fn is_canceled(&self) -> bool {
+        *self.receiver.borrow()
+    }
+
+    /// Resolves once the session is canceled, never if its workflow actor finishes first
+    pub async fn canceled(&self) {
+        let mut receiver = self.receiver.clone();
+        if receiver.wait_for(|canceled| *canceled).await.is_err() {
+            std::future::pending::<()>().await;
+        }
+    }
+}
+
+/// Returned by the workflow actor once a canceled session stopped its tasks
+#[derive(Debug)]
+pub struct SessionCanceled;
+
+impl fmt::Display for SessionCanceled {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Flow session was canceled")
+    }
+}
+
+impl std::error::Error for SessionCanceled {}
+
+pub fn canceled_task_error() -> TaskError {
+    TaskError {
+        error: json!({
+            "message": "Task was canceled with its flow session",
+            "error_type": CANCELED_ERROR_TYPE
+        }),
+        context: json!({}),
+    }
+}
+
+pub fn is_canceled_task_error(task_error: &TaskError) -> bool {
+    task_error.error.get("error_type").and_then(Value::as_str) == Some(CANCELED_ERROR_TYPE)
+}
+
+/// Answers whoever is waiting on the session, a `/start/respond` caller or a Call Workflow action
+pub fn notify_session_canceled(state: &AppState, flow_session_id: &Uuid) {
+    if let Some((_, completion)) = state.flow_completions.remove(&flow_session_id.to_string()) {
+        if completion.needs_response {
+            info!(
+                "[CANCELLATION] Answering the caller waiting on flow session {}",
+                flow_session_id
+            );
+            let _ = completion
+                .sender
+                .send(cancellation_response(flow_session_id));
+        }
+    }
+
+    finish_workflow_call(state, flow_session_id, Err(SessionCanceled.to_string()));
+}
+
+/// Sets the cancel signal of a session running on this server.
+/// Returns false when no workflow actor registered one for it.
+pub fn signal_session_cancel(state: &AppState, flow_session_id: &Uuid) -> bool {
+    match state.session_cancels.get(&flow_session_id.to_string()) {
+        Some(cancel_sender) => {
+            cancel_sender.send_replace(true);
+            true
+        }
+        None => false,
+    }
+}
+
+/// Stops the sessions of this server that were canceled through another server.
+/// A session not picked up by a workflow actor yet is signaled on a later check.
+pub async fn cancel_request_loop(state: Arc<AppState>) {
+    info!("[CANCELLATION] Starting cancel request loop");
+
+    loop {
+        sleep(CANCEL_REQUEST_CHECK_INTERVAL).await;
+
+        if state
+            .shutdown_signal
+            .load(std::sync::atomic::Ordering::SeqCst)
+        {
+            info!("[CANCELLATION] Shutdown signal received, stopping cancel request loop");
+            break;
+        }
+
+        let runs = match get_cancel_requested_runs(&state).await {
+            Ok(runs) => runs,
+            Err(e) => {
+                error!("[CANCELLATION] Error fetching cancel requests: {}", e);
+                continue;
+            }
+        };
+
+        for run in runs {
+            if signal_session_cancel(&state, &run.flow_session_id) {
+                info!(
+                    "[CANCELLATION] Canceling flow session {} on request of another server",
+                    run.flow_session_id
+                );
+            }
+        }
+    }
+}
+
+/// Shaped like an `@anything/webhook_response` result so callers render it the same way
+pub fn cancellation_response(flow_session_id: &Uuid) -> Value {
+    json!({
+        "status_code": 409,
+        "headers": { "content-type": "application/json" },
+        "body": {
+            "error": SessionCanceled.to_string(),
+            "workflow_session_id": flow_session_id
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_cancel_signal() {
+        let (sender, signal) = CancelSignal::new();
+        assert!(!signal.is_canceled());
+
+        let waiting = signal.clone();
+        let handle = tokio::spawn(async move { waiting.canceled().await });
+
+        sender.send(true).unwrap();
+        handle.await.unwrap();
+        assert!(signal.is_canceled());
+
+        assert!(is_canceled_task_error(&canceled_task_error()));
+    }
+}
@@ -1,3 +1,4 @@
+use crate::system_plugins::loop_plugin::{LOOP_END_PLUGIN_NAME, LOOP_PLUGIN_NAME};
 use crate::types::{
     action_types::Action, react_flow_types::Edge, task_types::Task,
     workflow_types::WorkflowVersionDefinition,
@@ -6,6 +7,15 @@ use std::collections::{HashMap, HashSet, VecDeque};
 use tracing::{info, warn};
 use uuid::Uuid;
 
+/// The actions a loop node runs once per item
+#[derive(Debug, Clone)]
+pub struct LoopRegion {
+    /// The matching loop end that collects iteration results, if the workflow has one
+    pub end_action_id: Option<String>,
+    /// Every action between the loop node and its loop end, including nested loops
+    pub body: HashSet<String>,
+}
+
 /// Represents a task dependency graph and execution order
 #[derive(Debug, Clone)]
 pub struct DependencyGraph {
@@ -15,6 +25,10 @@ pub struct DependencyGraph {
     pub dependents: HashMap<String, Vec<String>>,
     /// Topologically sorted execution order
     pub execution_order: Vec<String>,
+    /// Map from loop action_id to the region it iterates over
+    pub loop_regions: HashMap<String, LoopRegion>,
+    /// Edges leaving an error handle as (source, target), taken only when the source fails
+    pub error_edges: HashSet<(String, String)>,
 }
 
 impl DependencyGraph {
@@ -22,6 +36,7 @@ impl DependencyGraph {
     pub fn new(workflow_def: &WorkflowVersionDefinition) -> Self {
         let mut dependencies: HashMap<String, Vec<String>> = HashMap::new();
         let mut dependents: HashMap<String, Vec<String>> = HashMap::new();
+        let mut error_edges = HashSet::new();
 
         // Initialize empty dependency lists for all actions
         for action in &workflow_def.actions {
@@ -42,11 +57,17 @@ impl DependencyGraph {
                 .entry(edge.source.clone())
                 .or_insert_with(Vec::new)
                 .push(edge.target.clone());
+
+            if edge.is_error_edge() {
+                error_edges.insert((edge.source.clone(), edge.target.clone()));
+            }
         }
 
         // Calculate topological order
         let execution_order = Self::topological_sort(&dependencies, &workflow_def.actions);
 
+        let loop_regions = Self::find_loop_regions(&dependents, &workflow_def.actions);
+
         info!(
             "[DEPENDENCY_RESOLVER] Created dependency graph with {} actions, execution order: {:?}",
             workflow_def.actions.len(),
@@ -57,7 +78,108 @@ impl DependencyGraph {
             dependencies,
             dependents,
             execution_order,
+            loop_regions,
+            error_edges,
+        }
+    }
+
+    /// Finds the body of every loop node by walking downstream until the matching loop end.
+    /// Nested loops are tracked by depth so an inner loop end does not close the outer loop.
+    fn find_loop_regions(
+        dependents: &HashMap<String, Vec<String>>,
+        actions: &[Action],
+    ) -> HashMap<String, LoopRegion> {
+        let plugin_names: HashMap<&str, &str> = actions
+            .iter()
+            .map(|action| (action.action_id.as_str(), action.plugin_name.as_str()))
+            .collect();
+
+        let mut loop_regions = HashMap::new();
+
+        for action in actions {
+            if action.plugin_name.as_str() != LOOP_PLUGIN_NAME {
+                continue;
+            }
+
+            let mut body = HashSet::new();
+            let mut end_action_id = None;
+            let mut visited = HashSet::new();
+            let mut stack: Vec<(String, usize)> = dependents
+                .get(&action.action_id)
+                .map(|targets| targets.iter().map(|t| (t.clone(), 0)).collect())
+                .unwrap_or_default();
+
+            while let Some((action_id, depth)) = stack.pop() {
+                // Depth can only exceed the action count when the graph has a cycle
+                if depth > actions.len() || !visited.insert((action_id.clone(), depth)) {
+                    continue;
+                }
+
+                let next_depth = match plugin_names.get(action_id.as_str()).copied() {
+                    Some(LOOP_END_PLUGIN_NAME) if depth == 0 => {
+                        end_action_id = Some(action_id);
+                        continue;
+                    }
+                    Some(LOOP_END_PLUGIN_NAME) => depth - 1,
+                    Some(LOOP_PLUGIN_NAME) => depth + 1,
+                    _ => depth,
+                };
+
+                body.insert(action_id.clone());
+
+                if let Some(targets) = dependents.get(&action_id) {
+                    stack.extend(targets.iter().map(|t| (t.clone(), next_depth)));
+                }
+            }
+
+            if end_action_id.is_none() {
+                warn!(
+                    "[DEPENDENCY_RESOLVER] Loop {} has no matching loop end, iterating over everything downstream",
+                    action.action_id
+                );
+            }
+
+            loop_regions.insert(
+                action.action_id.clone(),
+                LoopRegion {
+                    end_action_id,
+                    body,
+                },
+            );
         }
+
+        loop_regions
+    }
+
+    /// Gets the actions scheduled directly by a scope: the whole workflow when `loop_action_id`
+    /// is None, otherwise one iteration of that loop. Actions inside nested loop bodies belong
+    /// to the nested loop's iterations instead.
+    pub fn get_scope_actions(
+        &self,
+        actions: &[Action],
+        loop_action_id: Option<&str>,
+    ) -> Vec<Action> {
+        let scope_body = loop_action_id.and_then(|id| self.loop_regions.get(id));
+
+        let nested_bodies: HashSet<&String> = self
+            .loop_regions
+            .iter()
+            .filter(|(id, _)| match scope_body {
+                Some(region) => region.body.contains(*id),
+                None => true,
+            })
+            .flat_map(|(_, region)| region.body.iter())
+            .collect();
+
+        actions
+            .iter()
+            .filter(|action| match scope_body {
+                Some(region) => region.body.contains(&action.action_id),
+                None => true,
+            })
+            .filter(|action| !nested_bodies.contains(&action.action_id))
+            .cloned()
+            .collect()
     }
 
     /// Performs topological sort to determine execution order
@@ -123,20 +245,92 @@ impl DependencyGraph {
             .unwrap_or_default()
     }
 
+    /// Gets every action that runs before the given one, following dependencies back to the trigger
+    pub fn get_upstream_actions(&self, action_id: &str) -> HashSet<String> {
+        let mut upstream = HashSet::new();
+        let mut queue: VecDeque<&str> = VecDeque::from([action_id]);
+
+        while let Some(current) = queue.pop_front() {
+            for dependency in self.dependencies.get(current).into_iter().flatten() {
+                if upstream.insert(dependency.clone()) {
+                    queue.push_back(dependency);
+                }
+            }
+        }
+
+        upstream
+    }
+
+    /// Gets every action that runs after the given one, following dependents to the end of the workflow
+    pub fn get_downstream_actions(&self, action_id: &str) -> HashSet<String> {
+        let mut downstream = HashSet::new();
+        let mut queue: VecDeque<&str> = VecDeque::from([action_id]);
+
+        while let Some(current) = queue.pop_front() {
+            for dependent in self.dependents.get(current).into_iter().flatten() {
+                if downstream.insert(dependent.clone()) {
+                    queue.push_back(dependent);
+                }
+            }
+        }
+
+        downstream
+    }
+
     /// Gets the dependents for a given action
     pub fn get_dependents(&self, action_id: &str) -> Vec<String> {
         self.dependents.get(action_id).cloned().unwrap_or_default()
     }
 
+    /// True when the action has an error branch, so its failure does not fail the workflow
+    pub fn has_error_handler(&self, action_id: &str) -> bool {
+        self.error_edges
+            .iter()
+            .any(|(source, _)| source == action_id)
+    }
+
+    /// Gets the outgoing edges of an action that run on failure (`on_error`) or on success
+    pub fn get_branch_edges(&self, action_id: &str, on_error: bool) -> Vec<(String, String)> {
+        self.get_dependents(action_id)
+            .into_iter()
+            .map(|target| (action_id.to_string(), target))
+            .filter(|edge| self.error_edges.contains(edge) == on_error)
+            .collect()
+    }
+
+    /// Gets the outgoing edges a finished action will never take: all of them when a filter
+    /// stopped the branch, the success edges when it failed and the error edges when it succeeded
+    pub fn get_untaken_edges(
+        &self,
+        action_id: &str,
+        failed: bool,
+        halts_branch: bool,
+    ) -> Vec<(String, String)> {
+        if halts_branch {
+            return self
+                .get_dependents(action_id)
+                .into_iter()
+                .map(|target| (action_id.to_string(), target))
+                .collect();
+        }
+        self.get_branch_edges(action_id, !failed)
+    }
+
     /// Checks if all dependencies for an action are satisfied
+    /// Skipped dependencies count as satisfied so joins with a live branch still run
     pub fn are_dependencies_satisfied(
         &self,
         action_id: &str,
         completed_tasks: &HashMap<Uuid, Task>,
+        skipped_actions: &HashSet<String>,
     ) -> bool {
         let dependencies = self.get_dependencies(action_id);
 
         for dep_action_id in dependencies {
+            if skipped_actions.contains(&dep_action_id) {
+                continue;
+            }
+
             // Check if any completed task has this action_id
             let is_satisfied = completed_tasks
                 .values()
@@ -156,12 +350,15 @@ impl DependencyGraph {
         actions: &[Action],
         completed_tasks: &HashMap<Uuid, Task>,
         running_tasks: &HashSet<String>,
+        skipped_actions: &HashSet<String>,
     ) -> Vec<Action> {
         let mut ready_actions = Vec::new();
 
         for action in actions {
-            // Skip if already running or completed
-            if running_tasks.contains(&action.action_id) {
+            // Skip if already running, skipped or completed
+            if running_tasks.contains(&action.action_id)
+                || skipped_actions.contains(&action.action_id)
+            {
                 continue;
             }
 
@@ -173,7 +370,8 @@ impl DependencyGraph {
             }
 
             // Check if dependencies are satisfied
-            if self.are_dependencies_satisfied(&action.action_id, completed_tasks) {
+            if self.are_dependencies_satisfied(&action.action_id, completed_tasks, skipped_actions)
+            {
                 ready_actions.push(action.clone());
             }
         }
@@ -186,4 +384,44 @@ impl DependencyGraph {
 
         ready_actions
     }
+    /// Prunes every action that can no longer run once `source_action_id` finished, e.g. the
+    /// branch behind a filter that returned false, the error branch of a task that succeeded
+    /// or the success branch of a task that failed. An action is pruned once every incoming
+    /// edge is dead or comes from a pruned action, so nodes that also depend on a live branch
+    /// keep running. Returns the newly skipped action ids and adds them to `skipped_actions`.
+    pub fn prune_dead_branches(
+        &self,
+        source_action_id: &str,
+        dead_edges: &HashSet<(String, String)>,
+        skipped_actions: &mut HashSet<String>,
+    ) -> Vec<String> {
+        let mut newly_skipped = Vec::new();
+        let mut queue: VecDeque<String> = self.get_dependents(source_action_id).into();
+
+        while let Some(candidate) = queue.pop_front() {
+            if skipped_actions.contains(&candidate) {
+                continue;
+            }
+
+            let all_dependencies_dead = self.get_dependencies(&candidate).iter().all(|dep| {
+                skipped_actions.contains(dep)
+                    || dead_edges.contains(&(dep.clone(), candidate.clone()))
+            });
+
+            if all_dependencies_dead {
+                skipped_actions.insert(candidate.clone());
+                queue.extend(self.get_dependents(&candidate));
+                newly_skipped.push(candidate);
+            }
+        }
+
+        info!(
+            "[DEPENDENCY_RESOLVER] Action {} pruned {} downstream actions: {:?}",
+            source_action_id,
+            newly_skipped.len(),
+            newly_skipped
+        );
+
+        newly_skipped
+    }
 }
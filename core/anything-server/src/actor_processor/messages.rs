@@ -1,6 +1,8 @@
+use crate::actor_processor::cancellation::CancelSignal;
 use crate::processor::components::{ProcessorError, WorkflowExecutionContext};
 use crate::processor::execute_task::TaskResult;
 use crate::processor::processor::ProcessorMessage;
+use crate::types::action_types::RetryPolicy;
 use crate::types::task_types::Task;
 use std::collections::HashMap;
 use tokio::sync::oneshot;
@@ -20,6 +22,8 @@ pub enum ActorMessage {
         respond_to: oneshot::Sender<TaskResult>,
         context: WorkflowExecutionContext,
         in_memory_tasks: Option<HashMap<Uuid, Task>>,
+        retry_policy: Option<RetryPolicy>,
+        cancel: CancelSignal,
     },
     /// Execute a workflow (collection of tasks)
     ExecuteWorkflow {
@@ -1,8 +1,11 @@
 pub mod actor_pool;
 pub mod actor_system;
+pub mod cancellation;
 pub mod dependency_resolver;
 pub mod messages;
 pub mod processor;
+pub mod session_permits;
+pub mod status_updates;
 pub mod task_actor;
 pub mod tests;
 pub mod workflow_actor;
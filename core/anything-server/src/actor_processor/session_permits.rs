@@ -0,0 +1,169 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tokio::sync::{AcquireError, OwnedSemaphorePermit, Semaphore};
+use uuid::Uuid;
+
+/// Workflow permits of the flow sessions running on this server.
+/// A session gives its permit back while it waits on a workflow it called, so nested
+/// waiting calls can't take every permit and starve the sessions they wait on.
+pub struct SessionPermits {
+    semaphore: Arc<Semaphore>,
+    sessions: Mutex<HashMap<Uuid, SessionPermit>>,
+}
+
+#[derive(Default)]
+struct SessionPermit {
+    permit: Option<OwnedSemaphorePermit>,
+    waiting_calls: usize, // Calls of this session waiting on their called workflow
+}
+
+impl SessionPermits {
+    pub fn new(semaphore: Arc<Semaphore>) -> Self {
+        Self {
+            semaphore,
+            sessions: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Waits for a permit for the session to start running
+    pub async fn acquire(&self, flow_session_id: Uuid) -> Result<(), AcquireError> {
+        let permit = self.semaphore.clone().acquire_owned().await?;
+        self.sessions.lock().unwrap().insert(
+            flow_session_id,
+            SessionPermit {
+                permit: Some(permit),
+                waiting_calls: 0,
+            },
+        );
+        Ok(())
+    }
+
+    /// Gives the permit of a finished session back
+    pub fn release(&self, flow_session_id: &Uuid) {
+        self.sessions.lock().unwrap().remove(flow_session_id);
+    }
+
+    /// Gives the session's permit back until the returned guard is resumed
+    pub fn park(self: &Arc<Self>, flow_session_id: Uuid) -> ParkedSession {
+        if let Some(session) = self.sessions.lock().unwrap().get_mut(&flow_session_id) {
+            session.waiting_calls += 1;
+            session.permit = None;
+        }
+
+        ParkedSession {
+            permits: Arc::clone(self),
+            flow_session_id,
+            parked: true,
+        }
+    }
+
+    /// Counts one waiting call as done and returns whether the session still has to take its permit back
+    fn unpark(&self, flow_session_id: &Uuid) -> bool {
+        match self.sessions.lock().unwrap().get_mut(flow_session_id) {
+            Some(session) => {
+                session.waiting_calls = session.waiting_calls.saturating_sub(1);
+                session.waiting_calls == 0 && session.permit.is_none()
+            }
+            None => false,
+        }
+    }
+
+    /// Hands a permit back to a session that is still running and no longer waiting.
+    /// Otherwise the permit is dropped again.
+    fn restore(&self, flow_session_id: &Uuid, permit: OwnedSemaphorePermit) {
+        if let Some(session) = self.sessions.lock().unwrap().get_mut(flow_session_id) {
+            if session.waiting_calls == 0 && session.permit.is_none() {
+                session.permit = Some(permit);
+            }
+        }
+    }
+
+    #[cfg(test)]
+    fn holds_permit(&self, flow_session_id: &Uuid) -> bool {
+        self.sessions
+            .lock()
+            .unwrap()
+            .get(flow_session_id)
+            .map(|session| session.permit.is_some())
+            .unwrap_or(false)
+    }
+}
+
+/// A session waiting on a called workflow without holding its permit.
+/// Dropping the guard without resuming only stops counting the wait.
+pub struct ParkedSession {
+    permits: Arc<SessionPermits>,
+    flow_session_id: Uuid,
+    parked: bool,
+}
+
+impl ParkedSession {
+    /// Takes the session's permit back once none of its calls are waiting anymore
+    pub async fn resume(mut self) -> Result<(), AcquireError> {
+        self.parked = false;
+
+        if self.permits.unpark(&self.flow_session_id) {
+            let permit = self.permits.semaphore.clone().acquire_owned().await?;
+            self.permits.restore(&self.flow_session_id, permit);
+        }
+
+        Ok(())
+    }
+}
+
+impl Drop for ParkedSession {
+    fn drop(&mut self) {
+        if self.parked {
+            self.permits.unpark(&self.flow_session_id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_parked_session_frees_its_permit_for_the_called_workflow() {
+        let semaphore = Arc::new(Semaphore::new(1));
+        let permits = Arc::new(SessionPermits::new(semaphore.clone()));
+        let parent = Uuid::new_v4();
+        let child = Uuid::new_v4();
+
+        permits.acquire(parent).await.unwrap();
+        assert_eq!(semaphore.available_permits(), 0);
+
+        // The parent waits on its child, which can only run with the parent's permit
+        let parked = permits.park(parent);
+        permits.acquire(child).await.unwrap();
+        assert!(!permits.holds_permit(&parent));
+
+        permits.release(&child);
+        parked.resume().await.unwrap();
+        assert!(permits.holds_permit(&parent));
+        assert_eq!(semaphore.available_permits(), 0);
+
+        permits.release(&parent);
+        assert_eq!(semaphore.available_permits(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_session_stays_parked_while_another_call_waits() {
+        let semaphore = Arc::new(Semaphore::new(1));
+        let permits = Arc::new(SessionPermits::new(semaphore.clone()));
+        let parent = Uuid::new_v4();
+
+        permits.acquire(parent).await.unwrap();
+        let first_call = permits.park(parent);
+        let second_call = permits.park(parent);
+
+        first_call.resume().await.unwrap();
+        assert!(!permits.holds_permit(&parent));
+        assert_eq!(semaphore.available_permits(), 1);
+
+        // A call dropped mid wait, like a canceled task, still counts as done
+        drop(second_call);
+        permits.release(&parent);
+        assert_eq!(semaphore.available_permits(), 1);
+    }
+}
@@ -0,0 +1,20 @@
+use crate::status_updater::{Operation, StatusUpdateMessage};
+use crate::AppState;
+
+use tracing::error;
+
+/// Queues a task or flow session update for the database status processor.
+/// The channel is drained in order, so a task's create always lands before its updates.
+pub async fn send_status_update(
+    state: &AppState,
+    operation: Operation,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    state
+        .task_updater_sender
+        .send(StatusUpdateMessage { operation })
+        .await
+        .map_err(|e| {
+            error!("[ACTOR_STATUS_UPDATES] Failed to send status update: {}", e);
+            format!("Failed to send status update: {}", e).into()
+        })
+}
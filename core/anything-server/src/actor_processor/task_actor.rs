@@ -1,19 +1,33 @@
+use crate::actor_processor::cancellation::{
+    canceled_task_error, is_canceled_task_error, CancelSignal,
+};
 use crate::actor_processor::messages::ActorMessage;
+use crate::actor_processor::status_updates::send_status_update;
 use crate::metrics::METRICS;
 use crate::processor::components::{EnhancedSpanFactory, WorkflowExecutionContext};
 use crate::processor::execute_task::{execute_task, TaskResult};
-use crate::types::task_types::Task;
+use crate::processor::task_waits::parks_session;
+use crate::status_updater::Operation;
+use crate::system_plugins::call_workflow::MAX_TIMEOUT_SECONDS;
+use crate::types::action_types::{RetryPolicy, RETRY_ON_HTTP_5XX};
+use crate::types::task_types::{Task, TaskAttempt, TaskStatus};
 use crate::AppState;
 
 use opentelemetry::KeyValue;
 use postgrest::Postgrest;
 use std::sync::Arc;
 use std::time::{Duration, Instant};
-use tokio::sync::mpsc;
+use tokio::sync::{mpsc, Semaphore};
 use tokio::time::timeout;
 use tracing::{error, info, instrument, warn};
 use uuid::Uuid;
 
+/// Most tasks a task actor runs at once
+pub const MAX_CONCURRENT_TASKS: usize = 10;
+
+/// Outer timeout of a task attempt
+const TASK_TIMEOUT: Duration = Duration::from_secs(300);
+
 /// Actor for executing individual tasks
 pub struct TaskActor {
     id: Uuid,
@@ -21,6 +35,7 @@ pub struct TaskActor {
     client: Postgrest,
     span_factory: EnhancedSpanFactory,
     metrics_labels: Vec<KeyValue>,
+    task_slots: Arc<Semaphore>,
 }
 
 impl TaskActor {
@@ -37,12 +52,15 @@ impl TaskActor {
             client,
             span_factory,
             metrics_labels,
+            task_slots: Arc::new(Semaphore::new(MAX_CONCURRENT_TASKS)),
         }
     }
 
-    pub async fn run(mut self, mut receiver: mpsc::Receiver<ActorMessage>) {
+    pub async fn run(self, mut receiver: mpsc::Receiver<ActorMessage>) {
         info!("[TASK_ACTOR_{}] Starting task actor", self.id);
 
+        let actor = Arc::new(self);
+
         while let Some(message) = receiver.recv().await {
             match message {
                 ActorMessage::ExecuteTask {
@@ -50,26 +68,53 @@ impl TaskActor {
                     respond_to,
                     context,
                     in_memory_tasks,
+                    retry_policy,
+                    cancel,
                 } => {
-                    let result = self
-                        .handle_execute_task(task, context, in_memory_tasks.as_ref())
-                        .await;
-                    let _ = respond_to.send(result);
+                    // A Call Workflow task waits on tasks of the workflow it called,
+                    // which may be sent to this same actor. It runs without a slot,
+                    // other tasks wait here for one so a busy actor pushes back on its senders.
+                    let slot = if is_call_workflow_task(&task) {
+                        None
+                    } else {
+                        match Arc::clone(&actor.task_slots).acquire_owned().await {
+                            Ok(slot) => Some(slot),
+                            Err(e) => {
+                                error!("[TASK_ACTOR_{}] Task slots closed: {}", actor.id, e);
+                                break;
+                            }
+                        }
+                    };
+
+                    let actor = Arc::clone(&actor);
+                    tokio::spawn(async move {
+                        let result = actor
+                            .handle_execute_task(
+                                task,
+                                context,
+                                in_memory_tasks.as_ref(),
+                                retry_policy,
+                                cancel,
+                            )
+                            .await;
+                        drop(slot);
+                        let _ = respond_to.send(result);
+                    });
                 }
                 ActorMessage::Shutdown => {
-                    info!("[TASK_ACTOR_{}] Shutting down task actor", self.id);
+                    info!("[TASK_ACTOR_{}] Shutting down task actor", actor.id);
                     break;
                 }
                 _ => {
-                    warn!("[TASK_ACTOR_{}] Received unexpected message type", self.id);
+                    warn!("[TASK_ACTOR_{}] Received unexpected message type", actor.id);
                 }
             }
         }
 
-        info!("[TASK_ACTOR_{}] Task actor shutdown complete", self.id);
+        info!("[TASK_ACTOR_{}] Task actor shutdown complete", actor.id);
     }
 
-    #[instrument(skip(self, task, context, in_memory_tasks), fields(
+    #[instrument(skip(self, task, context, in_memory_tasks, retry_policy, cancel), fields(
         actor_id = %self.id,
         task_id = %task.task_id,
         plugin_name = ?task.plugin_name
@@ -79,6 +124,8 @@ impl TaskActor {
         task: Task,
         context: WorkflowExecutionContext,
         in_memory_tasks: Option<&std::collections::HashMap<uuid::Uuid, Task>>,
+        retry_policy: Option<RetryPolicy>,
+        cancel: CancelSignal,
     ) -> TaskResult {
         let task_span = self.create_task_execution_span(
             task.task_id,
@@ -109,135 +156,345 @@ impl TaskActor {
             info!("[TASK_ACTOR_{}] Executing task {}", self.id, task.task_id);
         }
 
-        // 🚀 TASK STARTING - Would normally update task status to running in database
-        info!(
-            "🚀 TASK STARTING: {} is now RUNNING (skipping database update for debugging)",
-            task.task_id
-        );
+        let started_at = chrono::Utc::now();
 
-        // Execute the task with timeout
-        let task_timeout = Duration::from_secs(300); // 5 minutes timeout - this is the outer timeout
-        let result = timeout(
-            task_timeout,
-            execute_task(self.state.clone(), &self.client, &task, in_memory_tasks),
+        // Tasks that were already sent when the session was canceled never start
+        if cancel.is_canceled() {
+            info!(
+                "[TASK_ACTOR_{}] Task {} not started, its flow session was canceled",
+                self.id, task.task_id
+            );
+            let task_error = canceled_task_error();
+            self.update_task_status(
+                task.task_id,
+                TaskStatus::Canceled,
+                None,
+                None,
+                Some(task_error.error.clone()),
+                None,
+                Some(started_at),
+            )
+            .await;
+            return Err(task_error);
+        }
+
+        self.update_task_status(
+            task.task_id,
+            TaskStatus::Running,
+            None,
+            None,
+            None,
+            Some(started_at),
+            None,
         )
         .await;
 
-        let execution_duration = start_time.elapsed();
-        METRICS.record_task_execution_time(execution_duration, &self.metrics_labels);
+        let retry_policy = retry_policy.map(|policy| policy.normalized());
+        let max_attempts = retry_policy
+            .as_ref()
+            .map(|policy| policy.max_attempts)
+            .unwrap_or(1);
+        let mut attempts: Vec<TaskAttempt> = Vec::new();
 
-        let end_time = chrono::Utc::now();
+        let result = loop {
+            let attempt = attempts.len() as u32 + 1;
+            let attempt_started_at = chrono::Utc::now();
+            let attempt_result = self.execute_attempt(&task, in_memory_tasks, &cancel).await;
+            let attempt_ended_at = chrono::Utc::now();
 
-        match result {
-            Ok(task_result) => {
-                match &task_result {
-                    Ok((result_value, context_value, started_at, ended_at)) => {
-                        if is_rustyscript_task {
-                            info!(
-                                "[TASK_ACTOR_{}] RustyScript task {} completed successfully in {:?}",
-                                self.id, task.task_id, execution_duration
-                            );
-                        } else {
-                            info!(
-                                "[TASK_ACTOR_{}] Task {} completed successfully in {:?}",
-                                self.id, task.task_id, execution_duration
-                            );
+            let retry_reason = retry_policy
+                .as_ref()
+                .and_then(|policy| Self::retry_reason(policy, &task, &attempt_result));
+
+            if retry_policy.is_some() {
+                attempts.push(TaskAttempt {
+                    attempt,
+                    started_at: attempt_started_at,
+                    ended_at: attempt_ended_at,
+                    status: match &attempt_result {
+                        Ok(_) => TaskStatus::Completed,
+                        Err(task_error) if is_canceled_task_error(task_error) => {
+                            TaskStatus::Canceled
                         }
-                        context.record_success();
+                        Err(_) => TaskStatus::Failed,
+                    },
+                    error: retry_reason.clone().or_else(|| {
+                        attempt_result
+                            .as_ref()
+                            .err()
+                            .map(|task_error| task_error.error.clone())
+                    }),
+                });
+            }
 
-                        // ✅ TASK COMPLETED - Would normally update task status to completed in database
-                        info!("✅ TASK COMPLETED: {} finished successfully in {:?} (skipping database update for debugging)", task.task_id, execution_duration);
-                    }
-                    Err(e) => {
-                        // Enhanced error logging with error type information
-                        let error_type = e
-                            .error
+            match (&retry_policy, retry_reason) {
+                (Some(policy), Some(reason)) if attempt < max_attempts => {
+                    let delay = policy.delay_after_attempt(attempt);
+                    warn!(
+                        "[TASK_ACTOR_{}] Task {} attempt {}/{} failed ({}), retrying in {:?}",
+                        self.id,
+                        task.task_id,
+                        attempt,
+                        max_attempts,
+                        reason
                             .get("error_type")
                             .and_then(|v| v.as_str())
-                            .unwrap_or("unknown_error");
-
-                        let execution_time = e
-                            .error
-                            .get("execution_time_ms")
-                            .and_then(|v| v.as_u64())
-                            .map(|ms| format!("{}ms", ms))
-                            .unwrap_or_else(|| format!("{:?}", execution_duration));
-
-                        if is_rustyscript_task {
-                            error!(
-                                "[TASK_ACTOR_{}] RustyScript task {} failed ({}): {} after {}",
-                                self.id,
-                                task.task_id,
-                                error_type,
-                                e.error
-                                    .get("message")
-                                    .and_then(|v| v.as_str())
-                                    .unwrap_or("Unknown error"),
-                                execution_time
-                            );
-                        } else {
-                            error!(
-                                "[TASK_ACTOR_{}] Task {} failed ({}): {} after {}",
-                                self.id,
-                                task.task_id,
-                                error_type,
-                                e.error
-                                    .get("message")
-                                    .and_then(|v| v.as_str())
-                                    .unwrap_or("Unknown error"),
-                                execution_time
-                            );
-                        }
-
-                        context.record_error(&format!(
-                            "Task execution failed ({}): {}",
-                            error_type,
-                            e.error
-                                .get("message")
-                                .and_then(|v| v.as_str())
-                                .unwrap_or("Unknown error")
-                        ));
-
-                        // ❌ TASK FAILED - Would normally update task status to failed in database
-                        info!("❌ TASK FAILED: {} encountered an error ({}): {} (skipping database update for debugging)", 
-                            task.task_id, error_type,
-                            e.error.get("message").and_then(|v| v.as_str()).unwrap_or("Unknown error")
-                        );
+                            .unwrap_or("unknown_error"),
+                        delay
+                    );
+                    self.update_task_attempts(task.task_id, &attempts).await;
+                    tokio::select! {
+                        _ = tokio::time::sleep(delay) => {}
+                        _ = cancel.canceled() => break Err(canceled_task_error()),
                     }
                 }
-                task_result
+                _ => break attempt_result,
             }
-            Err(_) => {
+        };
+
+        if !attempts.is_empty() {
+            self.update_task_attempts(task.task_id, &attempts).await;
+        }
+
+        let execution_duration = start_time.elapsed();
+        METRICS.record_task_execution_time(execution_duration, &self.metrics_labels);
+
+        let end_time = chrono::Utc::now();
+
+        match &result {
+            Ok((result_value, context_value, _, ended_at)) => {
+                if is_rustyscript_task {
+                    info!(
+                        "[TASK_ACTOR_{}] RustyScript task {} completed successfully in {:?}",
+                        self.id, task.task_id, execution_duration
+                    );
+                } else {
+                    info!(
+                        "[TASK_ACTOR_{}] Task {} completed successfully in {:?}",
+                        self.id, task.task_id, execution_duration
+                    );
+                }
+                context.record_success();
+
+                // Tasks that park the session stay waiting until they are resumed
+                let (status, ended_at) = if parks_session(task.plugin_name.as_ref()) {
+                    (TaskStatus::Waiting, None)
+                } else {
+                    (TaskStatus::Completed, Some(*ended_at))
+                };
+
+                self.update_task_status(
+                    task.task_id,
+                    status,
+                    result_value.clone(),
+                    Some(context_value.clone()),
+                    None,
+                    Some(started_at),
+                    ended_at,
+                )
+                .await;
+            }
+            Err(e) => {
+                // Enhanced error logging with error type information
+                let error_type = e
+                    .error
+                    .get("error_type")
+                    .and_then(|v| v.as_str())
+                    .unwrap_or("unknown_error");
+
+                let execution_time = e
+                    .error
+                    .get("execution_time_ms")
+                    .and_then(|v| v.as_u64())
+                    .map(|ms| format!("{}ms", ms))
+                    .unwrap_or_else(|| format!("{:?}", execution_duration));
+
                 if is_rustyscript_task {
                     error!(
-                        "[TASK_ACTOR_{}] RustyScript task {} timed out after {:?} (actor-level timeout)",
-                        self.id, task.task_id, task_timeout
+                        "[TASK_ACTOR_{}] RustyScript task {} failed ({}): {} after {}",
+                        self.id,
+                        task.task_id,
+                        error_type,
+                        e.error
+                            .get("message")
+                            .and_then(|v| v.as_str())
+                            .unwrap_or("Unknown error"),
+                        execution_time
                     );
                 } else {
                     error!(
-                        "[TASK_ACTOR_{}] Task {} timed out after {:?} (actor-level timeout)",
-                        self.id, task.task_id, task_timeout
+                        "[TASK_ACTOR_{}] Task {} failed ({}): {} after {}",
+                        self.id,
+                        task.task_id,
+                        error_type,
+                        e.error
+                            .get("message")
+                            .and_then(|v| v.as_str())
+                            .unwrap_or("Unknown error"),
+                        execution_time
                     );
                 }
-                context.record_error("Task execution timeout (actor-level)");
 
-                let timeout_error = serde_json::json!({
-                    "message": format!("Task {} timed out after {:?} (actor-level timeout)", task.task_id, task_timeout),
-                    "error_type": "actor_timeout",
-                    "timeout_duration_ms": task_timeout.as_millis()
-                });
+                context.record_error(&format!(
+                    "Task execution failed ({}): {}",
+                    error_type,
+                    e.error
+                        .get("message")
+                        .and_then(|v| v.as_str())
+                        .unwrap_or("Unknown error")
+                ));
+
+                // Canceled tasks did not fail, they were stopped with their session
+                let status = if is_canceled_task_error(e) {
+                    TaskStatus::Canceled
+                } else {
+                    TaskStatus::Failed
+                };
+
+                self.update_task_status(
+                    task.task_id,
+                    status,
+                    None,
+                    Some(e.context.clone()),
+                    Some(e.error.clone()),
+                    Some(started_at),
+                    Some(end_time),
+                )
+                .await;
+            }
+        }
+
+        result
+    }
 
-                // ⏰ TASK TIMEOUT - Would normally update task status to failed due to timeout in database
-                info!("⏰ TASK TIMEOUT: {} timed out after {:?} (actor-level timeout) (skipping database update for debugging)", task.task_id, task_timeout);
+    /// Runs the task once under the actor-level timeout, until it finishes or its session is canceled.
+    /// Canceling drops the task future, which aborts HTTP requests in flight. A RustyScript worker
+    /// can't be interrupted mid script, its result is discarded and the worker stops at its own timeout.
+    async fn execute_attempt(
+        &self,
+        task: &Task,
+        in_memory_tasks: Option<&std::collections::HashMap<uuid::Uuid, Task>>,
+        cancel: &CancelSignal,
+    ) -> TaskResult {
+        // A Call Workflow task waits up to its own timeout for the called workflow,
+        // so the outer timeout must not fire first
+        let task_timeout = if is_call_workflow_task(task) {
+            Duration::from_secs(MAX_TIMEOUT_SECONDS + 60)
+        } else {
+            TASK_TIMEOUT
+        };
+        let attempt = timeout(
+            task_timeout,
+            execute_task(self.state.clone(), &self.client, task, in_memory_tasks),
+        );
+
+        let attempt_result = tokio::select! {
+            attempt_result = attempt => attempt_result,
+            _ = cancel.canceled() => {
+                warn!(
+                    "[TASK_ACTOR_{}] Task {} stopped, its flow session was canceled",
+                    self.id, task.task_id
+                );
+                return Err(canceled_task_error());
+            }
+        };
+
+        match attempt_result {
+            Ok(task_result) => task_result,
+            Err(_) => {
+                error!(
+                    "[TASK_ACTOR_{}] Task {} timed out after {:?} (actor-level timeout)",
+                    self.id, task.task_id, task_timeout
+                );
 
                 Err(crate::processor::execute_task::TaskError {
-                    error: timeout_error,
+                    error: serde_json::json!({
+                        "message": format!("Task {} timed out after {:?} (actor-level timeout)", task.task_id, task_timeout),
+                        "error_type": "actor_timeout",
+                        "timeout_duration_ms": task_timeout.as_millis()
+                    }),
                     context: serde_json::json!({}),
                 })
             }
         }
     }
 
+    /// Returns the error to record when the policy wants this attempt retried.
+    /// HTTP responses with a 5xx status succeed as tasks, so they are checked separately.
+    fn retry_reason(
+        policy: &RetryPolicy,
+        task: &Task,
+        attempt_result: &TaskResult,
+    ) -> Option<serde_json::Value> {
+        match attempt_result {
+            Err(task_error) if is_canceled_task_error(task_error) => None,
+            Err(task_error) => {
+                let error_type = task_error
+                    .error
+                    .get("error_type")
+                    .and_then(|v| v.as_str())
+                    .unwrap_or("unknown_error");
+
+                policy
+                    .retries_error_type(error_type)
+                    .then(|| task_error.error.clone())
+            }
+            Ok((Some(result), _, _, _))
+                if task.plugin_name.as_ref().map(|p| p.as_str()) == Some("@anything/http") =>
+            {
+                let status_code =
+                    u16::try_from(result.get("status_code").and_then(|v| v.as_u64())?).ok()?;
+
+                policy.retries_status_code(status_code).then(|| {
+                    serde_json::json!({
+                        "message": format!("HTTP request returned status {}", status_code),
+                        "error_type": RETRY_ON_HTTP_5XX,
+                        "status_code": status_code
+                    })
+                })
+            }
+            Ok(_) => None,
+        }
+    }
+
+    async fn update_task_attempts(&self, task_id: Uuid, attempts: &[TaskAttempt]) {
+        let _ = send_status_update(
+            &self.state,
+            Operation::UpdateTaskAttempts {
+                task_id,
+                attempts: attempts.to_vec(),
+            },
+        )
+        .await;
+    }
+
+    /// Status updates are best effort, a failed send is logged and the task keeps its result
+    #[allow(clippy::too_many_arguments)]
+    async fn update_task_status(
+        &self,
+        task_id: Uuid,
+        status: TaskStatus,
+        result: Option<serde_json::Value>,
+        context: Option<serde_json::Value>,
+        error: Option<serde_json::Value>,
+        started_at: Option<chrono::DateTime<chrono::Utc>>,
+        ended_at: Option<chrono::DateTime<chrono::Utc>>,
+    ) {
+        let _ = send_status_update(
+            &self.state,
+            Operation::UpdateTask {
+                task_id,
+                started_at,
+                ended_at,
+                status,
+                result,
+                context,
+                error,
+            },
+        )
+        .await;
+    }
+
     fn create_task_execution_span(
         &self,
         task_id: Uuid,
@@ -253,3 +510,7 @@ impl TaskActor {
         )
     }
 }
+
+fn is_call_workflow_task(task: &Task) -> bool {
+    task.plugin_name.as_ref().map(|p| p.as_str()) == Some("@anything/call_workflow")
+}
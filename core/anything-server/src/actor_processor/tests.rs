@@ -2,11 +2,15 @@
 mod tests {
     use super::*;
     use crate::actor_processor::actor_system::ActorProcessor;
+    use crate::actor_processor::dependency_resolver::DependencyGraph;
     use crate::processor::processor::ProcessorMessage;
+    use crate::types::action_types::Action;
+    use crate::types::react_flow_types::Edge;
     use crate::types::task_types::Task;
     use crate::types::workflow_types::{DatabaseFlowVersion, WorkflowVersionDefinition};
     use crate::AppState;
     use postgrest::Postgrest;
+    use std::collections::{HashMap, HashSet};
     use std::sync::Arc;
     use tokio::sync::mpsc;
     use uuid::Uuid;
@@ -48,4 +52,303 @@ mod tests {
         // Test that the actor system shuts down gracefully
         todo!("Implement graceful shutdown test");
     }
+
+    fn test_action(action_id: &str, plugin_name: &str) -> Action {
+        serde_json::from_value(serde_json::json!({
+            "anything_action_version": "0.1.0",
+            "type": "action",
+            "plugin_name": plugin_name,
+            "plugin_version": "0.1.0",
+            "action_id": action_id,
+            "label": action_id,
+            "icon": "",
+            "plugin_config": {},
+            "plugin_config_schema": {}
+        }))
+        .unwrap()
+    }
+
+    fn test_edge(source: &str, target: &str) -> Edge {
+        Edge {
+            id: format!("{}->{}", source, target),
+            source: source.to_string(),
+            source_handle: None,
+            target: target.to_string(),
+            target_handle: None,
+            r#type: "anything".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_prune_dead_branches_skips_exclusive_descendants() {
+        // trigger -> filter -> a -> b, trigger -> c, (b, c) -> join
+        let workflow = WorkflowVersionDefinition {
+            actions: vec![
+                test_action("trigger", "@anything/webhook"),
+                test_action("filter", "@anything/filter"),
+                test_action("a", "@anything/http"),
+                test_action("b", "@anything/http"),
+                test_action("c", "@anything/http"),
+                test_action("join", "@anything/http"),
+            ],
+            edges: vec![
+                test_edge("trigger", "filter"),
+                test_edge("filter", "a"),
+                test_edge("a", "b"),
+                test_edge("trigger", "c"),
+                test_edge("b", "join"),
+                test_edge("c", "join"),
+            ],
+        };
+        let graph = DependencyGraph::new(&workflow);
+
+        let dead_edges = HashSet::from([("filter".to_string(), "a".to_string())]);
+        let mut skipped = HashSet::new();
+        let pruned = graph.prune_dead_branches("filter", &dead_edges, &mut skipped);
+
+        assert_eq!(pruned, vec!["a".to_string(), "b".to_string()]);
+        assert!(!skipped.contains("join"));
+        assert!(!skipped.contains("c"));
+
+        // The join only waits on its live dependency once the other side is skipped
+        assert!(!graph.are_dependencies_satisfied("join", &HashMap::new(), &skipped));
+    }
+
+    #[test]
+    fn test_prune_dead_branches_skips_join_when_all_parents_halted() {
+        // trigger -> f1 -> join, trigger -> f2 -> join
+        let workflow = WorkflowVersionDefinition {
+            actions: vec![
+                test_action("trigger", "@anything/webhook"),
+                test_action("f1", "@anything/filter"),
+                test_action("f2", "@anything/filter"),
+                test_action("join", "@anything/http"),
+            ],
+            edges: vec![
+                test_edge("trigger", "f1"),
+                test_edge("trigger", "f2"),
+                test_edge("f1", "join"),
+                test_edge("f2", "join"),
+            ],
+        };
+        let graph = DependencyGraph::new(&workflow);
+
+        let mut dead_edges = HashSet::from([("f1".to_string(), "join".to_string())]);
+        let mut skipped = HashSet::new();
+        assert!(graph
+            .prune_dead_branches("f1", &dead_edges, &mut skipped)
+            .is_empty());
+
+        dead_edges.insert(("f2".to_string(), "join".to_string()));
+        let pruned = graph.prune_dead_branches("f2", &dead_edges, &mut skipped);
+        assert_eq!(pruned, vec!["join".to_string()]);
+    }
+
+    #[test]
+    fn test_error_edges_split_success_and_failure_branches() {
+        // trigger -> http -> ok, http -(error)-> alert
+        let mut error_edge = test_edge("http", "alert");
+        error_edge.source_handle = Some("error".to_string());
+        let workflow = WorkflowVersionDefinition {
+            actions: vec![
+                test_action("trigger", "@anything/webhook"),
+                test_action("http", "@anything/http"),
+                test_action("ok", "@anything/http"),
+                test_action("alert", "@anything/http"),
+            ],
+            edges: vec![
+                test_edge("trigger", "http"),
+                test_edge("http", "ok"),
+                error_edge,
+            ],
+        };
+        let graph = DependencyGraph::new(&workflow);
+
+        assert!(graph.has_error_handler("http"));
+        assert!(!graph.has_error_handler("trigger"));
+        assert_eq!(
+            graph.get_branch_edges("http", true),
+            vec![("http".to_string(), "alert".to_string())]
+        );
+
+        // When the task fails its success branch is pruned and the error branch runs
+        let dead_edges: HashSet<_> = graph.get_branch_edges("http", false).into_iter().collect();
+        let mut skipped = HashSet::new();
+        let pruned = graph.prune_dead_branches("http", &dead_edges, &mut skipped);
+        assert_eq!(pruned, vec!["ok".to_string()]);
+        assert!(!skipped.contains("alert"));
+    }
+
+    #[test]
+    fn test_untaken_edges_follow_task_outcome() {
+        // Used both live and when a resumed session replays the tasks it already stored
+        let mut error_edge = test_edge("http", "alert");
+        error_edge.source_handle = Some("error".to_string());
+        let workflow = WorkflowVersionDefinition {
+            actions: vec![
+                test_action("http", "@anything/http"),
+                test_action("ok", "@anything/http"),
+                test_action("alert", "@anything/http"),
+            ],
+            edges: vec![test_edge("http", "ok"), error_edge],
+        };
+        let graph = DependencyGraph::new(&workflow);
+        let edge = |target: &str| ("http".to_string(), target.to_string());
+
+        assert_eq!(
+            graph.get_untaken_edges("http", false, false),
+            vec![edge("alert")]
+        );
+        assert_eq!(
+            graph.get_untaken_edges("http", true, false),
+            vec![edge("ok")]
+        );
+
+        let mut halted = graph.get_untaken_edges("http", false, true);
+        halted.sort();
+        assert_eq!(halted, vec![edge("alert"), edge("ok")]);
+    }
+
+    fn action_ids(actions: &[Action]) -> Vec<String> {
+        let mut ids: Vec<String> = actions.iter().map(|a| a.action_id.clone()).collect();
+        ids.sort();
+        ids
+    }
+
+    #[test]
+    fn test_loop_regions_split_workflow_into_scopes() {
+        // trigger -> outer_loop -> fetch -> inner_loop -> save -> inner_end -> outer_end -> notify
+        let workflow = WorkflowVersionDefinition {
+            actions: vec![
+                test_action("trigger", "@anything/webhook"),
+                test_action("outer_loop", "@anything/loop"),
+                test_action("fetch", "@anything/http"),
+                test_action("inner_loop", "@anything/loop"),
+                test_action("save", "@anything/http"),
+                test_action("inner_end", "@anything/loop_end"),
+                test_action("outer_end", "@anything/loop_end"),
+                test_action("notify", "@anything/http"),
+            ],
+            edges: vec![
+                test_edge("trigger", "outer_loop"),
+                test_edge("outer_loop", "fetch"),
+                test_edge("fetch", "inner_loop"),
+                test_edge("inner_loop", "save"),
+                test_edge("save", "inner_end"),
+                test_edge("inner_end", "outer_end"),
+                test_edge("outer_end", "notify"),
+            ],
+        };
+        let graph = DependencyGraph::new(&workflow);
+
+        assert_eq!(
+            graph.loop_regions["outer_loop"].end_action_id.as_deref(),
+            Some("outer_end")
+        );
+        assert_eq!(
+            graph.loop_regions["inner_loop"].end_action_id.as_deref(),
+            Some("inner_end")
+        );
+
+        assert_eq!(
+            action_ids(&graph.get_scope_actions(&workflow.actions, None)),
+            vec!["notify", "outer_end", "outer_loop", "trigger"]
+        );
+        assert_eq!(
+            action_ids(&graph.get_scope_actions(&workflow.actions, Some("outer_loop"))),
+            vec!["fetch", "inner_end", "inner_loop"]
+        );
+        assert_eq!(
+            action_ids(&graph.get_scope_actions(&workflow.actions, Some("inner_loop"))),
+            vec!["save"]
+        );
+    }
+
+    #[test]
+    fn test_loop_without_end_iterates_everything_downstream() {
+        let workflow = WorkflowVersionDefinition {
+            actions: vec![
+                test_action("trigger", "@anything/webhook"),
+                test_action("loop", "@anything/loop"),
+                test_action("a", "@anything/http"),
+                test_action("b", "@anything/http"),
+            ],
+            edges: vec![
+                test_edge("trigger", "loop"),
+                test_edge("loop", "a"),
+                test_edge("a", "b"),
+            ],
+        };
+        let graph = DependencyGraph::new(&workflow);
+
+        assert!(graph.loop_regions["loop"].end_action_id.is_none());
+        assert_eq!(
+            action_ids(&graph.get_scope_actions(&workflow.actions, None)),
+            vec!["loop", "trigger"]
+        );
+        assert_eq!(
+            action_ids(&graph.get_scope_actions(&workflow.actions, Some("loop"))),
+            vec!["a", "b"]
+        );
+    }
+
+    #[test]
+    fn test_loop_settings_batches_items() {
+        use crate::system_plugins::loop_plugin::LoopSettings;
+
+        let settings = LoopSettings::from_result(&Some(serde_json::json!({
+            "items": [1, 2, 3, 4, 5],
+            "concurrency": "3",
+            "batch_size": 2
+        })))
+        .unwrap();
+
+        assert_eq!(settings.concurrency, 3);
+        assert_eq!(
+            settings.iteration_items(),
+            vec![
+                serde_json::json!([1, 2]),
+                serde_json::json!([3, 4]),
+                serde_json::json!([5])
+            ]
+        );
+    }
+
+    #[test]
+    fn test_retry_policy_defaults_and_backoff() {
+        use crate::types::action_types::RetryPolicy;
+        use std::time::Duration;
+
+        let policy: RetryPolicy = serde_json::from_value(serde_json::json!({
+            "initial_delay_ms": 500,
+            "max_delay_ms": 3000
+        }))
+        .unwrap();
+        let policy = policy.normalized();
+
+        assert_eq!(policy.max_attempts, 3);
+        assert_eq!(policy.delay_after_attempt(1), Duration::from_millis(500));
+        assert_eq!(policy.delay_after_attempt(2), Duration::from_millis(1000));
+        assert_eq!(policy.delay_after_attempt(4), Duration::from_millis(3000));
+        assert!(policy.retries_error_type("execution_timeout"));
+        assert!(!policy.retries_status_code(503));
+    }
+
+    #[test]
+    fn test_retry_policy_only_retries_listed_errors() {
+        use crate::types::action_types::RetryPolicy;
+
+        let policy: RetryPolicy = serde_json::from_value(serde_json::json!({
+            "max_attempts": 100,
+            "retry_on": ["execution_timeout", "http_5xx"]
+        }))
+        .unwrap();
+        let policy = policy.normalized();
+
+        assert_eq!(policy.max_attempts, 10);
+        assert!(policy.retries_error_type("execution_timeout"));
+        assert!(!policy.retries_error_type("plugin_execution_error"));
+        assert!(policy.retries_status_code(502));
+        assert!(!policy.retries_status_code(404));
+    }
 }
@@ -1,15 +1,30 @@
 use crate::actor_processor::actor_pool::TaskActorPool;
+use crate::actor_processor::cancellation::{
+    is_canceled_task_error, notify_session_canceled, CancelSignal, SessionCanceled,
+};
 use crate::actor_processor::dependency_resolver::DependencyGraph;
 use crate::actor_processor::messages::ActorMessage;
+use crate::actor_processor::status_updates::send_status_update;
 use crate::metrics::METRICS;
 use crate::processor::components::{EnhancedSpanFactory, ProcessorError, WorkflowExecutionContext};
 use crate::processor::execute_task::TaskResult;
 use crate::processor::processor::ProcessorMessage;
-use crate::types::task_types::Task;
+use crate::processor::task_waits::{parks_session, SessionParked};
+use crate::status_updater::Operation;
+use crate::system_plugins::call_workflow::finish_workflow_call;
+use crate::system_plugins::loop_plugin::LoopSettings;
+use crate::types::action_types::{Action, ActionType};
+use crate::types::task_types::{
+    FlowSessionStatus, LoopContext, Task, TaskStatus, TriggerSessionStatus,
+};
 use crate::AppState;
 
+use futures::future::BoxFuture;
+use futures::stream::{self, StreamExt};
+
 use opentelemetry::KeyValue;
 use postgrest::Postgrest;
+use serde_json::Value;
 use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 use std::time::Instant;
@@ -46,26 +61,61 @@ impl WorkflowActor {
         }
     }
 
-    pub async fn run(mut self, mut receiver: mpsc::Receiver<ActorMessage>) {
+    pub async fn run(self, mut receiver: mpsc::Receiver<ActorMessage>) {
         info!("[WORKFLOW_ACTOR_{}] Starting workflow actor", self.id);
 
+        let actor = Arc::new(self);
+
         while let Some(message) = receiver.recv().await {
             match message {
                 ActorMessage::ExecuteWorkflow {
                     message,
                     respond_to,
                 } => {
-                    let result = self.handle_execute_workflow(message).await;
-                    let _ = respond_to.send(result);
+                    // Registered before the permit so a session waiting for one can already be canceled
+                    let (cancel_sender, cancel) = CancelSignal::new();
+                    actor
+                        .state
+                        .session_cancels
+                        .insert(message.flow_session_id.to_string(), cancel_sender);
+
+                    // Workflows run side by side, so one waiting on a workflow it called
+                    // can't hold up the call. The session permits cap how many run at once,
+                    // and are taken in the spawned task so waiting for one never blocks this loop.
+                    let actor = Arc::clone(&actor);
+                    tokio::spawn(async move {
+                        let flow_session_id = message.flow_session_id;
+                        let result =
+                            match actor.state.session_permits.acquire(flow_session_id).await {
+                                Ok(()) => {
+                                    let result =
+                                        actor.handle_execute_workflow(message, cancel).await;
+                                    actor.state.session_permits.release(&flow_session_id);
+                                    result
+                                }
+                                Err(e) => {
+                                    error!(
+                                        "[WORKFLOW_ACTOR_{}] Workflow semaphore closed: {}",
+                                        actor.id, e
+                                    );
+                                    Err(ProcessorError::SemaphoreError(e.to_string()))
+                                }
+                            };
+                        actor
+                            .state
+                            .session_cancels
+                            .remove(&flow_session_id.to_string());
+                        let _ = respond_to.send(result);
+                    });
                 }
                 ActorMessage::Shutdown => {
-                    info!("[WORKFLOW_ACTOR_{}] Shutting down workflow actor", self.id);
+                    info!("[WORKFLOW_ACTOR_{}] Shutting down workflow actor", actor.id);
                     break;
                 }
                 _ => {
                     warn!(
                         "[WORKFLOW_ACTOR_{}] Received unexpected message type",
-                        self.id
+                        actor.id
                     );
                 }
             }
@@ -73,11 +123,11 @@ impl WorkflowActor {
 
         info!(
             "[WORKFLOW_ACTOR_{}] Workflow actor shutdown complete",
-            self.id
+            actor.id
         );
     }
 
-    #[instrument(skip(self, message), fields(
+    #[instrument(skip(self, message, cancel), fields(
         actor_id = %self.id,
         flow_session_id = %message.flow_session_id,
         workflow_id = %message.workflow_id
@@ -85,6 +135,7 @@ impl WorkflowActor {
     async fn handle_execute_workflow(
         &self,
         message: ProcessorMessage,
+        cancel: CancelSignal,
     ) -> Result<(), ProcessorError> {
         let workflow_span = self.span_factory.create_workflow_execution_span(
             message.flow_session_id,
@@ -116,11 +167,67 @@ impl WorkflowActor {
         context.record_stage("processing_workflow");
 
         // Process the workflow using actor-based task execution
-        let result = self.process_workflow_with_actors(message, &context).await;
+        let result = self
+            .process_workflow_with_actors(message, &context, &cancel)
+            .await;
 
         let execution_duration = start_time.elapsed();
         METRICS.record_workflow_completed(execution_duration, &self.metrics_labels);
 
+        // A parked session is continued by a later message once its waiting task is resumed
+        let parked = matches!(&result, Err(e) if e.is::<SessionParked>());
+        let canceled = matches!(&result, Err(e) if e.is::<SessionCanceled>());
+
+        let (flow_session_status, trigger_session_status) = if parked {
+            (FlowSessionStatus::Waiting, TriggerSessionStatus::Waiting)
+        } else if canceled {
+            (FlowSessionStatus::Canceled, TriggerSessionStatus::Canceled)
+        } else if result.is_ok() {
+            (
+                FlowSessionStatus::Completed,
+                TriggerSessionStatus::Completed,
+            )
+        } else {
+            (FlowSessionStatus::Failed, TriggerSessionStatus::Failed)
+        };
+
+        let _ = send_status_update(
+            &self.state,
+            Operation::CompleteWorkflow {
+                flow_session_id: context.flow_session_id,
+                status: flow_session_status,
+                trigger_status: trigger_session_status,
+            },
+        )
+        .await;
+
+        if parked {
+            info!(
+                "[WORKFLOW_ACTOR_{}] Workflow {} is waiting for a task to be resumed",
+                self.id, context.flow_session_id
+            );
+            return Ok(());
+        }
+
+        if canceled {
+            info!(
+                "[WORKFLOW_ACTOR_{}] Workflow {} was canceled after {:?}",
+                self.id, context.flow_session_id, execution_duration
+            );
+            notify_session_canceled(&self.state, &context.flow_session_id);
+            return Ok(());
+        }
+
+        // A called workflow that never reached an Output returns null, or its error, to its caller
+        finish_workflow_call(
+            &self.state,
+            &context.flow_session_id,
+            match &result {
+                Ok(_) => Ok(Value::Null),
+                Err(e) => Err(e.to_string()),
+            },
+        );
+
         match result {
             Ok(_) => {
                 info!(
@@ -145,6 +252,7 @@ impl WorkflowActor {
         &self,
         message: ProcessorMessage,
         context: &WorkflowExecutionContext,
+        cancel: &CancelSignal,
     ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
         // Extract actions from the workflow definition
         let actions = &message.workflow_definition.actions;
@@ -164,40 +272,223 @@ impl WorkflowActor {
         // Track completed tasks (with their results for bundling)
         let completed_tasks = Arc::new(RwLock::new(HashMap::<Uuid, Task>::new()));
 
+        // Actions inside loop bodies are run by their loop node, once per item
+        let scope_actions = dependency_graph.get_scope_actions(actions, None);
+
+        self.execute_scope(
+            &scope_actions,
+            &dependency_graph,
+            &message,
+            context,
+            completed_tasks,
+            None,
+            cancel,
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    /// Runs a set of actions in dependency order until all of them completed or were skipped.
+    /// This is the whole workflow for the top level scope, or one iteration of a loop body.
+    /// Returns the actions skipped by filters.
+    #[allow(clippy::too_many_arguments)]
+    async fn execute_scope(
+        &self,
+        actions: &[Action],
+        dependency_graph: &DependencyGraph,
+        message: &ProcessorMessage,
+        context: &WorkflowExecutionContext,
+        completed_tasks: Arc<RwLock<HashMap<Uuid, Task>>>,
+        loop_context: Option<LoopContext>,
+        cancel: &CancelSignal,
+    ) -> Result<HashSet<String>, Box<dyn std::error::Error + Send + Sync>> {
         // Track currently running tasks
         let running_tasks = Arc::new(RwLock::new(HashSet::<String>::new()));
 
+        // Track edges that will never be taken (filters that returned false, error branches of
+        // tasks that succeeded, success branches of tasks that failed) and the actions pruned behind them
+        let mut dead_edges = HashSet::<(String, String)>::new();
+        let mut skipped_actions = HashSet::<String>::new();
+
+        // Pending tasks stored before a restart, run again under their original task id
+        let mut resumed_pending_tasks = HashMap::<String, Task>::new();
+
+        // Tasks that parked the session, nothing behind them runs until they are resumed
+        let mut waiting_actions = HashSet::<String>::new();
+
+        for mut task in Self::existing_scope_tasks(actions, message, &loop_context) {
+            let action_id = task.action_id.clone();
+            match task.task_status {
+                TaskStatus::Waiting if parks_session(task.plugin_name.as_ref()) => {
+                    waiting_actions.insert(action_id);
+                    continue;
+                }
+                TaskStatus::Pending | TaskStatus::Waiting => {
+                    resumed_pending_tasks.insert(action_id, task);
+                    continue;
+                }
+                TaskStatus::Canceled => {
+                    skipped_actions.insert(action_id);
+                    continue;
+                }
+                TaskStatus::Running => {
+                    // It may have had side effects before the server stopped, so it is failed rather than run twice
+                    warn!(
+                        "[WORKFLOW_ACTOR_{}] Task {} (action {}) was interrupted by a restart, marking it failed",
+                        self.id, task.task_id, action_id
+                    );
+                    let error = serde_json::json!({
+                        "message": "Task was interrupted by a server restart",
+                        "error_type": "interrupted"
+                    });
+                    send_status_update(
+                        &self.state,
+                        Operation::UpdateTask {
+                            task_id: task.task_id,
+                            started_at: None,
+                            ended_at: Some(chrono::Utc::now()),
+                            status: TaskStatus::Failed,
+                            result: None,
+                            context: None,
+                            error: Some(error.clone()),
+                        },
+                    )
+                    .await?;
+                    task.task_status = TaskStatus::Failed;
+                    task.error = Some(error);
+                }
+                TaskStatus::Completed | TaskStatus::Failed => {}
+            }
+
+            let failed = task.task_status == TaskStatus::Failed;
+            if failed && !dependency_graph.has_error_handler(&action_id) {
+                return Err(format!(
+                    "Task {} failed before the restart and has no error branch",
+                    task.task_id
+                )
+                .into());
+            }
+
+            info!(
+                "[WORKFLOW_ACTOR_{}] Resuming with stored task {} (action {}) as {}",
+                self.id,
+                task.task_id,
+                action_id,
+                task.task_status.as_str()
+            );
+
+            let halts_branch =
+                !failed && Self::filter_halts_branch(actions, &action_id, &task.result);
+            let unfinished_loop = !failed
+                && dependency_graph
+                    .loop_regions
+                    .get(&action_id)
+                    .map(|region| match &region.end_action_id {
+                        Some(end_action_id) => {
+                            !Self::scope_has_finished_action(message, &loop_context, end_action_id)
+                        }
+                        None => true,
+                    })
+                    .unwrap_or(false);
+
+            {
+                let mut completed = completed_tasks.write().await;
+                completed.insert(task.task_id, task.clone());
+            }
+
+            // Finish the iterations of a loop the restart interrupted, the ones already done are not re-run
+            if unfinished_loop {
+                if let Some(loop_end_task) = self
+                    .execute_loop(
+                        &task,
+                        dependency_graph,
+                        message,
+                        context,
+                        Arc::clone(&completed_tasks),
+                        cancel,
+                    )
+                    .await?
+                {
+                    let mut completed = completed_tasks.write().await;
+                    completed.insert(loop_end_task.task_id, loop_end_task);
+                }
+            }
+
+            self.prune_untaken_branches(
+                dependency_graph,
+                &action_id,
+                dependency_graph.get_untaken_edges(&action_id, failed, halts_branch),
+                &mut dead_edges,
+                &mut skipped_actions,
+                message,
+                &loop_context,
+            )
+            .await?;
+        }
+
         // Process tasks in dependency order
         loop {
+            // Nothing new starts once the session is canceled
+            if cancel.is_canceled() {
+                info!(
+                    "[WORKFLOW_ACTOR_{}] Flow session {} was canceled, stopping",
+                    self.id, context.flow_session_id
+                );
+                return Err(Box::new(SessionCanceled));
+            }
+
             // Get ready actions that can be executed now
             let ready_actions = {
                 let completed = completed_tasks.read().await;
                 let running = running_tasks.read().await;
-                dependency_graph.get_ready_actions(actions, &completed, &running)
+                dependency_graph
+                    .get_ready_actions(actions, &completed, &running, &skipped_actions)
+                    .into_iter()
+                    .filter(|action| !waiting_actions.contains(&action.action_id))
+                    .collect::<Vec<_>>()
             };
 
             if ready_actions.is_empty() {
-                // Check if all tasks are completed
+                // Check if all tasks in this scope are completed or skipped
                 let completed = completed_tasks.read().await;
-                let total_completed = completed.len();
+                let total_completed = actions
+                    .iter()
+                    .filter(|action| {
+                        completed
+                            .values()
+                            .any(|task| task.action_id == action.action_id)
+                    })
+                    .count();
+                let total_skipped = actions
+                    .iter()
+                    .filter(|action| skipped_actions.contains(&action.action_id))
+                    .count();
 
-                if total_completed == actions.len() {
+                if total_completed + total_skipped == actions.len() {
                     info!(
-                        "[WORKFLOW_ACTOR_{}] All {} tasks completed successfully",
-                        self.id, total_completed
+                        "[WORKFLOW_ACTOR_{}] All tasks finished: {} completed, {} skipped by filters",
+                        self.id, total_completed, total_skipped
                     );
                     break;
                 } else {
                     // Check if we have any running tasks
                     let running = running_tasks.read().await;
-                    if running.is_empty() {
+                    if running.is_empty() && !waiting_actions.is_empty() {
+                        info!(
+                            "[WORKFLOW_ACTOR_{}] Parking flow session, waiting on actions {:?}",
+                            self.id, waiting_actions
+                        );
+                        return Err(Box::new(SessionParked));
+                    } else if running.is_empty() {
                         // No ready actions and no running tasks - this indicates a problem
                         let remaining_actions: Vec<String> = actions
                             .iter()
                             .filter(|action| {
-                                !completed
-                                    .values()
-                                    .any(|task| task.action_id == action.action_id)
+                                !skipped_actions.contains(&action.action_id)
+                                    && !completed
+                                        .values()
+                                        .any(|task| task.action_id == action.action_id)
                             })
                             .map(|action| action.action_id.clone())
                             .collect();
@@ -231,13 +522,26 @@ impl WorkflowActor {
                     running.insert(action.action_id.clone());
                 }
 
-                // Convert action to task
-                let task = self
-                    .convert_action_to_task(&action, &message, 0) // processing_order not used in dependency-based execution
-                    .await?;
+                // Convert action to task, unless a resumed session already stored it
+                let task = match resumed_pending_tasks.remove(&action.action_id) {
+                    Some(task) => task,
+                    None => {
+                        let mut task = self
+                            .convert_action_to_task(&action, message, 0) // processing_order not used in dependency-based execution
+                            .await?;
+                        task.loop_context = loop_context.clone();
 
-                // 📝 TASK CREATION - Would normally create task in database
-                info!("📝 TASK CREATION: Creating task {} for action {} (skipping database creation for debugging)", task.task_id, action.action_id);
+                        send_status_update(
+                            &self.state,
+                            Operation::CreateTask {
+                                task_id: task.task_id,
+                                input: task.clone(),
+                            },
+                        )
+                        .await?;
+                        task
+                    }
+                };
 
                 info!(
                     "[WORKFLOW_ACTOR_{}] Created and executing task {} for action {}",
@@ -257,7 +561,10 @@ impl WorkflowActor {
                 let running_tasks_clone = Arc::clone(&running_tasks);
                 let action_id = action.action_id.clone();
                 let task_id = task.task_id;
+                let pending_task = task.clone();
+                let retry_policy = action.retry.clone();
                 let task_actor_pool = self.task_actor_pool.clone();
+                let task_cancel = cancel.clone();
 
                 let task_future = tokio::spawn(async move {
                     // Get in-memory tasks for bundling
@@ -268,7 +575,13 @@ impl WorkflowActor {
 
                     // Execute task with bundled context from previous tasks
                     let result = task_actor_pool
-                        .execute_task(task, task_context, Some(&in_memory_tasks))
+                        .execute_task(
+                            task,
+                            task_context,
+                            Some(&in_memory_tasks),
+                            retry_policy,
+                            task_cancel,
+                        )
                         .await;
 
                     // Remove from running tasks
@@ -277,7 +590,7 @@ impl WorkflowActor {
                         running.remove(&action_id);
                     }
 
-                    (task_id, action_id, result)
+                    (task_id, action_id, pending_task, result)
                 });
 
                 task_futures.push(task_future);
@@ -286,77 +599,167 @@ impl WorkflowActor {
             // Wait for this batch of tasks to complete
             for task_future in task_futures {
                 match task_future.await {
-                    Ok((task_id, action_id, result)) => {
+                    Ok((task_id, action_id, pending_task, result)) => {
                         match result {
-                            Ok(task_result) => {
+                            Ok(Ok((result_value, _, _, _)))
+                                if parks_session(pending_task.plugin_name.as_ref()) =>
+                            {
+                                // The task actor stored it as waiting, its branch continues once it is resumed
+                                info!(
+                                    "[WORKFLOW_ACTOR_{}] Task {} (action {}) is waiting: {:?}",
+                                    self.id, task_id, action_id, result_value
+                                );
+                                waiting_actions.insert(action_id);
+                            }
+                            Ok(Ok((result_value, context_value, started_at, ended_at))) => {
                                 info!(
                                     "[WORKFLOW_ACTOR_{}] Task {} (action {}) completed successfully",
                                     self.id, task_id, action_id
                                 );
 
-                                // Store completed task with its result for future bundling
-                                // Create a minimal task for in-memory storage
-                                //TODO: this seems kinda dangerous since some of this data is false!
-                                let mut completed_task = Task {
-                                    task_id,
-                                    account_id: Uuid::new_v4(), // Placeholder
-                                    task_status: crate::types::task_types::TaskStatus::Completed,
-                                    flow_id: context.workflow_id,
-                                    flow_version_id: Uuid::new_v4(), // Placeholder
-                                    action_label: "".to_string(),    // Placeholder
-                                    trigger_id: "".to_string(),      // Placeholder
-                                    trigger_session_id: Uuid::new_v4(), // Placeholder
-                                    trigger_session_status:
-                                        crate::types::task_types::TriggerSessionStatus::Completed,
-                                    flow_session_id: context.flow_session_id,
-                                    flow_session_status:
-                                        crate::types::task_types::FlowSessionStatus::Running,
-                                    action_id: action_id.clone(),
-                                    r#type: crate::types::action_types::ActionType::Action,
-                                    plugin_name: None,
-                                    plugin_version: None,
-                                    stage: crate::types::task_types::Stage::Production,
-                                    test_config: None,
-                                    config: crate::types::task_types::TaskConfig {
-                                        inputs: None,
-                                        inputs_schema: None,
-                                        plugin_config: None,
-                                        plugin_config_schema: None,
-                                    },
-                                    context: None,
-                                    started_at: None,
-                                    ended_at: None,
-                                    debug_result: None,
-                                    result: None,
-                                    error: None,
-                                    archived: false,
-                                    updated_at: None,
-                                    created_at: None,
-                                    updated_by: None,
-                                    created_by: None,
-                                    processing_order: 0,
-                                };
-
-                                // Extract result from TaskResult tuple
-                                if let Ok((result_value, context_value, _, _)) = &task_result {
-                                    completed_task.result = result_value.clone();
-                                    completed_task.context = Some(context_value.clone());
-                                }
+                                // Keep the completed task with its result for bundling later tasks
+                                let mut completed_task = pending_task;
+                                completed_task.task_status = TaskStatus::Completed;
+                                completed_task.result = result_value;
+                                completed_task.context = Some(context_value);
+                                completed_task.started_at = Some(started_at);
+                                completed_task.ended_at = Some(ended_at);
+
+                                let halts_branch = Self::filter_halts_branch(
+                                    actions,
+                                    &action_id,
+                                    &completed_task.result,
+                                );
+
+                                let loop_task = dependency_graph
+                                    .loop_regions
+                                    .contains_key(&action_id)
+                                    .then(|| completed_task.clone());
 
                                 {
                                     let mut completed = completed_tasks.write().await;
                                     completed.insert(task_id, completed_task);
                                 }
+
+                                // Run every iteration of a loop before its downstream actions become ready
+                                if let Some(loop_task) = loop_task {
+                                    let loop_end_task = self
+                                        .execute_loop(
+                                            &loop_task,
+                                            dependency_graph,
+                                            message,
+                                            context,
+                                            Arc::clone(&completed_tasks),
+                                            cancel,
+                                        )
+                                        .await?;
+
+                                    if let Some(loop_end_task) = loop_end_task {
+                                        let mut completed = completed_tasks.write().await;
+                                        completed.insert(loop_end_task.task_id, loop_end_task);
+                                    }
+                                }
+
+                                if halts_branch {
+                                    info!(
+                                        "[WORKFLOW_ACTOR_{}] Filter task {} (action {}) returned false, stopping branch",
+                                        self.id, task_id, action_id
+                                    );
+                                }
+
+                                self.prune_untaken_branches(
+                                    dependency_graph,
+                                    &action_id,
+                                    dependency_graph.get_untaken_edges(
+                                        &action_id,
+                                        false,
+                                        halts_branch,
+                                    ),
+                                    &mut dead_edges,
+                                    &mut skipped_actions,
+                                    message,
+                                    &loop_context,
+                                )
+                                .await?;
                             }
-                            Err(e) => {
+                            Ok(Err(task_error)) if is_canceled_task_error(&task_error) => {
+                                // The task actor recorded it as canceled, error branches don't run for it
+                                info!(
+                                    "[WORKFLOW_ACTOR_{}] Task {} (action {}) was canceled",
+                                    self.id, task_id, action_id
+                                );
+                                return Err(Box::new(SessionCanceled));
+                            }
+                            Ok(Err(task_error)) => {
+                                // The task actor already recorded the task as failed
                                 error!(
                                     "[WORKFLOW_ACTOR_{}] Task {} (action {}) failed: {:?}",
+                                    self.id, task_id, action_id, task_error.error
+                                );
+
+                                if !dependency_graph.has_error_handler(&action_id) {
+                                    return Err(format!(
+                                        "Task {} failed: {}",
+                                        task_id,
+                                        task_error
+                                            .error
+                                            .get("message")
+                                            .and_then(|v| v.as_str())
+                                            .unwrap_or("Unknown error")
+                                    )
+                                    .into());
+                                }
+
+                                info!(
+                                    "[WORKFLOW_ACTOR_{}] Action {} failed, continuing on its error branch",
+                                    self.id, action_id
+                                );
+
+                                // The failed task stays in the bundling context so the error branch can use {{actions.<id>.error}}
+                                let mut failed_task = pending_task;
+                                failed_task.task_status = TaskStatus::Failed;
+                                failed_task.error = Some(task_error.error);
+                                failed_task.context = Some(task_error.context);
+                                failed_task.ended_at = Some(chrono::Utc::now());
+
+                                {
+                                    let mut completed = completed_tasks.write().await;
+                                    completed.insert(task_id, failed_task);
+                                }
+
+                                self.prune_untaken_branches(
+                                    dependency_graph,
+                                    &action_id,
+                                    dependency_graph.get_untaken_edges(&action_id, true, false),
+                                    &mut dead_edges,
+                                    &mut skipped_actions,
+                                    message,
+                                    &loop_context,
+                                )
+                                .await?;
+                            }
+                            Err(e) => {
+                                error!(
+                                    "[WORKFLOW_ACTOR_{}] Task {} (action {}) could not be executed: {:?}",
                                     self.id, task_id, action_id, e
                                 );
 
-                                // 💥 WORKFLOW FAILURE - Would normally send workflow failure status to database
-                                info!("💥 WORKFLOW FAILURE: Workflow {} failed due to task {} failure (skipping database update for debugging)", context.flow_session_id, task_id);
-                                //TODO: we should probably send a failure status update for the task as well
+                                let _ = send_status_update(
+                                    &self.state,
+                                    Operation::UpdateTask {
+                                        task_id,
+                                        started_at: None,
+                                        ended_at: Some(chrono::Utc::now()),
+                                        status: TaskStatus::Failed,
+                                        result: None,
+                                        context: None,
+                                        error: Some(serde_json::json!({
+                                            "message": e.to_string(),
+                                            "error_type": "actor_error"
+                                        })),
+                                    },
+                                )
+                                .await;
 
                                 return Err(format!("Task {} failed: {:?}", task_id, e).into());
                             }
@@ -373,21 +776,318 @@ impl WorkflowActor {
             }
         }
 
-        // 🎉 WORKFLOW COMPLETED - Would normally send workflow completion status to database
-        info!("🎉 WORKFLOW COMPLETED: Workflow {} finished successfully with all tasks completed (skipping database update for debugging)", context.flow_session_id);
+        Ok(skipped_actions)
+    }
+
+    /// Runs the body of a loop once per item and builds the matching loop end task,
+    /// whose result is the array of per-iteration results.
+    fn execute_loop<'a>(
+        &'a self,
+        loop_task: &'a Task,
+        dependency_graph: &'a DependencyGraph,
+        message: &'a ProcessorMessage,
+        context: &'a WorkflowExecutionContext,
+        completed_tasks: Arc<RwLock<HashMap<Uuid, Task>>>,
+        cancel: &'a CancelSignal,
+    ) -> BoxFuture<'a, Result<Option<Task>, Box<dyn std::error::Error + Send + Sync>>> {
+        Box::pin(async move {
+            let loop_action_id = loop_task.action_id.clone();
+            let region = dependency_graph
+                .loop_regions
+                .get(&loop_action_id)
+                .ok_or_else(|| format!("No loop region found for action {}", loop_action_id))?;
+
+            let settings = LoopSettings::from_result(&loop_task.result)
+                .map_err(|e| format!("Loop {} failed: {}", loop_action_id, e))?;
+            let iteration_items = settings.iteration_items();
+            let total = iteration_items.len();
+
+            let body_actions = dependency_graph
+                .get_scope_actions(&message.workflow_definition.actions, Some(&loop_action_id));
+
+            info!(
+                "[WORKFLOW_ACTOR_{}] Loop {} running {} iterations over {} body actions (concurrency: {})",
+                self.id,
+                loop_action_id,
+                total,
+                body_actions.len(),
+                settings.concurrency
+            );
+
+            // Each iteration sees the tasks completed before the loop plus its own body tasks
+            let outer_tasks = completed_tasks.read().await.clone();
+
+            let iteration_results: Vec<_> = stream::iter(iteration_items.into_iter().enumerate())
+                .map(|(index, item)| {
+                    let iteration_tasks = Arc::new(RwLock::new(outer_tasks.clone()));
+                    let loop_context = LoopContext {
+                        loop_action_id: loop_action_id.clone(),
+                        item,
+                        index,
+                        total,
+                    };
+                    let body_actions = &body_actions;
+
+                    async move {
+                        let skipped = self
+                            .execute_scope(
+                                body_actions,
+                                dependency_graph,
+                                message,
+                                context,
+                                Arc::clone(&iteration_tasks),
+                                Some(loop_context),
+                                cancel,
+                            )
+                            .await?;
+                        let tasks = iteration_tasks.read().await.clone();
+                        Ok::<_, Box<dyn std::error::Error + Send + Sync>>((tasks, skipped))
+                    }
+                })
+                .buffered(settings.concurrency)
+                .collect()
+                .await;
+
+            let end_action_id = match &region.end_action_id {
+                Some(end_action_id) => end_action_id,
+                None => {
+                    // Without a loop end the iterations are fire and forget
+                    for result in iteration_results {
+                        result?;
+                    }
+                    return Ok(None);
+                }
+            };
+
+            let collected_from = dependency_graph.get_dependencies(end_action_id);
+            let mut results = Vec::with_capacity(total);
+
+            for iteration_result in iteration_results {
+                let (tasks, skipped) = iteration_result?;
+
+                let mut iteration_output = serde_json::Map::new();
+                for action_id in &collected_from {
+                    if skipped.contains(action_id) {
+                        continue;
+                    }
+                    if let Some(task) = tasks.values().find(|task| &task.action_id == action_id) {
+                        iteration_output.insert(
+                            action_id.clone(),
+                            task.result.clone().unwrap_or(serde_json::Value::Null),
+                        );
+                    }
+                }
+
+                // Iterations whose branch was stopped by a filter are left out of the results
+                if iteration_output.is_empty() {
+                    continue;
+                }
+
+                if collected_from.len() == 1 {
+                    results.extend(iteration_output.into_iter().map(|(_, result)| result));
+                } else {
+                    results.push(serde_json::Value::Object(iteration_output));
+                }
+            }
+
+            info!(
+                "[WORKFLOW_ACTOR_{}] Loop {} collected {} results into {}",
+                self.id,
+                loop_action_id,
+                results.len(),
+                end_action_id
+            );
+
+            let end_action = message
+                .workflow_definition
+                .actions
+                .iter()
+                .find(|action| &action.action_id == end_action_id)
+                .ok_or_else(|| format!("Loop end action {} not found", end_action_id))?;
+
+            let mut loop_end_task = self.convert_action_to_task(end_action, message, 0).await?;
+            loop_end_task.task_status = TaskStatus::Completed;
+            loop_end_task.result = Some(serde_json::Value::Array(results));
+            loop_end_task.started_at = loop_task.ended_at;
+            loop_end_task.ended_at = Some(chrono::Utc::now());
+            loop_end_task.loop_context = loop_task.loop_context.clone();
+
+            send_status_update(
+                &self.state,
+                Operation::CreateTask {
+                    task_id: loop_end_task.task_id,
+                    input: loop_end_task.clone(),
+                },
+            )
+            .await?;
+
+            Ok(Some(loop_end_task))
+        })
+    }
+
+    /// Marks edges as dead and records every action that can no longer run because of them
+    #[allow(clippy::too_many_arguments)]
+    async fn prune_untaken_branches(
+        &self,
+        dependency_graph: &DependencyGraph,
+        source_action_id: &str,
+        untaken_edges: Vec<(String, String)>,
+        dead_edges: &mut HashSet<(String, String)>,
+        skipped_actions: &mut HashSet<String>,
+        message: &ProcessorMessage,
+        loop_context: &Option<LoopContext>,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        if untaken_edges.is_empty() {
+            return Ok(());
+        }
+        dead_edges.extend(untaken_edges);
+
+        let pruned =
+            dependency_graph.prune_dead_branches(source_action_id, dead_edges, skipped_actions);
+
+        for pruned_action_id in pruned {
+            info!(
+                "[WORKFLOW_ACTOR_{}] Action {} skipped because its branch from {} was not taken",
+                self.id, pruned_action_id, source_action_id
+            );
+            self.record_skipped_action(&pruned_action_id, message, loop_context)
+                .await?;
+        }
 
         Ok(())
     }
 
+    /// Records an action pruned behind a filter or an untaken error branch as a canceled task so the session shows why it never ran
+    async fn record_skipped_action(
+        &self,
+        action_id: &str,
+        message: &ProcessorMessage,
+        loop_context: &Option<LoopContext>,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let action = match message
+            .workflow_definition
+            .actions
+            .iter()
+            .find(|action| action.action_id == action_id)
+        {
+            Some(action) => action,
+            None => return Ok(()),
+        };
+
+        let mut task = self.convert_action_to_task(action, message, 0).await?;
+        task.task_status = TaskStatus::Canceled;
+        task.ended_at = Some(chrono::Utc::now());
+        task.loop_context = loop_context.clone();
+
+        send_status_update(
+            &self.state,
+            Operation::CreateTask {
+                task_id: task.task_id,
+                input: task,
+            },
+        )
+        .await
+    }
+
+    /// Gets the tasks a resumed session already stored for these actions in this scope,
+    /// finished ones first so they are seeded before anything is pruned behind them
+    fn existing_scope_tasks(
+        actions: &[Action],
+        message: &ProcessorMessage,
+        loop_context: &Option<LoopContext>,
+    ) -> Vec<Task> {
+        let mut tasks: Vec<Task> = message
+            .existing_tasks
+            .values()
+            .filter(|task| Self::in_scope(task, loop_context))
+            .filter(|task| {
+                actions
+                    .iter()
+                    .any(|action| action.action_id == task.action_id)
+            })
+            .cloned()
+            .collect();
+
+        tasks.sort_by_key(|task| {
+            (
+                !matches!(
+                    task.task_status,
+                    TaskStatus::Completed | TaskStatus::Failed | TaskStatus::Running
+                ),
+                task.created_at,
+            )
+        });
+        tasks
+    }
+
+    /// True when a resumed session already stored a finished task for the action in this scope
+    fn scope_has_finished_action(
+        message: &ProcessorMessage,
+        loop_context: &Option<LoopContext>,
+        action_id: &str,
+    ) -> bool {
+        message.existing_tasks.values().any(|task| {
+            task.action_id == action_id
+                && task.task_status == TaskStatus::Completed
+                && Self::in_scope(task, loop_context)
+        })
+    }
+
+    fn in_scope(task: &Task, loop_context: &Option<LoopContext>) -> bool {
+        match (&task.loop_context, loop_context) {
+            (None, None) => true,
+            (Some(task_loop), Some(scope_loop)) => {
+                task_loop.loop_action_id == scope_loop.loop_action_id
+                    && task_loop.index == scope_loop.index
+            }
+            _ => false,
+        }
+    }
+
+    /// Returns true when the action is an `@anything/filter` whose result asks to stop the branch
+    fn filter_halts_branch(
+        actions: &[Action],
+        action_id: &str,
+        result: &Option<serde_json::Value>,
+    ) -> bool {
+        let is_filter = actions
+            .iter()
+            .find(|action| action.action_id == action_id)
+            .map(|action| action.plugin_name.as_str() == "@anything/filter")
+            .unwrap_or(false);
+
+        is_filter
+            && result
+                .as_ref()
+                .and_then(|value| value.get("should_continue"))
+                .and_then(|should_continue| should_continue.as_bool())
+                == Some(false)
+    }
+
     async fn convert_action_to_task(
         &self,
-        action: &crate::types::action_types::Action,
+        action: &Action,
         message: &ProcessorMessage,
         processing_order: i32,
     ) -> Result<Task, Box<dyn std::error::Error + Send + Sync>> {
         use crate::types::task_types::{Stage, TaskConfig};
 
-        let task = Task::builder()
+        // The trigger task was built by the trigger with its result, and its id is the trigger_id of every other task
+        if action.r#type == ActionType::Trigger {
+            if let Some(trigger_task) = message
+                .trigger_task
+                .as_ref()
+                .filter(|trigger_task| trigger_task.action_id == action.action_id)
+            {
+                let mut task = trigger_task.clone();
+                task.task_status = TaskStatus::Pending;
+                task.started_at = None;
+                task.processing_order = processing_order;
+                return Ok(task);
+            }
+        }
+
+        let mut task = Task::builder()
             .account_id(message.workflow_version.account_id)
             .flow_id(message.workflow_id)
             .flow_version_id(message.workflow_version.flow_version_id)
@@ -404,7 +1104,7 @@ impl WorkflowActor {
             .action_id(action.action_id.clone())
             .r#type(action.r#type.clone())
             .plugin_name(action.plugin_name.clone())
-            .plugin_version(action.plugin_version.clone())
+            .plugin_version(self.state.plugin_host.task_plugin_version(action))
             .stage(if message.workflow_version.published {
                 Stage::Production
             } else {
@@ -425,6 +1125,10 @@ impl WorkflowActor {
                 )) as Box<dyn std::error::Error + Send + Sync>
             })?;
 
+        // Tasks are created pending and move to running once a task actor picks them up
+        task.task_status = TaskStatus::Pending;
+        task.started_at = None;
+
         Ok(task)
     }
 }
@@ -1,6 +1,6 @@
 use crate::system_variables::get_system_variables;
 use crate::types::json_schema::JsonSchema;
-use crate::types::task_types::Task;
+use crate::types::task_types::{LoopContext, Task};
 
 use crate::AppState;
 use postgrest::Postgrest;
@@ -87,6 +87,7 @@ pub async fn bundle_tasks_cached_inputs_with_tasks(
         inputs_schema,
         refresh_auth,
         in_memory_tasks,
+        task.loop_context.as_ref(),
     )
     .await?;
 
@@ -142,6 +143,7 @@ pub async fn bundle_context_from_parts_with_tasks(
         inputs_schema,
         refresh_auth,
         in_memory_tasks,
+        None,
     )
     .await?;
 
@@ -170,6 +172,7 @@ pub async fn bundle_cached_inputs(
         inputs_schema,
         refresh_auth,
         None, // No in-memory tasks provided, will fetch from database
+        None,
     )
     .await
 }
@@ -183,6 +186,7 @@ pub async fn bundle_cached_inputs_with_tasks(
     inputs_schema: Option<&JsonSchema>,
     refresh_auth: bool,
     in_memory_tasks: Option<&HashMap<Uuid, Task>>, // Pass in-memory tasks from processor
+    loop_context: Option<&LoopContext>, // Current loop iteration when running inside a loop
 ) -> Result<Value, Box<dyn Error + Send + Sync>> {
     println!("[BUNDLER] Starting to bundle inputs");
 
@@ -240,6 +244,19 @@ pub async fn bundle_cached_inputs_with_tasks(
     }
     render_inputs_context.insert("actions".to_string(), serde_json::to_value(tasks_map)?);
 
+    // Add the current loop iteration so templates can use {{loop.item}} and {{loop.index}}
+    if let Some(loop_context) = loop_context {
+        render_inputs_context.insert(
+            "loop".to_string(),
+            json!({
+                "item": loop_context.item,
+                "index": loop_context.index,
+                "total": loop_context.total,
+                "action_id": loop_context.loop_action_id,
+            }),
+        );
+    }
+
     // Add system variables
     render_inputs_context.insert(
         "system".to_string(),
@@ -274,9 +291,12 @@ async fn fetch_completed_tasks(
     // If we have in-memory tasks (from processor), use those for better performance
     if let Some(tasks_map) = in_memory_tasks {
         println!("[BUNDLER] Using in-memory tasks for bundling context");
+        // Failed tasks are included so error branches can read {{actions.<id>.error}}
         let completed_tasks: Vec<Task> = tasks_map
             .values()
-            .filter(|task| task.task_status == TaskStatus::Completed)
+            .filter(|task| {
+                task.task_status == TaskStatus::Completed || task.task_status == TaskStatus::Failed
+            })
             .cloned()
             .collect();
         return Ok(completed_tasks);
@@ -296,7 +316,10 @@ async fn fetch_completed_tasks(
         .from("tasks")
         .auth(supabase_service_role_api_key)
         .eq("flow_session_id", flow_session_id)
-        .eq("task_status", "Completed")
+        .in_(
+            "task_status",
+            vec![TaskStatus::Completed.as_str(), TaskStatus::Failed.as_str()],
+        )
         .select("*")
         .execute()
         .await
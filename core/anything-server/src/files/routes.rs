@@ -1,4 +1,3 @@
-use aws_sdk_s3::primitives::ByteStream;
 use axum::{
     extract::{Multipart, Path, State},
     http::StatusCode,
@@ -10,9 +9,8 @@ use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
 
 use std::sync::Arc;
-use uuid::Uuid;
 
-use crate::{supabase_jwt_middleware::User, AppState};
+use crate::{files::utils::store_file, supabase_jwt_middleware::User, AppState};
 
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
@@ -45,7 +43,7 @@ pub struct FileResponse {
 }
 
 // Add this helper function at the top with other imports
-fn make_filename_url_safe(filename: &str) -> String {
+pub fn make_filename_url_safe(filename: &str) -> String {
     // Replace spaces and problematic characters with underscores or dashes
     // Remove or encode special characters that could cause URL issues
     filename
@@ -116,134 +114,44 @@ pub async fn upload_file(
     mut multipart: Multipart,
 ) -> impl IntoResponse {
     println!("[FILES] Starting file upload for account: {}", account_id);
-    let r2_client = &state.r2_client;
-    let bucket = std::env::var("R2_BUCKET").expect("R2_BUCKET must be set");
-    let cdn_domain = std::env::var("R2_PUBLIC_DOMAIN").expect("R2_PUBLIC_DOMAIN must be set");
 
     // Check access type from path parameter
-    let is_private = access == "private";
-    println!(
-        "[FILES] File access type: {}",
-        if is_private { "private" } else { "public" }
-    );
+    let access_type = if access == "private" {
+        FileAccessType::Private
+    } else {
+        FileAccessType::Public
+    };
+    println!("[FILES] File access type: {:?}", access_type);
 
-    while let Some(field) = multipart.next_field().await.unwrap() {
+    if let Some(field) = multipart.next_field().await.unwrap() {
         let original_filename = field.file_name().unwrap_or("unnamed").to_string();
-        let safe_filename = make_filename_url_safe(&original_filename);
         let content_type = field
             .content_type()
             .unwrap_or("application/octet-stream")
             .to_string();
         let data = field.bytes().await.unwrap();
 
-        println!(
-            "[FILES] Processing file: {} ({} bytes) - sanitized name: {}",
-            original_filename,
-            data.len(),
-            safe_filename
-        );
-
-        let file_id = Uuid::new_v4().to_string();
-        // Use the safe filename for the storage key
-        let r2_key = format!("{}/{}", account_id, safe_filename);
-
-        let mut put_object = r2_client
-            .put_object()
-            .bucket(&bucket)
-            .key(&r2_key)
-            .body(ByteStream::from(data.clone()))
-            .content_type(content_type.clone());
-
-        // Set ACL based on access type
-        if !is_private {
-            put_object = put_object.acl(aws_sdk_s3::types::ObjectCannedAcl::PublicRead);
-        }
-
-        match put_object.send().await {
-            Ok(_) => {
-                println!("[FILES] Successfully uploaded file to R2: {}", r2_key);
-                let file_metadata = FileMetadata {
-                    file_id: file_id.clone(),
-                    file_name: safe_filename, // Use the safe filename here
-                    file_size: data.len() as i64,
-                    content_type,
-                    account_id: account_id.clone(),
-                    path: Some(r2_key.clone()),
-                    public_url: if !is_private {
-                        Some(format!("{}/{}", cdn_domain, r2_key))
-                    } else {
-                        None
-                    },
-                    access_type: if is_private {
-                        FileAccessType::Private
-                    } else {
-                        FileAccessType::Public
-                    },
-                };
-
-                let client = &state.anything_client;
-                let response = match client
-                    .from("files")
-                    .auth(&user.jwt)
-                    .insert(serde_json::to_string(&file_metadata).unwrap())
-                    .execute()
-                    .await
-                {
-                    Ok(response) => response,
-                    Err(e) => {
-                        println!("[FILES] Failed to store file metadata: {:?}", e);
-                        // Cleanup R2 if database insert fails
-                        let _ = r2_client
-                            .delete_object()
-                            .bucket(&bucket)
-                            .key(&r2_key)
-                            .send()
-                            .await;
-                        return (
-                            StatusCode::INTERNAL_SERVER_ERROR,
-                            "Failed to store file metadata",
-                        )
-                            .into_response();
-                    }
-                };
-
-                // Check the actual response status
-                if response.status().is_success() {
-                    println!("[FILES] Successfully stored file metadata for: {}", file_id);
-                    return Json(json!({
-                        "status": "success",
-                        "file_id": file_id
-                    }))
-                    .into_response();
-                } else {
-                    println!(
-                        "[FILES] Failed to store file metadata. Status: {}, Body: {:?}",
-                        response.status(),
-                        response.text().await.unwrap_or_default()
-                    );
-                    // Cleanup R2 if database insert fails
-                    let _ = r2_client
-                        .delete_object()
-                        .bucket(&bucket)
-                        .key(&r2_key)
-                        .send()
-                        .await;
-                    return (
-                        StatusCode::INTERNAL_SERVER_ERROR,
-                        "Failed to store file metadata",
-                    )
-                        .into_response();
-                }
-            }
+        return match store_file(
+            &state,
+            &user.jwt,
+            &account_id,
+            &original_filename,
+            &content_type,
+            data,
+            access_type,
+        )
+        .await
+        {
+            Ok(file_metadata) => Json(json!({
+                "status": "success",
+                "file_id": file_metadata.file_id
+            }))
+            .into_response(),
             Err(e) => {
-                println!("[FILES] Failed to upload file to R2: {:?}", e);
-                return (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    "Failed to upload file to storage",
-                )
-                    .into_response();
+                println!("[FILES] Failed to store file: {}", e);
+                (StatusCode::INTERNAL_SERVER_ERROR, "Failed to store file").into_response()
             }
-        }
+        };
     }
 
     println!("[FILES] No file provided in request");
@@ -1,6 +1,8 @@
-use crate::files::routes::FileMetadata;
+use crate::files::routes::{make_filename_url_safe, FileAccessType, FileMetadata};
 use crate::templater::utils::FileRequirement;
 use crate::AppState;
+use aws_sdk_s3::primitives::ByteStream;
+use axum::body::Bytes;
 use dotenv::dotenv;
 use postgrest::Postgrest;
 use serde::{Deserialize, Serialize};
@@ -10,6 +12,7 @@ use std::collections::HashMap;
 use std::env;
 use std::error::Error;
 use std::sync::Arc;
+use uuid::Uuid;
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct FileData {
@@ -112,3 +115,100 @@ pub async fn get_files(
 
     Ok(files_data)
 }
+
+// Upload a file to R2 and record its metadata, removing the object again if the insert fails.
+// `auth` is the signed in user's JWT, or the service role key for uploads without a user like webhooks.
+pub async fn store_file(
+    state: &AppState,
+    auth: &str,
+    account_id: &str,
+    file_name: &str,
+    content_type: &str,
+    data: Bytes,
+    access_type: FileAccessType,
+) -> Result<FileMetadata, Box<dyn Error + Send + Sync>> {
+    dotenv().ok();
+    let bucket = env::var("R2_BUCKET").map_err(|_| "R2_BUCKET must be set")?;
+    let cdn_domain = env::var("R2_PUBLIC_DOMAIN").map_err(|_| "R2_PUBLIC_DOMAIN must be set")?;
+
+    let file_id = Uuid::new_v4().to_string();
+    let safe_filename = make_filename_url_safe(file_name);
+    // Keyed by file id so uploads sharing a name don't overwrite each other
+    let r2_key = format!("{}/{}/{}", account_id, file_id, safe_filename);
+    let is_private = matches!(access_type, FileAccessType::Private);
+
+    println!(
+        "[FILES] Storing file: {} ({} bytes) as {}",
+        file_name,
+        data.len(),
+        r2_key
+    );
+
+    let mut put_object = state
+        .r2_client
+        .put_object()
+        .bucket(&bucket)
+        .key(&r2_key)
+        .body(ByteStream::from(data.clone()))
+        .content_type(content_type);
+
+    if !is_private {
+        put_object = put_object.acl(aws_sdk_s3::types::ObjectCannedAcl::PublicRead);
+    }
+
+    put_object
+        .send()
+        .await
+        .map_err(|e| format!("Failed to upload file to storage: {:?}", e))?;
+
+    let file_metadata = FileMetadata {
+        file_id,
+        file_name: safe_filename,
+        file_size: data.len() as i64,
+        content_type: content_type.to_string(),
+        account_id: account_id.to_string(),
+        path: Some(r2_key.clone()),
+        public_url: if !is_private {
+            Some(format!("{}/{}", cdn_domain, r2_key))
+        } else {
+            None
+        },
+        access_type,
+    };
+
+    let response = state
+        .anything_client
+        .from("files")
+        .auth(auth)
+        .insert(serde_json::to_string(&file_metadata)?)
+        .execute()
+        .await;
+
+    let stored = match response {
+        Ok(response) if response.status().is_success() => Ok(()),
+        Ok(response) => Err(format!(
+            "Failed to store file metadata: {}",
+            response.text().await.unwrap_or_default()
+        )),
+        Err(e) => Err(format!("Failed to store file metadata: {}", e)),
+    };
+
+    if let Err(e) = stored {
+        // Cleanup R2 if database insert fails
+        let _ = state
+            .r2_client
+            .delete_object()
+            .bucket(&bucket)
+            .key(&r2_key)
+            .send()
+            .await;
+        return Err(e.into());
+    }
+
+    println!(
+        "[FILES] Successfully stored file metadata for: {}",
+        file_metadata.file_id
+    );
+
+    Ok(file_metadata)
+}
@@ -0,0 +1,237 @@
+use crate::processor::run_queue::INSTANCE_ID;
+use crate::AppState;
+
+use chrono::Utc;
+use dotenv::dotenv;
+use serde::Deserialize;
+use serde_json::json;
+use std::collections::HashSet;
+use std::env;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{error, info, warn};
+use uuid::Uuid;
+
+/// Lease held by the replica that fires cron triggers
+pub const TRIGGER_ENGINE_LEASE: &str = "trigger_engine";
+
+/// Lease held by the replica that polls WASM trigger plugins
+pub const PLUGIN_TRIGGER_LEASE: &str = "plugin_triggers";
+
+/// Prefix of the lease each instance holds on itself while it is alive.
+/// Other instances only take over its runs once that lease has expired.
+pub const INSTANCE_LEASE_PREFIX: &str = "instance:";
+
+/// How long a lease lasts without being renewed. A leader that dies is replaced after at most this long.
+pub const LEASE_TTL: Duration = Duration::from_secs(30);
+
+/// How often the holder renews its lease, well inside the TTL so a slow request doesn't lose it
+pub const LEASE_RENEW_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Takes the named lease for this instance, or renews it if this instance already holds it.
+/// Returns false while another live instance holds it.
+pub async fn acquire_lease(state: &AppState, lease_name: &str) -> Result<bool, String> {
+    dotenv().ok();
+    let supabase_service_role_api_key = env::var("SUPABASE_SERVICE_ROLE_API_KEY")
+        .expect("SUPABASE_SERVICE_ROLE_API_KEY must be set");
+
+    let response = state
+        .anything_client
+        .rpc(
+            "acquire_lease",
+            json!({
+                "p_lease_name": lease_name,
+                "p_holder_id": *INSTANCE_ID,
+                "p_ttl_seconds": LEASE_TTL.as_secs(),
+            })
+            .to_string(),
+        )
+        .auth(supabase_service_role_api_key)
+        .execute()
+        .await
+        .map_err(|e| format!("Failed to execute request: {}", e))?;
+
+    if !response.status().is_success() {
+        let body = response.text().await.unwrap_or_default();
+        return Err(format!("Failed to acquire lease {}: {}", lease_name, body));
+    }
+
+    let body = response
+        .text()
+        .await
+        .map_err(|e| format!("Failed to read response body: {}", e))?;
+
+    serde_json::from_str::<bool>(&body)
+        .map_err(|e| format!("Failed to parse lease response: {}", e))
+}
+
+/// Gives the lease up so another instance can take it without waiting for it to expire
+pub async fn release_lease(state: &AppState, lease_name: &str) {
+    dotenv().ok();
+    let supabase_service_role_api_key = env::var("SUPABASE_SERVICE_ROLE_API_KEY")
+        .expect("SUPABASE_SERVICE_ROLE_API_KEY must be set");
+
+    match state
+        .anything_client
+        .rpc(
+            "release_lease",
+            json!({
+                "p_lease_name": lease_name,
+                "p_holder_id": *INSTANCE_ID,
+            })
+            .to_string(),
+        )
+        .auth(supabase_service_role_api_key)
+        .execute()
+        .await
+    {
+        Ok(_) => info!("[LEASES] Released lease {}", lease_name),
+        Err(e) => error!("[LEASES] Failed to release lease {}: {}", lease_name, e),
+    }
+}
+
+/// Name of the heartbeat lease of a server instance
+pub fn instance_lease_name(instance_id: &Uuid) -> String {
+    format!("{}{}", INSTANCE_LEASE_PREFIX, instance_id)
+}
+
+/// Renews this instance's heartbeat lease until the process exits.
+/// The lease is not released on shutdown, flow sessions still running then are only
+/// taken over once it expires.
+pub async fn instance_heartbeat_loop(state: Arc<AppState>) {
+    let lease_name = instance_lease_name(&INSTANCE_ID);
+    let mut interval = tokio::time::interval(LEASE_RENEW_INTERVAL);
+
+    loop {
+        interval.tick().await;
+
+        match acquire_lease(&state, &lease_name).await {
+            Ok(true) => {}
+            Ok(false) => error!(
+                "[LEASES] Heartbeat lease {} is held by another instance",
+                lease_name
+            ),
+            Err(e) => warn!("[LEASES] Could not renew heartbeat {}: {}", lease_name, e),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct LeaseHolder {
+    holder_id: Uuid,
+}
+
+/// Gets the instances whose heartbeat lease has not expired
+pub async fn get_live_instances(state: &AppState) -> Result<HashSet<Uuid>, String> {
+    dotenv().ok();
+    let supabase_service_role_api_key = env::var("SUPABASE_SERVICE_ROLE_API_KEY")
+        .expect("SUPABASE_SERVICE_ROLE_API_KEY must be set");
+
+    let response = state
+        .anything_client
+        .from("leases")
+        .auth(supabase_service_role_api_key)
+        .select("holder_id")
+        .like("lease_name", format!("{}*", INSTANCE_LEASE_PREFIX))
+        .gt("expires_at", Utc::now().to_rfc3339())
+        .execute()
+        .await
+        .map_err(|e| format!("Failed to execute request: {}", e))?;
+
+    if !response.status().is_success() {
+        let body = response.text().await.unwrap_or_default();
+        return Err(format!("Failed to get live instances: {}", body));
+    }
+
+    let body = response
+        .text()
+        .await
+        .map_err(|e| format!("Failed to read response body: {}", e))?;
+
+    let holders: Vec<LeaseHolder> = serde_json::from_str(&body)
+        .map_err(|e| format!("Failed to parse live instances: {}", e))?;
+
+    Ok(holders.into_iter().map(|holder| holder.holder_id).collect())
+}
+
+/// Tracks whether this instance holds a lease, renewing it at most every `LEASE_RENEW_INTERVAL`
+pub struct Leadership {
+    lease_name: &'static str,
+    is_leader: bool,
+    last_renewed: Option<tokio::time::Instant>,
+}
+
+impl Leadership {
+    pub fn new(lease_name: &'static str) -> Self {
+        Self {
+            lease_name,
+            is_leader: false,
+            last_renewed: None,
+        }
+    }
+
+    /// Renews or tries to take the lease when due and returns whether this instance leads.
+    /// Losing the database counts as losing leadership, since another replica may take over.
+    pub async fn check(&mut self, state: &AppState) -> LeadershipChange {
+        let renew_due = self
+            .last_renewed
+            .map(|last_renewed| last_renewed.elapsed() >= LEASE_RENEW_INTERVAL)
+            .unwrap_or(true);
+
+        if !renew_due {
+            return if self.is_leader {
+                LeadershipChange::Kept
+            } else {
+                LeadershipChange::Follower
+            };
+        }
+
+        let was_leader = self.is_leader;
+        self.is_leader = match acquire_lease(state, self.lease_name).await {
+            Ok(is_leader) => is_leader,
+            Err(e) => {
+                warn!("[LEASES] Could not renew lease {}: {}", self.lease_name, e);
+                false
+            }
+        };
+        self.last_renewed = Some(tokio::time::Instant::now());
+
+        match (was_leader, self.is_leader) {
+            (false, true) => {
+                info!(
+                    "[LEASES] Instance {} became leader for {}",
+                    *INSTANCE_ID, self.lease_name
+                );
+                LeadershipChange::Acquired
+            }
+            (true, false) => {
+                warn!(
+                    "[LEASES] Instance {} lost leadership for {}",
+                    *INSTANCE_ID, self.lease_name
+                );
+                LeadershipChange::Lost
+            }
+            (true, true) => LeadershipChange::Kept,
+            (false, false) => LeadershipChange::Follower,
+        }
+    }
+
+    pub fn is_leader(&self) -> bool {
+        self.is_leader
+    }
+
+    /// How long until the lease should be renewed, or taken over if another instance holds it
+    pub fn time_until_renew(&self) -> Duration {
+        self.last_renewed
+            .map(|last_renewed| LEASE_RENEW_INTERVAL.saturating_sub(last_renewed.elapsed()))
+            .unwrap_or(Duration::ZERO)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LeadershipChange {
+    Acquired, // Just took the lease
+    Kept,     // Still holds the lease
+    Lost,     // Another instance holds the lease now
+    Follower, // Still doesn't hold the lease
+}
@@ -37,6 +37,8 @@ mod processor;
 mod actor_processor;
 mod system_variables;
 mod workflows; 
+mod workflow_analysis;
+mod output_schema;
 mod actions; 
 mod tasks; 
 mod auth;
@@ -57,8 +59,10 @@ mod types;
 mod templater;
 mod testing; 
 mod trigger_engine;
+mod leases;
 mod agents; 
 mod metrics;
+mod plugin_host;
 
 use tokio::sync::oneshot;
 use std::sync::atomic::AtomicBool;
@@ -89,16 +93,20 @@ pub struct AppState {
     r2_client: Arc<S3Client>,
     http_client: Arc<Client>,
     workflow_processor_semaphore: Arc<Semaphore>,
+    session_permits: Arc<actor_processor::session_permits::SessionPermits>, // Workflow permits of the flow sessions running on this server
     auth_states: DashMap<String, AuthState>,
     trigger_engine_signal: watch::Sender<String>,
     processor_sender: mpsc::Sender<ProcessorMessage>,
     task_updater_sender: mpsc::Sender<StatusUpdateMessage>,
     flow_completions: DashMap<String, FlowCompletion>,
+    workflow_calls: DashMap<String, oneshot::Sender<Result<Value, String>>>, // Call Workflow actions waiting on a flow session
+    session_cancels: DashMap<String, watch::Sender<bool>>, // Cancel signals of the flow sessions running on this server
     api_key_cache: DashMap<String, CachedApiKey>,
     account_access_cache: account_auth_middleware::AccountAccessCache,
     bundler_secrets_cache: DashMap<String, SecretsCache>,
     bundler_accounts_cache: DashMap<String, AccountsCache>,
     shutdown_signal: Arc<AtomicBool>,
+    plugin_host: plugin_host::PluginHost, // WASM plugins loaded from PLUGIN_DIR
     // WebSocket infrastructure
     // websocket_connections: DashMap<String, websocket::WebSocketConnection>,
     // workflow_broadcaster: websocket::WorkflowBroadcaster,
@@ -224,22 +232,30 @@ async fn main() {
        .build()
        .expect("Failed to build HTTP client");
 
+    let workflow_processor_semaphore = Arc::new(Semaphore::new(100)); //How many workflows we can run at once
+
     let state = Arc::new(AppState {
         anything_client: anything_client.clone(),
         marketplace_client: marketplace_client.clone(),
         public_client: public_client.clone(),
         r2_client: r2_client.clone(),
         http_client: Arc::new(http_client),
-        workflow_processor_semaphore: Arc::new(Semaphore::new(100)), //How many workflows we can run at once
+        workflow_processor_semaphore: workflow_processor_semaphore.clone(),
+        session_permits: Arc::new(actor_processor::session_permits::SessionPermits::new(
+            workflow_processor_semaphore,
+        )),
         auth_states: DashMap::new(),
         trigger_engine_signal,
         processor_sender: processor_tx,
         flow_completions: DashMap::new(),
+        workflow_calls: DashMap::new(),
+        session_cancels: DashMap::new(),
         api_key_cache: DashMap::new(),
         account_access_cache: account_auth_middleware::AccountAccessCache::new(Duration::from_secs(86400)),
         bundler_secrets_cache: DashMap::new(),
         bundler_accounts_cache: DashMap::new(),
         shutdown_signal: Arc::new(AtomicBool::new(false)),
+        plugin_host: plugin_host::PluginHost::load_from_env(),
         task_updater_sender: task_updater_tx.clone(), // Store the sender in AppState
     });
 
@@ -281,6 +297,14 @@ pub async fn root() -> impl IntoResponse {
     .route("/api/v1/workflow/:workflow_id/start/respond", any(system_plugins::webhook_trigger::run_workflow_and_respond))
     .route("/api/v1/workflow/:workflow_id/version/:workflow_version_id/start", any(system_plugins::webhook_trigger::run_workflow_version))
     .route("/api/v1/workflow/:workflow_id/version/:workflow_version_id/start/respond", any(system_plugins::webhook_trigger::run_workflow_version_and_respond))
+    .route("/api/v1/workflow/:workflow_id/session/:flow_session_id", get(system_plugins::webhook_trigger::session_status::get_workflow_session_status))
+
+    // Callbacks handed to the trigger plugin of a published workflow
+    .route("/api/v1/plugin_trigger/:workflow_id/:action_id/callback", any(plugin_host::triggers::handle_plugin_trigger_callback))
+
+    // Signed resume URLs of tasks waiting for approval
+    .route("/api/v1/waits/:task_id", get(system_plugins::approval::get_wait_with_signature))
+    .route("/api/v1/waits/:task_id", post(system_plugins::approval::resolve_wait_with_signature))
 
     // API routes for running agent tools - very simliar to webhooks just shapped differnt to capture relationshipe between agent and workflow
     .route("/api/v1/agent/:agent_id/tool/:tool_id/start/respond", post(system_plugins::agent_tool_trigger::run_workflow_as_tool_call_and_respond));
@@ -302,6 +326,22 @@ pub async fn root() -> impl IntoResponse {
             "/account/:account_id/workflow/:workflow_id/version/:workflow_version_id/publish",
             put(workflows::publish_workflow_version),
         )
+        .route(
+            "/account/:account_id/workflow/:workflow_id/version/:workflow_version_id/analyze",
+            get(workflow_analysis::analyze_workflow_version),
+        )
+        .route(
+            "/account/:account_id/workflow/:workflow_id/version/:workflow_version_id/plugin_versions",
+            get(plugin_host::versions::get_plugin_versions),
+        )
+        .route(
+            "/account/:account_id/workflow/:workflow_id/version/:workflow_version_id/upgrade_actions",
+            post(plugin_host::versions::upgrade_actions),
+        )
+        .route(
+            "/account/:account_id/workflow/:workflow_id/version/:workflow_version_id/action/:action_id/available_variables",
+            get(variables::get_available_variables),
+        )
         .route("/account/:account_id/workflow", post(workflows::create_workflow))
         .route("/account/:account_id/workflow/json", post(workflows::create_workflow_from_json))
         .route("/account/:account_id/workflow/:id", delete(workflows::delete_workflow))
@@ -330,6 +370,9 @@ pub async fn root() -> impl IntoResponse {
         //Tasks
         .route("/account/:account_id/tasks", get(tasks::get_tasks))
         .route("/account/:account_id/tasks/:workflow_id", get(tasks::get_task_by_workflow_id))
+        .route("/account/:account_id/waits/:task_id/resume", post(system_plugins::approval::resolve_wait))
+        .route("/account/:account_id/session/:flow_session_id/cancel", post(tasks::cancel_flow_session))
+        .route("/account/:account_id/session/:flow_session_id/replay", post(tasks::replay_flow_session))
 
         //Charts
         .route(
@@ -462,10 +505,25 @@ pub async fn root() -> impl IntoResponse {
         }
     });
 
+    // Keep this instance's heartbeat alive so other replicas leave its runs alone
+    tokio::spawn(leases::instance_heartbeat_loop(state.clone()));
+
+    // Resume flow sessions that a stopped server left unfinished
+    tokio::spawn(processor::hydrate_processor::hydrate_processor_loop(state.clone()));
+
+    // Resume waiting tasks whose timeout passed
+    tokio::spawn(processor::task_waits::wait_timeout_loop(state.clone()));
+
+    // Stop sessions of this server that were canceled through another server
+    tokio::spawn(actor_processor::cancellation::cancel_request_loop(state.clone()));
+
+
+    // Spawn cron job loop
+    // Initiates work to be done on schedule tasks
+    tokio::spawn(trigger_engine::cron_job_loop(state.clone()));
 
-    // // Spawn cron job loop
-    // // Initiates work to be done on schedule tasks
-    // tokio::spawn(trigger_engine::cron_job_loop(state.clone()));
+    // Polls trigger plugins of active workflows
+    tokio::spawn(plugin_host::triggers::plugin_trigger_loop(state.clone()));
 
     //Spawn task billing processing loop
     // tokio::spawn(billing::billing_usage_engine::billing_processing_loop(
@@ -487,7 +545,8 @@ pub async fn root() -> impl IntoResponse {
         // Set the shutdown signal
         state_clone.shutdown_signal.store(true, std::sync::atomic::Ordering::SeqCst);
         
-        // Give time for in-flight operations to complete
+        // Give time for in-flight operations to complete.
+        // Flow sessions still running after this are resumed from the run queue on the next start.
         sleep(Duration::from_secs(20)).await;
     });
 
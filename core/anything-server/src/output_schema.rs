@@ -0,0 +1,531 @@
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+use std::collections::HashMap;
+
+use crate::actor_processor::dependency_resolver::DependencyGraph;
+use crate::system_plugins::registry::load_schema_templates;
+use crate::system_variables::get_system_variables;
+use crate::types::action_types::Action;
+use crate::types::workflow_types::WorkflowVersionDefinition;
+use crate::workflow_analysis::AccountReferences;
+
+/// Deepest nesting listed for a result, wide API responses can nest arbitrarily deep
+const MAX_VARIABLE_DEPTH: usize = 6;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OutputSchemaSource {
+    Action,     // Declared on the action in the workflow
+    Plugin,     // Static schema of a system plugin
+    LastResult, // Inferred from the last successful run of the action
+}
+
+/// A variable path an action's inputs can reference, e.g. `actions.http.result.status_code`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AvailableVariable {
+    pub path: String,
+    #[serde(rename = "type")]
+    pub value_type: Option<String>, // None when the schema allows any value
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+}
+
+/// The result of an upstream action and the variables it makes available
+#[derive(Debug, Clone, Serialize)]
+pub struct ActionOutput {
+    pub action_id: String,
+    pub label: String,
+    pub plugin_name: String,
+    pub source: Option<OutputSchemaSource>, // None when nothing is known about the result yet
+    pub output_schema: Option<Value>,
+    pub variables: Vec<AvailableVariable>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AvailableVariables {
+    pub actions: Vec<ActionOutput>,
+    #[serde(rename = "loop")]
+    pub loop_variables: Vec<AvailableVariable>, // Empty outside a loop
+    pub secrets: Vec<AvailableVariable>,
+    pub accounts: Vec<AvailableVariable>,
+    pub files: Vec<AvailableVariable>,
+    pub system: Vec<AvailableVariable>,
+}
+
+/// Static output schemas of the system plugins by plugin name
+pub fn system_output_schemas() -> Result<HashMap<String, Value>, Box<dyn std::error::Error>> {
+    Ok(load_schema_templates()?
+        .into_iter()
+        .filter_map(|template| {
+            let definition = template.get("action_template_definition")?;
+            let plugin_name = definition.get("plugin_name")?.as_str()?.to_string();
+            let output_schema = definition.get("output_schema")?.clone();
+            Some((plugin_name, output_schema))
+        })
+        .collect())
+}
+
+/// Picks the schema describing an action's result, `last_result` is only used when
+/// neither the action nor its plugin declares one
+pub fn resolve_output_schema(
+    action: &Action,
+    plugin_schemas: &HashMap<String, Value>,
+    last_result: Option<&Value>,
+) -> Option<(OutputSchemaSource, Value)> {
+    if let Some(schema) = &action.output_schema {
+        return Some((OutputSchemaSource::Action, schema.clone()));
+    }
+    if let Some(schema) = plugin_schemas.get(action.plugin_name.as_str()) {
+        return Some((OutputSchemaSource::Plugin, schema.clone()));
+    }
+    last_result.map(|result| (OutputSchemaSource::LastResult, infer_schema(result)))
+}
+
+/// Builds a JSON schema describing the shape of a value.
+/// Array items are merged, so optional fields of the items show up as properties.
+pub fn infer_schema(value: &Value) -> Value {
+    match value {
+        Value::Null => json!({ "type": "null" }),
+        Value::Bool(_) => json!({ "type": "boolean" }),
+        Value::Number(n) if n.is_f64() => json!({ "type": "number" }),
+        Value::Number(_) => json!({ "type": "integer" }),
+        Value::String(_) => json!({ "type": "string" }),
+        Value::Array(items) => {
+            let mut schema = json!({ "type": "array" });
+            if let Some(items_schema) = items.iter().map(infer_schema).reduce(merge_schemas) {
+                schema["items"] = items_schema;
+            }
+            schema
+        }
+        Value::Object(map) => {
+            let properties: Map<String, Value> = map
+                .iter()
+                .map(|(key, value)| (key.clone(), infer_schema(value)))
+                .collect();
+            json!({ "type": "object", "properties": properties })
+        }
+    }
+}
+
+/// Combines two inferred schemas, values of different types fall back to any value
+fn merge_schemas(a: Value, b: Value) -> Value {
+    if a == b {
+        return a;
+    }
+    let number_types = ["integer", "number"];
+    match (a["type"].as_str(), b["type"].as_str()) {
+        (Some("object"), Some("object")) => {
+            let mut properties = a["properties"].as_object().cloned().unwrap_or_default();
+            for (key, schema) in b["properties"].as_object().cloned().unwrap_or_default() {
+                let merged = match properties.remove(&key) {
+                    Some(existing) => merge_schemas(existing, schema),
+                    None => schema,
+                };
+                properties.insert(key, merged);
+            }
+            json!({ "type": "object", "properties": properties })
+        }
+        (Some("array"), Some("array")) => match (a.get("items"), b.get("items")) {
+            (Some(a_items), Some(b_items)) => {
+                json!({ "type": "array", "items": merge_schemas(a_items.clone(), b_items.clone()) })
+            }
+            (Some(_), None) => a,
+            _ => b,
+        },
+        (Some(a_type), Some(b_type))
+            if number_types.contains(&a_type) && number_types.contains(&b_type) =>
+        {
+            json!({ "type": "number" })
+        }
+        _ => json!({}),
+    }
+}
+
+/// Lists the variable paths a schema makes available under `path`, including `path` itself
+pub fn schema_variables(path: &str, schema: &Value) -> Vec<AvailableVariable> {
+    let mut variables = Vec::new();
+    collect_variables(path.to_string(), schema, 0, &mut variables);
+    variables
+}
+
+fn collect_variables(
+    path: String,
+    schema: &Value,
+    depth: usize,
+    variables: &mut Vec<AvailableVariable>,
+) {
+    variables.push(AvailableVariable {
+        path: path.clone(),
+        value_type: schema
+            .get("type")
+            .and_then(Value::as_str)
+            .map(str::to_string),
+        description: schema
+            .get("description")
+            .and_then(Value::as_str)
+            .map(str::to_string),
+    });
+
+    if depth >= MAX_VARIABLE_DEPTH {
+        return;
+    }
+
+    if let Some(properties) = schema.get("properties").and_then(Value::as_object) {
+        for (key, property) in properties {
+            collect_variables(child_path(&path, key), property, depth + 1, variables);
+        }
+    }
+
+    if let Some(items) = schema.get("items").filter(|items| items.is_object()) {
+        collect_variables(format!("{}[0]", path), items, depth + 1, variables);
+    }
+}
+
+/// Keys that aren't plain names are quoted so the path parses back to the same key
+fn child_path(path: &str, key: &str) -> String {
+    let plain = !key.is_empty()
+        && key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+    if plain {
+        format!("{}.{}", path, key)
+    } else {
+        format!("{}[{}]", path, Value::String(key.to_string()))
+    }
+}
+
+/// Lists every variable `action_id`'s inputs can reference: the results of the actions
+/// that run before it, the current loop iteration and, with `account_references`, the
+/// account's secrets, connected accounts and files. `None` when the action isn't in the workflow.
+pub fn available_variables(
+    workflow: &WorkflowVersionDefinition,
+    action_id: &str,
+    plugin_schemas: &HashMap<String, Value>,
+    last_results: &HashMap<String, Value>,
+    account_references: Option<&AccountReferences>,
+) -> Option<AvailableVariables> {
+    if !workflow
+        .actions
+        .iter()
+        .any(|action| action.action_id == action_id)
+    {
+        return None;
+    }
+
+    let graph = DependencyGraph::new(workflow);
+    let upstream = graph.get_upstream_actions(action_id);
+
+    let mut schemas = HashMap::new();
+    let actions = graph
+        .execution_order
+        .iter()
+        .filter(|id| upstream.contains(*id))
+        .filter_map(|id| {
+            workflow
+                .actions
+                .iter()
+                .find(|action| &action.action_id == id)
+        })
+        .map(|action| {
+            let resolved =
+                resolve_output_schema(action, plugin_schemas, last_results.get(&action.action_id));
+            let path = format!("actions.{}.result", action.action_id);
+            let variables = match &resolved {
+                Some((_, schema)) => schema_variables(&path, schema),
+                None => schema_variables(&path, &json!({})),
+            };
+            if let Some((_, schema)) = &resolved {
+                schemas.insert(action.action_id.clone(), schema.clone());
+            }
+
+            ActionOutput {
+                action_id: action.action_id.clone(),
+                label: action.label.clone(),
+                plugin_name: action.plugin_name.to_string(),
+                source: resolved.as_ref().map(|(source, _)| *source),
+                output_schema: resolved.map(|(_, schema)| schema),
+                variables,
+            }
+        })
+        .collect();
+
+    // The innermost loop the action runs in, a loop end still sees its loop's last iteration
+    let enclosing_loop = graph
+        .loop_regions
+        .iter()
+        .filter(|(_, region)| {
+            region.body.contains(action_id) || region.end_action_id.as_deref() == Some(action_id)
+        })
+        .min_by_key(|(_, region)| region.body.len())
+        .map(|(loop_id, _)| loop_id);
+
+    let loop_variables = match enclosing_loop {
+        Some(loop_id) => {
+            let item_schema = schemas
+                .get(loop_id)
+                .and_then(|schema| schema.pointer("/properties/items/items"))
+                .cloned()
+                .unwrap_or_else(|| json!({}));
+            let mut variables = schema_variables("loop.item", &item_schema);
+            variables.extend([
+                variable("loop.index", "integer"),
+                variable("loop.total", "integer"),
+                variable("loop.action_id", "string"),
+            ]);
+            variables
+        }
+        None => Vec::new(),
+    };
+
+    let mut system: Vec<AvailableVariable> = get_system_variables()
+        .into_iter()
+        .map(|(name, value)| {
+            let schema = infer_schema(&value);
+            variable(
+                &format!("system.{}", name),
+                schema["type"].as_str().unwrap_or("string"),
+            )
+        })
+        .collect();
+    system.sort_by(|a, b| a.path.cmp(&b.path));
+
+    let (secrets, accounts, files) = match account_references {
+        Some(references) => (
+            sorted(&references.secrets)
+                .map(|name| variable(&format!("secrets.{}", name), "string"))
+                .collect(),
+            sorted(&references.accounts)
+                .map(|slug| variable(&format!("accounts.{}", slug), "object"))
+                .collect(),
+            sorted(&references.files)
+                .flat_map(|file_name| {
+                    [
+                        variable(&format!("files.{}.file_url", file_name), "string"),
+                        variable(&format!("files.{}.file_base64", file_name), "string"),
+                    ]
+                })
+                .collect(),
+        ),
+        None => (Vec::new(), Vec::new(), Vec::new()),
+    };
+
+    Some(AvailableVariables {
+        actions,
+        loop_variables,
+        secrets,
+        accounts,
+        files,
+        system,
+    })
+}
+
+fn variable(path: &str, value_type: &str) -> AvailableVariable {
+    AvailableVariable {
+        path: path.to_string(),
+        value_type: Some(value_type.to_string()),
+        description: None,
+    }
+}
+
+fn sorted(names: &std::collections::HashSet<String>) -> impl Iterator<Item = &String> {
+    let mut names: Vec<&String> = names.iter().collect();
+    names.sort();
+    names.into_iter()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::react_flow_types::Edge;
+
+    fn action(action_id: &str, plugin_name: &str, output_schema: Option<Value>) -> Action {
+        serde_json::from_value(json!({
+            "anything_action_version": "0.1.0",
+            "type": "action",
+            "plugin_name": plugin_name,
+            "plugin_version": "0.1.0",
+            "action_id": action_id,
+            "label": action_id,
+            "icon": "",
+            "plugin_config": {},
+            "plugin_config_schema": {},
+            "output_schema": output_schema
+        }))
+        .unwrap()
+    }
+
+    fn edge(source: &str, target: &str) -> Edge {
+        Edge {
+            id: format!("{}->{}", source, target),
+            source: source.to_string(),
+            source_handle: None,
+            target: target.to_string(),
+            target_handle: None,
+            r#type: "anything".to_string(),
+        }
+    }
+
+    fn paths(variables: &[AvailableVariable]) -> Vec<(&str, Option<&str>)> {
+        variables
+            .iter()
+            .map(|variable| (variable.path.as_str(), variable.value_type.as_deref()))
+            .collect()
+    }
+
+    #[test]
+    fn test_infer_schema() {
+        let schema = infer_schema(&json!({
+            "id": 1,
+            "users": [
+                { "name": "ada", "score": 1 },
+                { "name": "grace", "score": 2.5, "email": "g@example.com" }
+            ],
+            "mixed": [1, "a"]
+        }));
+
+        assert_eq!(
+            schema,
+            json!({
+                "type": "object",
+                "properties": {
+                    "id": { "type": "integer" },
+                    "users": {
+                        "type": "array",
+                        "items": {
+                            "type": "object",
+                            "properties": {
+                                "name": { "type": "string" },
+                                "score": { "type": "number" },
+                                "email": { "type": "string" }
+                            }
+                        }
+                    },
+                    "mixed": { "type": "array", "items": {} }
+                }
+            })
+        );
+    }
+
+    #[test]
+    fn test_schema_variables() {
+        let schema = infer_schema(&json!({ "items": [{ "first name": "ada" }] }));
+
+        assert_eq!(
+            paths(&schema_variables("actions.a.result", &schema)),
+            vec![
+                ("actions.a.result", Some("object")),
+                ("actions.a.result.items", Some("array")),
+                ("actions.a.result.items[0]", Some("object")),
+                (r#"actions.a.result.items[0]["first name"]"#, Some("string")),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_available_variables() {
+        let workflow = WorkflowVersionDefinition {
+            actions: vec![
+                action("trigger", "@anything/webhook", None),
+                action("http", "@anything/http", None),
+                action("custom", "@anything/custom", None),
+                action(
+                    "loop",
+                    "@anything/loop",
+                    Some(json!({
+                        "type": "object",
+                        "properties": {
+                            "items": {
+                                "type": "array",
+                                "items": { "type": "object", "properties": { "id": { "type": "integer" } } }
+                            }
+                        }
+                    })),
+                ),
+                action("body", "@anything/javascript", None),
+                action("later", "@anything/http", None),
+            ],
+            edges: vec![
+                edge("trigger", "http"),
+                edge("http", "custom"),
+                edge("custom", "loop"),
+                edge("loop", "body"),
+                edge("body", "later"),
+            ],
+        };
+        let plugin_schemas = HashMap::from([(
+            "@anything/http".to_string(),
+            json!({ "type": "object", "properties": { "status_code": { "type": "integer" } } }),
+        )]);
+        let last_results = HashMap::from([
+            ("custom".to_string(), json!({ "ok": true })),
+            ("http".to_string(), json!({ "ignored": true })),
+        ]);
+        let references = AccountReferences {
+            secrets: ["API_KEY".to_string()].into(),
+            ..Default::default()
+        };
+
+        let available = available_variables(
+            &workflow,
+            "body",
+            &plugin_schemas,
+            &last_results,
+            Some(&references),
+        )
+        .unwrap();
+
+        let sources: Vec<(&str, Option<OutputSchemaSource>)> = available
+            .actions
+            .iter()
+            .map(|output| (output.action_id.as_str(), output.source))
+            .collect();
+        assert_eq!(
+            sources,
+            vec![
+                ("trigger", None),
+                ("http", Some(OutputSchemaSource::Plugin)),
+                ("custom", Some(OutputSchemaSource::LastResult)),
+                ("loop", Some(OutputSchemaSource::Action)),
+            ]
+        );
+        assert_eq!(
+            paths(&available.actions[1].variables),
+            vec![
+                ("actions.http.result", Some("object")),
+                ("actions.http.result.status_code", Some("integer")),
+            ]
+        );
+        assert_eq!(
+            paths(&available.actions[2].variables),
+            vec![
+                ("actions.custom.result", Some("object")),
+                ("actions.custom.result.ok", Some("boolean")),
+            ]
+        );
+        assert_eq!(
+            paths(&available.loop_variables)[..2],
+            [
+                ("loop.item", Some("object")),
+                ("loop.item.id", Some("integer"))
+            ]
+        );
+        assert_eq!(
+            paths(&available.secrets),
+            vec![("secrets.API_KEY", Some("string"))]
+        );
+        assert!(available
+            .system
+            .iter()
+            .any(|variable| variable.path == "system.utc_timestamp"
+                && variable.value_type.as_deref() == Some("integer")));
+
+        let outside_loop =
+            available_variables(&workflow, "loop", &plugin_schemas, &last_results, None).unwrap();
+        assert!(outside_loop.loop_variables.is_empty());
+        assert!(outside_loop.secrets.is_empty());
+        assert!(
+            available_variables(&workflow, "missing", &plugin_schemas, &last_results, None)
+                .is_none()
+        );
+    }
+}
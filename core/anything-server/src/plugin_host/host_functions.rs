@@ -0,0 +1,718 @@
+use crate::bundler::accounts::fetch_cached_auth_accounts;
+use crate::bundler::secrets::get_decrypted_secrets;
+use crate::processor::db_calls::get_workflow_definition;
+use crate::processor::processor::ProcessorMessage;
+use crate::processor::run_queue::{enqueue_workflow, INSTANCE_ID};
+use crate::types::action_types::{ActionType, PluginName};
+use crate::types::task_types::{Stage, Task, TaskConfig};
+use crate::AppState;
+
+use chrono::Utc;
+use dotenv::dotenv;
+use extism::convert::Json;
+use extism::{CurrentPlugin, Error, Function, UserData, Val, ValType};
+use node_semver::Version;
+use reqwest::{redirect, Client, Method};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::env;
+use std::future::Future;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::runtime::Handle;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+/// Bumped when a host function is removed or changes shape, new functions keep the version.
+/// Mirrors `HOST_API_VERSION` in anything-pdk.
+pub const HOST_API_VERSION: u32 = 1;
+
+const PLUGIN_HTTP_TIMEOUT: Duration = Duration::from_secs(30);
+const MAX_REDIRECTS: usize = 10;
+
+/// Everything a host function may touch during one plugin call
+#[derive(Clone)]
+pub struct HostContext {
+    pub state: Arc<AppState>,
+    pub runtime: Handle,
+    pub account_id: Uuid,
+    pub plugin_id: String,
+    pub allowed_hosts: Arc<Vec<String>>,
+    pub logs: Arc<Mutex<Vec<Value>>>, // Written to the task's debug_result once the call returns
+    pub event_target: Option<EventTarget>, // Set for trigger plugins, where create_event starts the workflow
+}
+
+impl HostContext {
+    pub fn for_task(state: Arc<AppState>, task: &Task, plugin_id: &str) -> Self {
+        let allowed_hosts = state.plugin_host.allowed_hosts.clone();
+        Self {
+            state,
+            runtime: Handle::current(),
+            account_id: task.account_id,
+            plugin_id: plugin_id.to_string(),
+            allowed_hosts,
+            logs: Arc::new(Mutex::new(Vec::new())),
+            event_target: (task.r#type == ActionType::Trigger)
+                .then(|| EventTarget::from_trigger_task(task)),
+        }
+    }
+
+    /// For a trigger plugin running for a workflow, outside of any task
+    pub fn for_trigger(state: Arc<AppState>, target: &EventTarget, plugin_id: &str) -> Self {
+        let allowed_hosts = state.plugin_host.allowed_hosts.clone();
+        Self {
+            state,
+            runtime: Handle::current(),
+            account_id: target.account_id,
+            plugin_id: plugin_id.to_string(),
+            allowed_hosts,
+            logs: Arc::new(Mutex::new(Vec::new())),
+            event_target: Some(target.clone()),
+        }
+    }
+
+    pub fn take_logs(&self) -> Vec<Value> {
+        std::mem::take(&mut *self.logs.lock().unwrap())
+    }
+}
+
+/// The workflow trigger a plugin's events start sessions of
+#[derive(Debug, Clone)]
+pub struct EventTarget {
+    pub account_id: Uuid,
+    pub flow_id: Uuid,
+    pub flow_version_id: Uuid,
+    pub action_id: String,
+    pub action_label: String,
+    pub plugin_name: Option<PluginName>,
+    pub plugin_version: Option<Version>,
+    pub config: TaskConfig,
+}
+
+impl EventTarget {
+    pub fn from_trigger_task(task: &Task) -> Self {
+        Self {
+            account_id: task.account_id,
+            flow_id: task.flow_id,
+            flow_version_id: task.flow_version_id,
+            action_id: task.action_id.clone(),
+            action_label: task.action_label.clone(),
+            plugin_name: task.plugin_name.clone(),
+            plugin_version: task.plugin_version.clone(),
+            config: task.config.clone(),
+        }
+    }
+}
+
+/// Hosts plugins may reach with `host_http_request`, from the comma separated `PLUGIN_ALLOWED_HOSTS`
+pub fn allowed_hosts_from_env() -> Vec<String> {
+    env::var("PLUGIN_ALLOWED_HOSTS")
+        .unwrap_or_default()
+        .split(',')
+        .map(|host| host.trim().to_lowercase())
+        .filter(|host| !host.is_empty())
+        .collect()
+}
+
+/// `*` allows every host and `*.example.com` allows the subdomains of example.com
+pub fn is_host_allowed(allowed_hosts: &[String], host: &str) -> bool {
+    let host = host.to_lowercase();
+    allowed_hosts
+        .iter()
+        .any(|allowed| match allowed.strip_prefix("*.") {
+            Some(domain) => host.ends_with(&format!(".{}", domain)),
+            None => allowed == "*" || *allowed == host,
+        })
+}
+
+/// Client for `host_http_request`. Redirects are checked against the allowlist too,
+/// so an allowed host can't send a plugin on to one it may not reach.
+pub fn plugin_http_client(allowed_hosts: Arc<Vec<String>>) -> reqwest::Result<Client> {
+    Client::builder()
+        .timeout(PLUGIN_HTTP_TIMEOUT)
+        .redirect(redirect::Policy::custom(move |attempt| {
+            let host = attempt.url().host_str().unwrap_or_default().to_string();
+            if !is_host_allowed(&allowed_hosts, &host) {
+                attempt.error(format!("Redirect to {} is not allowed", host))
+            } else if attempt.previous().len() > MAX_REDIRECTS {
+                attempt.error("Too many redirects")
+            } else {
+                attempt.follow()
+            }
+        }))
+        .build()
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LogEntry {
+    #[serde(default = "default_log_level")]
+    pub level: String,
+    pub message: String,
+    #[serde(default)]
+    pub fields: Value,
+}
+
+fn default_log_level() -> String {
+    "info".to_string()
+}
+
+#[derive(Debug, Serialize)]
+pub struct Log {
+    pub time: String,
+    pub message: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct HttpRequest {
+    pub method: String,
+    pub url: String,
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+    #[serde(default)]
+    pub body: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct HttpResponse {
+    pub status: u16,
+    pub headers: HashMap<String, String>,
+    pub body: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SecretRequest {
+    pub name: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SecretResponse {
+    pub value: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AccountRequest {
+    pub slug: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AccountResponse {
+    pub account: Option<Value>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Event {
+    pub id: String,
+    pub name: String,
+    pub description: String,
+    pub timestamp: String,
+    #[serde(default, skip_serializing_if = "Value::is_null")]
+    pub payload: Value,
+}
+
+#[derive(Debug, Serialize)]
+pub struct EventCreated {
+    pub flow_session_id: Uuid,
+}
+
+/// The host functions every plugin is linked against
+pub fn host_functions() -> Vec<Function> {
+    vec![
+        Function::new(
+            "host_api_version",
+            [],
+            [ValType::I64],
+            UserData::new(()),
+            host_api_version,
+        ),
+        json_function("host_log", host_log),
+        json_function("host_http_request", host_http_request),
+        json_function("host_get_secret", host_get_secret),
+        json_function("host_get_account", host_get_account),
+        json_function("create_event", create_event),
+    ]
+}
+
+/// A host function taking and returning one JSON value, with the call's `HostContext`
+fn json_function<I, O>(name: &str, f: fn(&HostContext, I) -> Result<O, Error>) -> Function
+where
+    I: DeserializeOwned + 'static,
+    O: Serialize + 'static,
+{
+    Function::new(
+        name,
+        [ValType::I64],
+        [ValType::I64],
+        UserData::new(()),
+        move |plugin: &mut CurrentPlugin,
+              inputs: &[Val],
+              outputs: &mut [Val],
+              _user_data: UserData<()>| {
+            let Json(input): Json<I> = plugin.memory_get_val(&inputs[0])?;
+            let context = plugin.host_context::<HostContext>()?.clone();
+            let output = f(&context, input)?;
+            let handle = plugin.memory_new(Json(output))?;
+            outputs[0] = plugin.memory_to_val(handle);
+            Ok(())
+        },
+    )
+}
+
+fn host_api_version(
+    plugin: &mut CurrentPlugin,
+    _inputs: &[Val],
+    outputs: &mut [Val],
+    _user_data: UserData<()>,
+) -> Result<(), Error> {
+    let handle = plugin.memory_new(HOST_API_VERSION.to_string())?;
+    outputs[0] = plugin.memory_to_val(handle);
+    Ok(())
+}
+
+fn host_log(context: &HostContext, entry: LogEntry) -> Result<Log, Error> {
+    let time = Utc::now().to_rfc3339();
+    match entry.level.as_str() {
+        "warn" | "error" => warn!(
+            "[PLUGIN_HOST] {} {}: {}",
+            context.plugin_id, entry.level, entry.message
+        ),
+        _ => info!(
+            "[PLUGIN_HOST] {} {}: {}",
+            context.plugin_id, entry.level, entry.message
+        ),
+    }
+
+    context.logs.lock().unwrap().push(json!({
+        "time": time,
+        "level": entry.level,
+        "message": entry.message,
+        "fields": entry.fields,
+    }));
+
+    Ok(Log {
+        time,
+        message: entry.message,
+    })
+}
+
+fn host_http_request(context: &HostContext, request: HttpRequest) -> Result<HttpResponse, Error> {
+    let url = reqwest::Url::parse(&request.url)?;
+    let host = url.host_str().unwrap_or_default();
+    if !is_host_allowed(&context.allowed_hosts, host) {
+        return Err(Error::msg(format!(
+            "Plugin {} is not allowed to reach {}",
+            context.plugin_id, host
+        )));
+    }
+
+    let method = Method::from_str(&request.method.to_uppercase())?;
+    let mut builder = context.state.plugin_host.http_client.request(method, url);
+    for (key, value) in &request.headers {
+        builder = builder.header(key, value);
+    }
+    if let Some(body) = request.body {
+        builder = builder.body(body);
+    }
+
+    context.runtime.block_on(async {
+        let response = builder.send().await?;
+        let status = response.status().as_u16();
+        let headers = response
+            .headers()
+            .iter()
+            .map(|(key, value)| {
+                (
+                    key.to_string(),
+                    value.to_str().unwrap_or_default().to_string(),
+                )
+            })
+            .collect();
+        let body = response.text().await?;
+        Ok(HttpResponse {
+            status,
+            headers,
+            body,
+        })
+    })
+}
+
+fn host_get_secret(context: &HostContext, request: SecretRequest) -> Result<SecretResponse, Error> {
+    let secrets = context
+        .runtime
+        .block_on(get_decrypted_secrets(
+            context.state.clone(),
+            &context.state.anything_client,
+            &context.account_id.to_string(),
+        ))
+        .map_err(|e| Error::msg(e.to_string()))?;
+
+    Ok(SecretResponse {
+        value: secrets
+            .into_iter()
+            .find(|secret| secret.secret_name == request.name)
+            .map(|secret| secret.secret_value),
+    })
+}
+
+fn host_get_account(
+    context: &HostContext,
+    request: AccountRequest,
+) -> Result<AccountResponse, Error> {
+    let accounts = context
+        .runtime
+        .block_on(fetch_cached_auth_accounts(
+            context.state.clone(),
+            &context.state.anything_client,
+            &context.account_id.to_string(),
+            true,
+        ))
+        .map_err(|e| Error::msg(e.to_string()))?;
+
+    let account = accounts
+        .into_iter()
+        .find(|account| account.account_auth_provider_account_slug == request.slug)
+        .map(serde_json::to_value)
+        .transpose()?;
+
+    Ok(AccountResponse { account })
+}
+
+fn create_event(context: &HostContext, event: Event) -> Result<EventCreated, Error> {
+    let target = context.event_target.as_ref().ok_or_else(|| {
+        Error::msg(format!(
+            "Plugin {} can only create events while running as a trigger",
+            context.plugin_id
+        ))
+    })?;
+
+    let flow_session_id = context
+        .runtime
+        .block_on(fire_event(&context.state, target, event))
+        .map_err(Error::msg)?;
+
+    Ok(EventCreated { flow_session_id })
+}
+
+/// Starts a flow session of the target's workflow with the event as the trigger's result
+pub async fn fire_event(
+    state: &Arc<AppState>,
+    target: &EventTarget,
+    event: Event,
+) -> Result<Uuid, String> {
+    let workflow_version = get_workflow_definition(
+        state.clone(),
+        &target.flow_id,
+        Some(&target.flow_version_id),
+    )
+    .await?;
+
+    let mut builder = Task::builder()
+        .account_id(target.account_id)
+        .flow_id(target.flow_id)
+        .flow_version_id(target.flow_version_id)
+        .action_label(target.action_label.clone())
+        .trigger_id(target.action_id.clone())
+        .action_id(target.action_id.clone())
+        .r#type(ActionType::Trigger)
+        .stage(Stage::Production)
+        .config(target.config.clone())
+        .result(json!({
+            "message": "Successfully triggered task",
+            "created_at": Utc::now(),
+            "event": event,
+        }));
+    if let Some(plugin_name) = &target.plugin_name {
+        builder = builder.plugin_name(plugin_name.clone());
+    }
+    if let Some(plugin_version) = &target.plugin_version {
+        builder = builder.plugin_version(plugin_version.clone());
+    }
+    let task = builder.build()?;
+
+    // Plugins usually report what they saw since their last poll, which can include events
+    // they already reported. Those answer with the session they started the first time.
+    let idempotency_key = (!event.id.is_empty()).then(|| event_idempotency_key(target, &event.id));
+    if let Some(idempotency_key) = &idempotency_key {
+        let flow_session_id =
+            claim_event_fire(state, target, idempotency_key, &task.flow_session_id).await?;
+        if flow_session_id != task.flow_session_id {
+            info!(
+                "[PLUGIN_HOST] Event {} already started flow session {} of workflow {}",
+                event.id, flow_session_id, target.flow_id
+            );
+            return Ok(flow_session_id);
+        }
+    }
+
+    info!(
+        "[PLUGIN_HOST] Event {} starts flow session {} of workflow {}",
+        task.result
+            .as_ref()
+            .and_then(|result| result["event"]["id"].as_str())
+            .unwrap_or_default(),
+        task.flow_session_id,
+        target.flow_id
+    );
+
+    let flow_session_id = task.flow_session_id;
+    let processor_message = ProcessorMessage {
+        workflow_id: target.flow_id,
+        workflow_version: workflow_version.clone(),
+        workflow_definition: workflow_version.flow_definition.clone(),
+        flow_session_id,
+        trigger_session_id: task.trigger_session_id,
+        task_id: Some(task.task_id),
+        trigger_task: Some(task),
+        existing_tasks: HashMap::new(),
+    };
+
+    // An event whose session never started can be fired again
+    enqueue_or_release(enqueue_workflow(state, processor_message), || async {
+        match &idempotency_key {
+            Some(idempotency_key) => {
+                release_event_fire(state, idempotency_key, &flow_session_id).await
+            }
+            None => Ok(()),
+        }
+    })
+    .await?;
+
+    Ok(flow_session_id)
+}
+
+/// Runs `enqueue`, and `release` when it fails. The enqueue error is returned either way.
+async fn enqueue_or_release<E, R, F>(enqueue: E, release: R) -> Result<(), String>
+where
+    E: Future<Output = Result<(), String>>,
+    R: FnOnce() -> F,
+    F: Future<Output = Result<(), String>>,
+{
+    let Err(e) = enqueue.await else {
+        return Ok(());
+    };
+
+    if let Err(release_error) = release().await {
+        warn!(
+            "[PLUGIN_HOST] Failed to release event after its session failed to start: {}",
+            release_error
+        );
+    }
+    Err(e)
+}
+
+/// Identifies one event of a trigger, so the same event never starts two sessions
+pub fn event_idempotency_key(target: &EventTarget, event_id: &str) -> String {
+    format!("event:{}:{}:{}", target.flow_id, target.action_id, event_id)
+}
+
+/// Records the session an event starts. Returns the session it already started when
+/// the event was fired before.
+async fn claim_event_fire(
+    state: &AppState,
+    target: &EventTarget,
+    idempotency_key: &str,
+    flow_session_id: &Uuid,
+) -> Result<Uuid, String> {
+    dotenv().ok();
+    let supabase_service_role_api_key = env::var("SUPABASE_SERVICE_ROLE_API_KEY")
+        .map_err(|_| "SUPABASE_SERVICE_ROLE_API_KEY must be set".to_string())?;
+
+    let response = state
+        .anything_client
+        .from("trigger_fires")
+        .auth(&supabase_service_role_api_key)
+        .insert(
+            json!({
+                "idempotency_key": idempotency_key,
+                "flow_id": target.flow_id,
+                "action_id": target.action_id,
+                "account_id": target.account_id,
+                "scheduled_time": Utc::now(),
+                "fired_by": *INSTANCE_ID,
+                "flow_session_id": flow_session_id,
+            })
+            .to_string(),
+        )
+        .execute()
+        .await
+        .map_err(|e| format!("Failed to execute request: {}", e))?;
+
+    let status = response.status();
+    if status.is_success() {
+        return Ok(*flow_session_id);
+    }
+    if status != reqwest::StatusCode::CONFLICT {
+        let body = response.text().await.unwrap_or_default();
+        return Err(format!(
+            "Failed to claim event {}: {}",
+            idempotency_key, body
+        ));
+    }
+
+    let response = state
+        .anything_client
+        .from("trigger_fires")
+        .auth(&supabase_service_role_api_key)
+        .select("flow_session_id")
+        .eq("idempotency_key", idempotency_key)
+        .execute()
+        .await
+        .map_err(|e| format!("Failed to execute request: {}", e))?;
+
+    let body = response
+        .text()
+        .await
+        .map_err(|e| format!("Failed to read response body: {}", e))?;
+
+    let fires: Vec<Value> =
+        serde_json::from_str(&body).map_err(|e| format!("Failed to parse trigger fire: {}", e))?;
+
+    fires
+        .first()
+        .and_then(|fire| fire.get("flow_session_id"))
+        .and_then(Value::as_str)
+        .and_then(|flow_session_id| Uuid::parse_str(flow_session_id).ok())
+        .ok_or_else(|| format!("Event {} was already fired", idempotency_key))
+}
+
+/// Deletes the record of an event whose session didn't start
+async fn release_event_fire(
+    state: &AppState,
+    idempotency_key: &str,
+    flow_session_id: &Uuid,
+) -> Result<(), String> {
+    dotenv().ok();
+    let supabase_service_role_api_key = env::var("SUPABASE_SERVICE_ROLE_API_KEY")
+        .map_err(|_| "SUPABASE_SERVICE_ROLE_API_KEY must be set".to_string())?;
+
+    let response = state
+        .anything_client
+        .from("trigger_fires")
+        .auth(supabase_service_role_api_key)
+        .eq("idempotency_key", idempotency_key)
+        .eq("flow_session_id", flow_session_id.to_string())
+        .delete()
+        .execute()
+        .await
+        .map_err(|e| format!("Failed to execute request: {}", e))?;
+
+    if !response.status().is_success() {
+        let body = response.text().await.unwrap_or_default();
+        return Err(format!(
+            "Failed to release event {}: {}",
+            idempotency_key, body
+        ));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_host_allowed() {
+        let allowed_hosts = vec!["api.example.com".to_string(), "*.github.com".to_string()];
+
+        assert!(is_host_allowed(&allowed_hosts, "api.example.com"));
+        assert!(is_host_allowed(&allowed_hosts, "API.example.com"));
+        assert!(is_host_allowed(&allowed_hosts, "api.github.com"));
+        assert!(!is_host_allowed(&allowed_hosts, "github.com"));
+        assert!(!is_host_allowed(&allowed_hosts, "example.com"));
+        assert!(!is_host_allowed(&[], "api.example.com"));
+        assert!(is_host_allowed(&["*".to_string()], "anything.dev"));
+    }
+
+    #[test]
+    fn test_event_idempotency_key() {
+        let target = EventTarget {
+            account_id: Uuid::new_v4(),
+            flow_id: Uuid::new_v4(),
+            flow_version_id: Uuid::new_v4(),
+            action_id: "new_issue".to_string(),
+            action_label: "New Issue".to_string(),
+            plugin_name: None,
+            plugin_version: None,
+            config: TaskConfig {
+                inputs: None,
+                inputs_schema: None,
+                plugin_config: None,
+                plugin_config_schema: None,
+            },
+        };
+
+        assert_eq!(
+            event_idempotency_key(&target, "42"),
+            format!("event:{}:new_issue:42", target.flow_id)
+        );
+
+        // A newer version of the workflow keeps skipping the events it already fired
+        let republished = EventTarget {
+            flow_version_id: Uuid::new_v4(),
+            ..target.clone()
+        };
+        assert_eq!(
+            event_idempotency_key(&republished, "42"),
+            event_idempotency_key(&target, "42")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_event_is_released_when_its_session_fails_to_start() {
+        let released = Mutex::new(false);
+        let release = || async {
+            *released.lock().unwrap() = true;
+            Ok(())
+        };
+
+        let result =
+            enqueue_or_release(async { Err("processor is gone".to_string()) }, release).await;
+        assert_eq!(result, Err("processor is gone".to_string()));
+        assert!(*released.lock().unwrap());
+
+        *released.lock().unwrap() = false;
+        let release = || async {
+            *released.lock().unwrap() = true;
+            Ok(())
+        };
+        assert_eq!(enqueue_or_release(async { Ok(()) }, release).await, Ok(()));
+        assert!(!*released.lock().unwrap());
+    }
+
+    /// Answers every request with a redirect to `location`
+    async fn redirecting_server(location: String) -> std::net::SocketAddr {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let mut request = [0u8; 1024];
+                let _ = socket.read(&mut request).await;
+                let response = format!(
+                    "HTTP/1.1 302 Found\r\nLocation: {}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                    location
+                );
+                let _ = socket.write_all(response.as_bytes()).await;
+            }
+        });
+        addr
+    }
+
+    #[tokio::test]
+    async fn test_plugin_http_client_checks_redirects() {
+        let addr = redirecting_server("http://internal.example.com/secrets".to_string()).await;
+        let client = plugin_http_client(Arc::new(vec!["127.0.0.1".to_string()])).unwrap();
+
+        let error = client
+            .get(format!("http://{}/", addr))
+            .send()
+            .await
+            .unwrap_err();
+        assert!(error.is_redirect());
+        assert!(format!("{:?}", error).contains("Redirect to internal.example.com is not allowed"));
+    }
+}
@@ -0,0 +1,458 @@
+pub mod host_functions;
+pub mod triggers;
+pub mod versions;
+
+use extism::{CompiledPlugin, Manifest, Plugin, PluginBuilder, Pool, PoolBuilder, Wasm};
+use node_semver::Version;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, HashMap};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{error, info, warn};
+
+use crate::types::action_types::Action;
+use host_functions::{allowed_hosts_from_env, host_functions, plugin_http_client, HostContext};
+use versions::resolve_version;
+
+const DEFAULT_PLUGIN_DIR: &str = "plugins";
+const DEFAULT_MEMORY_MAX_MB: u32 = 64;
+const DEFAULT_FUEL_LIMIT: u64 = 1_000_000_000;
+const DEFAULT_TIMEOUT_SECS: u64 = 10;
+const DEFAULT_MAX_INSTANCES: usize = 4;
+const WASM_PAGE_SIZE: u32 = 64 * 1024;
+
+/// Resources a single `register` or `execute` call may use
+#[derive(Debug, Clone, PartialEq)]
+pub struct PluginLimits {
+    pub memory_max_pages: u32, // 64KiB WebAssembly pages
+    pub fuel: u64,             // Roughly the number of wasm instructions
+    pub timeout: Duration,
+    pub max_instances: usize, // Calls to the same plugin that can run at once
+}
+
+impl PluginLimits {
+    pub fn from_env() -> Self {
+        let memory_max_mb = env_or("PLUGIN_MEMORY_MAX_MB", DEFAULT_MEMORY_MAX_MB);
+        Self {
+            memory_max_pages: memory_max_mb.saturating_mul(1024 * 1024) / WASM_PAGE_SIZE,
+            fuel: env_or("PLUGIN_FUEL_LIMIT", DEFAULT_FUEL_LIMIT),
+            timeout: Duration::from_secs(env_or("PLUGIN_TIMEOUT_SECS", DEFAULT_TIMEOUT_SECS)),
+            max_instances: env_or("PLUGIN_MAX_INSTANCES", DEFAULT_MAX_INSTANCES),
+        }
+    }
+}
+
+fn env_or<T: std::str::FromStr>(key: &str, default: T) -> T {
+    env::var(key)
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(default)
+}
+
+/// What an anything-pdk plugin returns from `register`
+#[derive(Debug, Clone, Deserialize)]
+pub struct PluginRegistration {
+    pub trigger: bool,
+    pub label: String,
+    pub plugin_id: String,
+    #[serde(default = "default_plugin_version")]
+    pub version: Version, // Plugins built before versioning register as 0.1.0
+    pub icon: String,
+    pub description: String,
+    pub handles: Vec<Value>,
+    pub input: Value,
+    pub input_schema: Value,
+    pub output_schema: Value,
+}
+
+fn default_plugin_version() -> Version {
+    Version::from((0, 1, 0))
+}
+
+impl PluginRegistration {
+    /// Shaped like the schema templates in `system_plugins/registry` so the catalog lists it with them
+    pub fn action_template(&self) -> Value {
+        let r#type = if self.trigger { "trigger" } else { "action" };
+        json!({
+            "type": r#type,
+            "featured": false,
+            "action_template_definition": {
+                "anything_action_version": "0.1.0",
+                "type": r#type,
+                "plugin_name": self.plugin_id,
+                "plugin_version": self.version.to_string(),
+                "action_id": self.plugin_id,
+                "label": self.label,
+                "description": self.description,
+                "icon": self.icon,
+                "inputs": {},
+                "inputs_locked": false,
+                "inputs_schema": {},
+                "inputs_schema_locked": false,
+                "plugin_config": self.input,
+                "plugin_config_locked": false,
+                "plugin_config_schema": self.input_schema,
+                "plugin_config_schema_locked": true,
+                "output_schema": self.result_schema(),
+                "presentation": {
+                    "position": {
+                        "x": 300,
+                        "y": 100
+                    }
+                },
+                "handles": self.handles
+            }
+        })
+    }
+
+    /// Schema of the task result. `output_schema` describes the `{status, output, error}`
+    /// envelope, but `plugin_result` only stores `output`.
+    pub fn result_schema(&self) -> Value {
+        envelope_output_schema(&self.output_schema)
+    }
+}
+
+/// Picks the schema of `output` out of an envelope schema. A schema that doesn't describe
+/// the envelope is used as it is, like the output of plugins that don't wrap it.
+fn envelope_output_schema(schema: &Value) -> Value {
+    let properties = schema.get("properties");
+    match (
+        properties.and_then(|properties| properties.get("status")),
+        properties.and_then(|properties| properties.get("output")),
+    ) {
+        (Some(_), Some(output)) => output.clone(),
+        _ => schema.clone(),
+    }
+}
+
+/// A compiled `.wasm` plugin. Extism resets an instance's store before every call,
+/// so pooled instances never share memory between calls.
+pub struct WasmPlugin {
+    pub registration: PluginRegistration,
+    pub path: PathBuf,
+    pool: Arc<Pool>,
+    wait: Duration,
+}
+
+impl WasmPlugin {
+    /// Runs the plugin's `execute` export with the task's plugin config
+    pub async fn execute(
+        &self,
+        plugin_config: &Value,
+        context: HostContext,
+    ) -> Result<Option<Value>, Box<dyn std::error::Error + Send + Sync>> {
+        self.call("execute", plugin_config, context).await
+    }
+
+    /// Calls one of the plugin's exports with a JSON input, on a blocking thread
+    pub async fn call(
+        &self,
+        function: &'static str,
+        input: &Value,
+        context: HostContext,
+    ) -> Result<Option<Value>, Box<dyn std::error::Error + Send + Sync>> {
+        let pool = self.pool.clone();
+        let wait = self.wait;
+        let input = serde_json::to_string(input)?;
+
+        let output = tokio::task::spawn_blocking(move || {
+            pool.with_plugin(wait, |plugin| {
+                plugin.call_with_host_context::<&str, String, _>(function, &input, context)
+            })
+        })
+        .await?
+        .map_err(|e| format!("Plugin {} failed: {}", self.registration.plugin_id, e))?
+        .ok_or_else(|| {
+            format!(
+                "Plugin {} has no free instance after {:?}",
+                self.registration.plugin_id, wait
+            )
+        })?;
+
+        let result: Value = serde_json::from_str(&output)?;
+        plugin_result(result)
+    }
+
+    pub fn has_function(&self, function: &str) -> bool {
+        self.pool
+            .function_exists(function, self.wait)
+            .unwrap_or(false)
+    }
+}
+
+/// Turns the `{status, output, error}` an anything-pdk plugin returns into a task result
+pub fn plugin_result(
+    result: Value,
+) -> Result<Option<Value>, Box<dyn std::error::Error + Send + Sync>> {
+    match result.get("status").and_then(Value::as_str) {
+        Some("error") => {
+            let error = result.get("error").cloned().unwrap_or_else(|| json!({}));
+            let message = error
+                .get("message")
+                .and_then(Value::as_str)
+                .map(str::to_string)
+                .unwrap_or_else(|| error.to_string());
+            Err(message.into())
+        }
+        Some(_) => Ok(result.get("output").cloned()),
+        // Plugins that don't follow the envelope hand back their output directly
+        None => Ok(Some(result)),
+    }
+}
+
+/// Loads the `.wasm` plugins in a directory and runs them for tasks whose `plugin_name`
+/// isn't one of the built in `@anything/*` plugins. Several versions of a plugin can be
+/// loaded side by side, each task runs the version its action resolved to.
+pub struct PluginHost {
+    plugins: HashMap<String, BTreeMap<Version, WasmPlugin>>,
+    pub allowed_hosts: Arc<Vec<String>>, // Hosts plugins may call through host_http_request
+    pub http_client: reqwest::Client,    // Only follows redirects to allowed_hosts
+}
+
+impl Default for PluginHost {
+    fn default() -> Self {
+        let allowed_hosts = Arc::new(Vec::new());
+        Self {
+            plugins: HashMap::new(),
+            http_client: plugin_http_client(allowed_hosts.clone())
+                .expect("Failed to build plugin HTTP client"),
+            allowed_hosts,
+        }
+    }
+}
+
+impl PluginHost {
+    pub fn load_from_env() -> Self {
+        let plugin_dir = env::var("PLUGIN_DIR").unwrap_or_else(|_| DEFAULT_PLUGIN_DIR.to_string());
+        let mut host = Self::load(Path::new(&plugin_dir), &PluginLimits::from_env());
+        host.allowed_hosts = Arc::new(allowed_hosts_from_env());
+        host.http_client = plugin_http_client(host.allowed_hosts.clone())
+            .expect("Failed to build plugin HTTP client");
+        host
+    }
+
+    pub fn load(plugin_dir: &Path, limits: &PluginLimits) -> Self {
+        let mut host = Self::default();
+
+        let entries = match fs::read_dir(plugin_dir) {
+            Ok(entries) => entries,
+            Err(_) => {
+                info!(
+                    "[PLUGIN_HOST] No plugin directory at {}, skipping wasm plugins",
+                    plugin_dir.display()
+                );
+                return host;
+            }
+        };
+
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.extension().and_then(|ext| ext.to_str()) != Some("wasm") {
+                continue;
+            }
+
+            match load_plugin(&path, limits) {
+                Ok(plugin) => {
+                    let plugin_id = plugin.registration.plugin_id.clone();
+                    let version = plugin.registration.version.clone();
+                    let versions = host.plugins.entry(plugin_id.clone()).or_default();
+                    if let Some(existing) = versions.get(&version) {
+                        warn!(
+                            "[PLUGIN_HOST] Skipping {}, plugin {} {} is already loaded from {}",
+                            path.display(),
+                            plugin_id,
+                            version,
+                            existing.path.display()
+                        );
+                        continue;
+                    }
+                    info!(
+                        "[PLUGIN_HOST] Loaded plugin {} {} from {}",
+                        plugin_id,
+                        version,
+                        path.display()
+                    );
+                    versions.insert(version, plugin);
+                }
+                Err(e) => {
+                    error!("[PLUGIN_HOST] Failed to load {}: {}", path.display(), e);
+                }
+            }
+        }
+
+        info!("[PLUGIN_HOST] Loaded {} wasm plugins", host.plugins.len());
+        host
+    }
+
+    /// The newest loaded version of a plugin
+    pub fn get(&self, plugin_name: &str) -> Option<&WasmPlugin> {
+        self.plugins
+            .get(plugin_name)
+            .and_then(|versions| versions.values().next_back())
+    }
+
+    pub fn get_version(&self, plugin_name: &str, version: &Version) -> Option<&WasmPlugin> {
+        self.plugins
+            .get(plugin_name)
+            .and_then(|versions| versions.get(version))
+    }
+
+    /// Every loaded version of every plugin
+    pub fn plugins(&self) -> impl Iterator<Item = &WasmPlugin> {
+        self.plugins.values().flat_map(|versions| versions.values())
+    }
+
+    /// Every loaded version of a plugin, oldest first
+    pub fn versions(&self, plugin_name: &str) -> Vec<&WasmPlugin> {
+        self.plugins
+            .get(plugin_name)
+            .map(|versions| versions.values().collect())
+            .unwrap_or_default()
+    }
+
+    /// The plugin version an action runs, None when it pins a version that isn't loaded
+    pub fn resolve(&self, action: &Action) -> Option<&WasmPlugin> {
+        let versions = self.plugins.get(action.plugin_name.as_str())?;
+        let version = resolve_version(
+            versions.keys(),
+            &action.plugin_version,
+            action.plugin_version_range.as_ref(),
+        )?;
+        versions.get(&version)
+    }
+
+    /// The version recorded on a task created for an action. Built in plugins
+    /// and unresolvable ranges keep the version pinned on the action.
+    pub fn task_plugin_version(&self, action: &Action) -> Version {
+        self.resolve(action)
+            .map(|plugin| plugin.registration.version.clone())
+            .unwrap_or_else(|| action.plugin_version.clone())
+    }
+
+    /// Catalog entries for the newest loaded plugins of a type, `action` or `trigger`
+    pub fn action_templates(&self, r#type: &str) -> Vec<Value> {
+        self.plugins
+            .keys()
+            .filter_map(|plugin_name| self.get(plugin_name))
+            .map(|plugin| plugin.registration.action_template())
+            .filter(|template| template.get("type").and_then(Value::as_str) == Some(r#type))
+            .collect()
+    }
+}
+
+fn load_plugin(
+    path: &Path,
+    limits: &PluginLimits,
+) -> Result<WasmPlugin, Box<dyn std::error::Error + Send + Sync>> {
+    let manifest = Manifest::new([Wasm::file(path)])
+        .with_memory_max(limits.memory_max_pages)
+        .with_timeout(limits.timeout);
+
+    let compiled = CompiledPlugin::new(
+        PluginBuilder::new(manifest)
+            .with_wasi(false)
+            .with_functions(host_functions())
+            .with_fuel_limit(limits.fuel),
+    )?;
+
+    let mut plugin = Plugin::new_from_compiled(&compiled)?;
+    let registration = plugin.call::<&str, String>("register", "")?;
+    let registration: PluginRegistration = serde_json::from_str(&registration)?;
+
+    if registration.plugin_id.starts_with("@anything/") {
+        return Err(format!(
+            "plugin_id {} is reserved for built in plugins",
+            registration.plugin_id
+        )
+        .into());
+    }
+
+    let pool = PoolBuilder::new()
+        .with_max_instances(limits.max_instances)
+        .build(move || Plugin::new_from_compiled(&compiled));
+
+    Ok(WasmPlugin {
+        registration,
+        path: path.to_path_buf(),
+        pool: Arc::new(pool),
+        wait: limits.timeout,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plugin_result() {
+        let output = plugin_result(json!({
+            "status": "success",
+            "output": { "count": 2 },
+            "error": {}
+        }))
+        .unwrap();
+        assert_eq!(output, Some(json!({ "count": 2 })));
+
+        let error = plugin_result(json!({
+            "status": "error",
+            "output": {},
+            "error": { "message": "items must be an array" }
+        }))
+        .unwrap_err();
+        assert_eq!(error.to_string(), "items must be an array");
+
+        let template = PluginRegistration {
+            trigger: false,
+            label: "Example Plugin".to_string(),
+            plugin_id: "example_plugin".to_string(),
+            version: Version::parse("1.2.0").unwrap(),
+            icon: "<svg></svg>".to_string(),
+            description: "This is an example plugin".to_string(),
+            handles: vec![],
+            input: json!({ "url": "http://example.com" }),
+            input_schema: json!({ "type": "object" }),
+            output_schema: json!({
+                "type": "object",
+                "properties": {
+                    "status": { "type": "string", "enum": ["success", "error"] },
+                    "output": {
+                        "type": "object",
+                        "properties": { "count": { "type": "number" } }
+                    },
+                    "error": { "type": "object" }
+                },
+                "required": ["status"]
+            }),
+        }
+        .action_template();
+        assert_eq!(template["type"], "action");
+        assert_eq!(
+            template["action_template_definition"]["plugin_name"],
+            "example_plugin"
+        );
+        assert_eq!(
+            template["action_template_definition"]["plugin_version"],
+            "1.2.0"
+        );
+        assert_eq!(
+            template["action_template_definition"]["plugin_config"]["url"],
+            "http://example.com"
+        );
+        // Templates describe the stored output, not the envelope around it
+        assert_eq!(
+            template["action_template_definition"]["output_schema"],
+            json!({
+                "type": "object",
+                "properties": { "count": { "type": "number" } }
+            })
+        );
+        assert_eq!(
+            envelope_output_schema(&json!({ "type": "array" })),
+            json!({ "type": "array" })
+        );
+    }
+}
@@ -0,0 +1,533 @@
+use crate::bundler::bundle_context_from_parts;
+use crate::leases::{release_lease, Leadership, LeadershipChange, PLUGIN_TRIGGER_LEASE};
+use crate::plugin_host::host_functions::{EventTarget, HostContext};
+use crate::system_plugins::webhook_trigger::request_body::parse_request_body_in_memory;
+use crate::trigger_engine::next_fire_after;
+use crate::types::action_types::{Action, ActionType};
+use crate::types::task_types::TaskConfig;
+use crate::types::workflow_types::DatabaseFlowVersion;
+use crate::AppState;
+
+use axum::{
+    body::Bytes,
+    extract::{Path, Query, State},
+    http::{HeaderMap, Method, StatusCode},
+    response::IntoResponse,
+    Json,
+};
+use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
+use cron::Schedule;
+use dotenv::dotenv;
+use node_semver::Version;
+use postgrest::Postgrest;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::env;
+use std::str::FromStr;
+use std::sync::Arc;
+use tokio::task::JoinHandle;
+use tokio::time::{sleep, Duration};
+use tracing::{error, info, warn};
+use uuid::Uuid;
+
+// Longest the loop sleeps without checking its lease, even when no trigger polls sooner
+const MAX_IDLE_INTERVAL: Duration = Duration::from_secs(60);
+
+const DEFAULT_POLL_INTERVAL_SECS: u64 = 60;
+
+// Keeps a misconfigured trigger from calling its plugin in a tight loop
+const MIN_POLL_INTERVAL_SECS: u64 = 5;
+
+/// When a trigger plugin is polled, read from its rendered config
+#[derive(Debug, Clone, PartialEq)]
+pub enum PollSchedule {
+    Every(Duration),                           // `poll_interval_seconds`, 60 by default
+    Cron { expression: String, timezone: Tz }, // `cron_expression` and an optional `timezone`
+    CallbacksOnly,                             // `poll_interval_seconds` of 0
+}
+
+impl PollSchedule {
+    pub fn from_config(config: &Value) -> Self {
+        if let Some(expression) = config
+            .get("cron_expression")
+            .and_then(Value::as_str)
+            .map(str::trim)
+            .filter(|expression| !expression.is_empty())
+        {
+            let timezone = config
+                .get("timezone")
+                .and_then(Value::as_str)
+                .and_then(|timezone| timezone.trim().parse::<Tz>().ok())
+                .unwrap_or(Tz::UTC);
+            return PollSchedule::Cron {
+                expression: expression.to_string(),
+                timezone,
+            };
+        }
+
+        // Rendered config values can be numbers or strings
+        let interval = match config.get("poll_interval_seconds") {
+            Some(Value::Number(seconds)) => seconds.as_u64(),
+            Some(Value::String(seconds)) => seconds.trim().parse().ok(),
+            _ => None,
+        }
+        .unwrap_or(DEFAULT_POLL_INTERVAL_SECS);
+
+        match interval {
+            0 => PollSchedule::CallbacksOnly,
+            seconds => {
+                PollSchedule::Every(Duration::from_secs(seconds.max(MIN_POLL_INTERVAL_SECS)))
+            }
+        }
+    }
+
+    pub fn next_poll_after(&self, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        match self {
+            PollSchedule::Every(interval) => {
+                Some(after + chrono::Duration::from_std(*interval).ok()?)
+            }
+            PollSchedule::Cron {
+                expression,
+                timezone,
+            } => match Schedule::from_str(expression) {
+                Ok(schedule) => next_fire_after(&schedule, timezone, after),
+                Err(e) => {
+                    error!(
+                        "[PLUGIN_TRIGGERS] Error parsing cron expression {}: {}",
+                        expression, e
+                    );
+                    None
+                }
+            },
+            PollSchedule::CallbacksOnly => None,
+        }
+    }
+}
+
+/// A trigger plugin started for one active workflow
+#[derive(Debug, Clone)]
+pub struct PluginTrigger {
+    pub target: EventTarget,
+    pub plugin_id: String,
+    pub plugin_version: Version, // The loaded version the action resolved to
+    pub config: Value, // The trigger's plugin config rendered with the account's secrets and variables
+    pub schedule: PollSchedule,
+    pub next_poll: Option<DateTime<Utc>>,
+}
+
+impl PluginTrigger {
+    pub fn key(&self) -> String {
+        format!("{}:{}", self.target.flow_id, self.target.action_id)
+    }
+}
+
+/// Polls the trigger plugins of every active, published workflow and turns the events they
+/// create into flow sessions. Only the replica holding the lease polls, and events are
+/// recorded in `trigger_fires` by id, so events aren't doubled.
+pub async fn plugin_trigger_loop(state: Arc<AppState>) {
+    if state.plugin_host.action_templates("trigger").is_empty() {
+        info!("[PLUGIN_TRIGGERS] No trigger plugins loaded, not starting plugin triggers");
+        return;
+    }
+
+    let client = state.anything_client.clone();
+    let mut triggers = hydrate_plugin_triggers(&state, &client, HashMap::new()).await;
+
+    // Published and deleted workflows are signalled the same way as for cron triggers
+    let mut trigger_engine_signal_rx = state.trigger_engine_signal.subscribe();
+    let mut leadership = Leadership::new(PLUGIN_TRIGGER_LEASE);
+    let mut running_polls: HashMap<String, JoinHandle<()>> = HashMap::new();
+
+    loop {
+        let refresh_interval = if leadership.is_leader() {
+            time_until_next_poll(&triggers, Utc::now()).min(leadership.time_until_renew())
+        } else {
+            leadership.time_until_renew()
+        };
+
+        tokio::select! {
+            _ = sleep(refresh_interval) => {
+                if state.shutdown_signal.load(std::sync::atomic::Ordering::SeqCst) {
+                    if leadership.is_leader() {
+                        release_lease(&state, PLUGIN_TRIGGER_LEASE).await;
+                    }
+                    info!("[PLUGIN_TRIGGERS] Shutdown signal received, stopping plugin triggers");
+                    break;
+                }
+
+                match leadership.check(&state).await {
+                    LeadershipChange::Acquired | LeadershipChange::Kept => {}
+                    LeadershipChange::Lost | LeadershipChange::Follower => continue,
+                }
+
+                let now = Utc::now();
+                running_polls.retain(|_, poll| !poll.is_finished());
+                for (key, trigger) in triggers.iter_mut() {
+                    if trigger.next_poll.is_none_or(|next_poll| next_poll > now) {
+                        continue;
+                    }
+                    trigger.next_poll = trigger.schedule.next_poll_after(now);
+
+                    // Polls of one trigger never overlap, a second one would report the same events
+                    if running_polls.contains_key(key) {
+                        warn!(
+                            "[PLUGIN_TRIGGERS] Plugin {} is still polling for workflow {}, skipping this poll",
+                            trigger.plugin_id, trigger.target.flow_id
+                        );
+                        continue;
+                    }
+
+                    // A slow plugin delays only its own trigger
+                    running_polls.insert(
+                        key.clone(),
+                        tokio::spawn(poll_plugin_trigger(state.clone(), trigger.clone())),
+                    );
+                }
+            }
+            _ = trigger_engine_signal_rx.changed() => {
+                let workflow_id = trigger_engine_signal_rx.borrow().clone();
+                info!("[PLUGIN_TRIGGERS] Workflow {} changed, reloading plugin triggers", workflow_id);
+                triggers = hydrate_plugin_triggers(&state, &client, triggers).await;
+            }
+        }
+    }
+}
+
+fn time_until_next_poll(triggers: &HashMap<String, PluginTrigger>, now: DateTime<Utc>) -> Duration {
+    triggers
+        .values()
+        .filter_map(|trigger| trigger.next_poll)
+        .min()
+        .map(|next_poll| (next_poll - now).to_std().unwrap_or(Duration::ZERO))
+        .unwrap_or(MAX_IDLE_INTERVAL)
+        .min(MAX_IDLE_INTERVAL)
+}
+
+/// Calls the plugin's `poll` export, or `execute` for plugins without one
+async fn poll_plugin_trigger(state: Arc<AppState>, trigger: PluginTrigger) {
+    let Some(plugin) = state
+        .plugin_host
+        .get_version(&trigger.plugin_id, &trigger.plugin_version)
+    else {
+        return;
+    };
+
+    let function = if plugin.has_function("poll") {
+        "poll"
+    } else {
+        "execute"
+    };
+    info!(
+        "[PLUGIN_TRIGGERS] Polling plugin {} for workflow {} with {}",
+        trigger.plugin_id, trigger.target.flow_id, function
+    );
+
+    let context = HostContext::for_trigger(state.clone(), &trigger.target, &trigger.plugin_id);
+    if let Err(e) = plugin.call(function, &trigger.config, context).await {
+        error!(
+            "[PLUGIN_TRIGGERS] Plugin {} failed polling for workflow {}: {}",
+            trigger.plugin_id, trigger.target.flow_id, e
+        );
+    }
+}
+
+/// Loads the plugin triggers of every active, published workflow, keeping the next poll
+/// of the triggers that were already running
+async fn hydrate_plugin_triggers(
+    state: &Arc<AppState>,
+    client: &Postgrest,
+    existing: HashMap<String, PluginTrigger>,
+) -> HashMap<String, PluginTrigger> {
+    let flow_versions = match get_active_flow_versions(client, None).await {
+        Ok(flow_versions) => flow_versions,
+        Err(e) => {
+            error!("[PLUGIN_TRIGGERS] Error fetching flow versions: {}", e);
+            return existing;
+        }
+    };
+
+    let mut triggers = HashMap::new();
+    for flow_version in &flow_versions {
+        for action in &flow_version.flow_definition.actions {
+            let Some(mut trigger) = plugin_trigger_from_action(state, flow_version, action).await
+            else {
+                continue;
+            };
+
+            let key = trigger.key();
+            trigger.next_poll = match existing.get(&key) {
+                Some(running) if running.schedule == trigger.schedule => running.next_poll,
+                _ => trigger.schedule.next_poll_after(Utc::now()),
+            };
+            triggers.insert(key, trigger);
+        }
+    }
+
+    info!(
+        "[PLUGIN_TRIGGERS] Running {} plugin triggers",
+        triggers.len()
+    );
+    triggers
+}
+
+/// Gets the published versions of active workflows, or only the given workflow's
+async fn get_active_flow_versions(
+    client: &Postgrest,
+    workflow_id: Option<&Uuid>,
+) -> Result<Vec<DatabaseFlowVersion>, Box<dyn std::error::Error + Send + Sync>> {
+    dotenv().ok();
+    let supabase_service_role_api_key = env::var("SUPABASE_SERVICE_ROLE_API_KEY")
+        .map_err(|_| "SUPABASE_SERVICE_ROLE_API_KEY must be set")?;
+
+    let mut query = client
+        .from("flow_versions")
+        .auth(supabase_service_role_api_key)
+        .select("*, flows!inner(active)")
+        .eq("published", "true")
+        .eq("flows.active", "true");
+    if let Some(workflow_id) = workflow_id {
+        query = query.eq("flow_id", workflow_id.to_string());
+    }
+
+    let response = query.execute().await?;
+
+    let body = response.text().await?;
+    Ok(serde_json::from_str(&body)?)
+}
+
+/// The plugin trigger for a workflow's trigger action, when a loaded trigger plugin handles it
+pub async fn plugin_trigger_from_action(
+    state: &Arc<AppState>,
+    flow_version: &DatabaseFlowVersion,
+    action: &Action,
+) -> Option<PluginTrigger> {
+    if action.r#type != ActionType::Trigger {
+        return None;
+    }
+    let Some(plugin) = state.plugin_host.resolve(action) else {
+        if !state
+            .plugin_host
+            .versions(action.plugin_name.as_str())
+            .is_empty()
+        {
+            warn!(
+                "[PLUGIN_TRIGGERS] Trigger {} in workflow {} pins {} {}, which isn't loaded",
+                action.action_id, flow_version.flow_id, action.plugin_name, action.plugin_version
+            );
+        }
+        return None;
+    };
+    if !plugin.registration.trigger {
+        return None;
+    }
+
+    let config = match bundle_context_from_parts(
+        state.clone(),
+        &state.anything_client,
+        &flow_version.account_id.to_string(),
+        &Uuid::new_v4().to_string(),
+        action.inputs.as_ref(),
+        action.inputs_schema.as_ref(),
+        Some(&action.plugin_config),
+        Some(&action.plugin_config_schema),
+        false,
+    )
+    .await
+    {
+        Ok(config) => config,
+        Err(e) => {
+            error!(
+                "[PLUGIN_TRIGGERS] Failed to render config of trigger {} in workflow {}: {}",
+                action.action_id, flow_version.flow_id, e
+            );
+            return None;
+        }
+    };
+
+    let target = EventTarget {
+        account_id: flow_version.account_id,
+        flow_id: flow_version.flow_id,
+        flow_version_id: flow_version.flow_version_id,
+        action_id: action.action_id.clone(),
+        action_label: action.label.clone(),
+        plugin_name: Some(action.plugin_name.clone()),
+        plugin_version: Some(plugin.registration.version.clone()),
+        config: TaskConfig {
+            inputs: action.inputs.clone(),
+            inputs_schema: action.inputs_schema.clone(),
+            plugin_config: Some(action.plugin_config.clone()),
+            plugin_config_schema: Some(action.plugin_config_schema.clone()),
+        },
+    };
+
+    Some(PluginTrigger {
+        target,
+        plugin_id: plugin.registration.plugin_id.clone(),
+        plugin_version: plugin.registration.version.clone(),
+        schedule: PollSchedule::from_config(&config),
+        config,
+        next_poll: None,
+    })
+}
+
+/// Hands a request to the `on_callback` export of a published workflow's trigger plugin,
+/// which checks it and creates events from it
+pub async fn handle_plugin_trigger_callback(
+    Path((workflow_id, action_id)): Path<(String, String)>,
+    State(state): State<Arc<AppState>>,
+    method: Method,
+    Query(query): Query<HashMap<String, String>>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> impl IntoResponse {
+    let Ok(workflow_id) = Uuid::parse_str(&workflow_id) else {
+        return (StatusCode::BAD_REQUEST, "Invalid workflow id").into_response();
+    };
+
+    // Paused workflows read as not found, so they don't fire events
+    let flow_version = match get_active_flow_versions(&state.anything_client, Some(&workflow_id))
+        .await
+        .map(|flow_versions| flow_versions.into_iter().next())
+    {
+        Ok(Some(flow_version)) => flow_version,
+        Ok(None) => {
+            return (StatusCode::NOT_FOUND, "No published workflow found").into_response();
+        }
+        Err(e) => {
+            error!(
+                "[PLUGIN_TRIGGERS] Error fetching workflow {} for a callback: {}",
+                workflow_id, e
+            );
+            return (StatusCode::INTERNAL_SERVER_ERROR, "Failed to load workflow").into_response();
+        }
+    };
+
+    let trigger = match flow_version
+        .flow_definition
+        .actions
+        .iter()
+        .find(|action| action.action_id == action_id)
+    {
+        Some(action) => plugin_trigger_from_action(&state, &flow_version, action).await,
+        None => None,
+    };
+    let Some(trigger) = trigger else {
+        return (
+            StatusCode::NOT_FOUND,
+            "No plugin trigger found for this action",
+        )
+            .into_response();
+    };
+
+    let Some(plugin) = state
+        .plugin_host
+        .get_version(&trigger.plugin_id, &trigger.plugin_version)
+    else {
+        return (
+            StatusCode::NOT_FOUND,
+            "No plugin trigger found for this action",
+        )
+            .into_response();
+    };
+    if !plugin.has_function("on_callback") {
+        warn!(
+            "[PLUGIN_TRIGGERS] Plugin {} got a callback but doesn't export on_callback",
+            trigger.plugin_id
+        );
+        return (
+            StatusCode::BAD_REQUEST,
+            "This trigger doesn't accept callbacks",
+        )
+            .into_response();
+    }
+
+    // Nothing has checked the request yet, so uploaded files are handed to the plugin
+    // instead of being stored for the account
+    let body = match parse_request_body_in_memory(&headers, &body).await {
+        Ok(body) => body.unwrap_or(Value::Null),
+        Err((status, message)) => return (status, message).into_response(),
+    };
+
+    let headers: HashMap<String, String> = headers
+        .iter()
+        .map(|(key, value)| {
+            (
+                key.to_string(),
+                value.to_str().unwrap_or_default().to_string(),
+            )
+        })
+        .collect();
+
+    let input = json!({
+        "config": trigger.config,
+        "request": {
+            "method": method.as_str(),
+            "headers": headers,
+            "query": query,
+            "body": body,
+        }
+    });
+
+    let context = HostContext::for_trigger(state.clone(), &trigger.target, &trigger.plugin_id);
+    match plugin.call("on_callback", &input, context).await {
+        Ok(output) => Json(output.unwrap_or_else(|| json!({}))).into_response(),
+        Err(e) => {
+            error!(
+                "[PLUGIN_TRIGGERS] Plugin {} failed handling a callback for workflow {}: {}",
+                trigger.plugin_id, workflow_id, e
+            );
+            (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_poll_schedule_from_config() {
+        assert_eq!(
+            PollSchedule::from_config(&json!({})),
+            PollSchedule::Every(Duration::from_secs(60))
+        );
+        assert_eq!(
+            PollSchedule::from_config(&json!({ "poll_interval_seconds": "300" })),
+            PollSchedule::Every(Duration::from_secs(300))
+        );
+        assert_eq!(
+            PollSchedule::from_config(&json!({ "poll_interval_seconds": 1 })),
+            PollSchedule::Every(Duration::from_secs(MIN_POLL_INTERVAL_SECS))
+        );
+        assert_eq!(
+            PollSchedule::from_config(&json!({ "poll_interval_seconds": 0 })),
+            PollSchedule::CallbacksOnly
+        );
+
+        let schedule = PollSchedule::from_config(&json!({
+            "cron_expression": "0 */5 * * * *",
+            "timezone": "America/New_York"
+        }));
+        assert_eq!(
+            schedule,
+            PollSchedule::Cron {
+                expression: "0 */5 * * * *".to_string(),
+                timezone: Tz::America__New_York,
+            }
+        );
+
+        let now = DateTime::parse_from_rfc3339("2024-07-04T12:01:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        assert_eq!(
+            schedule.next_poll_after(now),
+            Some(
+                DateTime::parse_from_rfc3339("2024-07-04T12:05:00Z")
+                    .unwrap()
+                    .with_timezone(&Utc)
+            )
+        );
+        assert_eq!(PollSchedule::CallbacksOnly.next_poll_after(now), None);
+    }
+}
@@ -0,0 +1,485 @@
+use axum::{
+    body::to_bytes,
+    extract::{Extension, Path, State},
+    http::{HeaderMap, StatusCode},
+    response::IntoResponse,
+    Json,
+};
+use node_semver::{Range, Version};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+use tracing::{error, info};
+
+use crate::supabase_jwt_middleware::User;
+use crate::system_plugins::registry;
+use crate::types::action_types::Action;
+use crate::types::workflow_types::WorkflowVersionDefinition;
+use crate::workflow_analysis::fetch_flow_definition;
+use crate::workflows::update_workflow_version;
+use crate::AppState;
+
+/// The `action_template_definition` of every plugin version the server can run, by plugin_name
+pub type PluginCatalog = HashMap<String, BTreeMap<Version, Value>>;
+
+/// Picks the version an action runs: the newest available one in its range when it has
+/// one, otherwise exactly the version it pins
+pub fn resolve_version<'a>(
+    available: impl IntoIterator<Item = &'a Version>,
+    pinned: &Version,
+    range: Option<&Range>,
+) -> Option<Version> {
+    match range {
+        Some(range) => available
+            .into_iter()
+            .filter(|version| version.satisfies(range))
+            .max()
+            .cloned(),
+        None => available
+            .into_iter()
+            .find(|version| *version == pinned)
+            .cloned(),
+    }
+}
+
+/// Built in plugins from the schema registry, each at its one version, and every loaded wasm plugin version
+pub fn plugin_catalog(state: &AppState) -> PluginCatalog {
+    let mut catalog = PluginCatalog::new();
+
+    match registry::load_schema_templates() {
+        Ok(templates) => {
+            for template in templates {
+                insert_template(&mut catalog, &template["action_template_definition"]);
+            }
+        }
+        Err(e) => error!("[PLUGIN_VERSIONS] Failed to load schema templates: {}", e),
+    }
+
+    for plugin in state.plugin_host.plugins() {
+        let template = plugin.registration.action_template();
+        insert_template(&mut catalog, &template["action_template_definition"]);
+    }
+
+    catalog
+}
+
+fn insert_template(catalog: &mut PluginCatalog, definition: &Value) {
+    let plugin_name = definition.get("plugin_name").and_then(Value::as_str);
+    let version = definition
+        .get("plugin_version")
+        .and_then(Value::as_str)
+        .and_then(|version| Version::parse(version).ok());
+
+    if let (Some(plugin_name), Some(version)) = (plugin_name, version) {
+        catalog
+            .entry(plugin_name.to_string())
+            .or_default()
+            .insert(version, definition.clone());
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VersionStatus {
+    Current,          // Runs the newest version there is
+    UpgradeAvailable, // Runs an older version than the newest one
+    Missing,          // Pins a version, or a range, nothing available satisfies
+    UnknownPlugin,    // No version of the plugin is available
+}
+
+/// Which version of its plugin one action runs
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ActionVersionReport {
+    pub action_id: String,
+    pub action_label: String,
+    pub plugin_name: String,
+    pub plugin_version: Version,
+    pub plugin_version_range: Option<Range>,
+    pub resolved_version: Option<Version>,
+    pub latest_version: Option<Version>,
+    pub status: VersionStatus,
+}
+
+pub fn check_action_versions(
+    workflow: &WorkflowVersionDefinition,
+    catalog: &PluginCatalog,
+) -> Vec<ActionVersionReport> {
+    workflow
+        .actions
+        .iter()
+        .map(|action| check_action_version(action, catalog))
+        .collect()
+}
+
+fn check_action_version(action: &Action, catalog: &PluginCatalog) -> ActionVersionReport {
+    let versions = catalog.get(action.plugin_name.as_str());
+    let latest_version = versions.and_then(|versions| versions.keys().next_back().cloned());
+    let resolved_version = versions.and_then(|versions| {
+        resolve_version(
+            versions.keys(),
+            &action.plugin_version,
+            action.plugin_version_range.as_ref(),
+        )
+    });
+
+    let status = match (&resolved_version, &latest_version) {
+        (_, None) => VersionStatus::UnknownPlugin,
+        (None, Some(_)) => VersionStatus::Missing,
+        (Some(resolved), Some(latest)) if resolved < latest => VersionStatus::UpgradeAvailable,
+        (Some(_), Some(_)) => VersionStatus::Current,
+    };
+
+    ActionVersionReport {
+        action_id: action.action_id.clone(),
+        action_label: action.label.clone(),
+        plugin_name: action.plugin_name.to_string(),
+        plugin_version: action.plugin_version.clone(),
+        plugin_version_range: action.plugin_version_range.clone(),
+        resolved_version,
+        latest_version,
+        status,
+    }
+}
+
+/// Moves a config onto a newer schema. Fields the schema still allows are kept, fields it
+/// dropped are removed and fields it added start at the template's value.
+pub fn migrate_value(current: &Value, defaults: Option<&Value>, schema: Option<&Value>) -> Value {
+    let properties = schema
+        .and_then(|schema| schema.get("properties"))
+        .and_then(Value::as_object);
+    let allows_additional = schema
+        .and_then(|schema| schema.get("additionalProperties"))
+        .and_then(Value::as_bool)
+        != Some(false);
+
+    let mut migrated = defaults
+        .and_then(Value::as_object)
+        .cloned()
+        .unwrap_or_else(Map::new);
+
+    if let Some(current) = current.as_object() {
+        for (key, value) in current {
+            if allows_additional
+                || properties.is_some_and(|properties| properties.contains_key(key))
+            {
+                migrated.insert(key.clone(), value.clone());
+            }
+        }
+    }
+
+    Value::Object(migrated)
+}
+
+/// Moves an action to another version of its plugin, migrating its plugin config to the
+/// version's schema. Inputs are only migrated for plugins that declare an inputs schema,
+/// otherwise they are the user's own variables.
+pub fn upgrade_action(action: &Action, definition: &Value) -> Result<Action, String> {
+    let version = definition
+        .get("plugin_version")
+        .and_then(Value::as_str)
+        .and_then(|version| Version::parse(version).ok())
+        .ok_or("Plugin template has no valid plugin_version")?;
+
+    let mut upgraded = action.clone();
+
+    let plugin_config_schema = definition.get("plugin_config_schema");
+    upgraded.plugin_config = migrate_value(
+        &action.plugin_config,
+        definition.get("plugin_config"),
+        plugin_config_schema,
+    );
+    if let Some(schema) = plugin_config_schema {
+        upgraded.plugin_config_schema = serde_json::from_value(schema.clone())
+            .map_err(|e| format!("Invalid plugin_config_schema in {}: {}", version, e))?;
+    }
+
+    let inputs_schema = definition
+        .get("inputs_schema")
+        .filter(|schema| schema.get("properties").is_some());
+    if let Some(schema) = inputs_schema {
+        upgraded.inputs = Some(migrate_value(
+            action.inputs.as_ref().unwrap_or(&Value::Null),
+            definition.get("inputs"),
+            Some(schema),
+        ));
+        upgraded.inputs_schema = Some(
+            serde_json::from_value(schema.clone())
+                .map_err(|e| format!("Invalid inputs_schema in {}: {}", version, e))?,
+        );
+    }
+
+    // A range the new version falls outside of would resolve back to an older version
+    if let Some(range) = &action.plugin_version_range {
+        if !version.satisfies(range) {
+            upgraded.plugin_version_range = None;
+        }
+    }
+    upgraded.plugin_version = version;
+
+    Ok(upgraded)
+}
+
+/// Reports the plugin version each action of a flow version runs, and the ones pinning versions that are gone
+pub async fn get_plugin_versions(
+    Path((account_id, _workflow_id, workflow_version_id)): Path<(String, String, String)>,
+    State(state): State<Arc<AppState>>,
+    Extension(user): Extension<User>,
+) -> impl IntoResponse {
+    let workflow =
+        match fetch_flow_definition(&state, &user, &account_id, &workflow_version_id).await {
+            Ok(Some(workflow)) => workflow,
+            Ok(None) => return (StatusCode::NOT_FOUND, "Flow version not found").into_response(),
+            Err(e) => {
+                error!("[PLUGIN_VERSIONS] {}", e);
+                return (StatusCode::INTERNAL_SERVER_ERROR, e).into_response();
+            }
+        };
+
+    let actions = check_action_versions(&workflow, &plugin_catalog(&state));
+    let missing = actions
+        .iter()
+        .any(|action| action.status == VersionStatus::Missing);
+
+    Json(json!({
+        "valid": !missing,
+        "actions": actions,
+    }))
+    .into_response()
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct UpgradeActionsRequest {
+    pub action_ids: Option<Vec<String>>, // Every action with a newer version when not set
+}
+
+/// Moves actions of a flow version to the newest version of their plugins and saves it,
+/// a published version is saved as a new draft like any other edit
+pub async fn upgrade_actions(
+    Path((account_id, workflow_id, workflow_version_id)): Path<(String, String, String)>,
+    State(state): State<Arc<AppState>>,
+    Extension(user): Extension<User>,
+    payload: Option<Json<UpgradeActionsRequest>>,
+) -> impl IntoResponse {
+    let request = payload.map(|Json(request)| request).unwrap_or_default();
+
+    let mut workflow =
+        match fetch_flow_definition(&state, &user, &account_id, &workflow_version_id).await {
+            Ok(Some(workflow)) => workflow,
+            Ok(None) => return (StatusCode::NOT_FOUND, "Flow version not found").into_response(),
+            Err(e) => {
+                error!("[PLUGIN_VERSIONS] {}", e);
+                return (StatusCode::INTERNAL_SERVER_ERROR, e).into_response();
+            }
+        };
+
+    let catalog = plugin_catalog(&state);
+    let mut upgrades = Vec::new();
+
+    for action in workflow.actions.iter_mut() {
+        if let Some(action_ids) = &request.action_ids {
+            if !action_ids.contains(&action.action_id) {
+                continue;
+            }
+        }
+
+        let report = check_action_version(action, &catalog);
+        if !matches!(
+            report.status,
+            VersionStatus::UpgradeAvailable | VersionStatus::Missing
+        ) {
+            continue;
+        }
+        // Actions pinning a removed version move to the newest one too
+        let Some((latest_version, definition)) = catalog
+            .get(action.plugin_name.as_str())
+            .and_then(|versions| versions.iter().next_back())
+        else {
+            continue;
+        };
+
+        match upgrade_action(action, definition) {
+            Ok(upgraded) => {
+                info!(
+                    "[PLUGIN_VERSIONS] Upgrading action {} from {} {} to {}",
+                    action.action_id, action.plugin_name, action.plugin_version, latest_version
+                );
+                upgrades.push(json!({
+                    "action_id": action.action_id,
+                    "plugin_name": action.plugin_name,
+                    "from_version": action.plugin_version,
+                    "to_version": latest_version,
+                }));
+                *action = upgraded;
+            }
+            Err(e) => {
+                error!(
+                    "[PLUGIN_VERSIONS] Failed to upgrade action {}: {}",
+                    action.action_id, e
+                );
+                return (
+                    StatusCode::UNPROCESSABLE_ENTITY,
+                    format!("Failed to upgrade action {}: {}", action.action_id, e),
+                )
+                    .into_response();
+            }
+        }
+    }
+
+    if upgrades.is_empty() {
+        return Json(json!({ "upgrades": upgrades, "flow_version": null })).into_response();
+    }
+
+    let flow_definition = match serde_json::to_value(&workflow) {
+        Ok(flow_definition) => flow_definition,
+        Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    };
+
+    let saved = update_workflow_version(
+        Path((account_id, workflow_id, workflow_version_id)),
+        State(state),
+        Extension(user),
+        HeaderMap::new(),
+        Json(flow_definition),
+    )
+    .await
+    .into_response();
+
+    if !saved.status().is_success() {
+        return saved;
+    }
+
+    // The update responds with the saved rows as a JSON encoded string
+    let flow_version = match to_bytes(saved.into_body(), usize::MAX).await {
+        Ok(body) => serde_json::from_slice::<String>(&body)
+            .ok()
+            .and_then(|body| serde_json::from_str::<Value>(&body).ok())
+            .unwrap_or(Value::Null),
+        Err(_) => Value::Null,
+    };
+
+    Json(json!({
+        "upgrades": upgrades,
+        "flow_version": flow_version,
+    }))
+    .into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn action(plugin_version: &str, plugin_version_range: Option<&str>) -> Action {
+        serde_json::from_value(json!({
+            "anything_action_version": "0.1.0",
+            "type": "action",
+            "plugin_name": "@acme/slack",
+            "plugin_version": plugin_version,
+            "plugin_version_range": plugin_version_range,
+            "action_id": "send_message",
+            "label": "Send Message",
+            "icon": "",
+            "inputs": { "greeting": "hi" },
+            "plugin_config": { "channel": "#general", "as_user": true },
+            "plugin_config_schema": {
+                "type": "object",
+                "properties": { "channel": {}, "as_user": {} },
+                "additionalProperties": false
+            }
+        }))
+        .unwrap()
+    }
+
+    fn catalog(versions: &[&str]) -> PluginCatalog {
+        let mut catalog = PluginCatalog::new();
+        for version in versions {
+            insert_template(
+                &mut catalog,
+                &json!({
+                    "plugin_name": "@acme/slack",
+                    "plugin_version": version,
+                    "plugin_config": { "channel": "", "thread": null },
+                    "plugin_config_schema": {
+                        "type": "object",
+                        "properties": { "channel": {}, "thread": {} },
+                        "additionalProperties": false
+                    },
+                    "inputs_schema": {}
+                }),
+            );
+        }
+        catalog
+    }
+
+    #[test]
+    fn test_resolve_version() {
+        let available: Vec<Version> = ["1.0.0", "1.2.0", "1.3.1", "2.0.0"]
+            .iter()
+            .map(|version| Version::parse(version).unwrap())
+            .collect();
+        let pinned = Version::parse("1.2.0").unwrap();
+
+        assert_eq!(
+            resolve_version(&available, &pinned, None),
+            Some(Version::parse("1.2.0").unwrap())
+        );
+        assert_eq!(
+            resolve_version(&available, &pinned, Some(&Range::parse("^1.2.0").unwrap())),
+            Some(Version::parse("1.3.1").unwrap())
+        );
+        assert_eq!(
+            resolve_version(&available, &Version::parse("1.1.0").unwrap(), None),
+            None
+        );
+        assert_eq!(
+            resolve_version(&available, &pinned, Some(&Range::parse("^3").unwrap())),
+            None
+        );
+    }
+
+    #[test]
+    fn test_check_action_versions() {
+        let catalog = catalog(&["1.0.0", "1.1.0"]);
+
+        let current = check_action_version(&action("1.1.0", None), &catalog);
+        assert_eq!(current.status, VersionStatus::Current);
+
+        let upgrade = check_action_version(&action("1.0.0", None), &catalog);
+        assert_eq!(upgrade.status, VersionStatus::UpgradeAvailable);
+        assert_eq!(
+            upgrade.latest_version,
+            Some(Version::parse("1.1.0").unwrap())
+        );
+
+        let ranged = check_action_version(&action("1.0.0", Some("^1.0.0")), &catalog);
+        assert_eq!(ranged.status, VersionStatus::Current);
+        assert_eq!(
+            ranged.resolved_version,
+            Some(Version::parse("1.1.0").unwrap())
+        );
+
+        let missing = check_action_version(&action("0.9.0", None), &catalog);
+        assert_eq!(missing.status, VersionStatus::Missing);
+
+        let unknown = check_action_version(&action("1.0.0", None), &PluginCatalog::new());
+        assert_eq!(unknown.status, VersionStatus::UnknownPlugin);
+    }
+
+    #[test]
+    fn test_upgrade_action() {
+        let catalog = catalog(&["2.0.0"]);
+        let definition = &catalog["@acme/slack"][&Version::parse("2.0.0").unwrap()];
+
+        let upgraded = upgrade_action(&action("1.0.0", Some("^1.0.0")), definition).unwrap();
+
+        assert_eq!(upgraded.plugin_version, Version::parse("2.0.0").unwrap());
+        assert_eq!(upgraded.plugin_version_range, None);
+        // as_user was dropped from the schema, thread was added
+        assert_eq!(
+            upgraded.plugin_config,
+            json!({ "channel": "#general", "thread": null })
+        );
+        // The template has no inputs schema, so the action's own inputs stay
+        assert_eq!(upgraded.inputs, Some(json!({ "greeting": "hi" })));
+    }
+}
@@ -8,7 +8,7 @@ use uuid::Uuid;
 
 use crate::system_plugins::http::http_plugin::parse_headers;
 use crate::types::{
-    task_types::{FlowSessionStatus, Task, TaskStatus, TriggerSessionStatus},
+    task_types::{FlowSessionStatus, Task, TaskAttempt, TaskStatus, TriggerSessionStatus},
     workflow_types::DatabaseFlowVersion,
 };
 use crate::AppState;
@@ -138,12 +138,12 @@ pub async fn get_session_tasks(
         format!("Failed to parse tasks: {}", e)
     })?;
 
+    // Sessions that stopped before their first task was written have none
     if tasks.is_empty() {
         println!(
             "[PROCESSOR DB CALLS] No tasks found for session {}",
             flow_session_id
         );
-        return Err("No tasks found for session".to_string());
     }
 
     println!(
@@ -265,6 +265,73 @@ pub async fn update_task_status(
     Ok(())
 }
 
+pub async fn update_task_attempts(
+    state: Arc<AppState>,
+    task_id: &Uuid,
+    attempts: &[TaskAttempt],
+) -> Result<(), String> {
+    println!(
+        "[PROCESSOR DB CALLS] Recording {} attempts for task {}",
+        attempts.len(),
+        task_id
+    );
+    dotenv().ok();
+    let supabase_service_role_api_key = env::var("SUPABASE_SERVICE_ROLE_API_KEY")
+        .expect("SUPABASE_SERVICE_ROLE_API_KEY must be set");
+
+    state
+        .anything_client
+        .from("tasks")
+        .auth(supabase_service_role_api_key)
+        .eq("task_id", &task_id.to_string())
+        .update(serde_json::json!({ "attempts": attempts }).to_string())
+        .execute()
+        .await
+        .map_err(|e| {
+            println!(
+                "[PROCESSOR DB CALLS] Failed to execute update task attempts request: {}",
+                e
+            );
+            format!("Failed to execute request: {}", e)
+        })?;
+
+    println!("[PROCESSOR DB CALLS] Successfully recorded task attempts");
+    Ok(())
+}
+
+pub async fn update_task_debug_result(
+    state: Arc<AppState>,
+    task_id: &Uuid,
+    debug_result: &Value,
+) -> Result<(), String> {
+    println!(
+        "[PROCESSOR DB CALLS] Recording debug result for task {}",
+        task_id
+    );
+    dotenv().ok();
+    let supabase_service_role_api_key = env::var("SUPABASE_SERVICE_ROLE_API_KEY")
+        .expect("SUPABASE_SERVICE_ROLE_API_KEY must be set");
+
+    state
+        .anything_client
+        .from("tasks")
+        .auth(supabase_service_role_api_key)
+        .eq("task_id", task_id.to_string())
+        .update(serde_json::json!({ "debug_result": debug_result }).to_string())
+        .execute()
+        .await
+        .map_err(|e| {
+            println!(
+                "[PROCESSOR DB CALLS] Failed to execute update task debug result request: {}",
+                e
+            );
+            format!("Failed to execute request: {}", e)
+        })?;
+
+    println!("[PROCESSOR DB CALLS] Successfully recorded task debug result");
+    Ok(())
+}
+
 pub async fn update_flow_session_status(
     state: &AppState,
     flow_session_id: &Uuid,
@@ -312,6 +379,53 @@ pub async fn update_flow_session_status(
     Ok(())
 }
 
+/// Marks every task of a canceled flow session that had not finished as canceled
+pub async fn cancel_unfinished_tasks(
+    state: &AppState,
+    flow_session_id: &Uuid,
+) -> Result<(), String> {
+    println!(
+        "[PROCESSOR DB CALLS] Canceling unfinished tasks of flow session {}",
+        flow_session_id
+    );
+    dotenv().ok();
+    let supabase_service_role_api_key = env::var("SUPABASE_SERVICE_ROLE_API_KEY")
+        .expect("SUPABASE_SERVICE_ROLE_API_KEY must be set");
+
+    state
+        .anything_client
+        .from("tasks")
+        .auth(supabase_service_role_api_key)
+        .eq("flow_session_id", flow_session_id.to_string())
+        .in_(
+            "task_status",
+            [
+                TaskStatus::Pending.as_str(),
+                TaskStatus::Waiting.as_str(),
+                TaskStatus::Running.as_str(),
+            ],
+        )
+        .update(
+            serde_json::json!({
+                "task_status": TaskStatus::Canceled.as_str(),
+                "ended_at": Utc::now(),
+            })
+            .to_string(),
+        )
+        .execute()
+        .await
+        .map_err(|e| {
+            println!(
+                "[PROCESSOR DB CALLS] Failed to execute cancel tasks request: {}",
+                e
+            );
+            format!("Failed to execute request: {}", e)
+        })?;
+
+    println!("[PROCESSOR DB CALLS] Successfully canceled unfinished tasks");
+    Ok(())
+}
+
 pub fn redact_headers_from_context(context: &Value) -> Value {
     let mut new_context = context.clone();
 
@@ -5,8 +5,17 @@ use std::time::{Duration, Instant};
 use postgrest::Postgrest;
 use uuid::Uuid;
 
+use crate::actor_processor::status_updates::send_status_update;
 use crate::bundler::bundle_tasks_cached_context_with_tasks;
+use crate::plugin_host::host_functions::HostContext;
+use crate::plugin_host::WasmPlugin;
 use crate::processor::process_trigger_utils::process_trigger_task;
+use crate::status_updater::Operation;
+use crate::system_plugins::approval::process_approval_task;
+use crate::system_plugins::call_workflow::{
+    finish_workflow_call, process_call_workflow_task, MAX_TIMEOUT_SECONDS,
+};
+use crate::system_plugins::delay::process_delay_task;
 use crate::system_plugins::formatter_actions::{
     date_formatter::process_date_task, text_formatter::process_text_task,
 };
@@ -17,6 +26,8 @@ use crate::system_plugins::agent_tool_trigger_response::process_tool_call_result
 use crate::system_plugins::filter::process_filter_task;
 use crate::system_plugins::http::http_plugin::process_http_task;
 use crate::system_plugins::javascript::process_js_task;
+use crate::system_plugins::loop_plugin::{process_loop_end_task, process_loop_task};
+use crate::system_plugins::output::process_output_task;
 use crate::types::action_types::{ActionType, PluginName};
 use crate::types::task_types::Task;
 use crate::AppState;
@@ -163,6 +174,7 @@ fn get_plugin_timeout(plugin_name: &Option<PluginName>) -> Duration {
         Some("@anything/http") => Duration::from_secs(45),   // 45s for HTTP - network operations
         Some("@anything/webhook_response") => Duration::from_secs(20), // 20s for webhook response
         Some("@anything/agent_tool_call_response") => Duration::from_secs(30), // 30s for agent tools
+        Some("@anything/call_workflow") => Duration::from_secs(MAX_TIMEOUT_SECONDS + 30), // Waits for the called workflow
         _ => Duration::from_secs(15), // 15s default for other plugins
     }
 }
@@ -235,10 +247,55 @@ async fn execute_plugin_inner(
                     info!("[EXECUTE_TASK] Executing date formatter plugin");
                     process_date_task(bundled_plugin_config)
                 }
-                _ => {
-                    warn!("[EXECUTE_TASK] Unknown plugin: {}", plugin_name.as_str());
-                    process_missing_plugin(plugin_name.as_str(), &task.task_id.to_string())
+                "@anything/loop" => {
+                    info!("[EXECUTE_TASK] Executing loop plugin");
+                    process_loop_task(bundled_plugin_config)
+                }
+                "@anything/loop_end" => {
+                    info!("[EXECUTE_TASK] Executing loop end plugin");
+                    process_loop_end_task(bundled_inputs)
+                }
+                "@anything/call_workflow" => {
+                    info!("[EXECUTE_TASK] Executing call workflow plugin");
+                    process_call_workflow_task(state, task, bundled_plugin_config).await
+                }
+                "@anything/output" => {
+                    info!("[EXECUTE_TASK] Executing output plugin");
+                    let result = process_output_task(bundled_plugin_config);
+                    // Hand the output to the Call Workflow action waiting on this session
+                    if let Ok(Some(output)) = &result {
+                        finish_workflow_call(&state, &task.flow_session_id, Ok(output.clone()));
+                    }
+                    result
+                }
+                "@anything/approval" => {
+                    info!("[EXECUTE_TASK] Executing approval plugin");
+                    process_approval_task(state, task, bundled_plugin_config).await
                 }
+                "@anything/delay" => {
+                    info!("[EXECUTE_TASK] Executing delay plugin");
+                    process_delay_task(state, task, bundled_plugin_config).await
+                }
+                name => match get_task_plugin(&state, task, name) {
+                    Ok(Some(plugin)) => {
+                        info!(
+                            "[EXECUTE_TASK] Executing wasm plugin {} {}",
+                            name, plugin.registration.version
+                        );
+                        let context = HostContext::for_task(state.clone(), task, name);
+                        let result = plugin.execute(bundled_plugin_config, context.clone()).await;
+                        record_plugin_logs(&state, task, context.take_logs()).await;
+                        result
+                    }
+                    Ok(None) => {
+                        warn!("[EXECUTE_TASK] Unknown plugin: {}", name);
+                        process_missing_plugin(name, &task.task_id.to_string())
+                    }
+                    Err(e) => {
+                        warn!("[EXECUTE_TASK] {}", e);
+                        Err(e.into())
+                    }
+                },
             };
             result
         }
@@ -249,6 +306,59 @@ async fn execute_plugin_inner(
     }
 }
 
+/// Stores the lines a wasm plugin logged through `host_log` as the task's debug result
+async fn record_plugin_logs(state: &Arc<AppState>, task: &Task, logs: Vec<Value>) {
+    if logs.is_empty() {
+        return;
+    }
+
+    if let Err(e) = send_status_update(
+        state,
+        Operation::UpdateTaskDebugResult {
+            task_id: task.task_id,
+            debug_result: json!({ "plugin_logs": logs }),
+        },
+    )
+    .await
+    {
+        warn!(
+            "[EXECUTE_TASK] Failed to record plugin logs for task {}: {}",
+            task.task_id, e
+        );
+    }
+}
+
+/// The loaded version of a wasm plugin a task runs. Tasks record the version their
+/// action resolved to when they were created, so a removed version fails the task.
+fn get_task_plugin<'a>(
+    state: &'a AppState,
+    task: &Task,
+    plugin_name: &str,
+) -> Result<Option<&'a WasmPlugin>, String> {
+    let Some(version) = &task.plugin_version else {
+        return Ok(state.plugin_host.get(plugin_name));
+    };
+
+    let versions = state.plugin_host.versions(plugin_name);
+    if versions.is_empty() {
+        return Ok(None);
+    }
+
+    match state.plugin_host.get_version(plugin_name, version) {
+        Some(plugin) => Ok(Some(plugin)),
+        None => Err(format!(
+            "Plugin {} has no version {}, loaded versions are {}",
+            plugin_name,
+            version,
+            versions
+                .iter()
+                .map(|plugin| plugin.registration.version.to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        )),
+    }
+}
+
 pub fn process_missing_plugin(
     plugin_id: &str,
     task_id: &str,
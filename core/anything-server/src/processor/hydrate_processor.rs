@@ -1,241 +1,146 @@
-// use crate::{
-//     processor::{
-//         utils::create_workflow_graph, db_calls::update_flow_session_status,
-//         flow_session_cache::FlowSessionData, processor::ProcessorMessage,
-//     },
-//     types::{
-//         task_types::{FlowSessionStatus, Task, TaskStatus, TriggerSessionStatus},
-//         workflow_types::DatabaseFlowVersion,
-//     },
-//     AppState,
-// };
-
-// use dotenv::dotenv;
-// use postgrest::Postgrest;
-// use std::{
-//     collections::{HashMap, HashSet},
-//     env,
-//     sync::Arc,
-// };
-// use uuid::Uuid;
-
-// pub async fn hydrate_processor(state: Arc<AppState>) {
-//     println!("[HYDRATE PROCESSOR] Starting processor hydration");
-
-//     dotenv().ok();
-//     let supabase_service_role_api_key = env::var("SUPABASE_SERVICE_ROLE_API_KEY")
-//         .expect("SUPABASE_SERVICE_ROLE_API_KEY must be set");
-
-//     let client = state.anything_client.clone();
-
-//     // Get all running flow sessions before the current time
-//     let response = match client
-//         .from("tasks")
-//         .auth(supabase_service_role_api_key.clone())
-//         .select("*")
-//         .eq("flow_session_status", "running")
-//         .lt("created_at", chrono::Utc::now().to_rfc3339())
-//         .execute()
-//         .await
-//     {
-//         Ok(response) => response,
-//         Err(e) => {
-//             println!("[HYDRATE PROCESSOR] Error fetching flow sessions: {:?}", e);
-//             return;
-//         }
-//     };
-
-//     let body = match response.text().await {
-//         Ok(body) => body,
-//         Err(e) => {
-//             println!("[HYDRATE PROCESSOR] Error getting response text: {:?}", e);
-//             return;
-//         }
-//     };
-
-//     let tasks: Vec<Task> = match serde_json::from_str(&body) {
-//         Ok(tasks) => tasks,
-//         Err(e) => {
-//             println!("[HYDRATE PROCESSOR] Error parsing tasks: {:?}", e);
-//             return;
-//         }
-//     };
-
-//     println!(
-//         "[HYDRATE PROCESSOR] Found {} tasks to manage in hydrate",
-//         tasks.len()
-//     );
-
-//     let mut seen_sessions = HashMap::new();
-
-//     for task in tasks {
-//         let session_id = task.flow_session_id;
-//         let flow_version_id = task.flow_version_id;
-//         let trigger_session_id = task.trigger_session_id;
-
-//         if !seen_sessions.contains_key(&session_id) {
-//             let tasks_future =
-//                 get_flow_session_tasks(&client, &session_id, &supabase_service_role_api_key);
-//             let workflow_future =
-//                 get_workflow_definition(&client, &flow_version_id, &supabase_service_role_api_key);
-
-//             match tokio::try_join!(tasks_future, workflow_future) {
-//                 Ok((session_tasks, workflow_def)) => {
-//                     seen_sessions.insert(session_id.clone(), true);
-
-//                     let mut workflow_failed = false;
-
-//                     // Check if the workflow is completed but for some reason not marked as so
-//                     if let Some(workflow) = &workflow_def {
-//                         let graph = create_workflow_graph(&workflow.flow_definition);
-//                         let mut seen_actions = HashSet::new();
-
-//                         // Add all task action_ids we have to seen set
-//                         for task in &session_tasks {
-//                             if task.task_status == TaskStatus::Failed {
-//                                 workflow_failed = true;
-//                                 break;
-//                             }
-//                             seen_actions.insert(task.action_id.clone());
-//                         }
-
-//                         // Check if any nodes in graph are missing from our tasks
-//                         let mut finished_processing_graph = true;
-//                         for (action_id, _) in &graph {
-//                             if !seen_actions.contains(action_id) {
-//                                 finished_processing_graph = false;
-//                                 println!(
-//                                     "[HYDRATE PROCESSOR] Missing task for action {}",
-//                                     action_id
-//                                 );
-//                                 break;
-//                             }
-//                         }
-
-//                         if finished_processing_graph {
-//                             // We have all tasks - mark flow session as completed
-//                             println!(
-//                                 "[HYDRATE PROCESSOR] Marking flow session {} as {}",
-//                                 session_id,
-//                                 if workflow_failed {
-//                                     "failed"
-//                                 } else {
-//                                     "completed"
-//                                 }
-//                             );
-//                             //THis is basically cleanup. this should not happen often but if it does this will "cure" it
-//                             if let Err(e) = update_flow_session_status(
-//                                 &state,
-//                                 &Uuid::parse_str(&session_id).unwrap(),
-//                                 if workflow_failed {
-//                                     &FlowSessionStatus::Failed
-//                                 } else {
-//                                     &FlowSessionStatus::Completed
-//                                 },
-//                                 if workflow_failed {
-//                                     &TriggerSessionStatus::Failed
-//                                 } else {
-//                                     &TriggerSessionStatus::Completed
-//                                 },
-//                             )
-//                             .await
-//                             {
-//                                 println!(
-//                                     "[HYDRATE PROCESSOR] Failed to update flow session status: {}",
-//                                     e
-//                                 );
-//                             }
-//                             //get out of loop
-//                             continue;
-//                         } else {
-//                             println!(
-//                                 "[HYDRATE PROCESSOR] Starting up processor for flow session {}",
-//                                 session_id
-//                             );
-//                         }
-//                     }
-
-//                     //Put workflow in the cache
-//                     let flow_session_data = FlowSessionData {
-//                         workflow: workflow_def.clone(),
-//                         tasks: session_tasks.into_iter().map(|t| (t.task_id, t)).collect(),
-//                         flow_session_id: Uuid::parse_str(&session_id).unwrap(),
-//                         workflow_id: workflow_def.clone().unwrap().flow_id,
-//                         workflow_version_id: Some(flow_version_id),
-//                     };
-
-//                     println!("[HYDRATE PROCESSOR] Setting flow session data in cache");
-//                     // Set the flow session data in cache
-//                     {
-//                         let mut cache = state.flow_session_cache.write().await;
-//                         cache.set(&Uuid::parse_str(&session_id).unwrap(), flow_session_data);
-//                     }
-
-//                     //Send message to processor to start the workflow
-//                     let processor_message = ProcessorMessage {
-//                         workflow_id: workflow_def.unwrap().flow_id,
-//                         version_id: Some(flow_version_id),
-//                         flow_session_id: Uuid::parse_str(&session_id).unwrap(),
-//                         trigger_session_id: Uuid::parse_str(&trigger_session_id).unwrap(),
-//                         trigger_task: None,
-//                     };
-
-//                     if let Err(e) = state.processor_sender.send(processor_message).await {
-//                         println!(
-//                             "[HYDRATE PROCESSOR] Failed to send message to processor: {}",
-//                             e
-//                         );
-//                         return;
-//                     }
-//                 }
-//                 Err(e) => {
-//                     println!(
-//                         "[HYDRATE PROCESSOR] Error getting data for session {}: {:?}",
-//                         session_id, e
-//                     );
-//                 }
-//             }
-//         }
-//     }
-
-//     println!("[HYDRATE PROCESSOR] Completed processor hydration");
-// }
-
-// async fn get_workflow_definition(
-//     client: &Postgrest,
-//     version_id: &Uuid,
-//     api_key: &str,
-// ) -> Result<Option<DatabaseFlowVersion>, Box<dyn std::error::Error + Send + Sync>> {
-//     let response = client
-//         .from("flow_versions")
-//         .auth(api_key)
-//         .select("*")
-//         .eq("flow_version_id", version_id.to_string())
-//         .single()
-//         .execute()
-//         .await?;
-
-//     let body = response.text().await?;
-//     let version: DatabaseFlowVersion = serde_json::from_str(&body)?;
-
-//     Ok(Some(version))
-// }
-
-// async fn get_flow_session_tasks(
-//     client: &Postgrest,
-//     session_id: &str,
-//     api_key: &str,
-// ) -> Result<Vec<Task>, Box<dyn std::error::Error + Send + Sync>> {
-//     let response = client
-//         .from("tasks")
-//         .auth(api_key)
-//         .select("*")
-//         .eq("flow_session_id", session_id)
-//         .execute()
-//         .await?;
-
-//     let body = response.text().await?;
-//     let tasks: Vec<Task> = serde_json::from_str(&body)?;
-
-//     Ok(tasks)
-// }
+use crate::leases::{acquire_lease, get_live_instances, instance_lease_name, LEASE_TTL};
+use crate::processor::db_calls::{get_session_tasks, get_workflow_definition};
+use crate::processor::processor::ProcessorMessage;
+use crate::processor::run_queue::{
+    claim_run, get_unfinished_runs, is_orphaned, release_run_claim, QueuedRun, INSTANCE_ID,
+};
+use crate::types::action_types::ActionType;
+use crate::AppState;
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use tracing::{error, info, warn};
+
+/// Resumes orphaned flow sessions at startup and keeps looking for more, since an instance
+/// that stops after this one started only gives its runs up once its heartbeat expires.
+pub async fn hydrate_processor_loop(state: Arc<AppState>) {
+    let mut interval = tokio::time::interval(LEASE_TTL);
+
+    loop {
+        interval.tick().await;
+
+        if state
+            .shutdown_signal
+            .load(std::sync::atomic::Ordering::SeqCst)
+        {
+            break;
+        }
+
+        hydrate_processor(state.clone()).await;
+    }
+}
+
+/// Resumes every flow session still queued on a server instance that is no longer alive.
+/// Tasks already stored for the session are passed along as `existing_tasks`, so the
+/// workflow actor continues after the last finished task instead of starting over.
+pub async fn hydrate_processor(state: Arc<AppState>) {
+    info!("[HYDRATE PROCESSOR] Starting processor hydration");
+
+    // Other instances must see this one as alive before it claims anything,
+    // or they could take the runs it is about to resume
+    if let Err(e) = acquire_lease(&state, &instance_lease_name(&INSTANCE_ID)).await {
+        error!(
+            "[HYDRATE PROCESSOR] Could not renew instance heartbeat: {}",
+            e
+        );
+        return;
+    }
+
+    let live_instances = match get_live_instances(&state).await {
+        Ok(live_instances) => live_instances,
+        Err(e) => {
+            error!("[HYDRATE PROCESSOR] Error fetching live instances: {}", e);
+            return;
+        }
+    };
+
+    let runs: Vec<QueuedRun> = match get_unfinished_runs(&state).await {
+        Ok(runs) => runs
+            .into_iter()
+            .filter(|run| is_orphaned(run, &live_instances))
+            .collect(),
+        Err(e) => {
+            error!("[HYDRATE PROCESSOR] Error fetching unfinished runs: {}", e);
+            return;
+        }
+    };
+
+    info!(
+        "[HYDRATE PROCESSOR] Found {} orphaned runs to resume",
+        runs.len()
+    );
+
+    for run in runs {
+        match claim_run(&state, &run).await {
+            Ok(true) => {}
+            Ok(false) => continue,
+            Err(e) => {
+                warn!(
+                    "[HYDRATE PROCESSOR] Could not claim run {}: {}",
+                    run.flow_session_id, e
+                );
+                continue;
+            }
+        }
+
+        if let Err(e) = resume_run(&state, run.clone()).await {
+            error!(
+                "[HYDRATE PROCESSOR] Failed to resume flow session {}: {}",
+                run.flow_session_id, e
+            );
+
+            // Handing the run back leaves it orphaned, so a later pass tries again
+            if let Err(e) = release_run_claim(&state, &run).await {
+                error!(
+                    "[HYDRATE PROCESSOR] Failed to give up claim of run {}: {}",
+                    run.flow_session_id, e
+                );
+            }
+        }
+    }
+
+    info!("[HYDRATE PROCESSOR] Completed processor hydration");
+}
+
+/// Sends a queued run back to the processor with the tasks its session already stored
+pub async fn resume_run(state: &Arc<AppState>, run: QueuedRun) -> Result<(), String> {
+    let workflow_version =
+        get_workflow_definition(state.clone(), &run.flow_id, Some(&run.flow_version_id)).await?;
+
+    // Sessions that died before their first task was written have nothing to restore.
+    // Any other error fails the resume, starting over would repeat finished tasks.
+    let session_tasks = get_session_tasks(state.clone(), &run.flow_session_id).await?;
+
+    let trigger_task = run.trigger_task.clone().or_else(|| {
+        session_tasks
+            .iter()
+            .find(|task| task.r#type == ActionType::Trigger)
+            .cloned()
+    });
+
+    info!(
+        "[HYDRATE PROCESSOR] Resuming flow session {} with {} existing tasks",
+        run.flow_session_id,
+        session_tasks.len()
+    );
+
+    let processor_message = ProcessorMessage {
+        workflow_id: run.flow_id,
+        workflow_version: workflow_version.clone(),
+        workflow_definition: workflow_version.flow_definition.clone(),
+        flow_session_id: run.flow_session_id,
+        trigger_session_id: run.trigger_session_id,
+        task_id: trigger_task.as_ref().map(|task| task.task_id),
+        trigger_task,
+        existing_tasks: session_tasks
+            .into_iter()
+            .map(|task| (task.task_id, task))
+            .collect::<HashMap<_, _>>(),
+    };
+
+    state
+        .processor_sender
+        .send(processor_message)
+        .await
+        .map_err(|e| format!("Failed to send message to processor: {}", e))
+}
@@ -9,6 +9,9 @@ pub mod path_processor;
 pub mod process_trigger_utils;
 pub mod processor;
 pub mod processor_utils;
+pub mod replay;
+pub mod run_queue;
+pub mod task_waits;
 pub mod utils;
 
 #[cfg(test)]
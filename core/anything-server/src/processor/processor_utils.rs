@@ -107,7 +107,7 @@ pub async fn create_task_for_action(
         .action_id(action.action_id.clone())
         .r#type(action.r#type.clone())
         .plugin_name(action.plugin_name.clone())
-        .plugin_version(action.plugin_version.clone())
+        .plugin_version(ctx.state.plugin_host.task_plugin_version(action))
         .stage(if ctx.workflow.published {
             Stage::Production
         } else {
@@ -0,0 +1,284 @@
+use crate::actor_processor::dependency_resolver::DependencyGraph;
+use crate::actor_processor::status_updates::send_status_update;
+use crate::processor::db_calls::{get_session_tasks, get_workflow_definition};
+use crate::processor::processor::ProcessorMessage;
+use crate::processor::run_queue::{enqueue_workflow_replay, ReplaySource};
+use crate::status_updater::Operation;
+use crate::types::action_types::ActionType;
+use crate::types::task_types::{FlowSessionStatus, Task, TaskStatus, TriggerSessionStatus};
+use crate::AppState;
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::sync::Arc;
+use tracing::info;
+use uuid::Uuid;
+
+#[derive(Debug, Clone)]
+pub struct ReplayRequest {
+    pub account_id: Uuid,
+    pub flow_session_id: Uuid,
+    pub action_id: String,
+    pub use_published_version: bool, // Replay against the published version instead of the session's own
+}
+
+#[derive(Debug, Clone)]
+pub struct ReplayedSession {
+    pub flow_session_id: Uuid,
+    pub trigger_session_id: Uuid,
+    pub flow_version_id: Uuid,
+    pub reused_tasks: usize,
+}
+
+#[derive(Debug)]
+pub enum ReplayError {
+    SessionNotFound,
+    ActionNotFound(String),
+    Failed(String),
+}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReplayError::SessionNotFound => write!(f, "Flow session not found"),
+            ReplayError::ActionNotFound(action_id) => write!(
+                f,
+                "Action {} is not part of the workflow version being replayed",
+                action_id
+            ),
+            ReplayError::Failed(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+/// Starts a new flow session that runs the action and everything after it again.
+/// Completed tasks of the original session that are not behind the action are copied
+/// into the new session and handed to the processor as `existing_tasks`.
+pub async fn replay_session(
+    state: &Arc<AppState>,
+    request: ReplayRequest,
+) -> Result<ReplayedSession, ReplayError> {
+    let stored_tasks = get_session_tasks(state.clone(), &request.flow_session_id)
+        .await
+        .map_err(ReplayError::Failed)?;
+
+    // Sessions of other accounts read as not found
+    let original_trigger = stored_tasks
+        .iter()
+        .find(|task| task.r#type == ActionType::Trigger)
+        .filter(|task| task.account_id == request.account_id)
+        .ok_or(ReplayError::SessionNotFound)?;
+
+    let version_id = original_trigger.flow_version_id;
+    let workflow_version = get_workflow_definition(
+        state.clone(),
+        &original_trigger.flow_id,
+        (!request.use_published_version).then_some(&version_id),
+    )
+    .await
+    .map_err(ReplayError::Failed)?;
+
+    let definition = &workflow_version.flow_definition;
+    if !definition
+        .actions
+        .iter()
+        .any(|action| action.action_id == request.action_id)
+    {
+        return Err(ReplayError::ActionNotFound(request.action_id));
+    }
+
+    let dependency_graph = DependencyGraph::new(definition);
+    let mut rerun_actions = dependency_graph.get_downstream_actions(&request.action_id);
+    rerun_actions.insert(request.action_id.clone());
+
+    let flow_session_id = Uuid::new_v4();
+    let trigger_session_id = Uuid::new_v4();
+    let into_session = |task: &Task| {
+        let mut task = task.clone();
+        task.task_id = Uuid::new_v4();
+        task.flow_session_id = flow_session_id;
+        task.flow_session_status = FlowSessionStatus::Running;
+        task.trigger_session_id = trigger_session_id;
+        task.trigger_session_status = TriggerSessionStatus::Running;
+        task.flow_version_id = workflow_version.flow_version_id;
+        task
+    };
+
+    // Replaying from the trigger runs it again with the payload it received
+    let trigger_task = into_session(original_trigger);
+    let mut existing_tasks = HashMap::new();
+
+    for task in reused_tasks(&stored_tasks, &rerun_actions) {
+        let mut task = if task.task_id == original_trigger.task_id {
+            trigger_task.clone()
+        } else {
+            into_session(task)
+        };
+        if task.r#type != ActionType::Trigger {
+            task.trigger_id = trigger_task.task_id.to_string();
+        }
+
+        // The workflow actor only creates the tasks it runs, the reused ones are stored here
+        send_status_update(
+            state,
+            Operation::CreateTask {
+                task_id: task.task_id,
+                input: task.clone(),
+            },
+        )
+        .await
+        .map_err(|e| ReplayError::Failed(e.to_string()))?;
+
+        existing_tasks.insert(task.task_id, task);
+    }
+
+    info!(
+        "[REPLAY] Replaying flow session {} from action {} as {}, reusing {} tasks",
+        request.flow_session_id,
+        request.action_id,
+        flow_session_id,
+        existing_tasks.len()
+    );
+
+    let replayed = ReplayedSession {
+        flow_session_id,
+        trigger_session_id,
+        flow_version_id: workflow_version.flow_version_id,
+        reused_tasks: existing_tasks.len(),
+    };
+
+    let processor_message = ProcessorMessage {
+        workflow_id: original_trigger.flow_id,
+        workflow_version: workflow_version.clone(),
+        workflow_definition: workflow_version.flow_definition.clone(),
+        flow_session_id,
+        trigger_session_id,
+        task_id: Some(trigger_task.task_id),
+        trigger_task: Some(trigger_task),
+        existing_tasks,
+    };
+
+    enqueue_workflow_replay(
+        state,
+        processor_message,
+        ReplaySource {
+            flow_session_id: request.flow_session_id,
+            action_id: request.action_id,
+        },
+    )
+    .await
+    .map_err(ReplayError::Failed)?;
+
+    Ok(replayed)
+}
+
+/// The stored tasks a replay keeps: every completed task whose action does not run again.
+/// Failed, canceled and unfinished tasks are left behind so their actions run in the replay.
+pub fn reused_tasks<'a>(
+    stored_tasks: &'a [Task],
+    rerun_actions: &HashSet<String>,
+) -> Vec<&'a Task> {
+    stored_tasks
+        .iter()
+        .filter(|task| task.task_status == TaskStatus::Completed)
+        .filter(|task| !rerun_actions.contains(&task.action_id))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::react_flow_types::Edge;
+    use crate::types::task_types::TaskConfig;
+    use crate::types::workflow_types::WorkflowVersionDefinition;
+    use serde_json::json;
+
+    fn action(action_id: &str, r#type: &str) -> crate::types::action_types::Action {
+        serde_json::from_value(json!({
+            "anything_action_version": "0.1.0",
+            "type": r#type,
+            "plugin_name": "@anything/http",
+            "plugin_version": "0.1.0",
+            "action_id": action_id,
+            "label": action_id,
+            "icon": "",
+            "plugin_config": {},
+            "plugin_config_schema": {}
+        }))
+        .unwrap()
+    }
+
+    fn edge(source: &str, target: &str) -> Edge {
+        Edge {
+            id: format!("{}->{}", source, target),
+            source: source.to_string(),
+            source_handle: None,
+            target: target.to_string(),
+            target_handle: None,
+            r#type: "anything".to_string(),
+        }
+    }
+
+    fn task(action_id: &str, task_status: TaskStatus) -> Task {
+        let mut task = Task::builder()
+            .account_id(Uuid::new_v4())
+            .flow_id(Uuid::new_v4())
+            .flow_version_id(Uuid::new_v4())
+            .action_label(action_id.to_string())
+            .trigger_id("trigger".to_string())
+            .action_id(action_id.to_string())
+            .r#type(ActionType::Action)
+            .config(TaskConfig {
+                inputs: None,
+                inputs_schema: None,
+                plugin_config: None,
+                plugin_config_schema: None,
+            })
+            .build()
+            .unwrap();
+        task.task_status = task_status;
+        task
+    }
+
+    #[test]
+    fn test_reused_tasks() {
+        // trigger -> fetch -> transform -> notify, with log on a parallel branch
+        let definition = WorkflowVersionDefinition {
+            actions: vec![
+                action("trigger", "trigger"),
+                action("fetch", "action"),
+                action("transform", "action"),
+                action("notify", "action"),
+                action("log", "action"),
+            ],
+            edges: vec![
+                edge("trigger", "fetch"),
+                edge("fetch", "transform"),
+                edge("transform", "notify"),
+                edge("trigger", "log"),
+            ],
+        };
+        let graph = DependencyGraph::new(&definition);
+
+        let mut rerun_actions = graph.get_downstream_actions("transform");
+        rerun_actions.insert("transform".to_string());
+        assert_eq!(
+            rerun_actions,
+            HashSet::from(["transform".to_string(), "notify".to_string()])
+        );
+
+        let stored_tasks = vec![
+            task("trigger", TaskStatus::Completed),
+            task("fetch", TaskStatus::Completed),
+            task("transform", TaskStatus::Failed),
+            task("notify", TaskStatus::Canceled),
+            task("log", TaskStatus::Failed),
+        ];
+
+        let reused: Vec<&str> = reused_tasks(&stored_tasks, &rerun_actions)
+            .into_iter()
+            .map(|task| task.action_id.as_str())
+            .collect();
+        assert_eq!(reused, vec!["trigger", "fetch"]);
+    }
+}
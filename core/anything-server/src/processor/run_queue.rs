@@ -0,0 +1,540 @@
+use crate::processor::processor::ProcessorMessage;
+use crate::types::task_types::Task;
+use crate::AppState;
+
+use chrono::{DateTime, Utc};
+use dotenv::dotenv;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::HashSet;
+use std::env;
+use tracing::{error, info, warn};
+use uuid::Uuid;
+
+/// Identifies this server process in `run_queue.claimed_by`
+pub static INSTANCE_ID: Lazy<Uuid> = Lazy::new(Uuid::new_v4);
+
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum RunStatus {
+    Queued,    // Handed to the processor and not finished yet
+    Waiting,   // Parked by a task until it is resumed, not picked up after a restart
+    Completed, // Flow session finished
+    Failed,    // Flow session failed
+    Canceled,  // Flow session was canceled
+}
+
+impl RunStatus {
+    pub fn as_str(&self) -> &str {
+        match self {
+            RunStatus::Queued => "queued",
+            RunStatus::Waiting => "waiting",
+            RunStatus::Completed => "completed",
+            RunStatus::Failed => "failed",
+            RunStatus::Canceled => "canceled",
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct QueuedRun {
+    pub flow_session_id: Uuid,
+    pub account_id: Uuid,
+    pub flow_id: Uuid,
+    pub flow_version_id: Uuid,
+    pub trigger_session_id: Uuid,
+    pub trigger_task: Option<Task>,
+    pub run_status: RunStatus,
+    pub claimed_by: Option<Uuid>,
+    pub claimed_at: Option<DateTime<Utc>>,
+    pub parent_flow_session_id: Option<Uuid>, // Set when a Call Workflow action started the run
+    pub parent_task_id: Option<Uuid>,
+    #[serde(default)]
+    pub call_stack: Vec<Uuid>, // Flow ids of the calling workflows, outermost first
+    pub replay_of_flow_session_id: Option<Uuid>, // Set when the run replays an earlier session
+    pub replay_from_action_id: Option<String>,
+    #[serde(default)]
+    pub cancel_requested: bool, // Set when another server was asked to cancel the run
+}
+
+/// The run and task a Call Workflow action started a run from
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParentRun {
+    pub flow_session_id: Uuid,
+    pub task_id: Uuid,
+    pub call_stack: Vec<Uuid>, // Includes the calling workflow itself
+}
+
+/// The session and action a replayed run starts again from
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReplaySource {
+    pub flow_session_id: Uuid,
+    pub action_id: String,
+}
+
+/// Records the run in the persistent queue, then hands it to the processor.
+/// A failed insert is logged but does not block the run, it just won't survive a restart.
+pub async fn enqueue_workflow(state: &AppState, message: ProcessorMessage) -> Result<(), String> {
+    let run = queued_run(&message, None);
+
+    if let Err(e) = insert_run(state, &run).await {
+        warn!(
+            "[RUN_QUEUE] Failed to persist run {}, it will not be resumed after a restart: {}",
+            run.flow_session_id, e
+        );
+    }
+
+    state
+        .processor_sender
+        .send(message)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Records a run started by another workflow, then hands it to the processor.
+/// Unlike `enqueue_workflow` a failed insert fails the call, the recorded call stack is
+/// what stops nested calls from recursing forever.
+pub async fn enqueue_workflow_call(
+    state: &AppState,
+    message: ProcessorMessage,
+    parent: ParentRun,
+) -> Result<(), String> {
+    let run = queued_run(&message, Some(parent));
+
+    insert_run(state, &run).await?;
+
+    state
+        .processor_sender
+        .send(message)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Records a replay of an earlier session, then hands it to the processor.
+/// Like a call, a failed insert fails the replay so it is never left unrecorded.
+pub async fn enqueue_workflow_replay(
+    state: &AppState,
+    message: ProcessorMessage,
+    source: ReplaySource,
+) -> Result<(), String> {
+    let mut run = queued_run(&message, None);
+    run.replay_of_flow_session_id = Some(source.flow_session_id);
+    run.replay_from_action_id = Some(source.action_id);
+
+    insert_run(state, &run).await?;
+
+    state
+        .processor_sender
+        .send(message)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+fn queued_run(message: &ProcessorMessage, parent: Option<ParentRun>) -> QueuedRun {
+    QueuedRun {
+        flow_session_id: message.flow_session_id,
+        account_id: message.workflow_version.account_id,
+        flow_id: message.workflow_id,
+        flow_version_id: message.workflow_version.flow_version_id,
+        trigger_session_id: message.trigger_session_id,
+        trigger_task: message.trigger_task.clone(),
+        run_status: RunStatus::Queued,
+        claimed_by: Some(*INSTANCE_ID),
+        claimed_at: Some(Utc::now()),
+        parent_flow_session_id: parent.as_ref().map(|parent| parent.flow_session_id),
+        parent_task_id: parent.as_ref().map(|parent| parent.task_id),
+        call_stack: parent.map(|parent| parent.call_stack).unwrap_or_default(),
+        replay_of_flow_session_id: None,
+        replay_from_action_id: None,
+        cancel_requested: false,
+    }
+}
+
+async fn insert_run(state: &AppState, run: &QueuedRun) -> Result<(), String> {
+    dotenv().ok();
+    let supabase_service_role_api_key = env::var("SUPABASE_SERVICE_ROLE_API_KEY")
+        .expect("SUPABASE_SERVICE_ROLE_API_KEY must be set");
+
+    let response = state
+        .anything_client
+        .from("run_queue")
+        .auth(supabase_service_role_api_key)
+        .insert(serde_json::to_string(run).map_err(|e| e.to_string())?)
+        .execute()
+        .await
+        .map_err(|e| format!("Failed to execute request: {}", e))?;
+
+    if !response.status().is_success() {
+        let body = response.text().await.unwrap_or_default();
+        return Err(format!("Failed to insert run: {}", body));
+    }
+
+    Ok(())
+}
+
+pub async fn update_run_status(
+    state: &AppState,
+    flow_session_id: &Uuid,
+    run_status: &RunStatus,
+) -> Result<(), String> {
+    dotenv().ok();
+    let supabase_service_role_api_key = env::var("SUPABASE_SERVICE_ROLE_API_KEY")
+        .expect("SUPABASE_SERVICE_ROLE_API_KEY must be set");
+
+    state
+        .anything_client
+        .from("run_queue")
+        .auth(supabase_service_role_api_key)
+        .eq("flow_session_id", flow_session_id.to_string())
+        .update(json!({ "run_status": run_status.as_str() }).to_string())
+        .execute()
+        .await
+        .map_err(|e| format!("Failed to execute request: {}", e))?;
+
+    Ok(())
+}
+
+pub async fn get_queued_run(
+    state: &AppState,
+    flow_session_id: &Uuid,
+) -> Result<Option<QueuedRun>, String> {
+    dotenv().ok();
+    let supabase_service_role_api_key = env::var("SUPABASE_SERVICE_ROLE_API_KEY")
+        .expect("SUPABASE_SERVICE_ROLE_API_KEY must be set");
+
+    let response = state
+        .anything_client
+        .from("run_queue")
+        .auth(supabase_service_role_api_key)
+        .select("*")
+        .eq("flow_session_id", flow_session_id.to_string())
+        .execute()
+        .await
+        .map_err(|e| format!("Failed to execute request: {}", e))?;
+
+    let body = response
+        .text()
+        .await
+        .map_err(|e| format!("Failed to read response body: {}", e))?;
+
+    let runs: Vec<QueuedRun> =
+        serde_json::from_str(&body).map_err(|e| format!("Failed to parse queued run: {}", e))?;
+
+    Ok(runs.into_iter().next())
+}
+
+/// Gets the run a Call Workflow task started, if it started one
+pub async fn get_called_run(
+    state: &AppState,
+    parent_task_id: &Uuid,
+) -> Result<Option<QueuedRun>, String> {
+    dotenv().ok();
+    let supabase_service_role_api_key = env::var("SUPABASE_SERVICE_ROLE_API_KEY")
+        .expect("SUPABASE_SERVICE_ROLE_API_KEY must be set");
+
+    let response = state
+        .anything_client
+        .from("run_queue")
+        .auth(supabase_service_role_api_key)
+        .select("*")
+        .eq("parent_task_id", parent_task_id.to_string())
+        .order("created_at.asc")
+        .execute()
+        .await
+        .map_err(|e| format!("Failed to execute request: {}", e))?;
+
+    let body = response
+        .text()
+        .await
+        .map_err(|e| format!("Failed to read response body: {}", e))?;
+
+    let runs: Vec<QueuedRun> =
+        serde_json::from_str(&body).map_err(|e| format!("Failed to parse queued run: {}", e))?;
+
+    Ok(runs.into_iter().next())
+}
+
+/// Queues a parked run again on this instance. Only succeeds while the run is waiting,
+/// so a wait resumed twice, or before its session finished parking, can't start it twice.
+pub async fn requeue_waiting_run(
+    state: &AppState,
+    flow_session_id: &Uuid,
+) -> Result<Option<QueuedRun>, String> {
+    dotenv().ok();
+    let supabase_service_role_api_key = env::var("SUPABASE_SERVICE_ROLE_API_KEY")
+        .expect("SUPABASE_SERVICE_ROLE_API_KEY must be set");
+
+    let response = state
+        .anything_client
+        .from("run_queue")
+        .auth(supabase_service_role_api_key)
+        .eq("flow_session_id", flow_session_id.to_string())
+        .eq("run_status", RunStatus::Waiting.as_str())
+        .update(
+            json!({
+                "run_status": RunStatus::Queued.as_str(),
+                "claimed_by": *INSTANCE_ID,
+                "claimed_at": Utc::now(),
+            })
+            .to_string(),
+        )
+        .execute()
+        .await
+        .map_err(|e| format!("Failed to execute request: {}", e))?;
+
+    let body = response
+        .text()
+        .await
+        .map_err(|e| format!("Failed to read response body: {}", e))?;
+
+    let runs: Vec<QueuedRun> =
+        serde_json::from_str(&body).map_err(|e| format!("Failed to parse queued run: {}", e))?;
+
+    Ok(runs.into_iter().next())
+}
+
+/// Cancels a parked run. Only succeeds while the run is waiting, so it can't race a resume.
+pub async fn cancel_waiting_run(state: &AppState, flow_session_id: &Uuid) -> Result<bool, String> {
+    dotenv().ok();
+    let supabase_service_role_api_key = env::var("SUPABASE_SERVICE_ROLE_API_KEY")
+        .expect("SUPABASE_SERVICE_ROLE_API_KEY must be set");
+
+    let response = state
+        .anything_client
+        .from("run_queue")
+        .auth(supabase_service_role_api_key)
+        .eq("flow_session_id", flow_session_id.to_string())
+        .eq("run_status", RunStatus::Waiting.as_str())
+        .update(json!({ "run_status": RunStatus::Canceled.as_str() }).to_string())
+        .execute()
+        .await
+        .map_err(|e| format!("Failed to execute request: {}", e))?;
+
+    let body = response
+        .text()
+        .await
+        .map_err(|e| format!("Failed to read response body: {}", e))?;
+
+    let runs: Vec<QueuedRun> =
+        serde_json::from_str(&body).map_err(|e| format!("Failed to parse queued run: {}", e))?;
+
+    Ok(!runs.is_empty())
+}
+
+/// Asks the server running a queued run to cancel it. Returns false when the run
+/// is no longer queued, because it parked or finished since it was read.
+pub async fn request_run_cancel(state: &AppState, flow_session_id: &Uuid) -> Result<bool, String> {
+    dotenv().ok();
+    let supabase_service_role_api_key = env::var("SUPABASE_SERVICE_ROLE_API_KEY")
+        .expect("SUPABASE_SERVICE_ROLE_API_KEY must be set");
+
+    let response = state
+        .anything_client
+        .from("run_queue")
+        .auth(supabase_service_role_api_key)
+        .eq("flow_session_id", flow_session_id.to_string())
+        .eq("run_status", RunStatus::Queued.as_str())
+        .update(json!({ "cancel_requested": true }).to_string())
+        .execute()
+        .await
+        .map_err(|e| format!("Failed to execute request: {}", e))?;
+
+    let body = response
+        .text()
+        .await
+        .map_err(|e| format!("Failed to read response body: {}", e))?;
+
+    let runs: Vec<QueuedRun> =
+        serde_json::from_str(&body).map_err(|e| format!("Failed to parse queued run: {}", e))?;
+
+    Ok(!runs.is_empty())
+}
+
+/// Gets the queued runs of this instance that were asked to cancel
+pub async fn get_cancel_requested_runs(state: &AppState) -> Result<Vec<QueuedRun>, String> {
+    dotenv().ok();
+    let supabase_service_role_api_key = env::var("SUPABASE_SERVICE_ROLE_API_KEY")
+        .expect("SUPABASE_SERVICE_ROLE_API_KEY must be set");
+
+    let response = state
+        .anything_client
+        .from("run_queue")
+        .auth(supabase_service_role_api_key)
+        .select("*")
+        .eq("run_status", RunStatus::Queued.as_str())
+        .eq("claimed_by", INSTANCE_ID.to_string())
+        .eq("cancel_requested", "true")
+        .execute()
+        .await
+        .map_err(|e| format!("Failed to execute request: {}", e))?;
+
+    let body = response
+        .text()
+        .await
+        .map_err(|e| format!("Failed to read response body: {}", e))?;
+
+    serde_json::from_str(&body).map_err(|e| format!("Failed to parse queued runs: {}", e))
+}
+
+/// Gets every unfinished run claimed by another server instance.
+/// Use `is_orphaned` to keep only the ones whose instance is no longer alive.
+pub async fn get_unfinished_runs(state: &AppState) -> Result<Vec<QueuedRun>, String> {
+    dotenv().ok();
+    let supabase_service_role_api_key = env::var("SUPABASE_SERVICE_ROLE_API_KEY")
+        .expect("SUPABASE_SERVICE_ROLE_API_KEY must be set");
+
+    let response = state
+        .anything_client
+        .from("run_queue")
+        .auth(supabase_service_role_api_key)
+        .select("*")
+        .eq("run_status", RunStatus::Queued.as_str())
+        .neq("claimed_by", INSTANCE_ID.to_string())
+        .order("created_at.asc")
+        .execute()
+        .await
+        .map_err(|e| format!("Failed to execute request: {}", e))?;
+
+    let body = response
+        .text()
+        .await
+        .map_err(|e| format!("Failed to read response body: {}", e))?;
+
+    serde_json::from_str(&body).map_err(|e| format!("Failed to parse queued runs: {}", e))
+}
+
+/// Returns true when a queued run has no live owner and can be taken over.
+/// Runs of instances whose heartbeat lease is still live are left alone, they are
+/// being executed right now.
+pub fn is_orphaned(run: &QueuedRun, live_instances: &HashSet<Uuid>) -> bool {
+    match run.claimed_by {
+        Some(claimed_by) => claimed_by != *INSTANCE_ID && !live_instances.contains(&claimed_by),
+        None => true,
+    }
+}
+
+/// Moves a run to this instance. Only succeeds if nobody claimed it since it was read,
+/// so two servers starting at once can't both resume the same flow session.
+pub async fn claim_run(state: &AppState, run: &QueuedRun) -> Result<bool, String> {
+    dotenv().ok();
+    let supabase_service_role_api_key = env::var("SUPABASE_SERVICE_ROLE_API_KEY")
+        .expect("SUPABASE_SERVICE_ROLE_API_KEY must be set");
+
+    let previous_claim = run
+        .claimed_by
+        .map(|claimed_by| claimed_by.to_string())
+        .unwrap_or_default();
+
+    let response = state
+        .anything_client
+        .from("run_queue")
+        .auth(supabase_service_role_api_key)
+        .eq("flow_session_id", run.flow_session_id.to_string())
+        .eq("claimed_by", previous_claim)
+        .update(
+            json!({
+                "claimed_by": *INSTANCE_ID,
+                "claimed_at": Utc::now(),
+            })
+            .to_string(),
+        )
+        .execute()
+        .await
+        .map_err(|e| format!("Failed to execute request: {}", e))?;
+
+    let body = response
+        .text()
+        .await
+        .map_err(|e| format!("Failed to read response body: {}", e))?;
+
+    let claimed: Vec<serde_json::Value> = serde_json::from_str(&body).map_err(|e| {
+        error!("[RUN_QUEUE] Failed to parse claim response: {}", e);
+        format!("Failed to parse claim response: {}", e)
+    })?;
+
+    info!(
+        "[RUN_QUEUE] Claim for run {} {}",
+        run.flow_session_id,
+        if claimed.is_empty() {
+            "lost to another instance"
+        } else {
+            "succeeded"
+        }
+    );
+
+    Ok(!claimed.is_empty())
+}
+
+/// Gives a claimed run back to the instance that held it before `claim_run`.
+/// Only succeeds while this instance still holds it.
+pub async fn release_run_claim(state: &AppState, run: &QueuedRun) -> Result<(), String> {
+    dotenv().ok();
+    let supabase_service_role_api_key = env::var("SUPABASE_SERVICE_ROLE_API_KEY")
+        .expect("SUPABASE_SERVICE_ROLE_API_KEY must be set");
+
+    state
+        .anything_client
+        .from("run_queue")
+        .auth(supabase_service_role_api_key)
+        .eq("flow_session_id", run.flow_session_id.to_string())
+        .eq("claimed_by", INSTANCE_ID.to_string())
+        .update(
+            json!({
+                "claimed_by": run.claimed_by,
+                "claimed_at": run.claimed_at,
+            })
+            .to_string(),
+        )
+        .execute()
+        .await
+        .map_err(|e| format!("Failed to execute request: {}", e))?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run_claimed_by(claimed_by: Option<Uuid>) -> QueuedRun {
+        QueuedRun {
+            flow_session_id: Uuid::new_v4(),
+            account_id: Uuid::new_v4(),
+            flow_id: Uuid::new_v4(),
+            flow_version_id: Uuid::new_v4(),
+            trigger_session_id: Uuid::new_v4(),
+            trigger_task: None,
+            run_status: RunStatus::Queued,
+            claimed_by,
+            claimed_at: Some(Utc::now()),
+            parent_flow_session_id: None,
+            parent_task_id: None,
+            call_stack: Vec::new(),
+            replay_of_flow_session_id: None,
+            replay_from_action_id: None,
+            cancel_requested: false,
+        }
+    }
+
+    #[test]
+    fn test_run_of_live_instance_is_not_orphaned() {
+        let live_instance = Uuid::new_v4();
+        let dead_instance = Uuid::new_v4();
+        let live_instances = HashSet::from([live_instance]);
+
+        assert!(!is_orphaned(
+            &run_claimed_by(Some(live_instance)),
+            &live_instances
+        ));
+        assert!(!is_orphaned(
+            &run_claimed_by(Some(*INSTANCE_ID)),
+            &live_instances
+        ));
+        assert!(is_orphaned(
+            &run_claimed_by(Some(dead_instance)),
+            &live_instances
+        ));
+        assert!(is_orphaned(&run_claimed_by(None), &live_instances));
+    }
+}
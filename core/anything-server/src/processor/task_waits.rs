@@ -0,0 +1,554 @@
+use crate::processor::db_calls::update_task_status;
+use crate::processor::hydrate_processor::resume_run;
+use crate::processor::run_queue::{requeue_waiting_run, update_run_status, RunStatus};
+use crate::system_plugins::delay::delay_resolution;
+use crate::types::action_types::PluginName;
+use crate::types::task_types::TaskStatus;
+use crate::AppState;
+
+use base64::Engine;
+use chrono::{DateTime, Utc};
+use dotenv::dotenv;
+use openssl::{hash::MessageDigest, memcmp, pkey::PKey, sign::Signer};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::env;
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::time::sleep;
+use tracing::{error, info, warn};
+use uuid::Uuid;
+
+/// Plugins whose tasks park the flow session instead of completing
+const WAITING_PLUGINS: &[&str] = &["@anything/approval", "@anything/delay"];
+
+pub const APPROVAL_WAIT: &str = "approval";
+pub const DELAY_WAIT: &str = "delay";
+
+const WAIT_TIMEOUT_CHECK_INTERVAL: Duration = Duration::from_secs(10);
+
+// How often and how many times a timer retries a session that has not finished parking
+const PARKING_RETRY_INTERVAL: Duration = Duration::from_secs(1);
+const PARKING_RETRIES: u32 = 30;
+
+/// True when a task of this plugin leaves its session waiting until the task is resumed
+pub fn parks_session(plugin_name: Option<&PluginName>) -> bool {
+    plugin_name
+        .map(|plugin_name| WAITING_PLUGINS.contains(&plugin_name.as_str()))
+        .unwrap_or(false)
+}
+
+/// Returned by the workflow actor when nothing can run until a waiting task is resumed
+#[derive(Debug)]
+pub struct SessionParked;
+
+impl fmt::Display for SessionParked {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Flow session is waiting for a task to be resumed")
+    }
+}
+
+impl std::error::Error for SessionParked {}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum WaitDecision {
+    Approved,
+    Rejected,
+}
+
+impl WaitDecision {
+    pub fn as_str(&self) -> &str {
+        match self {
+            WaitDecision::Approved => "approved",
+            WaitDecision::Rejected => "rejected",
+        }
+    }
+
+    /// Accepts the decision as a verb or as its outcome, "approve" or "approved"
+    pub fn parse(decision: &str) -> Option<Self> {
+        match decision.trim().to_lowercase().as_str() {
+            "approve" | "approved" => Some(WaitDecision::Approved),
+            "reject" | "rejected" => Some(WaitDecision::Rejected),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct TaskWait {
+    pub task_id: Uuid,
+    pub account_id: Uuid,
+    pub flow_id: Uuid,
+    pub flow_version_id: Uuid,
+    pub flow_session_id: Uuid,
+    pub action_id: String,
+    pub wait_type: String,
+    pub wait_status: String, // waiting until resumed, then resumed
+    pub expires_at: Option<DateTime<Utc>>,
+    pub timeout_decision: Option<WaitDecision>,
+    pub timeout_data: Option<Value>,
+    pub details: Option<Value>,
+    pub resolution: Option<Value>,
+    pub resolved_at: Option<DateTime<Utc>>,
+}
+
+impl TaskWait {
+    pub fn is_waiting(&self) -> bool {
+        self.wait_status == "waiting"
+    }
+}
+
+#[derive(Debug)]
+pub enum ResumeError {
+    AlreadyResumed,
+    SessionNotParked, // The session is still running the tasks next to the wait
+    Failed(String),
+}
+
+impl fmt::Display for ResumeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ResumeError::AlreadyResumed => write!(f, "The task was already resumed or canceled"),
+            ResumeError::SessionNotParked => write!(
+                f,
+                "The flow session is still running, try again once it is waiting"
+            ),
+            ResumeError::Failed(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+/// The result a waiting task completes with, read by later actions as `{{actions.<id>.result}}`
+pub fn wait_resolution(
+    decision: WaitDecision,
+    data: Value,
+    timed_out: bool,
+    resolved_at: DateTime<Utc>,
+) -> Value {
+    json!({
+        "decision": decision.as_str(),
+        "approved": decision == WaitDecision::Approved,
+        "timed_out": timed_out,
+        "resolved_at": resolved_at,
+        "data": data,
+    })
+}
+
+/// Relative URL anyone holding it can use to view and resume the wait without signing in
+pub fn resume_url(task_id: &Uuid) -> Result<String, String> {
+    Ok(format!(
+        "/api/v1/waits/{}?signature={}",
+        task_id,
+        resume_signature(task_id)?
+    ))
+}
+
+pub fn resume_signature(task_id: &Uuid) -> Result<String, String> {
+    sign_task_id(signing_secret().as_bytes(), task_id).map_err(|e| e.to_string())
+}
+
+pub fn verify_resume_signature(task_id: &Uuid, signature: &str) -> bool {
+    verify_task_id(signing_secret().as_bytes(), task_id, signature)
+}
+
+fn signing_secret() -> String {
+    dotenv().ok();
+    env::var("SUPABASE_JWT_SECRET").expect("SUPABASE_JWT_SECRET must be set")
+}
+
+fn sign_task_id(secret: &[u8], task_id: &Uuid) -> Result<String, openssl::error::ErrorStack> {
+    let key = PKey::hmac(secret)?;
+    let mut signer = Signer::new(MessageDigest::sha256(), &key)?;
+    signer.update(format!("wait:{}", task_id).as_bytes())?;
+    Ok(base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(signer.sign_to_vec()?))
+}
+
+fn verify_task_id(secret: &[u8], task_id: &Uuid, signature: &str) -> bool {
+    let expected = match sign_task_id(secret, task_id) {
+        Ok(expected) => expected,
+        Err(_) => return false,
+    };
+    signature.len() == expected.len() && memcmp::eq(signature.as_bytes(), expected.as_bytes())
+}
+
+/// The result a wait completes with once its `expires_at` passed
+pub fn timeout_resolution(wait: &TaskWait, resolved_at: DateTime<Utc>) -> Value {
+    match wait.wait_type.as_str() {
+        DELAY_WAIT => delay_resolution(wait.expires_at, resolved_at),
+        _ => wait_resolution(
+            wait.timeout_decision.unwrap_or(WaitDecision::Rejected),
+            wait.timeout_data.clone().unwrap_or(Value::Null),
+            true,
+            resolved_at,
+        ),
+    }
+}
+
+/// Stores the wait for a task, replacing the one a re-run of the same task left behind.
+/// Waits that expire also get an in-memory timer so they resume on time, the timeout
+/// loop resumes the ones whose timer was lost in a restart.
+pub async fn create_wait(state: &Arc<AppState>, wait: &TaskWait) -> Result<(), String> {
+    dotenv().ok();
+    let supabase_service_role_api_key = env::var("SUPABASE_SERVICE_ROLE_API_KEY")
+        .expect("SUPABASE_SERVICE_ROLE_API_KEY must be set");
+
+    let response = state
+        .anything_client
+        .from("task_waits")
+        .auth(supabase_service_role_api_key)
+        .upsert(serde_json::to_string(wait).map_err(|e| e.to_string())?)
+        .on_conflict("task_id")
+        .execute()
+        .await
+        .map_err(|e| format!("Failed to execute request: {}", e))?;
+
+    if !response.status().is_success() {
+        let body = response.text().await.unwrap_or_default();
+        return Err(format!("Failed to store wait: {}", body));
+    }
+
+    if let Some(expires_at) = wait.expires_at {
+        tokio::spawn(resume_when_expired(state.clone(), wait.task_id, expires_at));
+    }
+
+    Ok(())
+}
+
+async fn resume_when_expired(state: Arc<AppState>, task_id: Uuid, expires_at: DateTime<Utc>) {
+    sleep((expires_at - Utc::now()).to_std().unwrap_or(Duration::ZERO)).await;
+
+    for _ in 0..PARKING_RETRIES {
+        let wait = match get_wait(&state, &task_id).await {
+            Ok(Some(wait)) if wait.is_waiting() => wait,
+            Ok(_) => return,
+            Err(e) => {
+                warn!(
+                    "[TASK WAITS] Failed to load wait for task {}: {}",
+                    task_id, e
+                );
+                return;
+            }
+        };
+
+        match resume_expired_wait(&state, &wait).await {
+            Err(ResumeError::SessionNotParked) => sleep(PARKING_RETRY_INTERVAL).await,
+            Ok(_) | Err(ResumeError::AlreadyResumed) => return,
+            Err(e) => {
+                warn!(
+                    "[TASK WAITS] Failed to resume wait for task {}: {}",
+                    task_id, e
+                );
+                return;
+            }
+        }
+    }
+}
+
+async fn resume_expired_wait(state: &Arc<AppState>, wait: &TaskWait) -> Result<Value, ResumeError> {
+    let resolved_at = Utc::now();
+    resume_wait(
+        state,
+        wait,
+        resolved_at,
+        timeout_resolution(wait, resolved_at),
+    )
+    .await
+}
+
+pub async fn get_wait(state: &AppState, task_id: &Uuid) -> Result<Option<TaskWait>, String> {
+    dotenv().ok();
+    let supabase_service_role_api_key = env::var("SUPABASE_SERVICE_ROLE_API_KEY")
+        .expect("SUPABASE_SERVICE_ROLE_API_KEY must be set");
+
+    let response = state
+        .anything_client
+        .from("task_waits")
+        .auth(supabase_service_role_api_key)
+        .select("*")
+        .eq("task_id", task_id.to_string())
+        .execute()
+        .await
+        .map_err(|e| format!("Failed to execute request: {}", e))?;
+
+    let body = response
+        .text()
+        .await
+        .map_err(|e| format!("Failed to read response body: {}", e))?;
+
+    let waits: Vec<TaskWait> =
+        serde_json::from_str(&body).map_err(|e| format!("Failed to parse wait: {}", e))?;
+
+    Ok(waits.into_iter().next())
+}
+
+/// Completes a waiting task with the resolution and continues its flow session.
+/// The wait is resolved before the run is queued again so only one resume can win,
+/// and is reopened when the session has not finished parking yet or could not be resumed.
+pub async fn resume_wait(
+    state: &Arc<AppState>,
+    wait: &TaskWait,
+    resolved_at: DateTime<Utc>,
+    resolution: Value,
+) -> Result<Value, ResumeError> {
+    if !wait.is_waiting() {
+        return Err(ResumeError::AlreadyResumed);
+    }
+
+    if !set_wait_status(
+        state,
+        &wait.task_id,
+        "waiting",
+        json!({
+            "wait_status": "resumed",
+            "resolution": resolution,
+            "resolved_at": resolved_at,
+        }),
+    )
+    .await
+    .map_err(ResumeError::Failed)?
+    {
+        return Err(ResumeError::AlreadyResumed);
+    }
+
+    let run = match requeue_waiting_run(state, &wait.flow_session_id).await {
+        Ok(Some(run)) => run,
+        outcome => {
+            reopen_wait(state, wait).await;
+            return Err(match outcome {
+                Err(e) => ResumeError::Failed(e),
+                _ => ResumeError::SessionNotParked,
+            });
+        }
+    };
+
+    info!(
+        "[TASK WAITS] Task {} {} wait resumed, resuming flow session {}",
+        wait.task_id, wait.wait_type, wait.flow_session_id
+    );
+
+    // Written before the session is loaded again so the workflow actor sees the task completed
+    let resumed = match update_task_status(
+        state.clone(),
+        &wait.task_id,
+        &TaskStatus::Completed,
+        None,
+        Some(resolution.clone()),
+        None,
+        None,
+        Some(resolved_at),
+    )
+    .await
+    {
+        Ok(()) => resume_run(state, run).await,
+        Err(e) => Err(e),
+    };
+
+    // Nothing picks up a queued run of a live instance, so park it again and let the
+    // wait be resumed once more instead of leaving the session stuck
+    if let Err(e) = resumed {
+        if let Err(park_error) =
+            update_run_status(state, &wait.flow_session_id, &RunStatus::Waiting).await
+        {
+            error!(
+                "[TASK WAITS] Failed to park flow session {} again: {}",
+                wait.flow_session_id, park_error
+            );
+        }
+        reopen_wait(state, wait).await;
+        return Err(ResumeError::Failed(e));
+    }
+
+    Ok(resolution)
+}
+
+/// Moves a resolved wait back to waiting after its session could not be resumed
+async fn reopen_wait(state: &AppState, wait: &TaskWait) {
+    if let Err(e) = set_wait_status(
+        state,
+        &wait.task_id,
+        "resumed",
+        json!({
+            "wait_status": "waiting",
+            "resolution": Value::Null,
+            "resolved_at": Value::Null,
+        }),
+    )
+    .await
+    {
+        error!(
+            "[TASK WAITS] Failed to reopen wait for task {}: {}",
+            wait.task_id, e
+        );
+    }
+}
+
+/// Closes the open waits of a canceled flow session so their timers and resume links do nothing
+pub async fn cancel_session_waits(state: &AppState, flow_session_id: &Uuid) -> Result<(), String> {
+    dotenv().ok();
+    let supabase_service_role_api_key = env::var("SUPABASE_SERVICE_ROLE_API_KEY")
+        .expect("SUPABASE_SERVICE_ROLE_API_KEY must be set");
+
+    state
+        .anything_client
+        .from("task_waits")
+        .auth(supabase_service_role_api_key)
+        .eq("flow_session_id", flow_session_id.to_string())
+        .eq("wait_status", "waiting")
+        .update(
+            json!({
+                "wait_status": "canceled",
+                "resolved_at": Utc::now(),
+            })
+            .to_string(),
+        )
+        .execute()
+        .await
+        .map_err(|e| format!("Failed to execute request: {}", e))?;
+
+    Ok(())
+}
+
+/// Moves a wait from one status to another, returning false when it was not in `from` anymore
+async fn set_wait_status(
+    state: &AppState,
+    task_id: &Uuid,
+    from: &str,
+    update: Value,
+) -> Result<bool, String> {
+    dotenv().ok();
+    let supabase_service_role_api_key = env::var("SUPABASE_SERVICE_ROLE_API_KEY")
+        .expect("SUPABASE_SERVICE_ROLE_API_KEY must be set");
+
+    let response = state
+        .anything_client
+        .from("task_waits")
+        .auth(supabase_service_role_api_key)
+        .eq("task_id", task_id.to_string())
+        .eq("wait_status", from)
+        .update(update.to_string())
+        .execute()
+        .await
+        .map_err(|e| format!("Failed to execute request: {}", e))?;
+
+    let body = response
+        .text()
+        .await
+        .map_err(|e| format!("Failed to read response body: {}", e))?;
+
+    let updated: Vec<Value> =
+        serde_json::from_str(&body).map_err(|e| format!("Failed to parse wait update: {}", e))?;
+
+    Ok(!updated.is_empty())
+}
+
+async fn get_expired_waits(state: &AppState) -> Result<Vec<TaskWait>, String> {
+    dotenv().ok();
+    let supabase_service_role_api_key = env::var("SUPABASE_SERVICE_ROLE_API_KEY")
+        .expect("SUPABASE_SERVICE_ROLE_API_KEY must be set");
+
+    let response = state
+        .anything_client
+        .from("task_waits")
+        .auth(supabase_service_role_api_key)
+        .select("*")
+        .eq("wait_status", "waiting")
+        .lte("expires_at", Utc::now().to_rfc3339())
+        .order("expires_at.asc")
+        .execute()
+        .await
+        .map_err(|e| format!("Failed to execute request: {}", e))?;
+
+    let body = response
+        .text()
+        .await
+        .map_err(|e| format!("Failed to read response body: {}", e))?;
+
+    serde_json::from_str(&body).map_err(|e| format!("Failed to parse waits: {}", e))
+}
+
+/// Resumes waits whose `expires_at` passed without their timer firing, like the ones
+/// parked before a restart. Every replica runs it, resolving a wait only succeeds once.
+pub async fn wait_timeout_loop(state: Arc<AppState>) {
+    info!("[TASK WAITS] Starting wait timeout loop");
+
+    loop {
+        sleep(WAIT_TIMEOUT_CHECK_INTERVAL).await;
+
+        if state
+            .shutdown_signal
+            .load(std::sync::atomic::Ordering::SeqCst)
+        {
+            info!("[TASK WAITS] Shutdown signal received, stopping wait timeout loop");
+            break;
+        }
+
+        let waits = match get_expired_waits(&state).await {
+            Ok(waits) => waits,
+            Err(e) => {
+                error!("[TASK WAITS] Error fetching expired waits: {}", e);
+                continue;
+            }
+        };
+
+        for wait in waits {
+            match resume_expired_wait(&state, &wait).await {
+                Ok(_) | Err(ResumeError::AlreadyResumed) => {}
+                // Picked up again on the next check
+                Err(ResumeError::SessionNotParked) => {}
+                Err(e) => warn!(
+                    "[TASK WAITS] Failed to time out wait for task {}: {}",
+                    wait.task_id, e
+                ),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resume_signature() {
+        let task_id = Uuid::new_v4();
+        let signature = sign_task_id(b"secret", &task_id).unwrap();
+
+        assert!(verify_task_id(b"secret", &task_id, &signature));
+        assert!(!verify_task_id(b"other secret", &task_id, &signature));
+        assert!(!verify_task_id(b"secret", &Uuid::new_v4(), &signature));
+        assert!(!verify_task_id(b"secret", &task_id, ""));
+    }
+
+    #[test]
+    fn test_wait_resolution() {
+        let resolved_at = Utc::now();
+
+        assert_eq!(
+            WaitDecision::parse(" Approve"),
+            Some(WaitDecision::Approved)
+        );
+        assert_eq!(
+            WaitDecision::parse("rejected"),
+            Some(WaitDecision::Rejected)
+        );
+        assert_eq!(WaitDecision::parse("maybe"), None);
+
+        assert_eq!(
+            wait_resolution(
+                WaitDecision::Rejected,
+                json!({ "reason": "too expensive" }),
+                true,
+                resolved_at
+            ),
+            json!({
+                "decision": "rejected",
+                "approved": false,
+                "timed_out": true,
+                "resolved_at": resolved_at,
+                "data": { "reason": "too expensive" }
+            })
+        );
+    }
+}
@@ -1,5 +1,12 @@
-use crate::processor::db_calls::{create_task, update_flow_session_status, update_task_status};
-use crate::types::task_types::{FlowSessionStatus, Task, TaskStatus, TriggerSessionStatus};
+use crate::processor::db_calls::{
+    cancel_unfinished_tasks, create_task, update_flow_session_status, update_task_attempts,
+    update_task_debug_result, update_task_status,
+};
+use crate::processor::task_waits::cancel_session_waits;
+use crate::types::task_types::{
+    FlowSessionStatus, Task, TaskAttempt, TaskStatus, TriggerSessionStatus,
+};
+use crate::processor::run_queue::{update_run_status, RunStatus};
 use crate::AppState;
 use crate::metrics::METRICS;
 use chrono::{DateTime, Utc};
@@ -26,6 +33,14 @@ pub enum Operation {
         task_id: Uuid,
         input: Task,
     },
+    UpdateTaskAttempts {
+        task_id: Uuid,
+        attempts: Vec<TaskAttempt>,
+    },
+    UpdateTaskDebugResult {
+        task_id: Uuid,
+        debug_result: Value,
+    },
     CompleteWorkflow {
         flow_session_id: Uuid,
         status: FlowSessionStatus,
@@ -43,6 +58,8 @@ fn get_operation_type(operation: &Operation) -> &'static str {
     match operation {
         Operation::UpdateTask { .. } => "update_task",
         Operation::CreateTask { .. } => "create_task", 
+        Operation::UpdateTaskAttempts { .. } => "update_task_attempts",
+        Operation::UpdateTaskDebugResult { .. } => "update_task_debug_result",
         Operation::CompleteWorkflow { .. } => "complete_workflow",
     }
 }
@@ -56,10 +73,9 @@ pub async fn task_database_status_processor(
     info!("[TASK PROCESSOR] Starting status updater processor");
 
     while let Some(message) = receiver.recv().await {
-        // Check shutdown signal
+        // Keep draining during shutdown so finished tasks are stored before the run queue resumes the session
         if state.shutdown_signal.load(std::sync::atomic::Ordering::SeqCst) {
-            info!("[TASK PROCESSOR] Shutdown signal received, stopping status updater");
-            break;
+            info!("[TASK PROCESSOR] Shutdown signal received, draining remaining status updates");
         }
 
         // Record queue wait time
@@ -74,6 +90,8 @@ pub async fn task_database_status_processor(
         let operation_kind = match &message.operation {
             Operation::UpdateTask { .. } => "UpdateTask",
             Operation::CreateTask { .. } => "CreateTask",
+            Operation::UpdateTaskAttempts { .. } => "UpdateTaskAttempts",
+            Operation::UpdateTaskDebugResult { .. } => "UpdateTaskDebugResult",
             Operation::CompleteWorkflow { .. } => "CompleteWorkflow",
         };
 
@@ -132,18 +150,42 @@ pub async fn task_database_status_processor(
                                 create_task(state.clone(), input)
                             }).await
                         }
+                        Operation::UpdateTaskAttempts { task_id, attempts } => {
+                            span!(Level::DEBUG, "update_task_attempts_db_call", task_id = %task_id).in_scope(|| {
+                                update_task_attempts(state.clone(), task_id, attempts)
+                            }).await
+                        }
+                        Operation::UpdateTaskDebugResult { task_id, debug_result } => {
+                            span!(Level::DEBUG, "update_task_debug_result_db_call", task_id = %task_id).in_scope(|| {
+                                update_task_debug_result(state.clone(), task_id, debug_result)
+                            }).await
+                        }
                         Operation::CompleteWorkflow {
                             flow_session_id,
                             status,
                             trigger_status,
                         } => {
-                            span!(Level::DEBUG, "complete_workflow_db_call", flow_session_id = %flow_session_id, flow_status = ?status).in_scope(|| {
+                            let run_status = match status {
+                                FlowSessionStatus::Failed => RunStatus::Failed,
+                                FlowSessionStatus::Waiting => RunStatus::Waiting,
+                                FlowSessionStatus::Canceled => RunStatus::Canceled,
+                                _ => RunStatus::Completed,
+                            };
+                            span!(Level::DEBUG, "complete_workflow_db_call", flow_session_id = %flow_session_id, flow_status = ?status).in_scope(|| async {
                                 update_flow_session_status(
                                     &state,
                                     flow_session_id,
                                     status,
                                     trigger_status,
                                 )
+                                .await?;
+                                // Tasks that never got to run and open waits go with a canceled session
+                                if matches!(status, FlowSessionStatus::Canceled) {
+                                    cancel_unfinished_tasks(&state, flow_session_id).await?;
+                                    cancel_session_waits(&state, flow_session_id).await?;
+                                }
+                                // Finished and parked runs leave the run queue so they are not resumed after a restart
+                                update_run_status(&state, flow_session_id, &run_status).await
                             })
                             .await
                         }
@@ -16,6 +16,7 @@ use std::env;
 use std::sync::Arc;
 use uuid::Uuid;
 
+use crate::processor::run_queue::enqueue_workflow;
 use crate::{processor::processor::ProcessorMessage, types::workflow_types::DatabaseFlowVersion};
 use crate::{
     types::{
@@ -187,7 +188,7 @@ pub async fn run_workflow_as_tool_call_and_respond(
         existing_tasks: HashMap::new(), // No existing tasks for new workflows
     };
 
-    if let Err(e) = state.processor_sender.send(processor_message).await {
+    if let Err(e) = enqueue_workflow(&state, processor_message).await {
         println!("[TEST WORKFLOW] Failed to send message to processor: {}", e);
         return (
             StatusCode::INTERNAL_SERVER_ERROR,
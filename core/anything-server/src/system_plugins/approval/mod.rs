@@ -0,0 +1,306 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Extension, Json,
+};
+use chrono::Utc;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{info, instrument};
+use uuid::Uuid;
+
+use crate::processor::task_waits::{
+    create_wait, get_wait, resume_url, resume_wait, verify_resume_signature, wait_resolution,
+    ResumeError, TaskWait, WaitDecision, APPROVAL_WAIT,
+};
+use crate::supabase_jwt_middleware::User;
+use crate::types::task_types::Task;
+use crate::AppState;
+
+const DEFAULT_TIMEOUT_SECONDS: u64 = 86_400;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ApprovalSettings {
+    pub title: String,
+    pub message: String,
+    pub timeout: Option<Duration>, // None waits until someone decides
+    pub timeout_decision: WaitDecision,
+    pub timeout_data: Value,
+}
+
+impl ApprovalSettings {
+    pub fn from_plugin_config(config: &Value) -> Result<Self, String> {
+        let text = |key: &str| {
+            config
+                .get(key)
+                .and_then(Value::as_str)
+                .unwrap_or_default()
+                .to_string()
+        };
+
+        // 0 turns the timeout off
+        let timeout_seconds = match config.get("timeout_seconds") {
+            Some(Value::Number(n)) => n.as_u64(),
+            Some(Value::String(s)) if !s.trim().is_empty() => Some(
+                s.trim()
+                    .parse::<u64>()
+                    .map_err(|_| format!("Invalid timeout_seconds '{}'", s))?,
+            ),
+            _ => None,
+        }
+        .unwrap_or(DEFAULT_TIMEOUT_SECONDS);
+
+        let timeout_decision = match config.get("timeout_decision").and_then(Value::as_str) {
+            None | Some("") => WaitDecision::Rejected,
+            Some(decision) => WaitDecision::parse(decision)
+                .ok_or_else(|| format!("Unknown timeout_decision '{}'", decision))?,
+        };
+
+        let timeout_data = match config.get("timeout_data") {
+            Some(Value::String(raw)) if raw.trim().is_empty() => json!({}),
+            Some(Value::String(raw)) => serde_json::from_str::<Value>(raw)
+                .map_err(|_| "Approval timeout_data must be valid JSON".to_string())?,
+            Some(Value::Null) | None => json!({}),
+            Some(data) => data.clone(),
+        };
+
+        Ok(Self {
+            title: text("title"),
+            message: text("message"),
+            timeout: (timeout_seconds > 0).then(|| Duration::from_secs(timeout_seconds)),
+            timeout_decision,
+            timeout_data,
+        })
+    }
+}
+
+/// Stores a wait for the task and returns where it can be approved or rejected.
+/// The task actor leaves the task waiting, and the flow session parks once nothing else can run.
+#[instrument(skip(state, task, bundled_plugin_config))]
+pub async fn process_approval_task(
+    state: Arc<AppState>,
+    task: &Task,
+    bundled_plugin_config: &Value,
+) -> Result<Option<Value>, Box<dyn std::error::Error + Send + Sync>> {
+    let settings = ApprovalSettings::from_plugin_config(bundled_plugin_config)?;
+
+    let expires_at = settings
+        .timeout
+        .map(|timeout| Utc::now() + chrono::Duration::seconds(timeout.as_secs() as i64));
+
+    let details = json!({
+        "title": settings.title,
+        "message": settings.message,
+    });
+
+    let wait = TaskWait {
+        task_id: task.task_id,
+        account_id: task.account_id,
+        flow_id: task.flow_id,
+        flow_version_id: task.flow_version_id,
+        flow_session_id: task.flow_session_id,
+        action_id: task.action_id.clone(),
+        wait_type: APPROVAL_WAIT.to_string(),
+        wait_status: "waiting".to_string(),
+        expires_at,
+        timeout_decision: Some(settings.timeout_decision),
+        timeout_data: Some(settings.timeout_data.clone()),
+        details: Some(details.clone()),
+        resolution: None,
+        resolved_at: None,
+    };
+
+    create_wait(&state, &wait).await?;
+
+    info!(
+        "[APPROVAL] Task {} waiting for approval until {}",
+        task.task_id,
+        expires_at
+            .map(|expires_at| expires_at.to_rfc3339())
+            .unwrap_or_else(|| "someone decides".to_string())
+    );
+
+    Ok(Some(json!({
+        "status": "waiting",
+        "resume_url": resume_url(&task.task_id)?,
+        "expires_at": expires_at,
+        "title": settings.title,
+        "message": settings.message,
+    })))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SignatureQuery {
+    pub signature: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ResolveWaitInput {
+    pub decision: String,
+    #[serde(default)]
+    pub data: Value,
+}
+
+/// Shows what a signed resume URL is asking to approve
+pub async fn get_wait_with_signature(
+    Path(task_id): Path<Uuid>,
+    Query(query): Query<SignatureQuery>,
+    State(state): State<Arc<AppState>>,
+) -> impl IntoResponse {
+    println!("[APPROVAL] Getting wait for task {}", task_id);
+
+    let wait = match signed_wait(&state, &task_id, &query.signature).await {
+        Ok(wait) => wait,
+        Err(response) => return response,
+    };
+
+    Json(json!({
+        "task_id": wait.task_id,
+        "flow_session_id": wait.flow_session_id,
+        "action_id": wait.action_id,
+        "wait_status": wait.wait_status,
+        "details": wait.details,
+        "expires_at": wait.expires_at,
+        "resolution": wait.resolution,
+    }))
+    .into_response()
+}
+
+/// Approves or rejects a wait through its signed resume URL
+pub async fn resolve_wait_with_signature(
+    Path(task_id): Path<Uuid>,
+    Query(query): Query<SignatureQuery>,
+    State(state): State<Arc<AppState>>,
+    Json(input): Json<ResolveWaitInput>,
+) -> impl IntoResponse {
+    println!(
+        "[APPROVAL] Resolving wait for task {} by signed URL",
+        task_id
+    );
+
+    match signed_wait(&state, &task_id, &query.signature).await {
+        Ok(wait) => resolve(&state, wait, input).await,
+        Err(response) => response,
+    }
+}
+
+/// Approves or rejects a wait of the account as a signed in member
+pub async fn resolve_wait(
+    Path((account_id, task_id)): Path<(String, Uuid)>,
+    State(state): State<Arc<AppState>>,
+    Extension(_user): Extension<User>,
+    Json(input): Json<ResolveWaitInput>,
+) -> impl IntoResponse {
+    println!(
+        "[APPROVAL] Resolving wait for task {} in account {}",
+        task_id, account_id
+    );
+
+    let wait = match get_wait(&state, &task_id).await {
+        // Waits of other accounts read as not found
+        Ok(Some(wait)) if wait.account_id.to_string() == account_id => wait,
+        Ok(_) => return (StatusCode::NOT_FOUND, "Wait not found").into_response(),
+        Err(e) => {
+            println!("[APPROVAL] {}", e);
+            return (StatusCode::INTERNAL_SERVER_ERROR, "Failed to load wait").into_response();
+        }
+    };
+
+    resolve(&state, wait, input).await
+}
+
+async fn signed_wait(
+    state: &AppState,
+    task_id: &Uuid,
+    signature: &str,
+) -> Result<TaskWait, Response> {
+    if !verify_resume_signature(task_id, signature) {
+        return Err((StatusCode::UNAUTHORIZED, "Invalid signature").into_response());
+    }
+
+    match get_wait(state, task_id).await {
+        Ok(Some(wait)) => Ok(wait),
+        Ok(None) => Err((StatusCode::NOT_FOUND, "Wait not found").into_response()),
+        Err(e) => {
+            println!("[APPROVAL] {}", e);
+            Err((StatusCode::INTERNAL_SERVER_ERROR, "Failed to load wait").into_response())
+        }
+    }
+}
+
+async fn resolve(state: &Arc<AppState>, wait: TaskWait, input: ResolveWaitInput) -> Response {
+    // Delays only resume when their time comes
+    if wait.wait_type != APPROVAL_WAIT {
+        return (StatusCode::NOT_FOUND, "Wait not found").into_response();
+    }
+
+    let decision = match WaitDecision::parse(&input.decision) {
+        Some(decision) => decision,
+        None => {
+            return (
+                StatusCode::BAD_REQUEST,
+                "decision must be approve or reject",
+            )
+                .into_response()
+        }
+    };
+
+    let resolved_at = Utc::now();
+    let resolution = wait_resolution(decision, input.data, false, resolved_at);
+
+    match resume_wait(state, &wait, resolved_at, resolution).await {
+        Ok(resolution) => Json(resolution).into_response(),
+        Err(e @ ResumeError::AlreadyResumed) | Err(e @ ResumeError::SessionNotParked) => {
+            (StatusCode::CONFLICT, e.to_string()).into_response()
+        }
+        Err(ResumeError::Failed(e)) => {
+            println!("[APPROVAL] Failed to resume task {}: {}", wait.task_id, e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed to resume the task",
+            )
+                .into_response()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_settings_from_plugin_config() {
+        let settings = ApprovalSettings::from_plugin_config(&json!({
+            "title": "Refund",
+            "message": "Refund order 42?",
+            "timeout_seconds": "3600",
+            "timeout_decision": "approve",
+            "timeout_data": "{\"note\": \"auto approved\"}"
+        }))
+        .unwrap();
+
+        assert_eq!(settings.title, "Refund");
+        assert_eq!(settings.timeout, Some(Duration::from_secs(3600)));
+        assert_eq!(settings.timeout_decision, WaitDecision::Approved);
+        assert_eq!(settings.timeout_data, json!({ "note": "auto approved" }));
+
+        let defaults = ApprovalSettings::from_plugin_config(&json!({})).unwrap();
+        assert_eq!(
+            defaults.timeout,
+            Some(Duration::from_secs(DEFAULT_TIMEOUT_SECONDS))
+        );
+        assert_eq!(defaults.timeout_decision, WaitDecision::Rejected);
+        assert_eq!(defaults.timeout_data, json!({}));
+
+        let forever =
+            ApprovalSettings::from_plugin_config(&json!({ "timeout_seconds": 0 })).unwrap();
+        assert_eq!(forever.timeout, None);
+
+        assert!(
+            ApprovalSettings::from_plugin_config(&json!({ "timeout_decision": "later" })).is_err()
+        );
+    }
+}
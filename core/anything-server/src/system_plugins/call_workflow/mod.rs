@@ -0,0 +1,557 @@
+use dotenv::dotenv;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::env;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::oneshot;
+use tokio::time::{sleep, timeout};
+use tracing::{info, instrument, warn};
+use uuid::Uuid;
+
+use crate::actor_processor::cancellation::SessionCanceled;
+use crate::processor::db_calls::get_session_tasks;
+use crate::processor::processor::ProcessorMessage;
+use crate::processor::run_queue::{
+    enqueue_workflow_call, get_called_run, get_queued_run, ParentRun, RunStatus,
+};
+use crate::types::action_types::ActionType;
+use crate::types::task_types::{Task, TaskConfig, TaskStatus};
+use crate::types::workflow_types::DatabaseFlowVersion;
+use crate::AppState;
+
+pub const INPUT_PLUGIN_NAME: &str = "@anything/input";
+pub const OUTPUT_PLUGIN_NAME: &str = "@anything/output";
+
+/// Most workflows a chain of calls can go through, including the one that started it
+pub const MAX_CALL_DEPTH: usize = 5;
+
+const DEFAULT_TIMEOUT_SECONDS: u64 = 120;
+pub const MAX_TIMEOUT_SECONDS: u64 = 600;
+
+// How often a waiting call reads its called session's outcome from the database, for
+// sessions that don't run on this server
+const CALLED_RUN_CHECK_INTERVAL: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CallMode {
+    Wait,          // Wait for the called workflow's Output
+    FireAndForget, // Start the called workflow and continue right away
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CallWorkflowSettings {
+    pub workflow_id: Uuid,
+    pub workflow_version_id: Option<Uuid>, // None runs the published version
+    pub inputs: Value,
+    pub mode: CallMode,
+    pub timeout: Duration,
+}
+
+impl CallWorkflowSettings {
+    pub fn from_plugin_config(config: &Value) -> Result<Self, String> {
+        let workflow_id = match config.get("workflow_id").and_then(Value::as_str) {
+            Some(id) if !id.trim().is_empty() => {
+                Uuid::parse_str(id.trim()).map_err(|_| format!("Invalid workflow_id '{}'", id))?
+            }
+            _ => return Err("Call workflow requires a workflow_id".to_string()),
+        };
+
+        let workflow_version_id = match config.get("workflow_version_id").and_then(Value::as_str) {
+            Some(id) if !id.trim().is_empty() => Some(
+                Uuid::parse_str(id.trim())
+                    .map_err(|_| format!("Invalid workflow_version_id '{}'", id))?,
+            ),
+            _ => None,
+        };
+
+        let inputs = match config.get("inputs") {
+            Some(Value::Object(inputs)) => Value::Object(inputs.clone()),
+            Some(Value::String(raw)) if raw.trim().is_empty() => json!({}),
+            Some(Value::String(raw)) => match serde_json::from_str::<Value>(raw) {
+                Ok(inputs @ Value::Object(_)) => inputs,
+                _ => return Err("Call workflow inputs must be an object".into()),
+            },
+            Some(Value::Null) | None => json!({}),
+            Some(_) => return Err("Call workflow inputs must be an object".into()),
+        };
+
+        let mode = match config.get("mode").and_then(Value::as_str) {
+            None | Some("") | Some("wait") => CallMode::Wait,
+            Some("fire_and_forget") => CallMode::FireAndForget,
+            Some(mode) => return Err(format!("Unknown call workflow mode '{}'", mode)),
+        };
+
+        let timeout_seconds = match config.get("timeout_seconds") {
+            Some(Value::Number(n)) => n.as_u64(),
+            Some(Value::String(s)) => s.trim().parse::<u64>().ok(),
+            _ => None,
+        }
+        .filter(|seconds| *seconds > 0)
+        .unwrap_or(DEFAULT_TIMEOUT_SECONDS)
+        .min(MAX_TIMEOUT_SECONDS);
+
+        Ok(Self {
+            workflow_id,
+            workflow_version_id,
+            inputs,
+            mode,
+            timeout: Duration::from_secs(timeout_seconds),
+        })
+    }
+}
+
+/// Checks a call to `workflow_id` from the end of `call_stack` can't recurse
+pub fn check_call_stack(call_stack: &[Uuid], workflow_id: &Uuid) -> Result<(), String> {
+    if call_stack.contains(workflow_id) {
+        return Err(format!(
+            "Workflow {} is already running in this chain of calls, calling it again would recurse",
+            workflow_id
+        ));
+    }
+
+    if call_stack.len() >= MAX_CALL_DEPTH {
+        return Err(format!(
+            "Workflow calls can only be nested {} workflows deep",
+            MAX_CALL_DEPTH
+        ));
+    }
+
+    Ok(())
+}
+
+/// Starts the configured workflow as a new flow session whose Input trigger returns the
+/// inputs. Waits for the flow's Output unless the mode is fire and forget.
+#[instrument(skip(state, task, bundled_plugin_config))]
+pub async fn process_call_workflow_task(
+    state: Arc<AppState>,
+    task: &Task,
+    bundled_plugin_config: &Value,
+) -> Result<Option<Value>, Box<dyn std::error::Error + Send + Sync>> {
+    let settings = CallWorkflowSettings::from_plugin_config(bundled_plugin_config)?;
+
+    info!(
+        "[CALL_WORKFLOW] Flow session {} calling workflow {}",
+        task.flow_session_id, settings.workflow_id
+    );
+
+    // A task run again, after a restart or a retry, keeps the session it already started
+    if let Some(run) = get_called_run(&state, &task.task_id).await? {
+        info!(
+            "[CALL_WORKFLOW] Task {} already started flow session {}, not starting another",
+            task.task_id, run.flow_session_id
+        );
+        return match settings.mode {
+            CallMode::Wait => {
+                let pending_call = PendingCall::register(&state, run.flow_session_id);
+                wait_for_called_session(&state, task, &settings, pending_call).await
+            }
+            CallMode::FireAndForget => Ok(Some(json!({
+                "flow_session_id": run.flow_session_id,
+                "output": Value::Null
+            }))),
+        };
+    }
+
+    // The caller's own run holds the workflows that called it
+    let mut call_stack = get_queued_run(&state, &task.flow_session_id)
+        .await?
+        .map(|run| run.call_stack)
+        .unwrap_or_default();
+    call_stack.push(task.flow_id);
+    check_call_stack(&call_stack, &settings.workflow_id)?;
+
+    let workflow_version = fetch_called_flow_version(&state, &task.account_id, &settings).await?;
+
+    let input_node = workflow_version
+        .flow_definition
+        .actions
+        .iter()
+        .find(|action| action.r#type == ActionType::Trigger)
+        .filter(|action| action.plugin_name.as_str() == INPUT_PLUGIN_NAME)
+        .ok_or("The called workflow must start with an Input trigger")?;
+
+    let trigger_task = Task::builder()
+        .account_id(task.account_id)
+        .flow_id(workflow_version.flow_id)
+        .flow_version_id(workflow_version.flow_version_id)
+        .action_label(input_node.label.clone())
+        .trigger_id(input_node.action_id.clone())
+        .action_id(input_node.action_id.clone())
+        .r#type(ActionType::Trigger)
+        .plugin_name(input_node.plugin_name.clone())
+        .plugin_version(input_node.plugin_version.clone())
+        .stage(task.stage.clone())
+        .config(TaskConfig {
+            inputs: input_node.inputs.clone(),
+            inputs_schema: input_node.inputs_schema.clone(),
+            plugin_config: Some(input_node.plugin_config.clone()),
+            plugin_config_schema: Some(input_node.plugin_config_schema.clone()),
+        })
+        .result(settings.inputs.clone())
+        .build()?;
+
+    let flow_session_id = trigger_task.flow_session_id;
+
+    // Registered before the run starts so a fast Output can't be missed
+    let pending_call = match settings.mode {
+        CallMode::Wait => Some(PendingCall::register(&state, flow_session_id)),
+        CallMode::FireAndForget => None,
+    };
+
+    let processor_message = ProcessorMessage {
+        workflow_id: workflow_version.flow_id,
+        workflow_version: workflow_version.clone(),
+        workflow_definition: workflow_version.flow_definition.clone(),
+        flow_session_id,
+        trigger_session_id: trigger_task.trigger_session_id,
+        trigger_task: Some(trigger_task.clone()),
+        task_id: Some(trigger_task.task_id),
+        existing_tasks: HashMap::new(),
+    };
+
+    let parent = ParentRun {
+        flow_session_id: task.flow_session_id,
+        task_id: task.task_id,
+        call_stack,
+    };
+
+    if let Err(e) = enqueue_workflow_call(&state, processor_message, parent).await {
+        return Err(format!("Failed to start the called workflow: {}", e).into());
+    }
+
+    let Some(pending_call) = pending_call else {
+        info!(
+            "[CALL_WORKFLOW] Started flow session {} without waiting",
+            flow_session_id
+        );
+        return Ok(Some(json!({
+            "flow_session_id": flow_session_id,
+            "output": Value::Null
+        })));
+    };
+
+    wait_for_called_session(&state, task, &settings, pending_call).await
+}
+
+/// Waits, for at most the call's timeout, until the called session reaches an Output or finishes
+async fn wait_for_called_session(
+    state: &Arc<AppState>,
+    task: &Task,
+    settings: &CallWorkflowSettings,
+    mut pending_call: PendingCall<'_>,
+) -> Result<Option<Value>, Box<dyn std::error::Error + Send + Sync>> {
+    let flow_session_id = pending_call.flow_session_id;
+
+    // The caller's permit is free for the called workflow while it waits
+    let parked = state.session_permits.park(task.flow_session_id);
+    let outcome = timeout(
+        settings.timeout,
+        receive_call_outcome(state, flow_session_id, &mut pending_call.receiver),
+    )
+    .await;
+    parked.resume().await?;
+
+    match outcome {
+        Ok(Ok(Ok(output))) => Ok(Some(json!({
+            "flow_session_id": flow_session_id,
+            "output": output
+        }))),
+        Ok(Ok(Err(e))) => {
+            Err(format!("Called workflow session {} failed: {}", flow_session_id, e).into())
+        }
+        Ok(Err(_)) => Err(format!(
+            "Called workflow session {} stopped without finishing",
+            flow_session_id
+        )
+        .into()),
+        Err(_) => {
+            warn!(
+                "[CALL_WORKFLOW] Timed out waiting for flow session {}",
+                flow_session_id
+            );
+            Err(format!(
+                "Called workflow session {} did not finish within {} seconds",
+                flow_session_id,
+                settings.timeout.as_secs()
+            )
+            .into())
+        }
+    }
+}
+
+/// Waits for the outcome the called session hands over when it runs on this server.
+/// A session resumed on another server after a restart can't, so its stored outcome is read too.
+async fn receive_call_outcome(
+    state: &Arc<AppState>,
+    flow_session_id: Uuid,
+    receiver: &mut oneshot::Receiver<Result<Value, String>>,
+) -> Result<Result<Value, String>, oneshot::error::RecvError> {
+    loop {
+        tokio::select! {
+            outcome = &mut *receiver => return outcome,
+            _ = sleep(CALLED_RUN_CHECK_INTERVAL) => {
+                match stored_call_outcome(state, &flow_session_id).await {
+                    Ok(Some(outcome)) => return Ok(outcome),
+                    Ok(None) => {}
+                    Err(e) => warn!(
+                        "[CALL_WORKFLOW] Failed to read the outcome of flow session {}: {}",
+                        flow_session_id, e
+                    ),
+                }
+            }
+        }
+    }
+}
+
+/// The outcome of a called session from its run and stored tasks, `None` while it is still running
+async fn stored_call_outcome(
+    state: &Arc<AppState>,
+    flow_session_id: &Uuid,
+) -> Result<Option<Result<Value, String>>, String> {
+    let run = get_queued_run(state, flow_session_id)
+        .await?
+        .ok_or_else(|| format!("Flow session {} has no run", flow_session_id))?;
+
+    // Read after the run, so a run that just finished can't miss its Output
+    let tasks = get_session_tasks(state.clone(), flow_session_id).await?;
+    if let Some(output) = stored_output(&tasks) {
+        return Ok(Some(Ok(output)));
+    }
+
+    Ok(match run.run_status {
+        RunStatus::Queued | RunStatus::Waiting => None,
+        RunStatus::Completed => Some(Ok(Value::Null)),
+        RunStatus::Failed => Some(Err("Flow session failed".to_string())),
+        RunStatus::Canceled => Some(Err(SessionCanceled.to_string())),
+    })
+}
+
+/// The result of a session's first completed Output, like the one handed to a waiting caller
+pub fn stored_output(tasks: &[Task]) -> Option<Value> {
+    tasks
+        .iter()
+        .filter(|task| task.task_status == TaskStatus::Completed)
+        .filter(|task| {
+            task.plugin_name.as_ref().map(|name| name.as_str()) == Some(OUTPUT_PLUGIN_NAME)
+        })
+        .min_by_key(|task| task.processing_order)
+        .and_then(|task| task.result.clone())
+}
+
+/// A Call Workflow action's entry in `workflow_calls`.
+/// Removed when the action stops waiting, including when its task is dropped mid wait.
+struct PendingCall<'a> {
+    state: &'a AppState,
+    flow_session_id: Uuid,
+    receiver: oneshot::Receiver<Result<Value, String>>,
+}
+
+impl<'a> PendingCall<'a> {
+    fn register(state: &'a AppState, flow_session_id: Uuid) -> Self {
+        let (sender, receiver) = oneshot::channel();
+        state
+            .workflow_calls
+            .insert(flow_session_id.to_string(), sender);
+        Self {
+            state,
+            flow_session_id,
+            receiver,
+        }
+    }
+}
+
+impl Drop for PendingCall<'_> {
+    fn drop(&mut self) {
+        self.state
+            .workflow_calls
+            .remove(&self.flow_session_id.to_string());
+    }
+}
+
+/// Hands a called flow session's outcome to the Call Workflow action waiting on it.
+/// The first outcome wins, so an Output followed by the session completing only sends the Output.
+pub fn finish_workflow_call(
+    state: &AppState,
+    flow_session_id: &Uuid,
+    outcome: Result<Value, String>,
+) {
+    if let Some((_, sender)) = state.workflow_calls.remove(&flow_session_id.to_string()) {
+        info!(
+            "[CALL_WORKFLOW] Returning outcome of flow session {} to its caller",
+            flow_session_id
+        );
+        let _ = sender.send(outcome);
+    }
+}
+
+/// Gets the version to run, only from the caller's own account
+async fn fetch_called_flow_version(
+    state: &AppState,
+    account_id: &Uuid,
+    settings: &CallWorkflowSettings,
+) -> Result<DatabaseFlowVersion, String> {
+    dotenv().ok();
+    let supabase_service_role_api_key = env::var("SUPABASE_SERVICE_ROLE_API_KEY")
+        .expect("SUPABASE_SERVICE_ROLE_API_KEY must be set");
+
+    let query = state
+        .anything_client
+        .from("flow_versions")
+        .auth(supabase_service_role_api_key)
+        .eq("account_id", account_id.to_string())
+        .eq("flow_id", settings.workflow_id.to_string())
+        .select("*");
+
+    let query = match &settings.workflow_version_id {
+        Some(version_id) => query.eq("flow_version_id", version_id.to_string()),
+        None => query.eq("published", "true"),
+    };
+
+    let response = query
+        .execute()
+        .await
+        .map_err(|e| format!("Failed to execute request: {}", e))?;
+
+    let body = response
+        .text()
+        .await
+        .map_err(|e| format!("Failed to read response body: {}", e))?;
+
+    let versions: Vec<DatabaseFlowVersion> =
+        serde_json::from_str(&body).map_err(|e| format!("Failed to parse flow version: {}", e))?;
+
+    versions
+        .into_iter()
+        .next()
+        .ok_or_else(|| match settings.workflow_version_id {
+            Some(version_id) => format!(
+                "Workflow {} has no version {}",
+                settings.workflow_id, version_id
+            ),
+            None => format!(
+                "Workflow {} has no published version to call",
+                settings.workflow_id
+            ),
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::action_types::PluginName;
+
+    #[test]
+    fn test_settings_from_plugin_config() {
+        let workflow_id = Uuid::new_v4();
+
+        let settings = CallWorkflowSettings::from_plugin_config(&json!({
+            "workflow_id": workflow_id.to_string(),
+            "workflow_version_id": "",
+            "inputs": "{\"name\": \"ada\"}",
+            "mode": "fire_and_forget",
+            "timeout_seconds": "100000"
+        }))
+        .unwrap();
+
+        assert_eq!(settings.workflow_id, workflow_id);
+        assert_eq!(settings.workflow_version_id, None);
+        assert_eq!(settings.inputs, json!({ "name": "ada" }));
+        assert_eq!(settings.mode, CallMode::FireAndForget);
+        assert_eq!(settings.timeout, Duration::from_secs(MAX_TIMEOUT_SECONDS));
+
+        let defaults = CallWorkflowSettings::from_plugin_config(&json!({
+            "workflow_id": workflow_id.to_string()
+        }))
+        .unwrap();
+        assert_eq!(defaults.inputs, json!({}));
+        assert_eq!(defaults.mode, CallMode::Wait);
+        assert_eq!(
+            defaults.timeout,
+            Duration::from_secs(DEFAULT_TIMEOUT_SECONDS)
+        );
+
+        assert!(CallWorkflowSettings::from_plugin_config(&json!({ "workflow_id": "" })).is_err());
+        assert!(CallWorkflowSettings::from_plugin_config(&json!({
+            "workflow_id": workflow_id.to_string(),
+            "inputs": "[1, 2]"
+        }))
+        .is_err());
+        assert!(CallWorkflowSettings::from_plugin_config(&json!({
+            "workflow_id": workflow_id.to_string(),
+            "mode": "later"
+        }))
+        .is_err());
+    }
+
+    fn task(plugin_name: &str, task_status: TaskStatus, result: Value, order: i32) -> Task {
+        let mut task = Task::builder()
+            .account_id(Uuid::new_v4())
+            .flow_id(Uuid::new_v4())
+            .flow_version_id(Uuid::new_v4())
+            .action_label(plugin_name.to_string())
+            .trigger_id("trigger".to_string())
+            .action_id(plugin_name.to_string())
+            .r#type(ActionType::Action)
+            .plugin_name(PluginName::new(plugin_name.to_string()).unwrap())
+            .config(TaskConfig {
+                inputs: None,
+                inputs_schema: None,
+                plugin_config: None,
+                plugin_config_schema: None,
+            })
+            .result(result)
+            .build()
+            .unwrap();
+        task.task_status = task_status;
+        task.processing_order = order;
+        task
+    }
+
+    #[test]
+    fn test_stored_output() {
+        let tasks = vec![
+            task(
+                "@anything/http",
+                TaskStatus::Completed,
+                json!({ "ok": true }),
+                1,
+            ),
+            task(
+                OUTPUT_PLUGIN_NAME,
+                TaskStatus::Completed,
+                json!({ "id": 2 }),
+                3,
+            ),
+            task(
+                OUTPUT_PLUGIN_NAME,
+                TaskStatus::Completed,
+                json!({ "id": 1 }),
+                2,
+            ),
+            task(
+                OUTPUT_PLUGIN_NAME,
+                TaskStatus::Failed,
+                json!({ "id": 0 }),
+                0,
+            ),
+        ];
+
+        // The first Output to complete is what the caller got
+        assert_eq!(stored_output(&tasks), Some(json!({ "id": 1 })));
+        assert_eq!(stored_output(&tasks[..1]), None);
+    }
+
+    #[test]
+    fn test_check_call_stack() {
+        let (a, b, c) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+
+        assert!(check_call_stack(&[a], &b).is_ok());
+        assert!(check_call_stack(&[a], &a).is_err());
+        assert!(check_call_stack(&[a, b], &a).is_err());
+
+        let deep: Vec<Uuid> = (0..MAX_CALL_DEPTH).map(|_| Uuid::new_v4()).collect();
+        assert!(check_call_stack(&deep[..MAX_CALL_DEPTH - 1], &c).is_ok());
+        assert!(check_call_stack(&deep, &c).is_err());
+    }
+}
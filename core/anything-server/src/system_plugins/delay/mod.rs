@@ -0,0 +1,279 @@
+use chrono::{
+    DateTime, Duration as ChronoDuration, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Utc,
+};
+use chrono_tz::Tz;
+use serde_json::{json, Value};
+use std::sync::Arc;
+use tracing::{info, instrument};
+
+use crate::processor::task_waits::{create_wait, TaskWait, DELAY_WAIT};
+use crate::types::task_types::Task;
+use crate::AppState;
+
+const NAIVE_DATETIME_FORMATS: &[&str] = &[
+    "%Y-%m-%dT%H:%M:%S",
+    "%Y-%m-%d %H:%M:%S",
+    "%Y-%m-%dT%H:%M",
+    "%Y-%m-%d %H:%M",
+];
+
+const TIME_FORMATS: &[&str] = &["%H:%M:%S", "%H:%M"];
+
+/// Longest delay a duration can ask for
+pub const MAX_DELAY_DAYS: i64 = 365;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DelaySettings {
+    For(ChronoDuration),                // A fixed amount of time after the task runs
+    Until { at: String, timezone: Tz }, // A datetime, date or time of day in the timezone
+}
+
+impl DelaySettings {
+    pub fn from_plugin_config(config: &Value) -> Result<Self, String> {
+        let text = |key: &str| {
+            config
+                .get(key)
+                .and_then(Value::as_str)
+                .map(str::trim)
+                .unwrap_or_default()
+                .to_string()
+        };
+
+        match text("mode").as_str() {
+            "" | "duration" => {
+                let amount = match config.get("amount") {
+                    Some(Value::Number(n)) => n.as_f64(),
+                    Some(Value::String(s)) => s.trim().parse::<f64>().ok(),
+                    _ => None,
+                }
+                .filter(|amount| amount.is_finite() && *amount >= 0.0)
+                .ok_or("Delay amount must be a number of at least 0")?;
+
+                let unit_seconds = match text("unit").as_str() {
+                    "seconds" => 1.0,
+                    "" | "minutes" => 60.0,
+                    "hours" => 3_600.0,
+                    "days" => 86_400.0,
+                    unit => return Err(format!("Unknown delay unit '{}'", unit)),
+                };
+
+                let seconds = amount * unit_seconds;
+                if seconds > (MAX_DELAY_DAYS * 86_400) as f64 {
+                    return Err(format!("Delay can be at most {} days", MAX_DELAY_DAYS));
+                }
+
+                Ok(DelaySettings::For(ChronoDuration::milliseconds(
+                    (seconds * 1_000.0) as i64,
+                )))
+            }
+            "until" => {
+                let at = text("until");
+                if at.is_empty() {
+                    return Err("Delay until requires a date or time".to_string());
+                }
+
+                let timezone = match text("timezone").as_str() {
+                    "" => Tz::UTC,
+                    timezone => timezone
+                        .parse::<Tz>()
+                        .map_err(|_| format!("Unknown timezone '{}'", timezone))?,
+                };
+
+                Ok(DelaySettings::Until { at, timezone })
+            }
+            mode => Err(format!("Unknown delay mode '{}'", mode)),
+        }
+    }
+
+    /// When the delay is over. A time of day is its next occurrence, and a datetime
+    /// that already passed resumes right away.
+    pub fn resume_at(&self, now: DateTime<Utc>) -> Result<DateTime<Utc>, String> {
+        match self {
+            DelaySettings::For(duration) => now
+                .checked_add_signed(*duration)
+                .ok_or_else(|| "Delay ends too far in the future".to_string()),
+            DelaySettings::Until { at, timezone } => {
+                if let Ok(datetime) = DateTime::parse_from_rfc3339(at) {
+                    return Ok(datetime.with_timezone(&Utc));
+                }
+
+                let naive = NAIVE_DATETIME_FORMATS
+                    .iter()
+                    .find_map(|format| NaiveDateTime::parse_from_str(at, format).ok())
+                    .or_else(|| {
+                        NaiveDate::parse_from_str(at, "%Y-%m-%d")
+                            .ok()
+                            .map(|date| date.and_time(NaiveTime::MIN))
+                    });
+
+                if let Some(naive) = naive {
+                    return local_to_utc(timezone, naive);
+                }
+
+                let time = TIME_FORMATS
+                    .iter()
+                    .find_map(|format| NaiveTime::parse_from_str(at, format).ok())
+                    .ok_or_else(|| format!("Could not read '{}' as a date or time", at))?;
+
+                let today = now.with_timezone(timezone).date_naive();
+                let resume_at = local_to_utc(timezone, today.and_time(time))?;
+                if resume_at > now {
+                    Ok(resume_at)
+                } else {
+                    let tomorrow = today
+                        .checked_add_signed(ChronoDuration::days(1))
+                        .ok_or_else(|| "Delay ends too far in the future".to_string())?;
+                    local_to_utc(timezone, tomorrow.and_time(time))
+                }
+            }
+        }
+    }
+}
+
+/// Reads a wall clock time in the timezone, taking the earlier one when clocks go back
+fn local_to_utc(timezone: &Tz, naive: NaiveDateTime) -> Result<DateTime<Utc>, String> {
+    timezone
+        .from_local_datetime(&naive)
+        .earliest()
+        .map(|local| local.with_timezone(&Utc))
+        .ok_or_else(|| format!("{} does not exist in {}", naive, timezone))
+}
+
+/// The result a delay task completes with once its time came
+pub fn delay_resolution(delayed_until: Option<DateTime<Utc>>, resumed_at: DateTime<Utc>) -> Value {
+    json!({
+        "delayed_until": delayed_until,
+        "resumed_at": resumed_at,
+    })
+}
+
+/// Stores a timer for the task. The task actor leaves the task waiting and the flow
+/// session parks, so nothing holds a workflow actor while the delay runs.
+#[instrument(skip(state, task, bundled_plugin_config))]
+pub async fn process_delay_task(
+    state: Arc<AppState>,
+    task: &Task,
+    bundled_plugin_config: &Value,
+) -> Result<Option<Value>, Box<dyn std::error::Error + Send + Sync>> {
+    let settings = DelaySettings::from_plugin_config(bundled_plugin_config)?;
+    let delayed_until = settings.resume_at(Utc::now())?;
+
+    let wait = TaskWait {
+        task_id: task.task_id,
+        account_id: task.account_id,
+        flow_id: task.flow_id,
+        flow_version_id: task.flow_version_id,
+        flow_session_id: task.flow_session_id,
+        action_id: task.action_id.clone(),
+        wait_type: DELAY_WAIT.to_string(),
+        wait_status: "waiting".to_string(),
+        expires_at: Some(delayed_until),
+        timeout_decision: None,
+        timeout_data: None,
+        details: None,
+        resolution: None,
+        resolved_at: None,
+    };
+
+    create_wait(&state, &wait).await?;
+
+    info!(
+        "[DELAY] Task {} delayed until {}",
+        task.task_id,
+        delayed_until.to_rfc3339()
+    );
+
+    Ok(Some(json!({
+        "status": "waiting",
+        "delayed_until": delayed_until,
+    })))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utc(datetime: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(datetime)
+            .unwrap()
+            .with_timezone(&Utc)
+    }
+
+    #[test]
+    fn test_delay_for_duration() {
+        let now = utc("2024-07-04T12:00:00Z");
+
+        let settings = DelaySettings::from_plugin_config(&json!({
+            "mode": "duration",
+            "amount": "1.5",
+            "unit": "hours"
+        }))
+        .unwrap();
+        assert_eq!(
+            settings.resume_at(now).unwrap(),
+            utc("2024-07-04T13:30:00Z")
+        );
+
+        let defaults = DelaySettings::from_plugin_config(&json!({ "amount": 10 })).unwrap();
+        assert_eq!(
+            defaults.resume_at(now).unwrap(),
+            utc("2024-07-04T12:10:00Z")
+        );
+
+        assert!(DelaySettings::from_plugin_config(&json!({ "amount": "-1" })).is_err());
+        assert!(
+            DelaySettings::from_plugin_config(&json!({ "amount": 366, "unit": "days" })).is_err()
+        );
+        assert!(
+            DelaySettings::from_plugin_config(&json!({ "amount": 1e12, "unit": "days" })).is_err()
+        );
+
+        // A delay past the last datetime chrono can hold fails instead of panicking
+        assert!(DelaySettings::For(ChronoDuration::days(1_000_000_000))
+            .resume_at(now)
+            .is_err());
+        assert!(
+            DelaySettings::from_plugin_config(&json!({ "amount": 1, "unit": "weeks" })).is_err()
+        );
+    }
+
+    #[test]
+    fn test_delay_until() {
+        let now = utc("2024-07-04T12:00:00Z");
+        let until = |at: &str, timezone: &str| {
+            DelaySettings::from_plugin_config(&json!({
+                "mode": "until",
+                "until": at,
+                "timezone": timezone
+            }))
+            .unwrap()
+            .resume_at(now)
+            .unwrap()
+        };
+
+        assert_eq!(
+            until("2024-07-05T09:00:00+02:00", "America/New_York"),
+            utc("2024-07-05T07:00:00Z")
+        );
+        assert_eq!(
+            until("2024-07-05 09:00", "America/New_York"),
+            utc("2024-07-05T13:00:00Z")
+        );
+        assert_eq!(until("2024-07-05", ""), utc("2024-07-05T00:00:00Z"));
+
+        // It is 8am in New York, so 9am is later today. In Tokyo it is 9pm and 9am is tomorrow's
+        assert_eq!(
+            until("09:00", "America/New_York"),
+            utc("2024-07-04T13:00:00Z")
+        );
+        assert_eq!(until("09:00", "Asia/Tokyo"), utc("2024-07-05T00:00:00Z"));
+
+        assert!(DelaySettings::from_plugin_config(&json!({
+            "mode": "until",
+            "until": "09:00",
+            "timezone": "Mars/Olympus"
+        }))
+        .is_err());
+        assert!(DelaySettings::from_plugin_config(&json!({ "mode": "until" })).is_err());
+    }
+}
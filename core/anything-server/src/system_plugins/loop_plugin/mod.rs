@@ -0,0 +1,118 @@
+use serde_json::{json, Value};
+use tracing::{info, instrument};
+
+pub const LOOP_PLUGIN_NAME: &str = "@anything/loop";
+pub const LOOP_END_PLUGIN_NAME: &str = "@anything/loop_end";
+
+const DEFAULT_CONCURRENCY: usize = 1;
+const MAX_CONCURRENCY: usize = 50;
+const DEFAULT_BATCH_SIZE: usize = 1;
+
+/// Normalized loop settings produced by the loop task and consumed by the workflow actor
+#[derive(Debug, Clone, PartialEq)]
+pub struct LoopSettings {
+    pub items: Vec<Value>,
+    pub concurrency: usize,
+    pub batch_size: usize,
+}
+
+impl LoopSettings {
+    /// Reads the settings back out of a completed loop task result
+    pub fn from_result(result: &Option<Value>) -> Result<Self, String> {
+        let result = result
+            .as_ref()
+            .ok_or_else(|| "Loop task has no result".to_string())?;
+
+        let items = result
+            .get("items")
+            .and_then(|items| items.as_array())
+            .cloned()
+            .ok_or_else(|| "Loop task result is missing items".to_string())?;
+
+        Ok(Self {
+            items,
+            concurrency: read_positive_usize(result.get("concurrency"), DEFAULT_CONCURRENCY)
+                .min(MAX_CONCURRENCY),
+            batch_size: read_positive_usize(result.get("batch_size"), DEFAULT_BATCH_SIZE),
+        })
+    }
+
+    /// Splits items into the values each iteration receives as `{{loop.item}}`
+    /// A batch size of 1 yields the items themselves, larger sizes yield arrays of items
+    pub fn iteration_items(&self) -> Vec<Value> {
+        if self.batch_size <= 1 {
+            return self.items.clone();
+        }
+
+        self.items
+            .chunks(self.batch_size)
+            .map(|chunk| Value::Array(chunk.to_vec()))
+            .collect()
+    }
+}
+
+fn read_positive_usize(value: Option<&Value>, default: usize) -> usize {
+    let parsed = match value {
+        Some(Value::Number(n)) => n.as_u64().map(|n| n as usize),
+        Some(Value::String(s)) => s.trim().parse::<usize>().ok(),
+        _ => None,
+    };
+
+    match parsed {
+        Some(n) if n > 0 => n,
+        _ => default,
+    }
+}
+
+/// Validates the loop configuration and returns the items to iterate over.
+/// The iterations themselves are run by the workflow actor, not here.
+#[instrument(skip(bundled_plugin_config))]
+pub fn process_loop_task(
+    bundled_plugin_config: &Value,
+) -> Result<Option<Value>, Box<dyn std::error::Error + Send + Sync>> {
+    info!("[LOOP] Starting loop task processing");
+
+    let items = match &bundled_plugin_config["items"] {
+        Value::Array(items) => items.clone(),
+        Value::String(raw) if raw.trim().is_empty() => Vec::new(),
+        Value::String(raw) => match serde_json::from_str::<Value>(raw) {
+            Ok(Value::Array(items)) => items,
+            _ => return Err("Loop items must be an array".into()),
+        },
+        Value::Null => Vec::new(),
+        _ => return Err("Loop items must be an array".into()),
+    };
+
+    let concurrency = read_positive_usize(
+        bundled_plugin_config.get("concurrency"),
+        DEFAULT_CONCURRENCY,
+    )
+    .min(MAX_CONCURRENCY);
+    let batch_size = read_positive_usize(
+        bundled_plugin_config.get("batch_size"),
+        DEFAULT_BATCH_SIZE,
+    );
+
+    info!(
+        "[LOOP] Looping over {} items (concurrency: {}, batch size: {})",
+        items.len(),
+        concurrency,
+        batch_size
+    );
+
+    Ok(Some(json!({
+        "count": items.len(),
+        "items": items,
+        "concurrency": concurrency,
+        "batch_size": batch_size
+    })))
+}
+
+/// Loop end results are collected by the workflow actor. When a loop end runs outside of
+/// the actor (e.g. single action testing) it just echoes back its inputs.
+pub fn process_loop_end_task(
+    bundled_inputs: &Value,
+) -> Result<Option<Value>, Box<dyn std::error::Error + Send + Sync>> {
+    info!("[LOOP] Processing loop end outside of a loop");
+    Ok(Some(bundled_inputs.clone()))
+}
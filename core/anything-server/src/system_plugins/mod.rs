@@ -1,7 +1,11 @@
+pub mod approval;
+pub mod call_workflow;
+pub mod delay;
 pub mod formatter_actions;
 pub mod http;
 pub mod input;
 pub mod javascript;
+pub mod loop_plugin;
 pub mod output;
 pub mod registry;
 pub mod webhook_response;
@@ -1,3 +1,5 @@
 pub mod webhook_trigger;
 pub use webhook_trigger::*;
 pub mod webhook_trigger_utils;
+pub mod session_status;
+pub mod request_body;
@@ -0,0 +1,421 @@
+use axum::{
+    body::{Body, Bytes},
+    extract::{FromRequest, Multipart, Request},
+    http::{header::CONTENT_TYPE, HeaderMap, StatusCode},
+};
+
+use base64::Engine;
+use dotenv::dotenv;
+use serde_json::{json, Map, Value};
+use std::env;
+use std::sync::Arc;
+
+use crate::{
+    files::{routes::FileAccessType, utils::store_file},
+    AppState,
+};
+
+/// Deepest element nesting accepted in an XML body
+pub const MAX_XML_DEPTH: usize = 100;
+
+/// What happens to the files of a multipart body
+enum BodyFiles<'a> {
+    // Stored privately for the account and replaced by their file metadata
+    Store {
+        state: Arc<AppState>,
+        account_id: &'a str,
+    },
+    // Kept in the payload as `{file_name, content_type, size, content_base64}`
+    InMemory,
+}
+
+/// Parses a webhook request body according to its `Content-Type` into the trigger payload.
+///
+/// JSON is passed through, `application/x-www-form-urlencoded` and `multipart/form-data`
+/// become an object of their fields, and XML is converted to JSON.
+/// Files in a multipart body are stored privately for the account and replaced by their file metadata.
+/// Returns `None` for empty bodies and content types that have no structured form;
+/// those are still available to the workflow as `raw_body`.
+pub async fn parse_request_body(
+    state: Arc<AppState>,
+    account_id: &str,
+    headers: &HeaderMap,
+    body: &Bytes,
+) -> Result<Option<Value>, (StatusCode, String)> {
+    parse_body(headers, body, BodyFiles::Store { state, account_id }).await
+}
+
+/// Parses a request body like `parse_request_body`, but keeps multipart files in the payload
+/// instead of storing them. For requests nothing has checked yet.
+pub async fn parse_request_body_in_memory(
+    headers: &HeaderMap,
+    body: &Bytes,
+) -> Result<Option<Value>, (StatusCode, String)> {
+    parse_body(headers, body, BodyFiles::InMemory).await
+}
+
+async fn parse_body(
+    headers: &HeaderMap,
+    body: &Bytes,
+    files: BodyFiles<'_>,
+) -> Result<Option<Value>, (StatusCode, String)> {
+    if body.is_empty() {
+        return Ok(None);
+    }
+
+    let content_type = headers
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or("");
+    let mime_type = content_type
+        .split(';')
+        .next()
+        .unwrap_or("")
+        .trim()
+        .to_lowercase();
+
+    match mime_type.as_str() {
+        // Callers that don't send a content type have always been treated as JSON
+        "" => Ok(serde_json::from_slice(body).ok()),
+        "application/x-www-form-urlencoded" => Ok(Some(parse_form_body(body))),
+        "multipart/form-data" => parse_multipart_body(files, content_type, body)
+            .await
+            .map(Some),
+        mime if mime == "application/json" || mime.ends_with("+json") => {
+            Ok(serde_json::from_slice(body).ok())
+        }
+        mime if mime == "application/xml" || mime == "text/xml" || mime.ends_with("+xml") => {
+            let xml = std::str::from_utf8(body).map_err(|_| {
+                (
+                    StatusCode::BAD_REQUEST,
+                    "XML body is not valid UTF-8".to_string(),
+                )
+            })?;
+            xml_to_json(xml).map(Some).map_err(|e| {
+                println!("[WEBHOOK API] Failed to parse XML body: {}", e);
+                (StatusCode::BAD_REQUEST, format!("Invalid XML body: {}", e))
+            })
+        }
+        _ => Ok(None),
+    }
+}
+
+/// Parses an `application/x-www-form-urlencoded` body into an object of its fields.
+///
+/// Values stay strings since form posts don't carry types and values like phone numbers
+/// or zip codes would be mangled as numbers. Repeated keys and `key[]` keys become arrays.
+pub fn parse_form_body(body: &[u8]) -> Value {
+    let mut fields = Map::new();
+
+    for pair in body
+        .split(|byte| *byte == b'&')
+        .filter(|pair| !pair.is_empty())
+    {
+        let (key, value) = match pair.iter().position(|byte| *byte == b'=') {
+            Some(index) => (&pair[..index], &pair[index + 1..]),
+            None => (pair, &[][..]),
+        };
+
+        let key = decode_form_component(key);
+        let value = Value::String(decode_form_component(value));
+
+        match key.strip_suffix("[]") {
+            Some(base_key) => insert_array_field(&mut fields, base_key.to_string(), value),
+            None => insert_field(&mut fields, key, value),
+        }
+    }
+
+    Value::Object(fields)
+}
+
+fn decode_form_component(bytes: &[u8]) -> String {
+    let component = String::from_utf8_lossy(bytes).replace('+', " ");
+    match urlencoding::decode(&component) {
+        Ok(decoded) => decoded.into_owned(),
+        Err(_) => component,
+    }
+}
+
+/// Adds a field, turning it into an array when the key was already seen
+fn insert_field(fields: &mut Map<String, Value>, key: String, value: Value) {
+    match fields.get_mut(&key) {
+        Some(Value::Array(values)) => values.push(value),
+        Some(existing) => {
+            let first = existing.take();
+            *existing = Value::Array(vec![first, value]);
+        }
+        None => {
+            fields.insert(key, value);
+        }
+    }
+}
+
+/// Adds a field that is always an array, even with a single value
+fn insert_array_field(fields: &mut Map<String, Value>, key: String, value: Value) {
+    if fields.contains_key(&key) {
+        insert_field(fields, key, value);
+    } else {
+        fields.insert(key, Value::Array(vec![value]));
+    }
+}
+
+async fn parse_multipart_body(
+    files: BodyFiles<'_>,
+    content_type: &str,
+    body: &Bytes,
+) -> Result<Value, (StatusCode, String)> {
+    let request = Request::builder()
+        .header(CONTENT_TYPE, content_type)
+        .body(Body::from(body.clone()))
+        .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+
+    let mut multipart = Multipart::from_request(request, &()).await.map_err(|e| {
+        (
+            StatusCode::BAD_REQUEST,
+            format!("Invalid multipart body: {}", e),
+        )
+    })?;
+
+    let mut fields = Map::new();
+
+    while let Some(field) = multipart.next_field().await.map_err(|e| {
+        (
+            StatusCode::BAD_REQUEST,
+            format!("Invalid multipart body: {}", e),
+        )
+    })? {
+        let name = field.name().unwrap_or("file").to_string();
+
+        let Some(file_name) = field.file_name().map(str::to_string) else {
+            let text = field.text().await.map_err(|e| {
+                (
+                    StatusCode::BAD_REQUEST,
+                    format!("Invalid multipart field: {}", e),
+                )
+            })?;
+            insert_field(&mut fields, name, Value::String(text));
+            continue;
+        };
+
+        let content_type = field
+            .content_type()
+            .unwrap_or("application/octet-stream")
+            .to_string();
+        let data = field.bytes().await.map_err(|e| {
+            (
+                StatusCode::BAD_REQUEST,
+                format!("Invalid multipart file: {}", e),
+            )
+        })?;
+
+        // Browsers send an empty part for file inputs left blank
+        if file_name.is_empty() && data.is_empty() {
+            continue;
+        }
+
+        let (state, account_id) = match &files {
+            BodyFiles::Store { state, account_id } => (state, *account_id),
+            BodyFiles::InMemory => {
+                let file_value = json!({
+                    "file_name": file_name,
+                    "content_type": content_type,
+                    "size": data.len(),
+                    "content_base64": base64::engine::general_purpose::STANDARD.encode(&data),
+                });
+                insert_field(&mut fields, name, file_value);
+                continue;
+            }
+        };
+
+        dotenv().ok();
+        let supabase_service_role_api_key =
+            env::var("SUPABASE_SERVICE_ROLE_API_KEY").map_err(|_| {
+                println!("[WEBHOOK API] SUPABASE_SERVICE_ROLE_API_KEY is not set");
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "Failed to store uploaded file".to_string(),
+                )
+            })?;
+
+        let file_metadata = store_file(
+            state,
+            &supabase_service_role_api_key,
+            account_id,
+            &file_name,
+            &content_type,
+            data,
+            FileAccessType::Private,
+        )
+        .await
+        .map_err(|e| {
+            println!("[WEBHOOK API] Failed to store uploaded file: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed to store uploaded file".to_string(),
+            )
+        })?;
+
+        let file_value = serde_json::to_value(&file_metadata).unwrap_or(Value::Null);
+        insert_field(&mut fields, name, file_value);
+    }
+
+    Ok(Value::Object(fields))
+}
+
+/// Converts an XML document to JSON.
+///
+/// The result is an object keyed by the root element's name. Attributes become `@name` keys,
+/// repeated child elements become arrays, and elements with only text become strings.
+/// Text next to attributes or children is kept under `#text`.
+/// Documents with a DTD are rejected so entity expansion can't blow up the payload.
+pub fn xml_to_json(xml: &str) -> Result<Value, String> {
+    let document = roxmltree::Document::parse(xml).map_err(|e| e.to_string())?;
+    let root = document.root_element();
+
+    let mut object = Map::new();
+    object.insert(
+        root.tag_name().name().to_string(),
+        element_to_json(root, 0)?,
+    );
+    Ok(Value::Object(object))
+}
+
+fn element_to_json(element: roxmltree::Node, depth: usize) -> Result<Value, String> {
+    if depth >= MAX_XML_DEPTH {
+        return Err(format!("Elements are nested deeper than {}", MAX_XML_DEPTH));
+    }
+
+    let mut fields = Map::new();
+    for attribute in element.attributes() {
+        fields.insert(
+            format!("@{}", attribute.name()),
+            Value::String(attribute.value().to_string()),
+        );
+    }
+
+    // CDATA sections are text nodes too, comments and processing instructions are dropped
+    let mut text = String::new();
+    for child in element.children() {
+        if child.is_element() {
+            let child_value = element_to_json(child, depth + 1)?;
+            insert_field(
+                &mut fields,
+                child.tag_name().name().to_string(),
+                child_value,
+            );
+        } else if child.is_text() {
+            text.push_str(child.text().unwrap_or_default());
+        }
+    }
+
+    let text = text.trim();
+    if fields.is_empty() {
+        return Ok(Value::String(text.to_string()));
+    }
+    if !text.is_empty() {
+        fields.insert("#text".to_string(), Value::String(text.to_string()));
+    }
+    Ok(Value::Object(fields))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_form_body_keeps_string_values() {
+        let body = b"From=%2B15551234567&Body=Hello+there&NumMedia=0&tag[]=a&tag[]=b&to=x&to=y";
+
+        assert_eq!(
+            parse_form_body(body),
+            json!({
+                "From": "+15551234567",
+                "Body": "Hello there",
+                "NumMedia": "0",
+                "tag": ["a", "b"],
+                "to": ["x", "y"],
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_in_memory_multipart_body_keeps_files() {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            CONTENT_TYPE,
+            "multipart/form-data; boundary=X".parse().unwrap(),
+        );
+        let body = Bytes::from(
+            "--X\r\nContent-Disposition: form-data; name=\"note\"\r\n\r\nhi\r\n\
+             --X\r\nContent-Disposition: form-data; name=\"doc\"; filename=\"a.txt\"\r\n\
+             Content-Type: text/plain\r\n\r\nabc\r\n--X--\r\n",
+        );
+
+        assert_eq!(
+            parse_request_body_in_memory(&headers, &body).await.unwrap(),
+            Some(json!({
+                "note": "hi",
+                "doc": {
+                    "file_name": "a.txt",
+                    "content_type": "text/plain",
+                    "size": 3,
+                    "content_base64": "YWJj",
+                }
+            }))
+        );
+    }
+
+    #[test]
+    fn test_form_body_single_array_key_is_array() {
+        assert_eq!(
+            parse_form_body(b"ids[]=1&flag"),
+            json!({ "ids": ["1"], "flag": "" })
+        );
+    }
+
+    #[test]
+    fn test_xml_to_json() {
+        let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+            <!-- order export -->
+            <order id="42" status='paid'>
+                <customer>Jane &amp; Co</customer>
+                <item sku="A1">Widget</item>
+                <item sku="B2"/>
+                <note><![CDATA[<fragile>]]></note>
+                <empty></empty>
+            </order>"#;
+
+        assert_eq!(
+            xml_to_json(xml).unwrap(),
+            json!({
+                "order": {
+                    "@id": "42",
+                    "@status": "paid",
+                    "customer": "Jane & Co",
+                    "item": [
+                        { "@sku": "A1", "#text": "Widget" },
+                        { "@sku": "B2" },
+                    ],
+                    "note": "<fragile>",
+                    "empty": "",
+                }
+            })
+        );
+    }
+
+    #[test]
+    fn test_xml_to_json_rejects_malformed_documents() {
+        assert!(xml_to_json("<a><b></a>").is_err());
+        assert!(xml_to_json("<a>").is_err());
+        assert!(xml_to_json("<a></a><b></b>").is_err());
+        assert!(xml_to_json("<a>&bogus;</a>").is_err());
+        assert!(xml_to_json(&format!(
+            "{}{}",
+            "<a>".repeat(MAX_XML_DEPTH + 1),
+            "</a>".repeat(MAX_XML_DEPTH + 1)
+        ))
+        .is_err());
+        assert!(xml_to_json(r#"<!DOCTYPE a [<!ENTITY lol "lol">]><a>&lol;</a>"#).is_err());
+    }
+}
@@ -0,0 +1,206 @@
+use axum::{
+    extract::{Path, State},
+    http::{HeaderMap, StatusCode},
+    response::IntoResponse,
+    Json,
+};
+
+use dotenv::dotenv;
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::{env, sync::Arc};
+use uuid::Uuid;
+
+use crate::{
+    processor::run_queue::{get_queued_run, RunStatus},
+    types::action_types::PluginName,
+    types::task_types::{FlowSessionStatus, Task, TaskStatus},
+    AppState,
+};
+
+use super::webhook_trigger_utils::validate_api_key;
+
+/// Where a caller can poll for a flow session started through the webhook API
+pub fn session_status_url(workflow_id: &str, flow_session_id: &Uuid) -> String {
+    format!(
+        "/api/v1/workflow/{}/session/{}",
+        workflow_id, flow_session_id
+    )
+}
+
+#[derive(Debug, Serialize)]
+pub struct SessionTaskStatus {
+    pub task_id: Uuid,
+    pub action_id: String,
+    pub action_label: String,
+    pub plugin_name: Option<PluginName>,
+    pub task_status: TaskStatus,
+    pub started_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub ended_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub error: Option<Value>,
+}
+
+/// Gets the status of a flow session, the status of each of its tasks and the
+/// `@anything/webhook_response` payload once the workflow produced it.
+/// Lets callers of `/start/respond` that timed out pick the result up later.
+pub async fn get_workflow_session_status(
+    Path((workflow_id, flow_session_id)): Path<(String, String)>,
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    println!(
+        "[WEBHOOK API] Getting status of flow session {} for workflow {}",
+        flow_session_id, workflow_id
+    );
+
+    let api_key = match headers.get("Authorization").and_then(|h| h.to_str().ok()) {
+        Some(header) if header.starts_with("Bearer ") => header[7..].to_string(),
+        _ => return (StatusCode::UNAUTHORIZED, "Missing or invalid API key").into_response(),
+    };
+
+    let account_id = match validate_api_key(state.clone(), api_key).await {
+        Ok(account_id) => account_id,
+        Err(status) => return (status, "Invalid API key").into_response(),
+    };
+
+    let flow_session_uuid = match Uuid::parse_str(&flow_session_id) {
+        Ok(id) => id,
+        Err(_) => return (StatusCode::BAD_REQUEST, "Invalid flow session id").into_response(),
+    };
+
+    //Super User Access
+    dotenv().ok();
+    let supabase_service_role_api_key = env::var("SUPABASE_SERVICE_ROLE_API_KEY")
+        .expect("SUPABASE_SERVICE_ROLE_API_KEY must be set");
+
+    // Scoped to the API key's account so other accounts' sessions read as not found
+    let response = match state
+        .anything_client
+        .from("tasks")
+        .auth(supabase_service_role_api_key)
+        .eq("account_id", &account_id)
+        .eq("flow_id", &workflow_id)
+        .eq("flow_session_id", &flow_session_id)
+        .select("*")
+        .order("created_at.asc")
+        .execute()
+        .await
+    {
+        Ok(response) => response,
+        Err(err) => {
+            println!("[WEBHOOK API] Failed to execute request: {:?}", err);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed to execute request",
+            )
+                .into_response();
+        }
+    };
+
+    let body = match response.text().await {
+        Ok(body) => body,
+        Err(err) => {
+            println!("[WEBHOOK API] Failed to read response body: {:?}", err);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed to read response body",
+            )
+                .into_response();
+        }
+    };
+
+    let tasks: Vec<Task> = match serde_json::from_str(&body) {
+        Ok(tasks) => tasks,
+        Err(err) => {
+            println!("[WEBHOOK API] Failed to parse tasks: {:?}", err);
+            return (StatusCode::INTERNAL_SERVER_ERROR, "Failed to parse tasks").into_response();
+        }
+    };
+
+    // A session that was accepted but has not stored a task yet is still waiting in the run queue
+    if tasks.is_empty() {
+        let queued = match get_queued_run(&state, &flow_session_uuid).await {
+            Ok(run) => run.filter(|run| {
+                run.account_id.to_string() == account_id
+                    && run.flow_id.to_string() == workflow_id
+                    && run.run_status == RunStatus::Queued
+            }),
+            Err(err) => {
+                println!("[WEBHOOK API] Failed to read run queue: {}", err);
+                None
+            }
+        };
+
+        if queued.is_none() {
+            return (StatusCode::NOT_FOUND, "Flow session not found").into_response();
+        }
+    }
+
+    let status = session_status_from_tasks(&tasks);
+    let response = webhook_response_from_tasks(&tasks);
+
+    let task_statuses: Vec<SessionTaskStatus> = tasks
+        .into_iter()
+        .map(|task| SessionTaskStatus {
+            task_id: task.task_id,
+            action_id: task.action_id,
+            action_label: task.action_label,
+            plugin_name: task.plugin_name,
+            task_status: task.task_status,
+            started_at: task.started_at,
+            ended_at: task.ended_at,
+            error: task.error,
+        })
+        .collect();
+
+    Json(json!({
+        "workflow_id": workflow_id,
+        "workflow_session_id": flow_session_id,
+        "status": status,
+        "tasks": task_statuses,
+        "response": response,
+    }))
+    .into_response()
+}
+
+/// Works out the status of a whole flow session from its stored tasks.
+/// The final status is written to every task when the session ends; before that the
+/// session is running once any task has started.
+pub fn session_status_from_tasks(tasks: &[Task]) -> FlowSessionStatus {
+    if let Some(task) = tasks.iter().find(|task| {
+        matches!(
+            task.flow_session_status,
+            FlowSessionStatus::Completed | FlowSessionStatus::Failed | FlowSessionStatus::Canceled
+        )
+    }) {
+        return task.flow_session_status.clone();
+    }
+
+    if tasks
+        .iter()
+        .any(|task| task.task_status == TaskStatus::Waiting)
+    {
+        return FlowSessionStatus::Waiting;
+    }
+
+    if tasks
+        .iter()
+        .any(|task| task.task_status != TaskStatus::Pending)
+    {
+        return FlowSessionStatus::Running;
+    }
+
+    FlowSessionStatus::Pending
+}
+
+/// Gets the payload built by the workflow's `@anything/webhook_response` action, if it ran
+pub fn webhook_response_from_tasks(tasks: &[Task]) -> Option<Value> {
+    tasks
+        .iter()
+        .find(|task| {
+            task.plugin_name.as_ref().map(|name| name.as_str())
+                == Some("@anything/webhook_response")
+                && task.task_status == TaskStatus::Completed
+        })
+        .and_then(|task| task.result.clone())
+}
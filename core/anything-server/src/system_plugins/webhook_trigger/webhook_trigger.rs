@@ -1,4 +1,5 @@
 use axum::{
+    body::Bytes,
     extract::{Path, Query, State},
     http::{HeaderMap, Method, StatusCode},
     response::IntoResponse,
@@ -8,7 +9,7 @@ use axum::{
 use std::time::Duration;
 
 use dotenv::dotenv;
-use serde_json::{json, Value};
+use serde_json::json;
 use std::{collections::HashMap, env, sync::Arc};
 use uuid::Uuid;
 
@@ -21,6 +22,7 @@ use crate::{
     AppState, FlowCompletion,
 };
 
+use crate::processor::run_queue::enqueue_workflow;
 use crate::{processor::processor::ProcessorMessage, types::workflow_types::DatabaseFlowVersion};
 
 use tokio::sync::oneshot;
@@ -28,6 +30,8 @@ use tokio::time::timeout;
 
 use tracing::error;
 
+use super::request_body::parse_request_body;
+use super::session_status::session_status_url;
 use super::webhook_trigger_utils::{
     convert_request_to_payload, parse_response_action_response_into_api_response,
     validate_request_method, validate_required_input_and_response_plugins, validate_security_model,
@@ -42,7 +46,7 @@ pub async fn run_workflow_and_respond(
     State(state): State<Arc<AppState>>,
     headers: HeaderMap,
     query: Option<Query<HashMap<String, String>>>,
-    body: Option<Json<Value>>,
+    body: Bytes,
 ) -> impl IntoResponse {
     println!("[WEBHOOK API] Handling run workflow and respond");
     // println!("[WEBHOOK API] Payload: {:?}", payload);
@@ -158,7 +162,8 @@ pub async fn run_workflow_and_respond(
     println!("[WEBHOOK API] Bundled context: {:?}", rendered_inputs);
 
     //Validate security model
-    if let Some(response) = validate_security_model(&rendered_inputs, &headers, state.clone()).await
+    if let Some(response) =
+        validate_security_model(&rendered_inputs, &headers, &body, state.clone()).await
     {
         return response.into_response();
     }
@@ -168,7 +173,13 @@ pub async fn run_workflow_and_respond(
         return response.into_response();
     }
 
-    let processed_payload = convert_request_to_payload(method.clone(), query, body);
+    let parsed_body =
+        match parse_request_body(state.clone(), &account_id.to_string(), &headers, &body).await {
+            Ok(parsed_body) => parsed_body,
+            Err(response) => return response.into_response(),
+        };
+
+    let processed_payload = convert_request_to_payload(method.clone(), query, parsed_body);
 
     // Create a task to initiate the flow
     println!("[WEBHOOK API] Creating task for workflow execution");
@@ -194,6 +205,7 @@ pub async fn run_workflow_and_respond(
                     "headers": headers.iter().map(|(k,v)| (k.as_str(), String::from_utf8_lossy(v.as_bytes()).into_owned())).collect::<HashMap<_,_>>(),
                     "body": processed_payload.clone(),
                     "method": method.to_string(),
+                    "raw_body": String::from_utf8_lossy(&body),
                 }))
         .build() {
             Ok(task) => task,
@@ -228,7 +240,7 @@ pub async fn run_workflow_and_respond(
         existing_tasks: HashMap::new(), // No existing tasks for new workflows
     };
 
-    if let Err(e) = state.processor_sender.send(processor_message).await {
+    if let Err(e) = enqueue_workflow(&state, processor_message).await {
         println!("[TEST WORKFLOW] Failed to send message to processor: {}", e);
         return (
             StatusCode::INTERNAL_SERVER_ERROR,
@@ -264,7 +276,8 @@ pub async fn run_workflow_and_respond(
                 StatusCode::REQUEST_TIMEOUT,
                 Json(json!({
                     "error": "Workflow execution timed out",
-                    "workflow_session_id": flow_session_id
+                    "workflow_session_id": flow_session_id,
+                    "status_url": session_status_url(&workflow_id, &flow_session_id)
                 })),
             )
                 .into_response()
@@ -278,7 +291,7 @@ pub async fn run_workflow_version_and_respond(
     State(state): State<Arc<AppState>>,
     headers: HeaderMap,
     query: Option<Query<HashMap<String, String>>>,
-    body: Option<Json<Value>>,
+    body: Bytes,
 ) -> impl IntoResponse {
     println!("[WEBHOOK API] Handling run workflow and respond");
     println!("[WEBHOOK API] Payload: {:?}", body);
@@ -393,7 +406,8 @@ pub async fn run_workflow_version_and_respond(
     println!("[WEBHOOK API] Bundled context: {:?}", rendered_inputs);
 
     //Validate security model
-    if let Some(response) = validate_security_model(&rendered_inputs, &headers, state.clone()).await
+    if let Some(response) =
+        validate_security_model(&rendered_inputs, &headers, &body, state.clone()).await
     {
         return response.into_response();
     }
@@ -403,7 +417,13 @@ pub async fn run_workflow_version_and_respond(
         return response.into_response();
     }
 
-    let processed_payload = convert_request_to_payload(method.clone(), query, body);
+    let parsed_body =
+        match parse_request_body(state.clone(), &account_id.to_string(), &headers, &body).await {
+            Ok(parsed_body) => parsed_body,
+            Err(response) => return response.into_response(),
+        };
+
+    let processed_payload = convert_request_to_payload(method.clone(), query, parsed_body);
 
     // Create a task to initiate the flow
     println!("[WEBHOOK API] Creating task for workflow execution");
@@ -429,6 +449,7 @@ pub async fn run_workflow_version_and_respond(
                 "headers": headers.iter().map(|(k,v)| (k.as_str(), String::from_utf8_lossy(v.as_bytes()).into_owned())).collect::<HashMap<_,_>>(),
                 "body": processed_payload.clone(),
                 "method": method.to_string(),
+                "raw_body": String::from_utf8_lossy(&body),
             }))
     .build() {
         Ok(task) => task,
@@ -461,7 +482,7 @@ pub async fn run_workflow_version_and_respond(
         existing_tasks: HashMap::new(), // No existing tasks for new workflows
     };
 
-    if let Err(e) = state.processor_sender.send(processor_message).await {
+    if let Err(e) = enqueue_workflow(&state, processor_message).await {
         println!("[TEST WORKFLOW] Failed to send message to processor: {}", e);
         return (
             StatusCode::INTERNAL_SERVER_ERROR,
@@ -499,7 +520,8 @@ pub async fn run_workflow_version_and_respond(
                 StatusCode::REQUEST_TIMEOUT,
                 Json(json!({
                     "error": "Workflow execution timed out",
-                    "workflow_session_id": task.flow_session_id
+                    "workflow_session_id": task.flow_session_id,
+                    "status_url": session_status_url(&workflow_id, &task.flow_session_id)
                 })),
             )
                 .into_response()
@@ -513,7 +535,7 @@ pub async fn run_workflow(
     State(state): State<Arc<AppState>>,
     headers: HeaderMap,
     query: Option<Query<HashMap<String, String>>>,
-    body: Option<Json<Value>>,
+    body: Bytes,
 ) -> impl IntoResponse {
     println!("[WEBHOOK API] Handling run workflow and respond");
     println!("[WEBHOOK API] Payload: {:?}", body);
@@ -628,7 +650,8 @@ pub async fn run_workflow(
     println!("[WEBHOOK API] Bundled context: {:?}", rendered_inputs);
 
     //Validate security model
-    if let Some(response) = validate_security_model(&rendered_inputs, &headers, state.clone()).await
+    if let Some(response) =
+        validate_security_model(&rendered_inputs, &headers, &body, state.clone()).await
     {
         return response.into_response();
     }
@@ -638,7 +661,13 @@ pub async fn run_workflow(
         return response.into_response();
     }
 
-    let processed_payload = convert_request_to_payload(method.clone(), query, body);
+    let parsed_body =
+        match parse_request_body(state.clone(), &account_id.to_string(), &headers, &body).await {
+            Ok(parsed_body) => parsed_body,
+            Err(response) => return response.into_response(),
+        };
+
+    let processed_payload = convert_request_to_payload(method.clone(), query, parsed_body);
 
     // Create a task to initiate the flow
     println!("[WEBHOOK API] Creating task for workflow execution");
@@ -663,6 +692,7 @@ pub async fn run_workflow(
                 "headers": headers.iter().map(|(k,v)| (k.as_str(), String::from_utf8_lossy(v.as_bytes()).into_owned())).collect::<HashMap<_,_>>(),
                 "body": processed_payload.clone(),
                 "method": method.to_string(),
+                "raw_body": String::from_utf8_lossy(&body),
             }))
     .build() {
         Ok(task) => task,
@@ -683,7 +713,7 @@ pub async fn run_workflow(
         existing_tasks: HashMap::new(), // No existing tasks for new workflows
     };
 
-    if let Err(e) = state.processor_sender.send(processor_message).await {
+    if let Err(e) = enqueue_workflow(&state, processor_message).await {
         println!("[TEST WORKFLOW] Failed to send message to processor: {}", e);
         return (
             StatusCode::INTERNAL_SERVER_ERROR,
@@ -697,6 +727,7 @@ pub async fn run_workflow(
         "success": true,
         "message": "Workflow started!",
         "workflow_session_id": task.flow_session_id,
+        "status_url": session_status_url(&workflow_id, &task.flow_session_id),
         "workflow_id": workflow_id,
         "workflow_version_id": workflow_version.flow_version_id
     }))
@@ -709,7 +740,7 @@ pub async fn run_workflow_version(
     State(state): State<Arc<AppState>>,
     headers: HeaderMap,
     query: Option<Query<HashMap<String, String>>>,
-    body: Option<Json<Value>>,
+    body: Bytes,
 ) -> impl IntoResponse {
     println!("[WEBHOOK API] Handling run workflow and respond");
     println!("[WEBHOOK API] Payload: {:?}", body);
@@ -824,7 +855,8 @@ pub async fn run_workflow_version(
     println!("[WEBHOOK API] Bundled context: {:?}", rendered_inputs);
 
     //Validate security model
-    if let Some(response) = validate_security_model(&rendered_inputs, &headers, state.clone()).await
+    if let Some(response) =
+        validate_security_model(&rendered_inputs, &headers, &body, state.clone()).await
     {
         return response.into_response();
     }
@@ -834,7 +866,13 @@ pub async fn run_workflow_version(
         return response.into_response();
     }
 
-    let processed_payload = convert_request_to_payload(method.clone(), query, body);
+    let parsed_body =
+        match parse_request_body(state.clone(), &account_id.to_string(), &headers, &body).await {
+            Ok(parsed_body) => parsed_body,
+            Err(response) => return response.into_response(),
+        };
+
+    let processed_payload = convert_request_to_payload(method.clone(), query, parsed_body);
 
     // Create a task to initiate the flow
     println!("[WEBHOOK API] Creating task for workflow execution");
@@ -859,6 +897,7 @@ pub async fn run_workflow_version(
                 "headers": headers.iter().map(|(k,v)| (k.as_str(), String::from_utf8_lossy(v.as_bytes()).into_owned())).collect::<HashMap<_,_>>(),
                 "body": processed_payload.clone(),
                 "method": method.to_string(),
+                "raw_body": String::from_utf8_lossy(&body),
             }))
     .build() {
         Ok(task) => task,
@@ -879,7 +918,7 @@ pub async fn run_workflow_version(
         existing_tasks: HashMap::new(), // No existing tasks for new workflows
     };
 
-    if let Err(e) = state.processor_sender.send(processor_message).await {
+    if let Err(e) = enqueue_workflow(&state, processor_message).await {
         println!("[TEST WORKFLOW] Failed to send message to processor: {}", e);
         return (
             StatusCode::INTERNAL_SERVER_ERROR,
@@ -893,6 +932,7 @@ pub async fn run_workflow_version(
         "success": true,
         "message": "Workflow started!",
         "workflow_session_id": flow_session_id.to_string(),
+        "status_url": session_status_url(&workflow_id, &flow_session_id),
         "workflow_id": workflow_id,
         "workflow_version_id": workflow_version.flow_version_id
     }))
@@ -5,6 +5,8 @@ use axum::{
     Json,
 };
 
+use base64::Engine;
+use openssl::{hash::MessageDigest, memcmp, pkey::PKey, sign::Signer};
 use serde_json::{json, Value};
 
 use std::collections::HashMap;
@@ -129,6 +131,7 @@ pub async fn validate_api_key(state: Arc<AppState>, api_key: String) -> Result<S
 pub async fn validate_security_model(
     rendered_inputs: &Value,
     headers: &HeaderMap,
+    raw_body: &[u8],
     state: Arc<AppState>,
 ) -> Option<impl IntoResponse> {
     // Extract the security model from the rendered inputs
@@ -252,6 +255,21 @@ pub async fn validate_security_model(
             }
             None
         }
+        "hmac_signature" => {
+            println!("[WEBHOOK API] Validating HMAC signature");
+            match validate_hmac_signature(
+                rendered_inputs,
+                headers,
+                raw_body,
+                chrono::Utc::now().timestamp(),
+            ) {
+                Ok(()) => None,
+                Err(message) => {
+                    println!("[WEBHOOK API] HMAC validation failed: {}", message);
+                    Some((StatusCode::UNAUTHORIZED, message).into_response())
+                }
+            }
+        }
         _ => {
             println!("[WEBHOOK API] Invalid security model specified");
             Some((StatusCode::BAD_REQUEST, "Invalid security model").into_response())
@@ -259,6 +277,155 @@ pub async fn validate_security_model(
     }
 }
 
+// Default window a signed timestamp may be off by before the request is treated as a replay
+pub const DEFAULT_HMAC_TIMESTAMP_TOLERANCE_SECONDS: u64 = 300;
+
+/// Checks a signature computed over the raw request body, the way Stripe, GitHub, Shopify and Slack sign webhooks.
+///
+/// Reads from the rendered inputs:
+/// - `hmac_secret`: the signing secret, usually `{{secrets.NAME}}` from the account vault
+/// - `hmac_algorithm`: `sha1`, `sha256` (default) or `sha512`
+/// - `hmac_header`: header carrying the signature
+/// - `hmac_encoding`: `hex` (default) or `base64`
+/// - `hmac_prefix`: text before the signature like `sha256=`. Headers holding several comma separated
+///   values (`t=...,v1=...`) are searched for every part starting with the prefix.
+/// - `hmac_timestamp_header`: header carrying a unix timestamp, enables replay protection. When it is the
+///   signature header itself the timestamp is read from its `t=` part.
+/// - `hmac_timestamp_tolerance_seconds`: how old or new the timestamp may be, 300 by default. Negative values are rejected.
+/// - `hmac_signed_payload`: what was signed, `{body}` by default. `{timestamp}` is replaced by the timestamp.
+pub fn validate_hmac_signature(
+    rendered_inputs: &Value,
+    headers: &HeaderMap,
+    raw_body: &[u8],
+    now: i64,
+) -> Result<(), &'static str> {
+    let input = |key: &str| {
+        rendered_inputs
+            .get(key)
+            .and_then(|v| v.as_str())
+            .map(str::trim)
+            .filter(|v| !v.is_empty())
+    };
+
+    let secret = input("hmac_secret").ok_or("Invalid signature configuration")?;
+    let header_name = input("hmac_header").ok_or("Invalid signature configuration")?;
+    let digest = match input("hmac_algorithm").unwrap_or("sha256") {
+        "sha1" => MessageDigest::sha1(),
+        "sha256" => MessageDigest::sha256(),
+        "sha512" => MessageDigest::sha512(),
+        _ => return Err("Invalid signature configuration"),
+    };
+    let prefix = rendered_inputs
+        .get("hmac_prefix")
+        .and_then(|v| v.as_str())
+        .unwrap_or("");
+
+    let signature_header = headers
+        .get(header_name)
+        .and_then(|v| v.to_str().ok())
+        .ok_or("Missing signature header")?;
+
+    // Replay protection only applies when the sender signs a timestamp
+    let timestamp = match input("hmac_timestamp_header") {
+        Some(timestamp_header) => {
+            let timestamp = if timestamp_header.eq_ignore_ascii_case(header_name) {
+                signature_header
+                    .split(',')
+                    .find_map(|part| part.trim().strip_prefix("t="))
+            } else {
+                headers.get(timestamp_header).and_then(|v| v.to_str().ok())
+            }
+            .and_then(|v| v.trim().parse::<i64>().ok())
+            .ok_or("Missing signature timestamp")?;
+
+            let tolerance = match rendered_inputs
+                .get("hmac_timestamp_tolerance_seconds")
+                .and_then(|v| {
+                    v.as_i64()
+                        .or_else(|| v.as_str().and_then(|s| s.trim().parse().ok()))
+                }) {
+                Some(tolerance) => {
+                    u64::try_from(tolerance).map_err(|_| "Invalid signature configuration")?
+                }
+                None => DEFAULT_HMAC_TIMESTAMP_TOLERANCE_SECONDS,
+            };
+
+            // The timestamp comes from the sender, so it can be anywhere in the i64 range
+            if now.abs_diff(timestamp) > tolerance {
+                return Err("Signature timestamp outside of tolerance");
+            }
+            Some(timestamp)
+        }
+        None => None,
+    };
+
+    let signed_payload = match input("hmac_signed_payload") {
+        Some(template) if template != "{body}" => {
+            let timestamp = timestamp.map(|t| t.to_string()).unwrap_or_default();
+            let (before, after) = template.split_once("{body}").unwrap_or((template, ""));
+            let mut payload = before.replace("{timestamp}", &timestamp).into_bytes();
+            if template.contains("{body}") {
+                payload.extend_from_slice(raw_body);
+            }
+            payload.extend_from_slice(after.replace("{timestamp}", &timestamp).as_bytes());
+            payload
+        }
+        _ => raw_body.to_vec(),
+    };
+
+    let expected = compute_hmac(digest, secret.as_bytes(), &signed_payload)
+        .map_err(|_| "Failed to compute signature")?;
+
+    let encoding = input("hmac_encoding").unwrap_or("hex");
+    let candidates: Vec<&str> = if prefix.is_empty() {
+        vec![signature_header.trim()]
+    } else {
+        signature_header
+            .split(',')
+            .filter_map(|part| part.trim().strip_prefix(prefix))
+            .collect()
+    };
+
+    let matches = candidates.into_iter().any(|candidate| {
+        let provided = match encoding {
+            "base64" => base64::engine::general_purpose::STANDARD
+                .decode(candidate.trim())
+                .ok(),
+            _ => decode_hex(candidate.trim()),
+        };
+        provided
+            .map(|provided| provided.len() == expected.len() && memcmp::eq(&provided, &expected))
+            .unwrap_or(false)
+    });
+
+    if matches {
+        Ok(())
+    } else {
+        Err("Invalid signature")
+    }
+}
+
+fn compute_hmac(
+    digest: MessageDigest,
+    secret: &[u8],
+    payload: &[u8],
+) -> Result<Vec<u8>, openssl::error::ErrorStack> {
+    let key = PKey::hmac(secret)?;
+    let mut signer = Signer::new(digest, &key)?;
+    signer.update(payload)?;
+    signer.sign_to_vec()
+}
+
+fn decode_hex(value: &str) -> Option<Vec<u8>> {
+    if value.len() % 2 != 0 {
+        return None;
+    }
+    (0..value.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(value.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
 pub fn validate_request_method(
     rendered_inputs: &Value,
     request_method: &str,
@@ -302,7 +469,7 @@ pub fn validate_request_method(
 pub fn convert_request_to_payload(
     method: axum::http::Method,
     query: Option<Query<HashMap<String, String>>>,
-    body: Option<Json<Value>>,
+    body: Option<Value>,
 ) -> Value {
     match method {
         axum::http::Method::GET => {
@@ -366,7 +533,7 @@ pub fn convert_request_to_payload(
         }
         _ => {
             // For non-GET requests, merge query params with body if both exist
-            let mut final_payload = body.map_or(json!({}), |Json(payload)| payload);
+            let mut final_payload = body.unwrap_or_else(|| json!({}));
 
             if let Some(Query(params)) = query {
                 if let Value::Object(ref mut map) = final_payload {
@@ -497,3 +664,133 @@ pub fn parse_response_action_response_into_api_response(stored_result: Value) ->
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BODY: &[u8] = br#"{"action":"opened"}"#;
+
+    fn headers(pairs: &[(&'static str, &str)]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for (name, value) in pairs {
+            headers.insert(*name, HeaderValue::from_str(value).unwrap());
+        }
+        headers
+    }
+
+    #[test]
+    fn test_hmac_signature_github_style() {
+        let inputs = json!({
+            "hmac_secret": "gh_secret",
+            "hmac_header": "X-Hub-Signature-256",
+            "hmac_prefix": "sha256="
+        });
+        let signed = headers(&[(
+            "x-hub-signature-256",
+            "sha256=b4277092b43931a789931bee582b102e57a681dea0fa82ba07c3c20de4ad9707",
+        )]);
+
+        assert_eq!(validate_hmac_signature(&inputs, &signed, BODY, 0), Ok(()));
+        assert_eq!(
+            validate_hmac_signature(&inputs, &signed, br#"{"action":"closed"}"#, 0),
+            Err("Invalid signature")
+        );
+        assert_eq!(
+            validate_hmac_signature(&inputs, &HeaderMap::new(), BODY, 0),
+            Err("Missing signature header")
+        );
+
+        let sha1_inputs = json!({
+            "hmac_secret": "gh_secret",
+            "hmac_header": "X-Hub-Signature",
+            "hmac_algorithm": "sha1",
+            "hmac_prefix": "sha1="
+        });
+        let sha1_signed = headers(&[(
+            "x-hub-signature",
+            "sha1=469d5a0deeca520c8abf2e15a9ab985bf959a54f",
+        )]);
+        assert_eq!(
+            validate_hmac_signature(&sha1_inputs, &sha1_signed, BODY, 0),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn test_hmac_signature_base64_encoding() {
+        let inputs = json!({
+            "hmac_secret": "shop_secret",
+            "hmac_header": "X-Shopify-Hmac-Sha256",
+            "hmac_encoding": "base64"
+        });
+        let signed = headers(&[(
+            "x-shopify-hmac-sha256",
+            "6jdQj6RMoo/y2ilw1Ulzld5f5hjERynkAG0TO03mQb0=",
+        )]);
+
+        assert_eq!(validate_hmac_signature(&inputs, &signed, BODY, 0), Ok(()));
+    }
+
+    #[test]
+    fn test_hmac_signature_rejects_replayed_timestamps() {
+        // Stripe signs "{timestamp}.{body}" and sends "t=...,v1=..." in one header
+        let inputs = json!({
+            "hmac_secret": "whsec_test",
+            "hmac_header": "Stripe-Signature",
+            "hmac_prefix": "v1=",
+            "hmac_timestamp_header": "Stripe-Signature",
+            "hmac_timestamp_tolerance_seconds": "300",
+            "hmac_signed_payload": "{timestamp}.{body}"
+        });
+        let signed = headers(&[(
+            "stripe-signature",
+            "t=1700000000,v1=0000,v1=05b82fecc44b706d9d261c030899aa32f52a887e2a55a448d784fab853f42fb3",
+        )]);
+
+        assert_eq!(
+            validate_hmac_signature(&inputs, &signed, BODY, 1_700_000_100),
+            Ok(())
+        );
+        assert_eq!(
+            validate_hmac_signature(&inputs, &signed, BODY, 1_700_001_000),
+            Err("Signature timestamp outside of tolerance")
+        );
+    }
+
+    #[test]
+    fn test_hmac_signature_extreme_timestamps_and_tolerances() {
+        let inputs = json!({
+            "hmac_secret": "whsec_test",
+            "hmac_header": "Stripe-Signature",
+            "hmac_prefix": "v1=",
+            "hmac_timestamp_header": "Stripe-Signature",
+            "hmac_signed_payload": "{timestamp}.{body}"
+        });
+
+        for timestamp in [i64::MIN, i64::MAX] {
+            let signed = headers(&[(
+                "stripe-signature",
+                format!("t={},v1=0000", timestamp).as_str(),
+            )]);
+            assert_eq!(
+                validate_hmac_signature(&inputs, &signed, BODY, 1_700_000_000),
+                Err("Signature timestamp outside of tolerance")
+            );
+        }
+
+        let signed = headers(&[("stripe-signature", "t=-9223372036854775808,v1=0000")]);
+        assert_eq!(
+            validate_hmac_signature(&inputs, &signed, BODY, i64::MAX),
+            Err("Signature timestamp outside of tolerance")
+        );
+
+        let mut negative_tolerance = inputs.clone();
+        negative_tolerance["hmac_timestamp_tolerance_seconds"] = json!("-1");
+        let signed = headers(&[("stripe-signature", "t=1700000000,v1=0000")]);
+        assert_eq!(
+            validate_hmac_signature(&negative_tolerance, &signed, BODY, 1_700_000_000),
+            Err("Invalid signature configuration")
+        );
+    }
+}
@@ -10,9 +10,18 @@ use serde_json::Value;
 use std::sync::Arc;
 use tokio::try_join;
 
+use crate::actor_processor::cancellation::{notify_session_canceled, signal_session_cancel};
+use crate::actor_processor::status_updates::send_status_update;
+use crate::processor::replay::{replay_session, ReplayError, ReplayRequest};
+use crate::processor::run_queue::{
+    cancel_waiting_run, get_queued_run, request_run_cancel, RunStatus,
+};
+use crate::status_updater::Operation;
 use crate::supabase_jwt_middleware::User;
+use crate::types::task_types::{FlowSessionStatus, TriggerSessionStatus};
 use crate::AppState;
 use serde_json::json;
+use uuid::Uuid;
 
 #[derive(Deserialize)]
 pub struct PaginationParams {
@@ -251,3 +260,166 @@ pub async fn get_task_by_workflow_id(
 
     Json(response_with_meta).into_response()
 }
+
+/// Cancels a flow session of the account. A running session is signaled and stops its tasks,
+/// also when another server runs it. A parked one is canceled right away.
+pub async fn cancel_flow_session(
+    Path((account_id, flow_session_id)): Path<(String, Uuid)>,
+    State(state): State<Arc<AppState>>,
+    Extension(_user): Extension<User>,
+) -> impl IntoResponse {
+    println!(
+        "[TASKS] Canceling flow session {} for account_id: {}",
+        flow_session_id, account_id
+    );
+
+    let run = match get_queued_run(&state, &flow_session_id).await {
+        // Sessions of other accounts read as not found
+        Ok(Some(run)) if run.account_id.to_string() == account_id => run,
+        Ok(_) => return (StatusCode::NOT_FOUND, "Flow session not found").into_response(),
+        Err(e) => {
+            println!("[TASKS] Failed to load run: {}", e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed to load flow session",
+            )
+                .into_response();
+        }
+    };
+
+    let canceling = Json(json!({
+        "flow_session_id": flow_session_id,
+        "status": "canceling"
+    }));
+
+    if signal_session_cancel(&state, &flow_session_id) {
+        return (StatusCode::ACCEPTED, canceling).into_response();
+    }
+
+    match run.run_status {
+        RunStatus::Waiting => {}
+        // Running on another server, or not picked up by a workflow actor yet.
+        // The server that claimed the run signals the session once it sees the request.
+        RunStatus::Queued => {
+            return match request_run_cancel(&state, &flow_session_id).await {
+                Ok(true) => (StatusCode::ACCEPTED, canceling).into_response(),
+                Ok(false) => (
+                    StatusCode::CONFLICT,
+                    "Flow session changed while canceling, try again",
+                )
+                    .into_response(),
+                Err(e) => {
+                    println!("[TASKS] Failed to request cancel of run: {}", e);
+                    (
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        "Failed to cancel flow session",
+                    )
+                        .into_response()
+                }
+            };
+        }
+        RunStatus::Completed | RunStatus::Failed | RunStatus::Canceled => {
+            return (StatusCode::CONFLICT, "Flow session already finished").into_response()
+        }
+    }
+
+    // Parked sessions hold no actor, the run is canceled in the queue so a resume can't start it
+    match cancel_waiting_run(&state, &flow_session_id).await {
+        Ok(true) => {}
+        Ok(false) => {
+            return (
+                StatusCode::CONFLICT,
+                "Flow session is being resumed, try again",
+            )
+                .into_response()
+        }
+        Err(e) => {
+            println!("[TASKS] Failed to cancel run: {}", e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed to cancel flow session",
+            )
+                .into_response();
+        }
+    }
+
+    if let Err(e) = send_status_update(
+        &state,
+        Operation::CompleteWorkflow {
+            flow_session_id,
+            status: FlowSessionStatus::Canceled,
+            trigger_status: TriggerSessionStatus::Canceled,
+        },
+    )
+    .await
+    {
+        println!("[TASKS] Failed to record canceled flow session: {}", e);
+    }
+
+    notify_session_canceled(&state, &flow_session_id);
+
+    Json(json!({
+        "flow_session_id": flow_session_id,
+        "status": "canceled"
+    }))
+    .into_response()
+}
+
+#[derive(Deserialize)]
+pub struct ReplayFlowSessionInput {
+    action_id: String,
+    #[serde(default)]
+    use_published_version: bool,
+}
+
+/// Starts a new flow session that runs an action of an earlier one again, reusing the
+/// results of the tasks before it
+pub async fn replay_flow_session(
+    Path((account_id, flow_session_id)): Path<(String, Uuid)>,
+    State(state): State<Arc<AppState>>,
+    Extension(_user): Extension<User>,
+    Json(input): Json<ReplayFlowSessionInput>,
+) -> impl IntoResponse {
+    println!(
+        "[TASKS] Replaying flow session {} from action {} for account_id: {}",
+        flow_session_id, input.action_id, account_id
+    );
+
+    let account_id = match Uuid::parse_str(&account_id) {
+        Ok(account_id) => account_id,
+        Err(_) => return (StatusCode::NOT_FOUND, "Flow session not found").into_response(),
+    };
+
+    let request = ReplayRequest {
+        account_id,
+        flow_session_id,
+        action_id: input.action_id.clone(),
+        use_published_version: input.use_published_version,
+    };
+
+    match replay_session(&state, request).await {
+        Ok(replayed) => Json(json!({
+            "flow_session_id": replayed.flow_session_id,
+            "trigger_session_id": replayed.trigger_session_id,
+            "flow_version_id": replayed.flow_version_id,
+            "replay_of_flow_session_id": flow_session_id,
+            "replay_from_action_id": input.action_id,
+            "reused_tasks": replayed.reused_tasks
+        }))
+        .into_response(),
+        Err(e @ ReplayError::SessionNotFound) => {
+            (StatusCode::NOT_FOUND, e.to_string()).into_response()
+        }
+        Err(e @ ReplayError::ActionNotFound(_)) => {
+            (StatusCode::BAD_REQUEST, e.to_string()).into_response()
+        }
+        Err(ReplayError::Failed(e)) => {
+            println!("[TASKS] Failed to replay flow session: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed to replay flow session",
+            )
+                .into_response()
+        }
+    }
+}
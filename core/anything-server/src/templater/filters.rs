@@ -0,0 +1,353 @@
+use base64::Engine;
+use chrono::format::{Item, StrftimeItems};
+use chrono::{DateTime, NaiveDate, NaiveDateTime, TimeZone, Utc};
+use serde_json::Value;
+
+use super::selectors::{parse_path, PathSelector};
+
+/// A `{{ }}` expression: a variable path followed by any number of `| filter:arg` steps,
+/// e.g. `actions.x.name | default:"n/a" | upper`
+#[derive(Debug, Clone, PartialEq)]
+pub struct TemplateExpression {
+    pub path: String,
+    pub selectors: Vec<PathSelector>,
+    pub filters: Vec<Filter>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Filter {
+    Default(Value), // Used when the value is missing, null or an empty string
+    Upper,
+    Lower,
+    Trim,
+    Json,   // Encodes the value as a JSON string
+    Length, // Characters in a string or number, items in an array or keys in an object
+    Join(String),
+    Date(String), // strftime format for an RFC 3339 string, a date or unix seconds
+    Base64,
+    First,
+    Last,
+}
+
+impl TemplateExpression {
+    /// Parses the text between `{{` and `}}`
+    pub fn parse(expression: &str) -> Result<Self, String> {
+        let segments = split_outside_quotes(expression, '|')?;
+
+        let path = segments[0].trim();
+        let selectors = parse_path(path)?;
+
+        let filters = segments[1..]
+            .iter()
+            .map(|segment| Filter::parse(segment))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(TemplateExpression {
+            path: path.to_string(),
+            selectors,
+            filters,
+        })
+    }
+
+    /// Runs the filters over the value found at the path, `None` when it wasn't found.
+    /// A missing value stays missing unless a `default` filter fills it in.
+    pub fn apply_filters(&self, value: Option<Value>) -> Result<Option<Value>, String> {
+        self.filters
+            .iter()
+            .try_fold(value, |value, filter| filter.apply(value))
+    }
+}
+
+impl Filter {
+    fn parse(segment: &str) -> Result<Self, String> {
+        let segment = segment.trim();
+        let (name, args) = match segment.split_once(':') {
+            Some((name, args)) => (name.trim(), parse_arguments(args)?),
+            None => (segment, Vec::new()),
+        };
+
+        let filter = match name {
+            "default" => Filter::Default(single_argument(name, args)?),
+            "upper" => Filter::Upper,
+            "lower" => Filter::Lower,
+            "trim" => Filter::Trim,
+            "json" => Filter::Json,
+            "length" => Filter::Length,
+            "join" => match args.as_slice() {
+                [] => Filter::Join(",".to_string()),
+                _ => Filter::Join(string_argument(name, args)?),
+            },
+            "date" => {
+                let format = string_argument(name, args)?;
+                // chrono panics when displaying an invalid format, so reject it up front
+                if StrftimeItems::new(&format).any(|item| item == Item::Error) {
+                    return Err(format!("Invalid date format {:?}", format));
+                }
+                Filter::Date(format)
+            }
+            "base64" => Filter::Base64,
+            "first" => Filter::First,
+            "last" => Filter::Last,
+            "" => return Err("Missing filter name".to_string()),
+            _ => return Err(format!("Unknown filter '{}'", name)),
+        };
+
+        let takes_arguments = matches!(
+            filter,
+            Filter::Default(_) | Filter::Join(_) | Filter::Date(_)
+        );
+        if !takes_arguments && segment.contains(':') {
+            return Err(format!("Filter '{}' takes no arguments", name));
+        }
+
+        Ok(filter)
+    }
+
+    fn apply(&self, value: Option<Value>) -> Result<Option<Value>, String> {
+        if let Filter::Default(default) = self {
+            return Ok(match value {
+                None | Some(Value::Null) => Some(default.clone()),
+                Some(Value::String(s)) if s.is_empty() => Some(default.clone()),
+                value => value,
+            });
+        }
+
+        let Some(value) = value else {
+            return Ok(None);
+        };
+
+        let filtered = match self {
+            Filter::Default(_) => unreachable!(),
+            Filter::Upper => Value::String(display_string(&value).to_uppercase()),
+            Filter::Lower => Value::String(display_string(&value).to_lowercase()),
+            Filter::Trim => Value::String(display_string(&value).trim().to_string()),
+            Filter::Json => Value::String(value.to_string()),
+            Filter::Length => match &value {
+                Value::String(s) => Value::from(s.chars().count()),
+                Value::Array(items) => Value::from(items.len()),
+                Value::Object(map) => Value::from(map.len()),
+                Value::Null => return Err("length expects a value, got null".to_string()),
+                _ => Value::from(display_string(&value).chars().count()),
+            },
+            Filter::Join(separator) => match &value {
+                Value::Array(items) => Value::String(
+                    items
+                        .iter()
+                        .map(display_string)
+                        .collect::<Vec<_>>()
+                        .join(separator),
+                ),
+                _ => return Err(format!("join expects an array, got {}", value)),
+            },
+            Filter::Date(format) => Value::String(parse_date(&value)?.format(format).to_string()),
+            Filter::Base64 => Value::String(
+                base64::engine::general_purpose::STANDARD.encode(display_string(&value)),
+            ),
+            Filter::First => match &value {
+                Value::Array(items) => items.first().cloned().unwrap_or(Value::Null),
+                Value::String(s) => s.chars().next().map(String::from).into(),
+                _ => return Err(format!("first expects an array or string, got {}", value)),
+            },
+            Filter::Last => match &value {
+                Value::Array(items) => items.last().cloned().unwrap_or(Value::Null),
+                Value::String(s) => s.chars().last().map(String::from).into(),
+                _ => return Err(format!("last expects an array or string, got {}", value)),
+            },
+        };
+
+        Ok(Some(filtered))
+    }
+}
+
+/// Strings are used as they are, anything else as its JSON text
+fn display_string(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        _ => value.to_string(),
+    }
+}
+
+fn parse_date(value: &Value) -> Result<DateTime<Utc>, String> {
+    let date = match value {
+        Value::Number(n) => n
+            .as_i64()
+            .and_then(|seconds| Utc.timestamp_opt(seconds, 0).single()),
+        Value::String(s) => DateTime::parse_from_rfc3339(s)
+            .map(|date| date.with_timezone(&Utc))
+            .ok()
+            .or_else(|| {
+                NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S")
+                    .ok()
+                    .map(|date| date.and_utc())
+            })
+            .or_else(|| {
+                NaiveDate::parse_from_str(s, "%Y-%m-%d")
+                    .ok()
+                    .and_then(|date| date.and_hms_opt(0, 0, 0))
+                    .map(|date| date.and_utc())
+            })
+            .or_else(|| {
+                s.parse::<i64>()
+                    .ok()
+                    .and_then(|seconds| Utc.timestamp_opt(seconds, 0).single())
+            }),
+        _ => None,
+    };
+
+    date.ok_or_else(|| format!("date expects a date, time or unix timestamp, got {}", value))
+}
+
+fn single_argument(name: &str, mut args: Vec<Value>) -> Result<Value, String> {
+    if args.len() != 1 {
+        return Err(format!("Filter '{}' takes one argument", name));
+    }
+    Ok(args.remove(0))
+}
+
+fn string_argument(name: &str, args: Vec<Value>) -> Result<String, String> {
+    match single_argument(name, args)? {
+        Value::String(s) => Ok(s),
+        _ => Err(format!("Filter '{}' takes a quoted string", name)),
+    }
+}
+
+/// Arguments are comma separated JSON literals, or strings in single quotes
+fn parse_arguments(args: &str) -> Result<Vec<Value>, String> {
+    split_outside_quotes(args, ',')?
+        .into_iter()
+        .map(|arg| {
+            let arg = arg.trim();
+            if let Some(inner) = arg
+                .strip_prefix('\'')
+                .and_then(|arg| arg.strip_suffix('\''))
+            {
+                return Ok(Value::String(inner.to_string()));
+            }
+            serde_json::from_str(arg).map_err(|_| {
+                format!(
+                    "Invalid filter argument {}: expected a quoted string, number or boolean",
+                    arg
+                )
+            })
+        })
+        .collect()
+}
+
+/// Splits on a separator that isn't inside a quoted string
+fn split_outside_quotes(text: &str, separator: char) -> Result<Vec<&str>, String> {
+    let mut parts = Vec::new();
+    let mut quote: Option<char> = None;
+    let mut escaped = false;
+    let mut start = 0;
+
+    for (index, c) in text.char_indices() {
+        match quote {
+            Some(_) if escaped => escaped = false,
+            Some('"') if c == '\\' => escaped = true,
+            Some(open) if c == open => quote = None,
+            Some(_) => {}
+            None if c == '"' || c == '\'' => quote = Some(c),
+            None if c == separator => {
+                parts.push(&text[start..index]);
+                start = index + c.len_utf8();
+            }
+            None => {}
+        }
+    }
+
+    if quote.is_some() {
+        return Err(format!("Unclosed quote in {}", text));
+    }
+
+    parts.push(&text[start..]);
+    Ok(parts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn render(expression: &str, value: Option<Value>) -> Option<Value> {
+        TemplateExpression::parse(expression)
+            .unwrap()
+            .apply_filters(value)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_parse_expression() {
+        let expression =
+            TemplateExpression::parse(r#" actions.x.name | default:"a | b" | join:',' | upper "#)
+                .unwrap();
+
+        assert_eq!(expression.path, "actions.x.name");
+        assert_eq!(
+            expression.filters,
+            vec![
+                Filter::Default(json!("a | b")),
+                Filter::Join(",".to_string()),
+                Filter::Upper,
+            ]
+        );
+
+        assert!(TemplateExpression::parse("actions.x | shout").is_err());
+        assert!(TemplateExpression::parse("actions.x | upper:1").is_err());
+        assert!(TemplateExpression::parse(r#"actions.x | default:"open"#).is_err());
+        assert!(TemplateExpression::parse(" | upper").is_err());
+    }
+
+    #[test]
+    fn test_default_fills_missing_values() {
+        let expression = r#"name | default:"n/a" | upper"#;
+
+        assert_eq!(render(expression, None), Some(json!("N/A")));
+        assert_eq!(render(expression, Some(json!(""))), Some(json!("N/A")));
+        assert_eq!(render(expression, Some(json!("ada"))), Some(json!("ADA")));
+        assert_eq!(render("name | upper", None), None);
+    }
+
+    #[test]
+    fn test_filters() {
+        let tags = Some(json!(["a", "b", 3]));
+
+        assert_eq!(
+            render("tags | join:\", \"", tags.clone()),
+            Some(json!("a, b, 3"))
+        );
+        assert_eq!(render("tags | length", tags.clone()), Some(json!(3)));
+        assert_eq!(render("tags | last", tags.clone()), Some(json!(3)));
+        assert_eq!(render("tags | json", tags), Some(json!(r#"["a","b",3]"#)));
+        assert_eq!(
+            render("name | length", Some(json!("héllo"))),
+            Some(json!(5))
+        );
+        assert_eq!(
+            render("name | trim | lower", Some(json!(" Hi "))),
+            Some(json!("hi"))
+        );
+        assert_eq!(
+            render("name | base64", Some(json!("hello"))),
+            Some(json!("aGVsbG8="))
+        );
+    }
+
+    #[test]
+    fn test_date_filter() {
+        let expression = r#"created | date:"%Y-%m-%d""#;
+
+        assert_eq!(
+            render(expression, Some(json!("2024-07-04T15:30:00+02:00"))),
+            Some(json!("2024-07-04"))
+        );
+        assert_eq!(
+            render(expression, Some(json!(0))),
+            Some(json!("1970-01-01"))
+        );
+        assert!(TemplateExpression::parse(r#"created | date:"%Q""#).is_err());
+        assert!(TemplateExpression::parse(expression)
+            .unwrap()
+            .apply_filters(Some(json!("yesterday")))
+            .is_err());
+    }
+}
@@ -3,8 +3,13 @@ use std::collections::HashMap;
 use std::error::Error;
 
 use crate::types::json_schema::{ValidationField, ValidationFieldType};
+pub mod filters;
+pub mod selectors;
 pub mod utils;
 
+use filters::TemplateExpression;
+use selectors::select_path;
+
 #[derive(Debug)]
 pub struct TemplateError {
     pub message: String,
@@ -75,8 +80,13 @@ impl Templater {
                         variable: s.to_string(),
                     })?;
                     let close_idx = open_idx + close_idx;
-                    let variable = s[open_idx + 2..close_idx].trim().to_string();
-                    variables.push(variable);
+                    // Only the variable path, filters don't reference anything else
+                    let expression = TemplateExpression::parse(&s[open_idx + 2..close_idx])
+                        .map_err(|message| TemplateError {
+                            message,
+                            variable: s.to_string(),
+                        })?;
+                    variables.push(expression.path);
                     start = close_idx + 2;
                 }
             }
@@ -85,51 +95,29 @@ impl Templater {
         Ok(variables)
     }
 
-    fn get_value_from_path(
+    /// Looks up the variable path of a `{{ }}` expression and runs its filters over the value.
+    /// Returns `None` when the path isn't in the context and no filter supplied a default.
+    fn evaluate_expression(
         context: &Value,
-        path: &str,
+        expression: &str,
         expected_type: &ValidationFieldType,
-    ) -> Option<Value> {
-        let mut current = context;
-        let parts: Vec<&str> = path.split('.').collect();
-
-        for (i, part) in parts.iter().enumerate() {
-            if let Some(index_start) = part.find('[') {
-                let key = &part[..index_start];
-                let index_end = part.find(']').unwrap_or(part.len());
-                let index: usize = part[index_start + 1..index_end].parse().ok()?;
-
-                current = current.get(key)?;
-                if current.is_array() {
-                    current = current.get(index)?;
-                } else {
-                    return None; // Not an array when we expected one
-                }
-            } else {
-                current = current.get(part)?;
-            }
-
-            if let Value::String(s) = current {
-                // Only parse JSON if the expected type is not String
-                if *expected_type != ValidationFieldType::String {
-                    if let Ok(parsed) = serde_json::from_str(s) {
-                        if i < parts.len() - 1 {
-                            // If not the last part, continue traversing
-                            return Self::get_value_from_path(
-                                &parsed,
-                                &parts[i + 1..].join("."),
-                                expected_type,
-                            );
-                        } else {
-                            // If it's the last part, return the parsed value
-                            return Some(parsed);
-                        }
-                    }
-                }
-            }
-        }
-        println!("[GET VALUE FROM PATH] {}", current.clone());
-        Some(current.clone())
+    ) -> Result<Option<Value>, TemplateError> {
+        let to_template_error = |message: String| TemplateError {
+            message,
+            variable: expression.to_string(),
+        };
+
+        let expression_parts = TemplateExpression::parse(expression).map_err(to_template_error)?;
+        // Strings found at the end of the path stay strings when a string is expected
+        let value = select_path(
+            context,
+            &expression_parts.selectors,
+            *expected_type != ValidationFieldType::String,
+        );
+
+        expression_parts
+            .apply_filters(value)
+            .map_err(to_template_error)
     }
 
     pub fn render(
@@ -204,11 +192,11 @@ impl Templater {
                         let validation_key = variable.to_string();
                         let expected_validation_field =
                             Self::get_validation_field(validations, &validation_key)?;
-                        let value = Self::get_value_from_path(
+                        let value = Self::evaluate_expression(
                             context,
                             variable,
                             &expected_validation_field.r#type,
-                        );
+                        )?;
                         let value = match value {
                             Some(value) => value,
                             None => {
@@ -255,11 +243,11 @@ impl Templater {
                         return Ok(value);
                     } else {
                         // For nested variables, just get the value without validation
-                        let value = Self::get_value_from_path(
+                        let value = Self::evaluate_expression(
                             context,
                             variable,
                             &ValidationFieldType::Unknown,
-                        );
+                        )?;
                         let value = match value {
                             Some(value) => value,
                             None => {
@@ -334,11 +322,11 @@ impl Templater {
                         let validation_key = variable.to_string();
                         let expected_validation_field =
                             Self::get_validation_field(validations, &validation_key)?;
-                        let value = Self::get_value_from_path(
+                        let value = Self::evaluate_expression(
                             context,
                             variable,
                             &expected_validation_field.r#type,
-                        );
+                        )?;
                         let value = match value {
                             Some(value) => value,
                             None => {
@@ -388,11 +376,11 @@ impl Templater {
                         let top_field = &path[0];
                         let expected_validation_field =
                             Self::get_validation_field(validations, &top_field)?;
-                        let value = Self::get_value_from_path(
+                        let value = Self::evaluate_expression(
                             context,
                             variable,
                             &expected_validation_field.r#type,
-                        );
+                        )?;
 
                         println!("Validation Field: {:?}", expected_validation_field);
 
@@ -2248,4 +2236,156 @@ mod tests {
             json!(["ref1", "ref2", "ref3"])
         );
     }
+
+    #[test]
+    fn test_filter_expressions() {
+        let mut templater = Templater::new();
+        templater.add_template(
+            "test_template",
+            json!({
+                "greeting": "Hello {{ actions.user.result.name | upper }} from {{ actions.user.result.team | default:\"n/a\" }}",
+                "tag_count": "{{ actions.user.result.tags | length }}",
+                "tags": "{{ actions.user.result.tags | join:\", \" }}",
+                "joined": "{{actions.user.result.joined|date:\"%d/%m/%Y\"}}"
+            }),
+        );
+
+        let context = json!({
+            "actions": {
+                "user": {
+                    "result": {
+                        "name": "Alice",
+                        "tags": ["admin", "beta"],
+                        "joined": "2023-10-15T09:30:00Z"
+                    }
+                }
+            }
+        });
+
+        let mut validations = HashMap::new();
+        for (key, r#type) in [
+            ("greeting", ValidationFieldType::String),
+            ("tag_count", ValidationFieldType::Number),
+            ("tags", ValidationFieldType::String),
+            ("joined", ValidationFieldType::String),
+        ] {
+            validations.insert(
+                key.to_string(),
+                ValidationField {
+                    r#type,
+                    strict: true,
+                },
+            );
+        }
+
+        let result = templater
+            .render("test_template", &context, validations)
+            .unwrap();
+
+        assert_eq!(
+            result,
+            json!({
+                "greeting": "Hello ALICE from n/a",
+                "tag_count": 2,
+                "tags": "admin, beta",
+                "joined": "15/10/2023"
+            })
+        );
+    }
+
+    #[test]
+    fn test_unknown_filter_fails_to_render() {
+        let mut templater = Templater::new();
+        templater.add_template(
+            "test_template",
+            json!({ "name": "{{ variables.name | shout }}" }),
+        );
+
+        let mut validations = HashMap::new();
+        validations.insert(
+            "name".to_string(),
+            ValidationField {
+                r#type: ValidationFieldType::String,
+                strict: true,
+            },
+        );
+
+        let result = templater.render(
+            "test_template",
+            &json!({ "variables": { "name": "Alice" } }),
+            validations,
+        );
+
+        assert!(result.is_err());
+        assert!(templater.get_template_variables("test_template").is_err());
+    }
+
+    #[test]
+    fn test_template_variables_exclude_filters() {
+        let mut templater = Templater::new();
+        templater.add_template(
+            "test_template",
+            json!({ "name": "{{ variables.name | default:\"a | b\" | upper }} {{variables.id}}" }),
+        );
+
+        assert_eq!(
+            templater.get_template_variables("test_template").unwrap(),
+            vec!["variables.name".to_string(), "variables.id".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_selector_paths() {
+        let mut templater = Templater::new();
+        templater.add_template(
+            "test_template",
+            json!({
+                "emails": "{{ actions.crm.result.contacts[*].email }}",
+                "open_emails": "{{ actions.crm.result.contacts[?(@.status == \"open\")].email | join:\",\" }}",
+                "last_contact": "Last: {{ actions.crm.result.contacts[-1].name }}",
+                "region": "{{ actions.crm.result[\"meta.region\"] }}"
+            }),
+        );
+
+        let context = json!({
+            "actions": {
+                "crm": {
+                    "result": {
+                        "contacts": "[{\"name\": \"Ann\", \"email\": \"ann@example.com\", \"status\": \"open\"}, {\"name\": \"Bo\", \"email\": \"bo@example.com\", \"status\": \"closed\"}]",
+                        "meta.region": "eu"
+                    }
+                }
+            }
+        });
+
+        let mut validations = HashMap::new();
+        for (key, r#type) in [
+            ("emails", ValidationFieldType::Array),
+            ("open_emails", ValidationFieldType::String),
+            ("last_contact", ValidationFieldType::String),
+            ("region", ValidationFieldType::String),
+        ] {
+            validations.insert(
+                key.to_string(),
+                ValidationField {
+                    r#type,
+                    strict: true,
+                },
+            );
+        }
+
+        let result = templater
+            .render("test_template", &context, validations)
+            .unwrap();
+
+        assert_eq!(
+            result,
+            json!({
+                "emails": ["ann@example.com", "bo@example.com"],
+                "open_emails": "ann@example.com",
+                "last_contact": "Last: Bo",
+                "region": "eu"
+            })
+        );
+    }
 }
@@ -0,0 +1,497 @@
+use serde_json::Value;
+use std::borrow::Cow;
+use std::cmp::Ordering;
+
+/// One step of a variable path like `actions.http.result.items[*].email`
+#[derive(Debug, Clone, PartialEq)]
+pub enum PathSelector {
+    Key(String),       // `name` or `["name.with.dots"]`
+    Index(i64),        // `[0]`, negative counts from the end
+    Wildcard,          // `[*]` or `*`, every item of an array or value of an object
+    Filter(Predicate), // `[?(@.status == "open")]`, the items matching the predicate
+}
+
+impl PathSelector {
+    /// Whether the selector can match several values, which makes the path return an array
+    fn selects_many(&self) -> bool {
+        matches!(self, PathSelector::Wildcard | PathSelector::Filter(_))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Predicate {
+    pub path: Vec<PathSelector>, // Relative to the item, empty for `@` itself
+    pub comparison: Option<(Comparison, Value)>, // None checks the path exists and is truthy
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Comparison {
+    Equal,
+    NotEqual,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+}
+
+/// Parses a variable path into its selectors
+pub fn parse_path(path: &str) -> Result<Vec<PathSelector>, String> {
+    let mut parser = PathParser { path, pos: 0 };
+    let selectors = parser.parse_selectors(false)?;
+    if selectors.is_empty() {
+        return Err("Missing variable path".to_string());
+    }
+    Ok(selectors)
+}
+
+/// Finds the value a path points to in the context.
+///
+/// Strings holding JSON are parsed when the path continues into them. The value found at
+/// the end is only parsed when `parse_json_result` is set, so strings can be kept as strings.
+/// Paths with a wildcard or filter return an array of every match, flattened across
+/// wildcards, and an empty array when nothing matched.
+pub fn select_path(
+    context: &Value,
+    selectors: &[PathSelector],
+    parse_json_result: bool,
+) -> Option<Value> {
+    let mut nodes: Vec<Cow<Value>> = vec![Cow::Borrowed(context)];
+    let mut selects_many = false;
+
+    for (i, selector) in selectors.iter().enumerate() {
+        let is_last = i == selectors.len() - 1;
+        selects_many |= selector.selects_many();
+
+        nodes = nodes
+            .iter()
+            .flat_map(|node| match node {
+                Cow::Borrowed(node) => select_children(node, selector)
+                    .into_iter()
+                    .map(Cow::Borrowed)
+                    .collect::<Vec<_>>(),
+                Cow::Owned(node) => select_children(node, selector)
+                    .into_iter()
+                    .map(|child| Cow::Owned(child.clone()))
+                    .collect(),
+            })
+            .map(|child| {
+                if !is_last || parse_json_result {
+                    parse_json_string(child)
+                } else {
+                    child
+                }
+            })
+            .collect();
+    }
+
+    if selects_many {
+        Some(Value::Array(
+            nodes.into_iter().map(Cow::into_owned).collect(),
+        ))
+    } else {
+        nodes.into_iter().next().map(Cow::into_owned)
+    }
+}
+
+fn parse_json_string(value: Cow<Value>) -> Cow<Value> {
+    if let Value::String(s) = value.as_ref() {
+        if let Ok(parsed) = serde_json::from_str(s) {
+            return Cow::Owned(parsed);
+        }
+    }
+    value
+}
+
+fn select_children<'v>(value: &'v Value, selector: &PathSelector) -> Vec<&'v Value> {
+    match selector {
+        PathSelector::Key(key) => value.get(key.as_str()).into_iter().collect(),
+        PathSelector::Index(index) => {
+            let Some(items) = value.as_array() else {
+                return Vec::new();
+            };
+            let index = if *index < 0 {
+                items.len() as i64 + index
+            } else {
+                *index
+            };
+            usize::try_from(index)
+                .ok()
+                .and_then(|index| items.get(index))
+                .into_iter()
+                .collect()
+        }
+        PathSelector::Wildcard => children(value),
+        PathSelector::Filter(predicate) => children(value)
+            .into_iter()
+            .filter(|child| predicate.matches(child))
+            .collect(),
+    }
+}
+
+fn children(value: &Value) -> Vec<&Value> {
+    match value {
+        Value::Array(items) => items.iter().collect(),
+        Value::Object(map) => map.values().collect(),
+        _ => Vec::new(),
+    }
+}
+
+impl Predicate {
+    fn matches(&self, item: &Value) -> bool {
+        let value = select_path(item, &self.path, true);
+
+        match (&self.comparison, value) {
+            (None, Some(value)) => is_truthy(&value),
+            (None, None) => false,
+            (Some((Comparison::NotEqual, expected)), None) => !expected.is_null(),
+            (Some(_), None) => false,
+            (Some((comparison, expected)), Some(value)) => {
+                let ordering = compare_values(&value, expected);
+                match comparison {
+                    Comparison::Equal => ordering == Some(Ordering::Equal),
+                    Comparison::NotEqual => ordering != Some(Ordering::Equal),
+                    Comparison::Less => ordering == Some(Ordering::Less),
+                    Comparison::LessOrEqual => {
+                        matches!(ordering, Some(Ordering::Less | Ordering::Equal))
+                    }
+                    Comparison::Greater => ordering == Some(Ordering::Greater),
+                    Comparison::GreaterOrEqual => {
+                        matches!(ordering, Some(Ordering::Greater | Ordering::Equal))
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn is_truthy(value: &Value) -> bool {
+    match value {
+        Value::Null => false,
+        Value::Bool(b) => *b,
+        Value::String(s) => !s.is_empty(),
+        _ => true,
+    }
+}
+
+/// Numbers compare by value and strings alphabetically, anything else is only equal or not
+fn compare_values(left: &Value, right: &Value) -> Option<Ordering> {
+    match (left, right) {
+        (Value::Number(left), Value::Number(right)) => left.as_f64()?.partial_cmp(&right.as_f64()?),
+        (Value::String(left), Value::String(right)) => Some(left.cmp(right)),
+        _ if left == right => Some(Ordering::Equal),
+        _ => None,
+    }
+}
+
+struct PathParser<'a> {
+    path: &'a str,
+    pos: usize,
+}
+
+impl<'a> PathParser<'a> {
+    fn rest(&self) -> &'a str {
+        &self.path[self.pos..]
+    }
+
+    /// Parses selectors up to the end of the path, or for a predicate up to its comparison
+    fn parse_selectors(&mut self, in_predicate: bool) -> Result<Vec<PathSelector>, String> {
+        let mut selectors = Vec::new();
+        let mut expect_key = !in_predicate;
+
+        loop {
+            let rest = self.rest();
+            if in_predicate && (rest.is_empty() || rest.starts_with(is_predicate_boundary)) {
+                return Ok(selectors);
+            }
+
+            if rest.starts_with('[') {
+                self.pos += 1;
+                selectors.push(self.parse_bracket()?);
+                expect_key = false;
+            } else if let Some(after_dot) = rest.strip_prefix('.') {
+                if expect_key || after_dot.is_empty() {
+                    return Err(format!("Unexpected '.' in {}", self.path));
+                }
+                self.pos += 1;
+                expect_key = true;
+            } else if rest.is_empty() {
+                if expect_key && !selectors.is_empty() {
+                    return Err(format!("Path {} ends with '.'", self.path));
+                }
+                return Ok(selectors);
+            } else if expect_key || selectors.is_empty() {
+                let end = rest
+                    .find(|c: char| {
+                        c == '.' || c == '[' || (in_predicate && is_predicate_boundary(c))
+                    })
+                    .unwrap_or(rest.len());
+                let key = &rest[..end];
+                if key.contains(']') {
+                    return Err(format!("Unexpected ']' in {}", self.path));
+                }
+                self.pos += end;
+                selectors.push(if key == "*" {
+                    PathSelector::Wildcard
+                } else {
+                    PathSelector::Key(key.to_string())
+                });
+                expect_key = false;
+            } else {
+                return Err(format!(
+                    "Expected '.' or '[' at {:?} in {}",
+                    rest, self.path
+                ));
+            }
+        }
+    }
+
+    /// Parses the inside of `[...]`, after the opening bracket
+    fn parse_bracket(&mut self) -> Result<PathSelector, String> {
+        let rest = self.rest();
+
+        let selector = if rest.starts_with('*') {
+            self.pos += 1;
+            PathSelector::Wildcard
+        } else if rest.starts_with('"') || rest.starts_with('\'') {
+            let (key, length) = parse_quoted(rest)?;
+            self.pos += length;
+            PathSelector::Key(key)
+        } else if let Some(predicate) = rest.strip_prefix("?(") {
+            let length = find_closing_paren(predicate)
+                .ok_or_else(|| format!("Unclosed filter in {}", self.path))?;
+            let predicate = parse_predicate(predicate[..length].trim())?;
+            self.pos += "?(".len() + length + 1;
+            PathSelector::Filter(predicate)
+        } else {
+            let end = rest
+                .find(']')
+                .ok_or_else(|| format!("Unclosed '[' in {}", self.path))?;
+            let index = rest[..end].trim().parse::<i64>().map_err(|_| {
+                format!(
+                    "Expected an index, '*', a quoted key or a filter in [{}]",
+                    &rest[..end]
+                )
+            })?;
+            self.pos += end;
+            PathSelector::Index(index)
+        };
+
+        if !self.rest().starts_with(']') {
+            return Err(format!("Expected ']' in {}", self.path));
+        }
+        self.pos += 1;
+        Ok(selector)
+    }
+}
+
+fn is_predicate_boundary(c: char) -> bool {
+    c.is_whitespace() || matches!(c, '=' | '!' | '<' | '>')
+}
+
+fn parse_predicate(predicate: &str) -> Result<Predicate, String> {
+    let relative = predicate
+        .strip_prefix('@')
+        .ok_or_else(|| format!("Filter {} must start with @", predicate))?;
+
+    let mut parser = PathParser {
+        path: relative,
+        pos: 0,
+    };
+    let path = parser.parse_selectors(true)?;
+    let rest = parser.rest().trim_start();
+
+    if rest.is_empty() {
+        return Ok(Predicate {
+            path,
+            comparison: None,
+        });
+    }
+
+    let (comparison, operand) = [
+        ("==", Comparison::Equal),
+        ("!=", Comparison::NotEqual),
+        ("<=", Comparison::LessOrEqual),
+        (">=", Comparison::GreaterOrEqual),
+        ("<", Comparison::Less),
+        (">", Comparison::Greater),
+    ]
+    .into_iter()
+    .find_map(|(operator, comparison)| {
+        rest.strip_prefix(operator)
+            .map(|operand| (comparison, operand.trim()))
+    })
+    .ok_or_else(|| format!("Expected a comparison in filter {}", predicate))?;
+
+    let expected = if operand.starts_with('\'') {
+        let (value, length) = parse_quoted(operand)?;
+        if length != operand.len() {
+            return Err(format!("Unexpected text after {} in filter", operand));
+        }
+        Value::String(value)
+    } else {
+        serde_json::from_str(operand).map_err(|_| {
+            format!(
+                "Expected a quoted string, number, boolean or null in filter {}",
+                predicate
+            )
+        })?
+    };
+
+    Ok(Predicate {
+        path,
+        comparison: Some((comparison, expected)),
+    })
+}
+
+/// Parses a string in double quotes with JSON escapes, or in single quotes taken as is.
+/// Returns the string and how many bytes the quoted text took.
+fn parse_quoted(text: &str) -> Result<(String, usize), String> {
+    let quote = text.chars().next().unwrap_or('"');
+    let mut escaped = false;
+
+    for (index, c) in text.char_indices().skip(1) {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if quote == '"' => escaped = true,
+            _ if c == quote => {
+                let quoted = &text[..=index];
+                let value = if quote == '"' {
+                    serde_json::from_str(quoted).map_err(|e| e.to_string())?
+                } else {
+                    quoted[1..quoted.len() - 1].to_string()
+                };
+                return Ok((value, index + 1));
+            }
+            _ => {}
+        }
+    }
+
+    Err(format!("Unclosed quote in {}", text))
+}
+
+/// Finds the `)` closing a filter, skipping parentheses inside quoted strings
+fn find_closing_paren(text: &str) -> Option<usize> {
+    let mut depth = 0;
+    let mut quote: Option<char> = None;
+    let mut escaped = false;
+
+    for (index, c) in text.char_indices() {
+        match quote {
+            Some(_) if escaped => escaped = false,
+            Some('"') if c == '\\' => escaped = true,
+            Some(open) if c == open => quote = None,
+            Some(_) => {}
+            None => match c {
+                '"' | '\'' => quote = Some(c),
+                '(' => depth += 1,
+                ')' if depth == 0 => return Some(index),
+                ')' => depth -= 1,
+                _ => {}
+            },
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn select(context: &Value, path: &str) -> Option<Value> {
+        select_path(context, &parse_path(path).unwrap(), true)
+    }
+
+    fn context() -> Value {
+        json!({
+            "items": [
+                { "email": "a@example.com", "status": "open", "total": 5 },
+                { "email": "b@example.com", "status": "closed", "total": 12 },
+                { "email": "c@example.com", "status": "open", "total": 20 }
+            ],
+            "config": { "key.with.dots": "dotted", "nested": "{\"count\": 3}" }
+        })
+    }
+
+    #[test]
+    fn test_parse_path() {
+        assert_eq!(
+            parse_path(r#"items[0]["a.b"]['c'].d[*][-1]"#).unwrap(),
+            vec![
+                PathSelector::Key("items".to_string()),
+                PathSelector::Index(0),
+                PathSelector::Key("a.b".to_string()),
+                PathSelector::Key("c".to_string()),
+                PathSelector::Key("d".to_string()),
+                PathSelector::Wildcard,
+                PathSelector::Index(-1),
+            ]
+        );
+
+        assert!(parse_path("items[").is_err());
+        assert!(parse_path("items[name]").is_err());
+        assert!(parse_path("items..email").is_err());
+        assert!(parse_path("items.").is_err());
+        assert!(parse_path("items[?(status == 1)]").is_err());
+        assert!(parse_path("items[?(@.status ~ 1)]").is_err());
+    }
+
+    #[test]
+    fn test_select_keys_and_indexes() {
+        let context = context();
+
+        assert_eq!(
+            select(&context, "items[1].email"),
+            Some(json!("b@example.com"))
+        );
+        assert_eq!(select(&context, "items[-1].total"), Some(json!(20)));
+        assert_eq!(select(&context, "items[-4]"), None);
+        assert_eq!(
+            select(&context, r#"config["key.with.dots"]"#),
+            Some(json!("dotted"))
+        );
+        assert_eq!(select(&context, "config.nested.count"), Some(json!(3)));
+    }
+
+    #[test]
+    fn test_select_wildcards_and_filters() {
+        let context = context();
+
+        assert_eq!(
+            select(&context, "items[*].email"),
+            Some(json!(["a@example.com", "b@example.com", "c@example.com"]))
+        );
+        assert_eq!(
+            select(&context, r#"items[?(@.status=="open")].email"#),
+            Some(json!(["a@example.com", "c@example.com"]))
+        );
+        assert_eq!(
+            select(&context, "items[?(@.total >= 12)].total"),
+            Some(json!([12, 20]))
+        );
+        assert_eq!(
+            select(&context, "items[?(@.status == 'missing')]"),
+            Some(json!([]))
+        );
+        assert_eq!(
+            select(&context, "items[?(@.total)]").map(|v| v.as_array().unwrap().len()),
+            Some(3)
+        );
+    }
+
+    #[test]
+    fn test_final_json_string_is_kept_when_not_parsing() {
+        let context = context();
+        let selectors = parse_path("config.nested").unwrap();
+
+        assert_eq!(
+            select_path(&context, &selectors, false),
+            Some(json!("{\"count\": 3}"))
+        );
+        assert_eq!(
+            select_path(&context, &selectors, true),
+            Some(json!({ "count": 3 }))
+        );
+    }
+}
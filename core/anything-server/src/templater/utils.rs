@@ -21,27 +21,35 @@ pub fn get_template_file_requirements(
 
     // Analyze each variable for file patterns
     for var in variables {
-        // Look for variables with the pattern: files.*.file_extension.format
-        let parts: Vec<&str> = var.split('.').collect();
-
-        if parts.len() >= 2 && parts[0] == "files" {
-            // Check if this looks like a file pattern
-            if let Some((file_info, format)) = analyze_file_pattern(&parts) {
-                // Skip "files." and exclude the extension from file_name
-                let name_parts = &parts[1..parts.len() - 2];
-                file_requirements.push(FileRequirement {
-                    file_name: name_parts.join("."), // Everything except "files.", extension, and format
-                    file_name_with_extension: parts[1..parts.len() - 1].join("."), // Everything except "files." and format
-                    file_extension: file_info.to_string(),
-                    format: format.to_string(),
-                });
-            }
+        if let Some(requirement) = file_requirement_from_path(&var) {
+            file_requirements.push(requirement);
         }
     }
 
     Ok(file_requirements)
 }
 
+/// Gets the file a variable path like `files.report.pdf.file_url` loads, if it is one
+pub fn file_requirement_from_path(path: &str) -> Option<FileRequirement> {
+    // Look for variables with the pattern: files.*.file_extension.format
+    let parts: Vec<&str> = path.split('.').collect();
+
+    if parts.len() < 2 || parts[0] != "files" {
+        return None;
+    }
+
+    // Check if this looks like a file pattern
+    let (file_info, format) = analyze_file_pattern(&parts)?;
+    // Skip "files." and exclude the extension from file_name
+    let name_parts = &parts[1..parts.len() - 2];
+    Some(FileRequirement {
+        file_name: name_parts.join("."), // Everything except "files.", extension, and format
+        file_name_with_extension: parts[1..parts.len() - 1].join("."), // Everything except "files." and format
+        file_extension: file_info.to_string(),
+        format: format.to_string(),
+    })
+}
+
 fn analyze_file_pattern<'a>(parts: &[&'a str]) -> Option<(&'a str, &'a str)> {
     if parts.len() < 3 {
         return None;
@@ -160,4 +168,21 @@ mod tests {
         assert_eq!(requirements[3].file_extension, "docx");
         assert_eq!(requirements[3].format, "url");
     }
+
+    #[test]
+    fn test_file_requirements_with_filters() {
+        let template = json!({
+            "image": "{{ files.logo.png.file_url | default:\"\" }}",
+            "encoded": "{{files.report.csv.file_base64|trim}}"
+        });
+
+        let mut requirements = get_template_file_requirements(&template).unwrap();
+        requirements.sort_by(|a, b| a.file_name.cmp(&b.file_name));
+
+        assert_eq!(requirements.len(), 2);
+        assert_eq!(requirements[0].file_name_with_extension, "logo.png");
+        assert_eq!(requirements[0].format, "url");
+        assert_eq!(requirements[1].file_name_with_extension, "report.csv");
+        assert_eq!(requirements[1].format, "base64");
+    }
 }
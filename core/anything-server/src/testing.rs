@@ -11,6 +11,7 @@ use std::{collections::HashMap, sync::Arc};
 
 use crate::{
     processor::processor::ProcessorMessage,
+    processor::run_queue::enqueue_workflow,
     supabase_jwt_middleware::User,
     types::{
         action_types::ActionType,
@@ -161,7 +162,7 @@ pub async fn test_workflow(
         // workflow_graph: crate::processor::utils::create_workflow_graph(&workflow_version.flow_definition),
     };
 
-    if let Err(e) = state.processor_sender.send(processor_message).await {
+    if let Err(e) = enqueue_workflow(&state, processor_message).await {
         error!("[TESTING] Failed to send message to processor for task {}: {}", task.task_id, e);
         return (
             StatusCode::INTERNAL_SERVER_ERROR,
@@ -10,8 +10,10 @@ use serde_json::json;
 
 use crate::{
     bundler::bundle_context_from_parts,
+    leases::{release_lease, Leadership, LeadershipChange, TRIGGER_ENGINE_LEASE},
     metrics::METRICS,
     processor::processor::ProcessorMessage,
+    processor::run_queue::{enqueue_workflow, INSTANCE_ID},
     types::{
         action_types::{ActionType, PluginName},
         task_types::{Stage, Task, TaskConfig},
@@ -25,11 +27,44 @@ use std::sync::Arc;
 use std::time::Instant;
 use tokio::sync::RwLock;
 
+use chrono_tz::Tz;
 use cron::Schedule;
 use std::str::FromStr;
-use tracing::{error, info, Span};
+use tracing::{error, info, warn, Span};
 use uuid::Uuid;
 
+// Longest the loop sleeps without checking triggers, even when nothing is due sooner
+const MAX_IDLE_INTERVAL: Duration = Duration::from_secs(60);
+
+// Most missed runs a `fire_all` trigger replays after a restart, older ones are dropped
+pub const MAX_CATCH_UP_RUNS: usize = 100;
+
+/// What a cron trigger does with the runs it missed while no server was running
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum MisfirePolicy {
+    Skip, // Wait for the next scheduled time
+    #[default]
+    FireOnce, // Run once to catch up, however many runs were missed
+    FireAll, // Run every missed time in order, up to MAX_CATCH_UP_RUNS
+}
+
+impl MisfirePolicy {
+    pub fn from_input(value: Option<&str>) -> Self {
+        match value.map(|value| value.trim().to_lowercase()).as_deref() {
+            Some("skip") => MisfirePolicy::Skip,
+            Some("fire_all") => MisfirePolicy::FireAll,
+            Some("fire_once") | Some("") | None => MisfirePolicy::FireOnce,
+            Some(other) => {
+                warn!(
+                    "[TRIGGER_ENGINE] Unknown misfire policy {}, using fire_once",
+                    other
+                );
+                MisfirePolicy::FireOnce
+            }
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct InMemoryTrigger {
     pub account_id: String,
@@ -43,6 +78,88 @@ pub struct InMemoryTrigger {
     pub last_fired: Option<DateTime<Utc>>,
     pub next_fire: Option<DateTime<Utc>>,
     pub cron_expression: String,
+    pub timezone: Tz,
+    pub misfire_policy: MisfirePolicy,
+}
+
+/// Gets the first time the schedule fires strictly after `after`, evaluated in the trigger's timezone
+pub fn next_fire_after(
+    schedule: &Schedule,
+    timezone: &Tz,
+    after: DateTime<Utc>,
+) -> Option<DateTime<Utc>> {
+    schedule
+        .after(&after.with_timezone(timezone))
+        .next()
+        .map(|fire| fire.with_timezone(&Utc))
+}
+
+/// Picks the first fire time of a trigger whose last run was `last_fired`,
+/// applying its misfire policy to the runs that were due between then and `now`
+pub fn first_fire_after_restart(
+    schedule: &Schedule,
+    timezone: &Tz,
+    misfire_policy: MisfirePolicy,
+    last_fired: Option<DateTime<Utc>>,
+    now: DateTime<Utc>,
+) -> Option<DateTime<Utc>> {
+    let upcoming = next_fire_after(schedule, timezone, now);
+
+    let last_fired = match last_fired {
+        Some(last_fired) if misfire_policy != MisfirePolicy::Skip => last_fired,
+        _ => return upcoming,
+    };
+
+    // Walk back from now and keep only the most recent missed runs, so a long outage
+    // of a second-precision schedule can't flood the processor or spin through every tick.
+    // The walk starts a second past now since `rev` only yields times strictly before it.
+    let missed: Vec<DateTime<Utc>> = schedule
+        .after(&(now + chrono::Duration::seconds(1)).with_timezone(timezone))
+        .rev()
+        .map(|fire| fire.with_timezone(&Utc))
+        .skip_while(|fire| *fire > now)
+        .take_while(|fire| *fire > last_fired)
+        .take(MAX_CATCH_UP_RUNS)
+        .collect();
+
+    // Missed runs are newest first
+    match misfire_policy {
+        MisfirePolicy::FireAll => missed.last().copied().or(upcoming),
+        _ => missed.first().copied().or(upcoming),
+    }
+}
+
+/// Gets the fire time that follows a run scheduled for `fired`. Only `fire_all`
+/// triggers go on to replay scheduled times that are already in the past.
+pub fn next_fire_after_run(
+    schedule: &Schedule,
+    timezone: &Tz,
+    misfire_policy: MisfirePolicy,
+    fired: DateTime<Utc>,
+    now: DateTime<Utc>,
+) -> Option<DateTime<Utc>> {
+    match misfire_policy {
+        MisfirePolicy::FireAll => next_fire_after(schedule, timezone, fired),
+        _ => next_fire_after(schedule, timezone, fired.max(now)),
+    }
+}
+
+/// How long to sleep until the earliest trigger is due
+pub fn time_until_next_fire(
+    triggers: &HashMap<String, InMemoryTrigger>,
+    now: DateTime<Utc>,
+) -> Duration {
+    triggers
+        .values()
+        .filter_map(|trigger| trigger.next_fire)
+        .min()
+        .map(|next_fire| {
+            (next_fire - now)
+                .to_std()
+                .unwrap_or(Duration::ZERO)
+                .min(MAX_IDLE_INTERVAL)
+        })
+        .unwrap_or(MAX_IDLE_INTERVAL)
 }
 
 pub async fn cron_job_loop(state: Arc<AppState>) {
@@ -55,17 +172,44 @@ pub async fn cron_job_loop(state: Arc<AppState>) {
     let client = state.anything_client.clone();
     hydrate_triggers(state.clone(), &client, &trigger_state).await;
 
-    //How often we check for triggers to run
-    let refresh_interval = Duration::from_secs(60);
-
     // Clone state once here for use in the loop
     let state = Arc::new(state);
 
+    // Every replica keeps its triggers in memory, but only the lease holder fires them
+    let mut leadership = Leadership::new(TRIGGER_ENGINE_LEASE);
+
     loop {
+        // Wake up when the earliest trigger is due so schedules can fire to the second
+        let refresh_interval = if leadership.is_leader() {
+            let triggers = trigger_state.read().await;
+            time_until_next_fire(&triggers, Utc::now()).min(leadership.time_until_renew())
+        } else {
+            leadership.time_until_renew()
+        };
+
         tokio::select! {
             _ = sleep(refresh_interval) => {
                 // info!("[TRIGGER_ENGINE] Starting trigger check loop");
 
+                if state.shutdown_signal.load(std::sync::atomic::Ordering::SeqCst) {
+                    if leadership.is_leader() {
+                        release_lease(&state, TRIGGER_ENGINE_LEASE).await;
+                    }
+                    info!("[TRIGGER_ENGINE] Shutdown signal received, stopping trigger engine");
+                    break;
+                }
+
+                match leadership.check(&state).await {
+                    LeadershipChange::Acquired => {
+                        // The previous leader kept firing while this instance followed, so pick up where it left off
+                        if let Err(e) = restore_triggers_from_db(&client, &trigger_state).await {
+                            error!("[TRIGGER_ENGINE] Error restoring triggers after taking leadership: {:?}", e);
+                        }
+                    }
+                    LeadershipChange::Kept => {}
+                    LeadershipChange::Lost | LeadershipChange::Follower => continue,
+                }
+
                 //find triggers to run
                 let triggers_to_run = {
                     let triggers = trigger_state.read().await;
@@ -83,10 +227,27 @@ pub async fn cron_job_loop(state: Arc<AppState>) {
                     info!("[TRIGGER_ENGINE] Trigger should run for trigger_id ie workflow_id: {}",
                         trigger.plugin_name
                     );
-                    if let Err(e) = create_trigger_task(&state, &trigger).await {
+                    let scheduled_time = trigger.next_fire.unwrap_or_else(Utc::now);
+                    let idempotency_key = trigger_idempotency_key(&trigger, scheduled_time);
+
+                    // A leader that took over from one that died mid-tick skips runs it already fired
+                    let claimed = match claim_trigger_fire(&state, &trigger, &idempotency_key, scheduled_time).await {
+                        Ok(claimed) => claimed,
+                        Err(e) => {
+                            error!("[TRIGGER_ENGINE] Error claiming trigger run {}, skipping it: {:?}", idempotency_key, e);
+                            false
+                        }
+                    };
+
+                    if !claimed {
+                        info!("[TRIGGER_ENGINE] Trigger run {} was not claimed, moving to the next scheduled time", idempotency_key);
+                        if let Err(e) = update_trigger_last_run(&state, &id, &trigger, &trigger_state).await {
+                            error!("[TRIGGER_ENGINE] Error updating trigger last run: {:?}", e);
+                        }
+                    } else if let Err(e) = create_trigger_task(&state, &trigger, &idempotency_key, scheduled_time).await {
                         error!("[TRIGGER_ENGINE] Error creating trigger task: {:?}", e);
                     } else {
-                        if let Err(e) = update_trigger_last_run(&id, &trigger, &trigger_state).await {
+                        if let Err(e) = update_trigger_last_run(&state, &id, &trigger, &trigger_state).await {
                             error!("[TRIGGER_ENGINE] Error updating trigger last run: {:?}", e);
                         }
                     }
@@ -141,6 +302,8 @@ async fn update_triggers_for_workflow(
     let body = response.text().await?;
     let flow_versions: Vec<DatabaseFlowVersion> = serde_json::from_str(&body)?;
 
+    let last_fired_times = get_last_fired_times(client).await?;
+
     let mut new_triggers = HashMap::new();
 
     //Add new triggers to new_triggers
@@ -148,7 +311,11 @@ async fn update_triggers_for_workflow(
         let triggers_from_flow =
             create_in_memory_triggers_from_flow_definition(state.clone(), &flow_version, client)
                 .await;
-        new_triggers.extend(triggers_from_flow);
+        new_triggers.extend(
+            triggers_from_flow
+                .into_iter()
+                .map(|(id, trigger)| (id, restore_last_fired(trigger, &last_fired_times))),
+        );
     }
 
     //Delete Existing trigger for workflow_id from hashmap
@@ -244,6 +411,14 @@ pub async fn hydrate_triggers(
         flow_versions.len()
     );
 
+    let last_fired_times = get_last_fired_times(client).await.unwrap_or_else(|e| {
+        error!(
+            "[TRIGGER_ENGINE] Error fetching trigger last fired times: {:?}",
+            e
+        );
+        HashMap::new()
+    });
+
     let mut new_triggers = HashMap::new();
 
     //Add new triggers to new_triggers
@@ -253,6 +428,7 @@ pub async fn hydrate_triggers(
                 .await;
 
         for (workflow_id, new_trigger) in triggers_from_flow {
+            let new_trigger = restore_last_fired(new_trigger, &last_fired_times);
             // Check if the trigger already exists in memory
             let existing_triggers = triggers.read().await;
             if let Some(existing_trigger) = existing_triggers.get(&workflow_id) {
@@ -337,14 +513,25 @@ pub fn should_trigger_run(trigger: &InMemoryTrigger) -> bool {
 }
 
 async fn update_trigger_last_run(
+    state: &Arc<AppState>,
     id: &str,
     trigger: &InMemoryTrigger,
     triggers: &Arc<RwLock<HashMap<String, InMemoryTrigger>>>,
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
     info!("[TRIGGER_ENGINE] Updating trigger last run and next_run time");
 
+    let now = Utc::now();
+    // The scheduled time is recorded rather than now, so catching up after a restart starts from the right run
+    let fired = trigger.next_fire.unwrap_or(now);
+
     let new_next_fire = match Schedule::from_str(&trigger.cron_expression) {
-        Ok(schedule) => schedule.upcoming(Utc).next(),
+        Ok(schedule) => next_fire_after_run(
+            &schedule,
+            &trigger.timezone,
+            trigger.misfire_policy,
+            fired,
+            now,
+        ),
         Err(e) => {
             error!("[TRIGGER_ENGINE] Error parsing cron expression: {}", e);
             None
@@ -353,8 +540,15 @@ async fn update_trigger_last_run(
 
     info!("[TRIGGER_ENGINE] New next fire time: {:?}", new_next_fire);
 
+    if let Err(e) = persist_last_fired(state, trigger, fired).await {
+        error!(
+            "[TRIGGER_ENGINE] Error persisting trigger last fired time: {:?}",
+            e
+        );
+    }
+
     let updated_trigger = InMemoryTrigger {
-        last_fired: Some(Utc::now()),
+        last_fired: Some(fired),
         next_fire: new_next_fire,
         ..trigger.clone()
     };
@@ -370,9 +564,177 @@ async fn update_trigger_last_run(
     Ok(())
 }
 
+/// Stores when a trigger last fired so runs missed during a restart can be caught up
+async fn persist_last_fired(
+    state: &Arc<AppState>,
+    trigger: &InMemoryTrigger,
+    fired: DateTime<Utc>,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    dotenv().ok();
+    let supabase_service_role_api_key = env::var("SUPABASE_SERVICE_ROLE_API_KEY")
+        .expect("SUPABASE_SERVICE_ROLE_API_KEY must be set");
+
+    let response = state
+        .anything_client
+        .from("cron_triggers")
+        .auth(supabase_service_role_api_key)
+        .upsert(
+            json!({
+                "flow_id": trigger.flow_id,
+                "action_id": trigger.action_id,
+                "account_id": trigger.account_id,
+                "last_fired": fired,
+            })
+            .to_string(),
+        )
+        .on_conflict("flow_id,action_id")
+        .execute()
+        .await?;
+
+    if !response.status().is_success() {
+        let body = response.text().await?;
+        return Err(format!("Failed to persist last fired time: {}", body).into());
+    }
+
+    Ok(())
+}
+
+/// Gets the persisted last fired time of every cron trigger, keyed by (flow_id, action_id)
+async fn get_last_fired_times(
+    client: &Postgrest,
+) -> Result<HashMap<(String, String), DateTime<Utc>>, Box<dyn std::error::Error + Send + Sync>> {
+    dotenv().ok();
+    let supabase_service_role_api_key = env::var("SUPABASE_SERVICE_ROLE_API_KEY")
+        .expect("SUPABASE_SERVICE_ROLE_API_KEY must be set");
+
+    let response = client
+        .from("cron_triggers")
+        .auth(supabase_service_role_api_key)
+        .select("flow_id,action_id,last_fired")
+        .execute()
+        .await?;
+
+    let body = response.text().await?;
+    let rows: Vec<serde_json::Value> = serde_json::from_str(&body)?;
+
+    Ok(rows
+        .into_iter()
+        .filter_map(|row| {
+            let flow_id = row.get("flow_id")?.as_str()?.to_string();
+            let action_id = row.get("action_id")?.as_str()?.to_string();
+            let last_fired = row.get("last_fired")?.as_str()?.parse().ok()?;
+            Some(((flow_id, action_id), last_fired))
+        })
+        .collect())
+}
+
+/// Identifies one scheduled run of a trigger, so the same tick is never fired twice
+pub fn trigger_idempotency_key(trigger: &InMemoryTrigger, scheduled_time: DateTime<Utc>) -> String {
+    format!(
+        "cron:{}:{}:{}",
+        trigger.flow_id,
+        trigger.action_id,
+        scheduled_time.timestamp()
+    )
+}
+
+/// Records that this instance is firing a scheduled run. Returns false when the run was already fired.
+async fn claim_trigger_fire(
+    state: &Arc<AppState>,
+    trigger: &InMemoryTrigger,
+    idempotency_key: &str,
+    scheduled_time: DateTime<Utc>,
+) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+    dotenv().ok();
+    let supabase_service_role_api_key = env::var("SUPABASE_SERVICE_ROLE_API_KEY")
+        .expect("SUPABASE_SERVICE_ROLE_API_KEY must be set");
+
+    let response = state
+        .anything_client
+        .from("trigger_fires")
+        .auth(supabase_service_role_api_key)
+        .insert(
+            json!({
+                "idempotency_key": idempotency_key,
+                "flow_id": trigger.flow_id,
+                "action_id": trigger.action_id,
+                "account_id": trigger.account_id,
+                "scheduled_time": scheduled_time,
+                "fired_by": *INSTANCE_ID,
+            })
+            .to_string(),
+        )
+        .execute()
+        .await?;
+
+    let status = response.status();
+    if status == reqwest::StatusCode::CONFLICT {
+        return Ok(false);
+    }
+    if !status.is_success() {
+        let body = response.text().await?;
+        return Err(format!("Failed to claim trigger run: {}", body).into());
+    }
+
+    Ok(true)
+}
+
+/// Re-applies the persisted last fired times to every trigger in memory
+async fn restore_triggers_from_db(
+    client: &Postgrest,
+    triggers: &Arc<RwLock<HashMap<String, InMemoryTrigger>>>,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let last_fired_times = get_last_fired_times(client).await?;
+
+    let mut triggers = triggers.write().await;
+    for trigger in triggers.values_mut() {
+        *trigger = restore_last_fired(trigger.clone(), &last_fired_times);
+    }
+
+    Ok(())
+}
+
+/// Applies a trigger's persisted last fired time and misfire policy to its first fire time
+fn restore_last_fired(
+    trigger: InMemoryTrigger,
+    last_fired_times: &HashMap<(String, String), DateTime<Utc>>,
+) -> InMemoryTrigger {
+    let last_fired = last_fired_times
+        .get(&(trigger.flow_id.clone(), trigger.action_id.clone()))
+        .copied();
+
+    if last_fired.is_none() {
+        return trigger;
+    }
+
+    let next_fire = match Schedule::from_str(&trigger.cron_expression) {
+        Ok(schedule) => first_fire_after_restart(
+            &schedule,
+            &trigger.timezone,
+            trigger.misfire_policy,
+            last_fired,
+            Utc::now(),
+        ),
+        Err(_) => trigger.next_fire,
+    };
+
+    info!(
+        "[TRIGGER_ENGINE] Restored trigger for flow {} last fired at {:?}, next fire {:?} ({:?})",
+        trigger.flow_id, last_fired, next_fire, trigger.misfire_policy
+    );
+
+    InMemoryTrigger {
+        last_fired,
+        next_fire,
+        ..trigger
+    }
+}
+
 async fn create_trigger_task(
     state: &Arc<AppState>,
     trigger: &InMemoryTrigger,
+    idempotency_key: &str,
+    scheduled_time: DateTime<Utc>,
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
     let execution_start = Instant::now();
     let trigger_span = tracing::info_span!("create_trigger_task",
@@ -455,7 +817,9 @@ async fn create_trigger_task(
         .config(trigger.config.clone())
         .result(json!({
             "message": format!("Successfully triggered task"),
-                    "created_at": Utc::now()
+                    "created_at": Utc::now(),
+                    "scheduled_time": scheduled_time,
+                    "idempotency_key": idempotency_key
         }))
         .build()
     {
@@ -491,7 +855,7 @@ async fn create_trigger_task(
         // ),
     };
 
-    if let Err(e) = state.processor_sender.send(processor_message).await {
+    if let Err(e) = enqueue_workflow(&state, processor_message).await {
         error!(
             "[TRIGGER_ENGINE] Failed to send message to processor: {}",
             e
@@ -599,14 +963,30 @@ pub async fn create_in_memory_triggers_from_flow_definition(
                 .as_str()
                 .unwrap_or("* * * * *");
 
+            let timezone = match rendered_input["timezone"].as_str() {
+                Some(timezone) if !timezone.trim().is_empty() => {
+                    timezone.trim().parse::<Tz>().unwrap_or_else(|e| {
+                        error!(
+                            "[TRIGGER_ENGINE] Invalid timezone {}, using UTC: {}",
+                            timezone, e
+                        );
+                        Tz::UTC
+                    })
+                }
+                _ => Tz::UTC,
+            };
+
+            let misfire_policy =
+                MisfirePolicy::from_input(rendered_input["misfire_policy"].as_str());
+
             info!(
-                "[TRIGGER ENGINE] Using cron expression: {}",
-                cron_expression
+                "[TRIGGER ENGINE] Using cron expression: {} ({}, misfire policy {:?})",
+                cron_expression, timezone, misfire_policy
             );
 
             let next_fire = match Schedule::from_str(cron_expression) {
                 Ok(schedule) => {
-                    let next = schedule.upcoming(Utc).next();
+                    let next = next_fire_after(&schedule, &timezone, Utc::now());
                     info!("[TRIGGER ENGINE] Calculated next fire time: {:?}", next);
                     next
                 }
@@ -628,6 +1008,8 @@ pub async fn create_in_memory_triggers_from_flow_definition(
                 last_fired: None,
                 next_fire,
                 cron_expression: cron_expression.to_string(),
+                timezone,
+                misfire_policy,
             };
 
             triggers.insert(flow_id.to_string(), trigger);
@@ -638,3 +1020,129 @@ pub async fn create_in_memory_triggers_from_flow_definition(
 
     triggers
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn utc(hour: u32, minute: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 7, 1, hour, minute, 0).unwrap()
+    }
+
+    #[test]
+    fn test_schedule_is_evaluated_in_trigger_timezone() {
+        // 09:00 every day in New York is 13:00 UTC during daylight saving time
+        let schedule = Schedule::from_str("0 0 9 * * *").unwrap();
+        let new_york: Tz = "America/New_York".parse().unwrap();
+
+        assert_eq!(
+            next_fire_after(&schedule, &new_york, utc(12, 0)),
+            Some(utc(13, 0))
+        );
+        assert_eq!(
+            next_fire_after(&schedule, &Tz::UTC, utc(8, 0)),
+            Some(utc(9, 0))
+        );
+    }
+
+    #[test]
+    fn test_misfire_policies_after_restart() {
+        // Every 15 minutes, last fired at 10:00 and the server came back at 11:05
+        let schedule = Schedule::from_str("0 */15 * * * *").unwrap();
+        let last_fired = Some(utc(10, 0));
+        let now = utc(11, 5);
+
+        let first_fire =
+            |policy| first_fire_after_restart(&schedule, &Tz::UTC, policy, last_fired, now);
+
+        assert_eq!(first_fire(MisfirePolicy::Skip), Some(utc(11, 15)));
+        assert_eq!(first_fire(MisfirePolicy::FireOnce), Some(utc(11, 0)));
+        assert_eq!(first_fire(MisfirePolicy::FireAll), Some(utc(10, 15)));
+
+        // Catching up with fire_all goes through every missed time before returning to schedule
+        assert_eq!(
+            next_fire_after_run(
+                &schedule,
+                &Tz::UTC,
+                MisfirePolicy::FireAll,
+                utc(10, 15),
+                now
+            ),
+            Some(utc(10, 30))
+        );
+        assert_eq!(
+            next_fire_after_run(
+                &schedule,
+                &Tz::UTC,
+                MisfirePolicy::FireOnce,
+                utc(11, 0),
+                now
+            ),
+            Some(utc(11, 15))
+        );
+
+        // Nothing missed since the last run
+        assert_eq!(
+            first_fire_after_restart(
+                &schedule,
+                &Tz::UTC,
+                MisfirePolicy::FireAll,
+                Some(utc(11, 0)),
+                now
+            ),
+            Some(utc(11, 15))
+        );
+    }
+
+    #[test]
+    fn test_fire_all_only_replays_recent_runs() {
+        // Every second for an hour is more than MAX_CATCH_UP_RUNS
+        let schedule = Schedule::from_str("* * * * * *").unwrap();
+        let now = utc(11, 0);
+
+        let first_fire = first_fire_after_restart(
+            &schedule,
+            &Tz::UTC,
+            MisfirePolicy::FireAll,
+            Some(utc(10, 0)),
+            now,
+        );
+
+        assert_eq!(
+            first_fire,
+            Some(now - chrono::Duration::seconds(MAX_CATCH_UP_RUNS as i64 - 1))
+        );
+    }
+
+    #[test]
+    fn test_long_outage_only_walks_recent_runs() {
+        // Every second for a year; walking every missed tick would take millions of steps
+        let schedule = Schedule::from_str("* * * * * *").unwrap();
+        let now = utc(11, 0) + chrono::Duration::milliseconds(500);
+        let last_fired = Some(utc(11, 0) - chrono::Duration::days(365));
+
+        let first_fire =
+            |policy| first_fire_after_restart(&schedule, &Tz::UTC, policy, last_fired, now);
+
+        assert_eq!(first_fire(MisfirePolicy::FireOnce), Some(utc(11, 0)));
+        assert_eq!(
+            first_fire(MisfirePolicy::FireAll),
+            Some(utc(11, 0) - chrono::Duration::seconds(MAX_CATCH_UP_RUNS as i64 - 1))
+        );
+    }
+
+    #[test]
+    fn test_misfire_policy_from_input() {
+        assert_eq!(MisfirePolicy::from_input(Some("skip")), MisfirePolicy::Skip);
+        assert_eq!(
+            MisfirePolicy::from_input(Some("FIRE_ALL")),
+            MisfirePolicy::FireAll
+        );
+        assert_eq!(MisfirePolicy::from_input(None), MisfirePolicy::FireOnce);
+        assert_eq!(
+            MisfirePolicy::from_input(Some("sometimes")),
+            MisfirePolicy::FireOnce
+        );
+    }
+}
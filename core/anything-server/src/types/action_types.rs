@@ -1,5 +1,5 @@
 use super::react_flow_types::{HandleProps, NodePresentation};
-use node_semver::Version;
+use node_semver::{Range, Version};
 use serde::de::{self, Deserializer};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
@@ -54,6 +54,8 @@ pub struct Action {
     pub r#type: ActionType,
     pub plugin_name: PluginName, //We will use this to fetch current plugin schema etc from database vs in the workflow
     pub plugin_version: Version,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub plugin_version_range: Option<Range>, // e.g. "^1.2.0", tasks run the newest loaded version in it
     pub action_id: String, // This is a local action_id for in the workflow
     pub label: String,
     pub description: Option<String>,
@@ -68,6 +70,79 @@ pub struct Action {
     pub plugin_config_schema_locked: Option<bool>,
     pub presentation: Option<NodePresentation>,
     pub handles: Option<Vec<HandleProps>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retry: Option<RetryPolicy>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output_schema: Option<Value>, // JSON schema of the result, takes precedence over the plugin's
+}
+
+/// Error type used in `retry_on` to retry HTTP actions that got a 5xx response
+pub const RETRY_ON_HTTP_5XX: &str = "http_5xx";
+
+const MAX_RETRY_ATTEMPTS: u32 = 10;
+
+/// How the task actor retries a failing action.
+/// `max_attempts` includes the first run. An empty `retry_on` retries every error type
+/// (e.g. `plugin_execution_error`, `execution_timeout`), HTTP 5xx responses only when listed.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct RetryPolicy {
+    #[serde(default = "default_max_attempts")]
+    pub max_attempts: u32,
+    #[serde(default = "default_initial_delay_ms")]
+    pub initial_delay_ms: u64,
+    #[serde(default = "default_backoff_multiplier")]
+    pub backoff_multiplier: f64,
+    #[serde(default = "default_max_delay_ms")]
+    pub max_delay_ms: u64,
+    #[serde(default)]
+    pub retry_on: Vec<String>,
+}
+
+fn default_max_attempts() -> u32 {
+    3
+}
+
+fn default_initial_delay_ms() -> u64 {
+    1_000
+}
+
+fn default_backoff_multiplier() -> f64 {
+    2.0
+}
+
+fn default_max_delay_ms() -> u64 {
+    60_000
+}
+
+impl RetryPolicy {
+    /// Clamps user supplied values so a workflow can't retry forever or back off to zero
+    pub fn normalized(mut self) -> Self {
+        self.max_attempts = self.max_attempts.clamp(1, MAX_RETRY_ATTEMPTS);
+        if !self.backoff_multiplier.is_finite() || self.backoff_multiplier < 1.0 {
+            self.backoff_multiplier = 1.0;
+        }
+        self.max_delay_ms = self.max_delay_ms.max(self.initial_delay_ms);
+        self
+    }
+
+    /// Delay before the next attempt once `attempt` (starting at 1) has failed
+    pub fn delay_after_attempt(&self, attempt: u32) -> std::time::Duration {
+        let exponent = attempt.saturating_sub(1).min(i32::MAX as u32) as i32;
+        let delay_ms = self.initial_delay_ms as f64 * self.backoff_multiplier.powi(exponent);
+        std::time::Duration::from_millis(delay_ms.min(self.max_delay_ms as f64) as u64)
+    }
+
+    pub fn retries_error_type(&self, error_type: &str) -> bool {
+        self.retry_on.is_empty() || self.retry_on.iter().any(|retry_on| retry_on == error_type)
+    }
+
+    pub fn retries_status_code(&self, status_code: u16) -> bool {
+        status_code >= 500
+            && self
+                .retry_on
+                .iter()
+                .any(|retry_on| retry_on == RETRY_ON_HTTP_5XX)
+    }
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
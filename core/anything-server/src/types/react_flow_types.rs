@@ -19,12 +19,24 @@ pub struct HandleProps {
     pub position: String,
 }
 
+/// Source handle actions expose for their failure branch
+pub const ERROR_SOURCE_HANDLE: &str = "error";
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Edge {
     pub id: String,
     pub source: String,
+    #[serde(default, alias = "sourceHandle")] // React Flow edges saved by the studio use camelCase
     pub source_handle: Option<String>,
     pub target: String,
+    #[serde(default, alias = "targetHandle")]
     pub target_handle: Option<String>,
     pub r#type: String,
 }
+
+impl Edge {
+    /// Error edges only run when their source task fails
+    pub fn is_error_edge(&self) -> bool {
+        self.source_handle.as_deref() == Some(ERROR_SOURCE_HANDLE)
+    }
+}
@@ -98,6 +98,25 @@ impl TriggerSessionStatus {
     }
 }
 
+//Identifies which loop iteration a task ran in. Exposed to templates as {{loop.item}} and {{loop.index}}
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+pub struct LoopContext {
+    pub loop_action_id: String,
+    pub item: Value,
+    pub index: usize,
+    pub total: usize,
+}
+
+//One run of a task that has a retry policy. Kept on the task so the UI can show attempt history
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+pub struct TaskAttempt {
+    pub attempt: u32,
+    pub started_at: DateTime<Utc>,
+    pub ended_at: DateTime<Utc>,
+    pub status: TaskStatus,
+    pub error: Option<Value>,
+}
+
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct Task {
     pub task_id: Uuid,
@@ -131,6 +150,10 @@ pub struct Task {
     pub updated_by: Option<Uuid>,
     pub created_by: Option<Uuid>,
     pub processing_order: i32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub loop_context: Option<LoopContext>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub attempts: Option<Vec<TaskAttempt>>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -355,6 +378,8 @@ impl TaskBuilder {
             updated_by: self.updated_by,
             created_by: self.created_by,
             processing_order: self.processing_order.unwrap_or(0),
+            loop_context: None,
+            attempts: None,
         };
         Ok(task)
     }
@@ -5,16 +5,20 @@ use axum::{
     Json,
 };
 
+use futures::future::join_all;
 use serde_json::{json, Value};
+use std::collections::HashMap;
 use std::sync::Arc;
 
 use crate::{
     bundler::bundle_cached_inputs,
+    output_schema::{available_variables, system_output_schemas},
     supabase_jwt_middleware::User,
     types::{
-        task_types::Task,
+        task_types::{Task, TaskStatus},
         workflow_types::{DatabaseFlowVersion, WorkflowVersionDefinition},
     },
+    workflow_analysis::{fetch_flow_definition, AccountReferences},
     AppState,
 };
 
@@ -315,3 +319,113 @@ pub async fn get_flow_version_inputs(
     println!("[INPUTS] Returning response");
     Json(response_data).into_response()
 }
+
+// Variables available to an action before running the workflow
+pub async fn get_available_variables(
+    Path((account_id, workflow_id, workflow_version_id, action_id)): Path<(
+        String,
+        String,
+        String,
+        String,
+    )>,
+    State(state): State<Arc<AppState>>,
+    Extension(user): Extension<User>,
+) -> impl IntoResponse {
+    println!(
+        "[VARIABLES] Listing variables available to action {} in flow version {}",
+        action_id, workflow_version_id
+    );
+
+    let workflow =
+        match fetch_flow_definition(&state, &user, &account_id, &workflow_version_id).await {
+            Ok(Some(workflow)) => workflow,
+            Ok(None) => return (StatusCode::NOT_FOUND, "Flow version not found").into_response(),
+            Err(e) => {
+                println!("[VARIABLES] {}", e);
+                return (StatusCode::INTERNAL_SERVER_ERROR, e).into_response();
+            }
+        };
+
+    let plugin_schemas = match system_output_schemas() {
+        Ok(schemas) => schemas,
+        Err(e) => {
+            println!("[VARIABLES] Error loading system plugin schemas: {:?}", e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed to load plugin schemas",
+            )
+                .into_response();
+        }
+    };
+
+    // Only actions without a declared schema need their last result
+    let undeclared: Vec<&str> = workflow
+        .actions
+        .iter()
+        .filter(|action| {
+            action.output_schema.is_none()
+                && !plugin_schemas.contains_key(action.plugin_name.as_str())
+        })
+        .map(|action| action.action_id.as_str())
+        .collect();
+
+    let last_results: HashMap<String, Value> = join_all(
+        undeclared
+            .iter()
+            .map(|id| fetch_last_result(&state, &user, &account_id, &workflow_id, id)),
+    )
+    .await
+    .into_iter()
+    .zip(undeclared)
+    .filter_map(|(result, id)| result.map(|result| (id.to_string(), result)))
+    .collect();
+
+    let account_references = match AccountReferences::fetch(state.clone(), &account_id).await {
+        Ok(references) => references,
+        Err(e) => {
+            println!("[VARIABLES] {}", e);
+            return (StatusCode::INTERNAL_SERVER_ERROR, e).into_response();
+        }
+    };
+
+    match available_variables(
+        &workflow,
+        &action_id,
+        &plugin_schemas,
+        &last_results,
+        Some(&account_references),
+    ) {
+        Some(variables) => Json(variables).into_response(),
+        None => (StatusCode::NOT_FOUND, "Action not found").into_response(),
+    }
+}
+
+/// Result of the action's last successful run in any version of the workflow, so a draft
+/// that hasn't run yet still gets the shape from earlier versions
+async fn fetch_last_result(
+    state: &AppState,
+    user: &User,
+    account_id: &str,
+    workflow_id: &str,
+    action_id: &str,
+) -> Option<Value> {
+    let response = state
+        .anything_client
+        .from("tasks")
+        .auth(user.jwt.clone())
+        .eq("account_id", account_id)
+        .eq("flow_id", workflow_id)
+        .eq("action_id", action_id)
+        .eq("task_status", TaskStatus::Completed.as_str())
+        .not("is", "result", "null")
+        .select("result")
+        .order("created_at.desc")
+        .limit(1)
+        .execute()
+        .await
+        .map_err(|e| println!("[VARIABLES] Error fetching last result: {:?}", e))
+        .ok()?;
+
+    let tasks: Vec<Value> = response.json().await.ok()?;
+    tasks.into_iter().next()?.get_mut("result").map(Value::take)
+}
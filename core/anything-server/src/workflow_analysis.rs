@@ -0,0 +1,485 @@
+use axum::{
+    extract::{Extension, Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use dotenv::dotenv;
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::collections::HashSet;
+use std::env;
+use std::sync::Arc;
+
+use crate::actor_processor::dependency_resolver::DependencyGraph;
+use crate::bundler::accounts::fetch_cached_auth_accounts;
+use crate::bundler::secrets::get_decrypted_secrets;
+use crate::supabase_jwt_middleware::User;
+use crate::system_plugins::loop_plugin::LOOP_END_PLUGIN_NAME;
+use crate::system_variables::get_system_variables;
+use crate::templater::selectors::{parse_path, PathSelector};
+use crate::templater::utils::file_requirement_from_path;
+use crate::templater::Templater;
+use crate::types::action_types::Action;
+use crate::types::workflow_types::WorkflowVersionDefinition;
+use crate::AppState;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DiagnosticSeverity {
+    Error,   // Will fail or render wrong at runtime
+    Warning, // Might work depending on how the workflow runs
+}
+
+/// A problem with one variable reference in an action's inputs
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TemplateDiagnostic {
+    pub action_id: String,
+    pub action_label: String,
+    pub input: String,            // The top level input the reference is in
+    pub variable: Option<String>, // None when the template itself couldn't be parsed
+    pub severity: DiagnosticSeverity,
+    pub message: String,
+}
+
+/// Names the account's secrets, connected accounts and files are available under
+#[derive(Debug, Clone, Default)]
+pub struct AccountReferences {
+    pub secrets: HashSet<String>,
+    pub accounts: HashSet<String>,
+    pub files: HashSet<String>, // File names with extension
+}
+
+impl AccountReferences {
+    /// Loads the names from the same caches the bundler renders inputs from
+    pub async fn fetch(state: Arc<AppState>, account_id: &str) -> Result<Self, String> {
+        let client = &state.anything_client;
+
+        let (secrets, accounts, files) = tokio::join!(
+            get_decrypted_secrets(state.clone(), client, account_id),
+            fetch_cached_auth_accounts(state.clone(), client, account_id, false),
+            fetch_file_names(state.clone(), account_id)
+        );
+
+        Ok(Self {
+            secrets: secrets
+                .map_err(|e| format!("Failed to fetch secrets: {}", e))?
+                .into_iter()
+                .map(|secret| secret.secret_name)
+                .collect(),
+            accounts: accounts
+                .map_err(|e| format!("Failed to fetch accounts: {}", e))?
+                .into_iter()
+                .map(|account| account.account_auth_provider_account_slug)
+                .collect(),
+            files: files?,
+        })
+    }
+}
+
+async fn fetch_file_names(
+    state: Arc<AppState>,
+    account_id: &str,
+) -> Result<HashSet<String>, String> {
+    dotenv().ok();
+    let supabase_service_role_api_key = env::var("SUPABASE_SERVICE_ROLE_API_KEY")
+        .expect("SUPABASE_SERVICE_ROLE_API_KEY must be set");
+
+    let response = state
+        .anything_client
+        .from("files")
+        .auth(supabase_service_role_api_key)
+        .eq("account_id", account_id)
+        .select("file_name")
+        .execute()
+        .await
+        .map_err(|e| format!("Failed to fetch files: {}", e))?;
+
+    let files: Vec<Value> = response
+        .json()
+        .await
+        .map_err(|e| format!("Failed to parse files: {}", e))?;
+
+    Ok(files
+        .iter()
+        .filter_map(|file| file.get("file_name").and_then(Value::as_str))
+        .map(str::to_string)
+        .collect())
+}
+
+/// Checks every variable reference in the workflow's action inputs.
+///
+/// `actions.<id>` must name an action that runs before the referencing one. With
+/// `account_references`, `secrets.*`, `accounts.*` and `files.*` must exist for the account;
+/// without them only the workflow itself is checked.
+pub fn analyze_workflow(
+    workflow: &WorkflowVersionDefinition,
+    account_references: Option<&AccountReferences>,
+) -> Vec<TemplateDiagnostic> {
+    let graph = DependencyGraph::new(workflow);
+    let action_ids: HashSet<&str> = workflow
+        .actions
+        .iter()
+        .map(|action| action.action_id.as_str())
+        .collect();
+    let system_variables = get_system_variables();
+
+    let mut diagnostics = Vec::new();
+
+    for action in &workflow.actions {
+        let Some(Value::Object(inputs)) = &action.inputs else {
+            continue;
+        };
+
+        let upstream = graph.get_upstream_actions(&action.action_id);
+        let in_loop = action.plugin_name.as_str() == LOOP_END_PLUGIN_NAME
+            || graph
+                .loop_regions
+                .values()
+                .any(|region| region.body.contains(&action.action_id));
+
+        for (input, template) in inputs {
+            let diagnostic =
+                |variable: Option<&str>, severity, message: String| TemplateDiagnostic {
+                    action_id: action.action_id.clone(),
+                    action_label: action.label.clone(),
+                    input: input.clone(),
+                    variable: variable.map(str::to_string),
+                    severity,
+                    message,
+                };
+
+            let mut templater = Templater::new();
+            templater.add_template(input, template.clone());
+
+            let variables = match templater.get_template_variables(input) {
+                Ok(variables) => variables,
+                Err(e) => {
+                    diagnostics.push(diagnostic(
+                        None,
+                        DiagnosticSeverity::Error,
+                        format!("Invalid template: {}", e.message),
+                    ));
+                    continue;
+                }
+            };
+
+            for variable in variables {
+                let problem = check_variable(
+                    &variable,
+                    action,
+                    &action_ids,
+                    &upstream,
+                    in_loop,
+                    &system_variables,
+                    account_references,
+                );
+
+                if let Some((severity, message)) = problem {
+                    diagnostics.push(diagnostic(Some(&variable), severity, message));
+                }
+            }
+        }
+    }
+
+    diagnostics
+}
+
+/// True when any diagnostic will fail or render wrong at runtime
+pub fn has_errors(diagnostics: &[TemplateDiagnostic]) -> bool {
+    diagnostics
+        .iter()
+        .any(|diagnostic| diagnostic.severity == DiagnosticSeverity::Error)
+}
+
+fn check_variable(
+    variable: &str,
+    action: &Action,
+    action_ids: &HashSet<&str>,
+    upstream: &HashSet<String>,
+    in_loop: bool,
+    system_variables: &std::collections::HashMap<String, Value>,
+    account_references: Option<&AccountReferences>,
+) -> Option<(DiagnosticSeverity, String)> {
+    let selectors = match parse_path(variable) {
+        Ok(selectors) => selectors,
+        Err(e) => return Some((DiagnosticSeverity::Error, e)),
+    };
+
+    let (Some(PathSelector::Key(root)), name) = (selectors.first(), selectors.get(1)) else {
+        return Some((
+            DiagnosticSeverity::Error,
+            "Variables must start with a name like actions or secrets".to_string(),
+        ));
+    };
+    let name = match name {
+        Some(PathSelector::Key(name)) => Some(name.as_str()),
+        _ => None,
+    };
+
+    match (root.as_str(), name) {
+        ("actions", Some(action_id)) => {
+            if action_id == action.action_id {
+                Some((
+                    DiagnosticSeverity::Error,
+                    "An action can't reference its own results".to_string(),
+                ))
+            } else if !action_ids.contains(action_id) {
+                Some((
+                    DiagnosticSeverity::Error,
+                    format!("No action with id '{}' in this workflow", action_id),
+                ))
+            } else if !upstream.contains(action_id) {
+                Some((
+                    DiagnosticSeverity::Error,
+                    format!(
+                        "Action '{}' doesn't run before this action, so its results aren't available",
+                        action_id
+                    ),
+                ))
+            } else {
+                None
+            }
+        }
+        ("secrets", Some(secret)) => account_references
+            .filter(|references| !references.secrets.contains(secret))
+            .map(|_| {
+                (
+                    DiagnosticSeverity::Error,
+                    format!("No secret named '{}'", secret),
+                )
+            }),
+        ("accounts", Some(slug)) => account_references
+            .filter(|references| !references.accounts.contains(slug))
+            .map(|_| {
+                (
+                    DiagnosticSeverity::Error,
+                    format!("No connected account '{}'", slug),
+                )
+            }),
+        ("files", Some(_)) => match file_requirement_from_path(variable) {
+            None => Some((
+                DiagnosticSeverity::Error,
+                "Files are referenced as files.<name>.<extension>.file_url or .file_base64"
+                    .to_string(),
+            )),
+            Some(requirement) => account_references
+                .filter(|references| {
+                    !references
+                        .files
+                        .contains(&requirement.file_name_with_extension)
+                })
+                .map(|_| {
+                    (
+                        DiagnosticSeverity::Error,
+                        format!("No file named '{}'", requirement.file_name_with_extension),
+                    )
+                }),
+        },
+        ("system", Some(name)) if !system_variables.contains_key(name) => Some((
+            DiagnosticSeverity::Error,
+            format!("No system variable '{}'", name),
+        )),
+        ("system", Some(_)) => None,
+        ("loop", _) if !in_loop => Some((
+            DiagnosticSeverity::Warning,
+            "loop is only set for actions inside a loop".to_string(),
+        )),
+        ("loop", _) => None,
+        ("actions" | "secrets" | "accounts" | "files" | "system", None) => Some((
+            DiagnosticSeverity::Error,
+            format!("{} must be followed by a name", root),
+        )),
+        _ => Some((
+            DiagnosticSeverity::Error,
+            format!(
+                "Unknown variable '{}', variables start with actions, secrets, accounts, files, loop or system",
+                root
+            ),
+        )),
+    }
+}
+
+/// Gets a stored flow version's definition as the signed in user
+pub async fn fetch_flow_definition(
+    state: &AppState,
+    user: &User,
+    account_id: &str,
+    workflow_version_id: &str,
+) -> Result<Option<WorkflowVersionDefinition>, String> {
+    let response = state
+        .anything_client
+        .from("flow_versions")
+        .auth(user.jwt.clone())
+        .eq("flow_version_id", workflow_version_id)
+        .eq("account_id", account_id)
+        .select("flow_definition")
+        .execute()
+        .await
+        .map_err(|e| format!("Failed to execute request: {}", e))?;
+
+    let body = response
+        .text()
+        .await
+        .map_err(|e| format!("Failed to read response body: {}", e))?;
+
+    let versions: Vec<Value> =
+        serde_json::from_str(&body).map_err(|e| format!("Failed to parse flow version: {}", e))?;
+
+    versions
+        .into_iter()
+        .next()
+        .map(|version| {
+            serde_json::from_value(version["flow_definition"].clone())
+                .map_err(|e| format!("Failed to parse flow definition: {}", e))
+        })
+        .transpose()
+}
+
+/// Checks the variable references in a flow version against the workflow and the account
+pub async fn analyze_workflow_version(
+    Path((account_id, _workflow_id, workflow_version_id)): Path<(String, String, String)>,
+    State(state): State<Arc<AppState>>,
+    Extension(user): Extension<User>,
+) -> impl IntoResponse {
+    println!(
+        "[WORKFLOW ANALYSIS] Analyzing flow version {}",
+        workflow_version_id
+    );
+
+    let workflow =
+        match fetch_flow_definition(&state, &user, &account_id, &workflow_version_id).await {
+            Ok(Some(workflow)) => workflow,
+            Ok(None) => return (StatusCode::NOT_FOUND, "Flow version not found").into_response(),
+            Err(e) => {
+                println!("[WORKFLOW ANALYSIS] {}", e);
+                return (StatusCode::INTERNAL_SERVER_ERROR, e).into_response();
+            }
+        };
+
+    let account_references = match AccountReferences::fetch(state.clone(), &account_id).await {
+        Ok(references) => references,
+        Err(e) => {
+            println!("[WORKFLOW ANALYSIS] {}", e);
+            return (StatusCode::INTERNAL_SERVER_ERROR, e).into_response();
+        }
+    };
+
+    let diagnostics = analyze_workflow(&workflow, Some(&account_references));
+
+    Json(json!({
+        "valid": !has_errors(&diagnostics),
+        "diagnostics": diagnostics,
+    }))
+    .into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::react_flow_types::Edge;
+
+    fn action(action_id: &str, inputs: Value) -> Action {
+        serde_json::from_value(json!({
+            "anything_action_version": "0.1.0",
+            "type": "action",
+            "plugin_name": "@anything/http",
+            "plugin_version": "0.1.0",
+            "action_id": action_id,
+            "label": action_id,
+            "icon": "",
+            "inputs": inputs,
+            "plugin_config": {},
+            "plugin_config_schema": {}
+        }))
+        .unwrap()
+    }
+
+    fn edge(source: &str, target: &str) -> Edge {
+        Edge {
+            id: format!("{}->{}", source, target),
+            source: source.to_string(),
+            source_handle: None,
+            target: target.to_string(),
+            target_handle: None,
+            r#type: "anything".to_string(),
+        }
+    }
+
+    fn workflow() -> WorkflowVersionDefinition {
+        WorkflowVersionDefinition {
+            actions: vec![
+                action("trigger", json!({})),
+                action(
+                    "fetch",
+                    json!({
+                        "url": "https://example.com/{{ actions.trigger.result.id }}",
+                        "headers": { "Authorization": "Bearer {{secrets.API_KEY}}" },
+                        "later": "{{actions.notify.result}}"
+                    }),
+                ),
+                action(
+                    "notify",
+                    json!({
+                        "body": "{{ actions.fetch.result.items[*].name | join:\", \" }}",
+                        "missing": "{{actions.old_id.result}}",
+                        "token": "{{accounts.slack.access_token}}",
+                        "file": "{{files.logo.png.file_url}}",
+                        "broken": "{{actions.fetch.result | shout}}",
+                        "item": "{{loop.item}}",
+                        "unknown": "{{inputs.url}}"
+                    }),
+                ),
+            ],
+            edges: vec![edge("trigger", "fetch"), edge("fetch", "notify")],
+        }
+    }
+
+    fn problems(diagnostics: &[TemplateDiagnostic]) -> Vec<(&str, &str, DiagnosticSeverity)> {
+        let mut problems: Vec<_> = diagnostics
+            .iter()
+            .map(|d| (d.action_id.as_str(), d.input.as_str(), d.severity))
+            .collect();
+        problems.sort_by(|a, b| (a.0, a.1).cmp(&(b.0, b.1)));
+        problems
+    }
+
+    #[test]
+    fn test_analyze_workflow_references() {
+        let diagnostics = analyze_workflow(&workflow(), None);
+
+        assert_eq!(
+            problems(&diagnostics),
+            vec![
+                ("fetch", "later", DiagnosticSeverity::Error),
+                ("notify", "broken", DiagnosticSeverity::Error),
+                ("notify", "item", DiagnosticSeverity::Warning),
+                ("notify", "missing", DiagnosticSeverity::Error),
+                ("notify", "unknown", DiagnosticSeverity::Error),
+            ]
+        );
+        assert!(has_errors(&diagnostics));
+    }
+
+    #[test]
+    fn test_analyze_workflow_account_references() {
+        let mut references = AccountReferences::default();
+        references.secrets.insert("OTHER_KEY".to_string());
+        references.accounts.insert("slack".to_string());
+
+        let diagnostics = analyze_workflow(&workflow(), Some(&references));
+        let account_problems: Vec<_> = diagnostics
+            .iter()
+            .filter(|d| d.input == "headers" || d.input == "token" || d.input == "file")
+            .map(|d| (d.input.as_str(), d.variable.as_deref()))
+            .collect();
+
+        assert_eq!(
+            account_problems,
+            vec![
+                ("headers", Some("secrets.API_KEY")),
+                ("file", Some("files.logo.png.file_url")),
+            ]
+        );
+    }
+}
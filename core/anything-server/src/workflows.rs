@@ -20,6 +20,9 @@ use chrono::Utc;
 
 use crate::agents::tools::update_agent_tool_if_needed_on_workflow_publish;
 use crate::system_workflows::create_workflow_from_template;
+use crate::workflow_analysis::{
+    analyze_workflow, fetch_flow_definition, has_errors, AccountReferences,
+};
 #[derive(Debug, Deserialize, Serialize)]
 pub struct BaseFlowVersionInput {
     account_id: String,
@@ -674,6 +677,17 @@ pub async fn update_workflow_version(
 ) -> impl IntoResponse {
     let client = &state.anything_client;
 
+    // Drafts are saved while they are being edited so broken references don't block the save,
+    // publishing is where they are enforced
+    if let Ok(workflow) = serde_json::from_value::<WorkflowVersionDefinition>(payload.clone()) {
+        for diagnostic in analyze_workflow(&workflow, None) {
+            println!(
+                "[WORKFLOW ANALYSIS] Flow version {} action {} input {}: {}",
+                workflow_version_id, diagnostic.action_id, diagnostic.input, diagnostic.message
+            );
+        }
+    }
+
     // Check if the flow_version is published
     let is_flow_version_published_resopnse = match client
         .from("flow_versions")
@@ -853,6 +867,49 @@ pub async fn publish_workflow_version(
 
     let client = &state.anything_client;
 
+    // Refuse to publish a version whose variable references would fail at runtime
+    let workflow =
+        match fetch_flow_definition(&state, &user, &account_id, &workflow_version_id).await {
+            Ok(Some(workflow)) => workflow,
+            Ok(None) => return (StatusCode::NOT_FOUND, "Flow version not found").into_response(),
+            Err(err) => {
+                eprintln!("Error: {}", err);
+                return (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "Failed to fetch flow version",
+                )
+                    .into_response();
+            }
+        };
+
+    let account_references = match AccountReferences::fetch(state.clone(), &account_id).await {
+        Ok(references) => references,
+        Err(err) => {
+            eprintln!("Error: {}", err);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed to fetch account references",
+            )
+                .into_response();
+        }
+    };
+
+    let diagnostics = analyze_workflow(&workflow, Some(&account_references));
+    if has_errors(&diagnostics) {
+        println!(
+            "Not publishing flow version {} with invalid references",
+            workflow_version_id
+        );
+        return (
+            StatusCode::UNPROCESSABLE_ENTITY,
+            Json(serde_json::json!({
+                "error": "Workflow has invalid variable references",
+                "diagnostics": diagnostics,
+            })),
+        )
+            .into_response();
+    }
+
     let unpublish_json = serde_json::json!({
         "published": false,
         "un_published": true,
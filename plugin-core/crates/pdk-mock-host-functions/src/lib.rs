@@ -1,66 +1,106 @@
-use anything_pdk::{AnythingPlugin, Event, Handle, Log};
+use anything_pdk::host::{
+    AccountRequest, AccountResponse, EventCreated, HttpRequest, HttpResponse, LogEntry,
+    SecretRequest, SecretResponse, HOST_API_VERSION,
+};
+use anything_pdk::{Event, Log};
 use extism_pdk::Memory;
-use serde::{Deserialize, Serialize};
-
-// #[derive(Deserialize, Serialize)]
-// struct Log {
-//     pub time: String,
-//     pub message: String,
-// }
-
-// #[derive(Deserialize, Serialize)]
-// struct Event {
-//     pub id: String,
-//     pub name: String,
-//     pub description: String,
-//     pub timestamp: String,
-// }
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::json;
+use std::collections::HashMap;
 
-#[no_mangle]
-pub extern "C" fn host_log(log_ptr: i64) -> i64 {
+// Reads the JSON a plugin passed to a host function
+fn read_input<T: DeserializeOwned>(ptr: i64) -> T {
     // Find the memory at the given pointer
-    let log_mem = Memory::find(log_ptr as u64).expect("can't find log memory");
+    let mem = Memory::find(ptr as u64).expect("can't find input memory");
 
     // Convert memory to a string
-    let log_msg = log_mem.to_string().expect("bad data?");
+    let data = mem.to_string().expect("bad data?");
 
-    // Create a log structure or message
-    let new_log = Log {
-        time: "2021-09-01".to_string(),
-        message: log_msg,
-    };
+    serde_json::from_str(&data).expect("deserialization failed")
+}
 
-    // Serialize the log to JSON
-    let output = serde_json::to_vec(&new_log).expect("serialization failed");
+// Hands JSON back to the plugin, returning the offset of its memory
+fn write_output<T: Serialize>(output: &T) -> i64 {
+    let output = serde_json::to_vec(output).expect("serialization failed");
 
     // Create new memory for the output
     let output_mem = Memory::from_bytes(&output);
 
     // Return the offset of the new memory as an i64
-    return output_mem.expect("cant return offset").offset() as i64;
+    output_mem.expect("cant return offset").offset() as i64
 }
 
 #[no_mangle]
-pub extern "C" fn create_event(event_ptr: i64) -> i64 {
-    // Find the memory at the given pointer
-    let event_mem = Memory::find(event_ptr as u64).expect("can't find event memory");
+pub extern "C" fn host_api_version() -> i64 {
+    let output_mem = Memory::from_bytes(HOST_API_VERSION.to_string());
+    output_mem.expect("cant return offset").offset() as i64
+}
 
-    // Convert memory to a string
-    let event_data = event_mem.to_string().expect("bad data?");
+#[no_mangle]
+pub extern "C" fn host_log(entry_ptr: i64) -> i64 {
+    let entry: LogEntry = read_input(entry_ptr);
 
-    // Deserialize the event data to the Event struct
-    let event: Event = serde_json::from_str(&event_data).expect("deserialization failed");
+    let new_log = Log {
+        time: "2021-09-01".to_string(),
+        message: entry.message,
+    };
 
-    // Here you can perform any operations you need with the event
-    // For example, log the event details or store them somewhere
-    // println!("Received event: {:?}", event);
+    write_output(&new_log)
+}
 
-    // Serialize the event back to JSON
-    let output = serde_json::to_vec(&event).expect("serialization failed");
+#[no_mangle]
+pub extern "C" fn host_http_request(request_ptr: i64) -> i64 {
+    let request: HttpRequest = read_input(request_ptr);
 
-    // Create new memory for the output
-    let output_mem = Memory::from_bytes(&output);
+    // Echo the request back so plugins can assert on what they sent
+    let response = HttpResponse {
+        status: 200,
+        headers: HashMap::from([("content-type".to_string(), "application/json".to_string())]),
+        body: json!({
+            "method": request.method,
+            "url": request.url,
+            "body": request.body,
+        })
+        .to_string(),
+    };
 
-    // Return the offset of the new memory as an i64
-    return output_mem.expect("cant return offset").offset() as i64;
+    write_output(&response)
+}
+
+#[no_mangle]
+pub extern "C" fn host_get_secret(request_ptr: i64) -> i64 {
+    let request: SecretRequest = read_input(request_ptr);
+
+    let response = SecretResponse {
+        value: Some(format!("mock_{}", request.name)),
+    };
+
+    write_output(&response)
+}
+
+#[no_mangle]
+pub extern "C" fn host_get_account(request_ptr: i64) -> i64 {
+    let request: AccountRequest = read_input(request_ptr);
+
+    let response = AccountResponse {
+        account: Some(json!({
+            "account_auth_provider_account_slug": request.slug,
+            "access_token": "mock_access_token",
+        })),
+    };
+
+    write_output(&response)
+}
+
+#[no_mangle]
+pub extern "C" fn create_event(event_ptr: i64) -> i64 {
+    // Deserialize the event data to the Event struct
+    let event: Event = read_input(event_ptr);
+
+    let created = EventCreated {
+        flow_session_id: format!("mock_session_{}", event.id),
+    };
+
+    write_output(&created)
 }
@@ -3,11 +3,6 @@ use extism_pdk::*;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
-#[host_fn]
-extern "ExtismHost" {
-    fn host_log(log: String) -> Json<Log>;
-}
-
 #[plugin_fn]
 pub fn execute(config: Value) -> FnResult<Value> {
     Ok(config)
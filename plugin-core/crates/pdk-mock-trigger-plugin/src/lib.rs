@@ -1,26 +1,22 @@
+use anything_pdk::host::emit_event;
 use anything_pdk::{AnythingPlugin, Event, Handle, Log};
 use extism_pdk::*;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
-#[host_fn]
-extern "ExtismHost" {
-    fn create_event(event: Json<Event>) -> String;
-}
-
 #[plugin_fn]
 pub fn execute(config: Value) -> FnResult<Value> {
     //TODO: Determine what events need to be created. Create Them
-    //TODO: host function for creating a real event not just stubbed string
     let event = Event {
         id: "1".to_string(),
         name: "Test Event".to_string(),
         description: "This is a test event".to_string(),
         timestamp: "2021-01-01T00:00:00Z".to_string(),
+        payload: config,
     };
 
     //Call Create Event
-    let _res = unsafe { create_event(Json(event))? };
+    let _res = emit_event(event)?;
 
     let res = serde_json::json!({
         "status": "success",
@@ -31,6 +27,28 @@ pub fn execute(config: Value) -> FnResult<Value> {
     Ok(res)
 }
 
+// Called with `{config, request}` when a request hits the trigger's callback url
+#[plugin_fn]
+pub fn on_callback(input: Value) -> FnResult<Value> {
+    let event = Event {
+        id: "2".to_string(),
+        name: "Test Callback Event".to_string(),
+        description: "This is a test event from a callback".to_string(),
+        timestamp: "2021-01-01T00:00:00Z".to_string(),
+        payload: input["request"]["body"].clone(),
+    };
+
+    let flow_session_id = emit_event(event)?;
+
+    let res = serde_json::json!({
+        "status": "success",
+        "output": { "flow_session_id": flow_session_id },
+        "error": {},
+    });
+
+    Ok(res)
+}
+
 #[plugin_fn]
 pub fn register() -> FnResult<AnythingPlugin> {
     //Used to let UI and users know how to configure actions
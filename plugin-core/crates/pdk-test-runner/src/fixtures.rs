@@ -0,0 +1,208 @@
+use serde::Deserialize;
+use serde_json::Value;
+use std::fs;
+use std::path::Path;
+
+/// One recorded case a plugin's `execute` is run against, read from a `.json` file
+#[derive(Debug, Clone, Deserialize)]
+pub struct Fixture {
+    #[serde(default)]
+    pub name: String, // The file name when not set
+    pub config: Value,
+    #[serde(default)]
+    pub inputs: Value, // Fills the `{{inputs.*}}` references in config, like the server's bundler
+    pub expected_output: Option<Value>, // Only the fields listed are compared
+    pub expected_error: Option<String>, // Part of the error message the plugin returns
+    pub expected_events: Option<usize>, // Events a trigger creates through create_event
+}
+
+/// Reads every fixture in a directory, in file name order
+pub fn load_fixtures(dir: &Path) -> Result<Vec<Fixture>, String> {
+    let entries = fs::read_dir(dir)
+        .map_err(|e| format!("Can't read fixtures from {}: {}", dir.display(), e))?;
+
+    let mut paths: Vec<_> = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().and_then(|ext| ext.to_str()) == Some("json"))
+        .collect();
+    paths.sort();
+
+    paths
+        .iter()
+        .map(|path| {
+            let data = fs::read_to_string(path)
+                .map_err(|e| format!("Can't read {}: {}", path.display(), e))?;
+            let mut fixture: Fixture = serde_json::from_str(&data)
+                .map_err(|e| format!("Invalid fixture {}: {}", path.display(), e))?;
+            if fixture.name.is_empty() {
+                fixture.name = path
+                    .file_stem()
+                    .and_then(|stem| stem.to_str())
+                    .unwrap_or_default()
+                    .to_string();
+            }
+            Ok(fixture)
+        })
+        .collect()
+}
+
+/// Replaces `{{inputs.path}}` references in a config. A string that is only a reference
+/// takes the input's JSON value, references inside longer strings are inserted as text.
+pub fn render_config(config: &Value, inputs: &Value) -> Value {
+    match config {
+        Value::String(text) => render_string(text, inputs),
+        Value::Array(items) => Value::Array(
+            items
+                .iter()
+                .map(|item| render_config(item, inputs))
+                .collect(),
+        ),
+        Value::Object(fields) => Value::Object(
+            fields
+                .iter()
+                .map(|(key, value)| (key.clone(), render_config(value, inputs)))
+                .collect(),
+        ),
+        other => other.clone(),
+    }
+}
+
+fn render_string(text: &str, inputs: &Value) -> Value {
+    // `{{inputs.a}} {{inputs.b}}` also starts and ends with braces, but isn't one reference
+    if let Some(path) = reference_path(text.trim()).filter(|path| !path.contains("}}")) {
+        return lookup(inputs, path).cloned().unwrap_or(Value::Null);
+    }
+
+    let mut rendered = String::new();
+    let mut rest = text;
+    while let Some(start) = rest.find("{{") {
+        let Some(end) = rest[start..].find("}}").map(|end| start + end + 2) else {
+            break;
+        };
+        rendered.push_str(&rest[..start]);
+        match reference_path(&rest[start..end]).and_then(|path| lookup(inputs, path)) {
+            Some(Value::String(value)) => rendered.push_str(value),
+            Some(value) => rendered.push_str(&value.to_string()),
+            None => rendered.push_str(&rest[start..end]),
+        }
+        rest = &rest[end..];
+    }
+    rendered.push_str(rest);
+
+    Value::String(rendered)
+}
+
+fn reference_path(reference: &str) -> Option<&str> {
+    reference
+        .strip_prefix("{{")?
+        .strip_suffix("}}")?
+        .trim()
+        .strip_prefix("inputs.")
+}
+
+fn lookup<'a>(value: &'a Value, path: &str) -> Option<&'a Value> {
+    path.split('.').try_fold(value, |value, key| match value {
+        Value::Array(items) => items.get(key.parse::<usize>().ok()?),
+        _ => value.get(key),
+    })
+}
+
+/// Describes the first field of `expected` that `actual` doesn't have. Objects only
+/// compare the fields listed in `expected`, everything else has to be equal.
+pub fn find_mismatch(expected: &Value, actual: &Value, path: &str) -> Option<String> {
+    match (expected, actual) {
+        (Value::Object(expected), Value::Object(actual)) => {
+            expected.iter().find_map(|(key, expected)| {
+                let path = format!("{}.{}", path, key);
+                match actual.get(key) {
+                    Some(actual) => find_mismatch(expected, actual, &path),
+                    None => Some(format!("{}: missing, expected {}", path, expected)),
+                }
+            })
+        }
+        (expected, actual) if expected == actual => None,
+        (expected, actual) => Some(format!("{}: expected {}, got {}", path, expected, actual)),
+    }
+}
+
+/// Picks the schema of `output` out of an `output_schema` describing the `{status, output, error}`
+/// envelope, the same way the server does for the task result it stores. Other schemas are used as they are.
+pub fn envelope_output_schema(schema: &Value) -> Value {
+    let properties = schema.get("properties");
+    match (
+        properties.and_then(|properties| properties.get("status")),
+        properties.and_then(|properties| properties.get("output")),
+    ) {
+        (Some(_), Some(output)) => output.clone(),
+        _ => schema.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_render_config() {
+        let inputs = json!({ "user": { "name": "Ada", "id": 7 }, "tags": ["a", "b"] });
+        let config = json!({
+            "user_id": "{{inputs.user.id}}",
+            "greeting": "Hello {{inputs.user.name}} ({{inputs.user.id}})",
+            "first_tag": "{{ inputs.tags.0 }}",
+            "missing": "{{inputs.nothing}}",
+            "literal": "{{secrets.API_KEY}}",
+        });
+
+        assert_eq!(
+            render_config(&config, &inputs),
+            json!({
+                "user_id": 7,
+                "greeting": "Hello Ada (7)",
+                "first_tag": "a",
+                "missing": null,
+                "literal": "{{secrets.API_KEY}}",
+            })
+        );
+    }
+
+    #[test]
+    fn test_find_mismatch() {
+        let actual = json!({ "status": 200, "body": { "id": 1, "tags": ["a"] }, "headers": {} });
+
+        assert_eq!(find_mismatch(&json!({ "status": 200 }), &actual, "$"), None);
+        assert_eq!(
+            find_mismatch(&json!({ "body": { "id": 2 } }), &actual, "$"),
+            Some("$.body.id: expected 2, got 1".to_string())
+        );
+        assert_eq!(
+            find_mismatch(&json!({ "body": { "tags": [] } }), &actual, "$"),
+            Some("$.body.tags: expected [], got [\"a\"]".to_string())
+        );
+        assert_eq!(
+            find_mismatch(&json!({ "error": {} }), &actual, "$"),
+            Some("$.error: missing, expected {}".to_string())
+        );
+    }
+
+    #[test]
+    fn test_envelope_output_schema() {
+        let envelope = json!({
+            "type": "object",
+            "properties": {
+                "status": { "type": "string", "enum": ["success", "error"] },
+                "output": { "type": "object", "required": ["id"] },
+                "error": { "type": "object" }
+            },
+            "required": ["status"]
+        });
+        assert_eq!(
+            envelope_output_schema(&envelope),
+            json!({ "type": "object", "required": ["id"] })
+        );
+
+        let output = json!({ "type": "object", "properties": { "output": { "type": "string" } } });
+        assert_eq!(envelope_output_schema(&output), output);
+    }
+}
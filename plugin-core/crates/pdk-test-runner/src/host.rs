@@ -0,0 +1,105 @@
+use anything_pdk::host::{
+    AccountRequest, AccountResponse, EventCreated, HttpRequest, HttpResponse, LogEntry,
+    SecretRequest, SecretResponse, HOST_API_VERSION,
+};
+use anything_pdk::{Event, Log};
+use extism::convert::Json;
+use extism::{host_fn, Function, UserData, PTR};
+use serde_json::json;
+use std::collections::HashMap;
+
+/// What a plugin did through the host functions during one call
+#[derive(Default)]
+pub struct HostCalls {
+    pub events: Vec<Event>, // Created by triggers through create_event
+}
+
+host_fn!(host_api_version() -> String {
+    Ok(HOST_API_VERSION.to_string())
+});
+
+host_fn!(host_log(entry: Json<LogEntry>) -> Json<Log> {
+    let Json(entry) = entry;
+    Ok(Json(Log {
+        time: "2021-09-01".to_string(),
+        message: entry.message,
+    }))
+});
+
+// No requests leave the runner, the request is echoed back so fixtures can assert on it
+host_fn!(host_http_request(request: Json<HttpRequest>) -> Json<HttpResponse> {
+    let Json(request) = request;
+    Ok(Json(HttpResponse {
+        status: 200,
+        headers: HashMap::from([("content-type".to_string(), "application/json".to_string())]),
+        body: json!({
+            "method": request.method,
+            "url": request.url,
+            "body": request.body,
+        })
+        .to_string(),
+    }))
+});
+
+host_fn!(host_get_secret(request: Json<SecretRequest>) -> Json<SecretResponse> {
+    let Json(request) = request;
+    Ok(Json(SecretResponse {
+        value: Some(format!("mock_{}", request.name)),
+    }))
+});
+
+host_fn!(host_get_account(request: Json<AccountRequest>) -> Json<AccountResponse> {
+    let Json(request) = request;
+    Ok(Json(AccountResponse {
+        account: Some(json!({
+            "account_auth_provider_account_slug": request.slug,
+            "access_token": "mock_access_token",
+        })),
+    }))
+});
+
+host_fn!(create_event(calls: HostCalls; event: Json<Event>) -> Json<EventCreated> {
+    let Json(event) = event;
+    let created = EventCreated {
+        flow_session_id: format!("mock_session_{}", event.id),
+    };
+    calls.get()?.lock().unwrap().events.push(event);
+    Ok(Json(created))
+});
+
+/// Stand ins for the host functions the Anything server links into plugins,
+/// answering like the xtp mocks in `pdk-mock-host-functions`
+pub fn stub_host_functions(calls: &UserData<HostCalls>) -> Vec<Function> {
+    vec![
+        Function::new(
+            "host_api_version",
+            [],
+            [PTR],
+            UserData::new(()),
+            host_api_version,
+        ),
+        Function::new("host_log", [PTR], [PTR], UserData::new(()), host_log),
+        Function::new(
+            "host_http_request",
+            [PTR],
+            [PTR],
+            UserData::new(()),
+            host_http_request,
+        ),
+        Function::new(
+            "host_get_secret",
+            [PTR],
+            [PTR],
+            UserData::new(()),
+            host_get_secret,
+        ),
+        Function::new(
+            "host_get_account",
+            [PTR],
+            [PTR],
+            UserData::new(()),
+            host_get_account,
+        ),
+        Function::new("create_event", [PTR], [PTR], calls.clone(), create_event),
+    ]
+}
@@ -0,0 +1,274 @@
+mod fixtures;
+mod host;
+mod report;
+
+use anything_pdk::AnythingPlugin;
+use extism::{Manifest, Plugin, PluginBuilder, UserData, Wasm};
+use jsonschema::JSONSchema;
+use serde_json::Value;
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::time::Instant;
+
+use fixtures::{envelope_output_schema, find_mismatch, load_fixtures, render_config, Fixture};
+use host::{stub_host_functions, HostCalls};
+use report::{TestCase, TestSuite};
+
+const USAGE: &str =
+    "Usage: anything-pdk-test-runner <plugin.wasm> [fixtures_dir] [--report <junit.xml>]";
+
+struct Args {
+    plugin: PathBuf,
+    fixtures: Option<PathBuf>,
+    report: Option<PathBuf>,
+}
+
+fn parse_args() -> Result<Args, String> {
+    let mut positional = Vec::new();
+    let mut report = None;
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--report" => report = Some(PathBuf::from(args.next().ok_or(USAGE)?)),
+            "-h" | "--help" => return Err(USAGE.to_string()),
+            _ => positional.push(PathBuf::from(arg)),
+        }
+    }
+
+    let mut positional = positional.into_iter();
+    Ok(Args {
+        plugin: positional.next().ok_or(USAGE)?,
+        fixtures: positional.next(),
+        report,
+    })
+}
+
+fn main() -> ExitCode {
+    let args = match parse_args() {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("{}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let suite = match run(&args) {
+        Ok(suite) => suite,
+        Err(e) => {
+            eprintln!("{}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    for case in &suite.cases {
+        let status = if case.failures.is_empty() {
+            "ok"
+        } else {
+            "FAILED"
+        };
+        println!("test {} ... {}", case.name, status);
+        for failure in &case.failures {
+            println!("    {}", failure);
+        }
+    }
+    println!(
+        "\n{}: {} passed; {} failed",
+        suite.name,
+        suite.cases.len() - suite.failures(),
+        suite.failures()
+    );
+
+    if let Some(report) = &args.report {
+        if let Err(e) = fs::write(report, suite.to_junit()) {
+            eprintln!("Can't write report to {}: {}", report.display(), e);
+            return ExitCode::FAILURE;
+        }
+    }
+
+    if suite.failures() > 0 {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
+
+fn run(args: &Args) -> Result<TestSuite, String> {
+    let fixtures = match &args.fixtures {
+        Some(dir) => load_fixtures(dir)?,
+        None => vec![],
+    };
+
+    let calls = UserData::new(HostCalls::default());
+    let manifest = Manifest::new([Wasm::file(&args.plugin)]);
+    let mut plugin = PluginBuilder::new(manifest)
+        .with_wasi(false)
+        .with_functions(stub_host_functions(&calls))
+        .build()
+        .map_err(|e| format!("Can't load {}: {}", args.plugin.display(), e))?;
+
+    let started = Instant::now();
+    let registration = plugin
+        .call::<&str, String>("register", "")
+        .map_err(|e| e.to_string())
+        .and_then(|registration| {
+            serde_json::from_str::<AnythingPlugin>(&registration).map_err(|e| e.to_string())
+        })
+        .map_err(|e| format!("register failed: {}", e))?;
+
+    let mut suite = TestSuite {
+        name: registration.plugin_id.clone(),
+        cases: vec![TestCase {
+            name: "register".to_string(),
+            failures: check_registration(&registration),
+            duration: started.elapsed(),
+        }],
+    };
+
+    for fixture in &fixtures {
+        let started = Instant::now();
+        let failures = run_fixture(&mut plugin, &calls, &registration, fixture);
+        suite.cases.push(TestCase {
+            name: fixture.name.clone(),
+            failures,
+            duration: started.elapsed(),
+        });
+    }
+
+    Ok(suite)
+}
+
+/// What the server needs from `register` to list the plugin and render its config form
+fn check_registration(registration: &AnythingPlugin) -> Vec<String> {
+    let mut failures = Vec::new();
+
+    let required = [
+        ("plugin_id", &registration.plugin_id),
+        ("label", &registration.label),
+        ("icon", &registration.icon),
+        ("description", &registration.description),
+    ];
+    for (field, value) in required {
+        if value.is_empty() {
+            failures.push(format!("{} is empty", field));
+        }
+    }
+
+    failures.extend(schema_errors(
+        "input",
+        &registration.input_schema,
+        &registration.input,
+    ));
+    if let Err(e) = JSONSchema::compile(&registration.output_schema) {
+        failures.push(format!("output_schema is invalid: {}", e));
+    }
+
+    failures
+}
+
+fn run_fixture(
+    plugin: &mut Plugin,
+    calls: &UserData<HostCalls>,
+    registration: &AnythingPlugin,
+    fixture: &Fixture,
+) -> Vec<String> {
+    let config = render_config(&fixture.config, &fixture.inputs);
+    let mut failures = schema_errors("config", &registration.input_schema, &config);
+
+    if let Ok(calls) = calls.get() {
+        *calls.lock().unwrap() = HostCalls::default();
+    }
+
+    let result = match plugin.call::<&str, String>("execute", &config.to_string()) {
+        Ok(result) => result,
+        Err(e) => {
+            failures.push(format!("execute failed: {}", e));
+            return failures;
+        }
+    };
+    let result: Value = match serde_json::from_str(&result) {
+        Ok(result) => result,
+        Err(e) => {
+            failures.push(format!("execute returned invalid JSON: {}", e));
+            return failures;
+        }
+    };
+
+    // The server reads `{status, output, error}`, plugins without it return their output directly
+    let (output, error) = match result.get("status").and_then(Value::as_str) {
+        Some("error") => (None, result.get("error").cloned()),
+        Some(_) => (result.get("output").cloned(), None),
+        None => (Some(result), None),
+    };
+
+    // Checked the way the server describes the stored result to later actions
+    if let Some(output) = &output {
+        failures.extend(schema_errors(
+            "output",
+            &envelope_output_schema(&registration.output_schema),
+            output,
+        ));
+    }
+
+    match (&fixture.expected_error, &error) {
+        (Some(expected), Some(error)) => {
+            let message = error
+                .get("message")
+                .and_then(Value::as_str)
+                .map(str::to_string)
+                .unwrap_or_else(|| error.to_string());
+            if !message.contains(expected.as_str()) {
+                failures.push(format!(
+                    "expected an error containing {:?}, got {:?}",
+                    expected, message
+                ));
+            }
+        }
+        (Some(expected), None) => {
+            failures.push(format!("expected an error containing {:?}", expected))
+        }
+        (None, Some(error)) => failures.push(format!("execute returned an error: {}", error)),
+        (None, None) => {}
+    }
+
+    if let Some(expected) = &fixture.expected_output {
+        match &output {
+            Some(output) => failures.extend(find_mismatch(expected, output, "$.output")),
+            None => failures.push("expected output, got none".to_string()),
+        }
+    }
+
+    if let Some(expected) = fixture.expected_events {
+        let events = calls
+            .get()
+            .map(|calls| calls.lock().unwrap().events.len())
+            .unwrap_or_default();
+        if events != expected {
+            failures.push(format!("expected {} events, got {}", expected, events));
+        }
+    }
+
+    failures
+}
+
+fn schema_errors(name: &str, schema: &Value, instance: &Value) -> Vec<String> {
+    let compiled = match JSONSchema::compile(schema) {
+        Ok(compiled) => compiled,
+        Err(e) => return vec![format!("schema for {} is invalid: {}", name, e)],
+    };
+
+    let errors = match compiled.validate(instance) {
+        Ok(()) => return vec![],
+        Err(errors) => errors,
+    };
+    errors
+        .map(|error| {
+            format!(
+                "{} doesn't match its schema at {}: {}",
+                name, error.instance_path, error
+            )
+        })
+        .collect()
+}
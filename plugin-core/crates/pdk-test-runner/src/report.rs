@@ -0,0 +1,106 @@
+use std::time::Duration;
+
+pub struct TestCase {
+    pub name: String,
+    pub duration: Duration,
+    pub failures: Vec<String>, // Every check of the case that failed, empty when it passed
+}
+
+pub struct TestSuite {
+    pub name: String,
+    pub cases: Vec<TestCase>,
+}
+
+impl TestSuite {
+    pub fn failures(&self) -> usize {
+        self.cases
+            .iter()
+            .filter(|case| !case.failures.is_empty())
+            .count()
+    }
+
+    /// A JUnit XML report, the format most CI systems show test results from
+    pub fn to_junit(&self) -> String {
+        let duration: Duration = self.cases.iter().map(|case| case.duration).sum();
+
+        let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        xml.push_str(&format!(
+            "<testsuites tests=\"{}\" failures=\"{}\" time=\"{:.3}\">\n",
+            self.cases.len(),
+            self.failures(),
+            duration.as_secs_f64()
+        ));
+        xml.push_str(&format!(
+            "  <testsuite name=\"{}\" tests=\"{}\" failures=\"{}\" errors=\"0\" time=\"{:.3}\">\n",
+            escape(&self.name),
+            self.cases.len(),
+            self.failures(),
+            duration.as_secs_f64()
+        ));
+
+        for case in &self.cases {
+            let open = format!(
+                "    <testcase name=\"{}\" classname=\"{}\" time=\"{:.3}\"",
+                escape(&case.name),
+                escape(&self.name),
+                case.duration.as_secs_f64()
+            );
+            if case.failures.is_empty() {
+                xml.push_str(&format!("{}/>\n", open));
+                continue;
+            }
+            xml.push_str(&format!("{}>\n", open));
+            xml.push_str(&format!(
+                "      <failure message=\"{}\">{}</failure>\n",
+                escape(&case.failures[0]),
+                escape(&case.failures.join("\n"))
+            ));
+            xml.push_str("    </testcase>\n");
+        }
+
+        xml.push_str("  </testsuite>\n</testsuites>\n");
+        xml
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_junit() {
+        let suite = TestSuite {
+            name: "example_plugin".to_string(),
+            cases: vec![
+                TestCase {
+                    name: "register".to_string(),
+                    duration: Duration::from_millis(5),
+                    failures: vec![],
+                },
+                TestCase {
+                    name: "get_user".to_string(),
+                    duration: Duration::from_millis(20),
+                    failures: vec!["$.output.name: expected \"Ada\", got <null>".to_string()],
+                },
+            ],
+        };
+
+        let xml = suite.to_junit();
+        assert!(xml.contains(
+            "<testsuite name=\"example_plugin\" tests=\"2\" failures=\"1\" errors=\"0\" time=\"0.025\">"
+        ));
+        assert!(xml
+            .contains("<testcase name=\"register\" classname=\"example_plugin\" time=\"0.005\"/>"));
+        assert!(xml.contains(
+            "<failure message=\"$.output.name: expected &quot;Ada&quot;, got &lt;null&gt;\">"
+        ));
+    }
+}
@@ -0,0 +1,116 @@
+//! Typed wrappers around the host functions the Anything server links into every plugin.
+//! Each host function takes and returns JSON, these keep plugins from touching that by hand.
+use crate::{Event, Log};
+use extism_pdk::*;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// The host function API these wrappers are written against. Hosts bump it when a
+/// host function is removed or changes shape.
+pub const HOST_API_VERSION: u32 = 1;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct LogEntry {
+    pub level: String,
+    pub message: String,
+    pub fields: Value,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct HttpRequest {
+    pub method: String,
+    pub url: String,
+    pub headers: HashMap<String, String>,
+    pub body: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct HttpResponse {
+    pub status: u16,
+    pub headers: HashMap<String, String>,
+    pub body: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SecretRequest {
+    pub name: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SecretResponse {
+    pub value: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct AccountRequest {
+    pub slug: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct AccountResponse {
+    pub account: Option<Value>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct EventCreated {
+    pub flow_session_id: String,
+}
+
+#[host_fn]
+extern "ExtismHost" {
+    fn host_api_version() -> String;
+    fn host_log(entry: Json<LogEntry>) -> Json<Log>;
+    fn host_http_request(request: Json<HttpRequest>) -> Json<HttpResponse>;
+    fn host_get_secret(request: Json<SecretRequest>) -> Json<SecretResponse>;
+    fn host_get_account(request: Json<AccountRequest>) -> Json<AccountResponse>;
+    fn create_event(event: Json<Event>) -> Json<EventCreated>;
+}
+
+/// The host function API version of the host running the plugin
+pub fn api_version() -> FnResult<u32> {
+    let version = unsafe { host_api_version()? };
+    Ok(version.parse()?)
+}
+
+/// Adds a line to the task's debug output. `level` is one of debug, info, warn or error.
+pub fn log(level: &str, message: &str, fields: Value) -> FnResult<Log> {
+    let entry = LogEntry {
+        level: level.to_string(),
+        message: message.to_string(),
+        fields,
+    };
+    let Json(log) = unsafe { host_log(Json(entry))? };
+    Ok(log)
+}
+
+/// Makes an HTTP request through the host. Hosts only reach the hosts they allowlist.
+pub fn http_request(request: HttpRequest) -> FnResult<HttpResponse> {
+    let Json(response) = unsafe { host_http_request(Json(request))? };
+    Ok(response)
+}
+
+/// Reads one of the account's secrets by name
+pub fn get_secret(name: &str) -> FnResult<Option<String>> {
+    let request = SecretRequest {
+        name: name.to_string(),
+    };
+    let Json(response) = unsafe { host_get_secret(Json(request))? };
+    Ok(response.value)
+}
+
+/// Reads one of the account's connected auth accounts by its slug
+pub fn get_account(slug: &str) -> FnResult<Option<Value>> {
+    let request = AccountRequest {
+        slug: slug.to_string(),
+    };
+    let Json(response) = unsafe { host_get_account(Json(request))? };
+    Ok(response.account)
+}
+
+/// Starts a flow session of the workflow a trigger plugin runs for, returning its id.
+/// Reporting an event id again returns the session the event started the first time.
+pub fn emit_event(event: Event) -> FnResult<String> {
+    let Json(created) = unsafe { create_event(Json(event))? };
+    Ok(created.flow_session_id)
+}
@@ -16,6 +16,7 @@ pub struct AnythingPlugin {
     pub trigger: bool,
     pub label: String,
     pub plugin_id: String,
+    pub version: String, // Semver of this build, hosts can load several side by side
     pub icon: String,
     pub description: String,
     pub handles: Vec<Handle>,
@@ -44,6 +45,7 @@ pub struct AnythingPluginBuilder {
     input_schema: Option<Value>,
     output_schema: Option<Value>,
     plugin_id: Option<String>,
+    version: Option<String>,
 }
 
 impl AnythingPluginBuilder {
@@ -71,6 +73,7 @@ impl AnythingPluginBuilder {
             input_schema: Some(serde_json::json!({})),
             output_schema: Some(serde_json::json!({})),
             plugin_id: Some("default_plugin_id".to_string()),
+            version: Some("0.1.0".to_string()),
         }
     }
 
@@ -124,6 +127,11 @@ impl AnythingPluginBuilder {
         self
     }
 
+    pub fn version(mut self, version: String) -> Self {
+        self.version = Some(version);
+        self
+    }
+
     pub fn build(self) -> AnythingPlugin {
         AnythingPlugin {
             trigger: self.trigger.unwrap_or(false),
@@ -153,6 +161,7 @@ impl AnythingPluginBuilder {
             plugin_id: self
                 .plugin_id
                 .unwrap_or_else(|| "default_plugin_id".to_string()),
+            version: self.version.unwrap_or_else(|| "0.1.0".to_string()),
         }
     }
 }
@@ -228,6 +237,7 @@ mod tests {
                 "additionalProperties": false
             }),
             plugin_id: "example_plugin".to_string(),
+            version: "0.1.0".to_string(),
         };
 
         let plugin_from_builder: AnythingPlugin = AnythingPlugin::builder()
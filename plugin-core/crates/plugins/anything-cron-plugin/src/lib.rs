@@ -1,14 +1,10 @@
+use anything_pdk::host::emit_event;
 use anything_pdk::{AnythingPlugin, Event, Handle, Log};
 use extism_pdk::*;
 use serde::Deserialize;
 use serde_json::{json, Value};
 
 
-#[host_fn]
-extern "ExtismHost" {
-    fn create_event(event: Json<Event>) -> String;
-}
-
 #[plugin_fn]
 pub fn execute(config: Value) -> FnResult<Value> {
     //TODO: add create event
@@ -17,10 +13,11 @@ pub fn execute(config: Value) -> FnResult<Value> {
         name: "Test Event".to_string(),
         description: "This is a test event".to_string(),
         timestamp: "2021-01-01T00:00:00Z".to_string(),
+        payload: config,
     };
 
     //Call Create Event
-    let _res = unsafe { create_event(Json(event))? };
+    let _res = emit_event(event)?;
 
     let res = serde_json::json!({
         "status": "success",
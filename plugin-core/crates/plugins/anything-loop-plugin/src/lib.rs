@@ -16,6 +16,8 @@ pub fn register() -> FnResult<AnythingPlugin> {
         .variables(vec![])
         .input(serde_json::json!({
             "items": ["hello", "world", "this", "is", "a", "test"],
+            "concurrency": 1,
+            "batch_size": 1,
         })) 
         .input_schema(serde_json::json!({
             "type": "object",
@@ -23,6 +25,14 @@ pub fn register() -> FnResult<AnythingPlugin> {
                 "items": {
                     "type": "array",
                 },
+                "concurrency": {
+                    "type": "integer",
+                    "minimum": 1
+                },
+                "batch_size": {
+                    "type": "integer",
+                    "minimum": 1
+                },
             },
             "required": ["items"],
             "additionalProperties": false
@@ -50,20 +60,30 @@ pub fn register() -> FnResult<AnythingPlugin> {
     Ok(plugin)
 }
 
+//The host runs the actions between the loop and its loop end once per item.
+//The plugin only validates the items so the host knows what to iterate over.
 #[plugin_fn]
 pub fn execute(config: Value) -> FnResult<Value> {
-    //TODO: Create new events from items in the input array
-
-    //Ok so i think the way this can work is ->
-    //You query the input of the next action in the flow
-    
-    //you could provide an api for plugins to set a variable?
-    //set variable
+    let items = match config.get("items") {
+        Some(Value::Array(items)) => items.clone(),
+        _ => {
+            return Ok(json!({
+                "status": "error",
+                "output": {},
+                "error": { "message": "items must be an array" },
+            }))
+        }
+    };
 
-    let res = serde_json::json!({
+    let res = json!({
         "status": "success",
-        "output": {},
-        "error": {}, 
+        "output": {
+            "items": items,
+            "count": items.len(),
+            "concurrency": config.get("concurrency").cloned().unwrap_or(json!(1)),
+            "batch_size": config.get("batch_size").cloned().unwrap_or(json!(1)),
+        },
+        "error": {},
     });
     Ok(res)
 }
\ No newline at end of file
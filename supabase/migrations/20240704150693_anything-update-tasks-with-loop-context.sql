@@ -0,0 +1,3 @@
+-- Tasks that run inside a loop record which iteration they belong to
+ALTER TABLE anything.tasks
+ADD COLUMN loop_context jsonb;
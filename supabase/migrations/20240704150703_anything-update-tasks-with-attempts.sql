@@ -0,0 +1,3 @@
+-- Tasks with a retry policy keep a history of every attempt
+ALTER TABLE anything.tasks
+ADD COLUMN attempts jsonb;
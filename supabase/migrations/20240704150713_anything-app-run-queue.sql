@@ -0,0 +1,32 @@
+-- Durable record of every workflow run handed to the processor.
+-- Rows stay queued or running until the flow session finishes, so runs lost in a restart
+-- can be picked up again by the next server that starts.
+CREATE TABLE IF NOT EXISTS anything.run_queue
+(
+    flow_session_id uuid unique NOT NULL primary key,
+    account_id uuid not null references basejump.accounts(id),
+    flow_id uuid not null references anything.flows(flow_id),
+    flow_version_id uuid not null references anything.flow_versions(flow_version_id),
+    trigger_session_id uuid NOT NULL,
+    trigger_task jsonb, -- the trigger task with its result, needed before the task row exists
+    run_status TEXT NOT NULL DEFAULT 'queued', -- queued until the flow session completes or fails
+    claimed_by uuid, -- the server instance currently running this flow session
+    claimed_at timestamp with time zone,
+
+    -- timestamps are useful for auditing
+    -- Basejump has some convenience functions defined below for automatically handling these
+    updated_at timestamp with time zone,
+    created_at timestamp with time zone
+);
+
+CREATE INDEX IF NOT EXISTS run_queue_run_status_idx ON anything.run_queue (run_status);
+
+-- protect the timestamps by setting created_at and updated_at to be read-only and managed by a trigger
+CREATE TRIGGER set_run_queue_timestamp
+    BEFORE INSERT OR UPDATE ON anything.run_queue
+    FOR EACH ROW
+EXECUTE PROCEDURE basejump.trigger_set_timestamps();
+
+-- enable RLS on the table
+-- The queue is only read and written by the server with the service role, so no policies are added
+ALTER TABLE anything.run_queue ENABLE ROW LEVEL SECURITY;
@@ -0,0 +1,27 @@
+-- Last time each cron trigger fired.
+-- The trigger engine keeps its schedule in memory, this lets it catch up on runs missed
+-- while no server was up according to the trigger's misfire policy.
+CREATE TABLE IF NOT EXISTS anything.cron_triggers
+(
+    flow_id uuid not null references anything.flows(flow_id) ON DELETE CASCADE,
+    action_id TEXT NOT NULL,
+    account_id uuid not null references basejump.accounts(id),
+    last_fired timestamp with time zone, -- the scheduled fire time of the last run, not when it was sent
+
+    -- timestamps are useful for auditing
+    -- Basejump has some convenience functions defined below for automatically handling these
+    updated_at timestamp with time zone,
+    created_at timestamp with time zone,
+
+    PRIMARY KEY (flow_id, action_id)
+);
+
+-- protect the timestamps by setting created_at and updated_at to be read-only and managed by a trigger
+CREATE TRIGGER set_cron_triggers_timestamp
+    BEFORE INSERT OR UPDATE ON anything.cron_triggers
+    FOR EACH ROW
+EXECUTE PROCEDURE basejump.trigger_set_timestamps();
+
+-- enable RLS on the table
+-- Only the trigger engine reads and writes this with the service role, so no policies are added
+ALTER TABLE anything.cron_triggers ENABLE ROW LEVEL SECURITY;
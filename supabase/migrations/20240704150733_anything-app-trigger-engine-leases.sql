@@ -0,0 +1,94 @@
+-- Leases let one server replica at a time act as the leader for a job, like firing cron triggers.
+-- The leader renews its lease well before it expires; if it dies another replica takes over once it has.
+CREATE TABLE IF NOT EXISTS anything.leases
+(
+    lease_name TEXT NOT NULL primary key,
+    holder_id uuid NOT NULL, -- the server instance holding the lease
+    expires_at timestamp with time zone NOT NULL,
+
+    -- timestamps are useful for auditing
+    -- Basejump has some convenience functions defined below for automatically handling these
+    updated_at timestamp with time zone,
+    created_at timestamp with time zone
+);
+
+-- protect the timestamps by setting created_at and updated_at to be read-only and managed by a trigger
+CREATE TRIGGER set_leases_timestamp
+    BEFORE INSERT OR UPDATE ON anything.leases
+    FOR EACH ROW
+EXECUTE PROCEDURE basejump.trigger_set_timestamps();
+
+-- enable RLS on the table
+-- Only the server uses leases with the service role, so no policies are added
+ALTER TABLE anything.leases ENABLE ROW LEVEL SECURITY;
+
+-- Takes or renews a lease in one statement so two replicas can't both win it.
+-- Returns true when p_holder_id holds the lease afterwards.
+CREATE OR REPLACE FUNCTION anything.acquire_lease(p_lease_name TEXT, p_holder_id uuid, p_ttl_seconds integer)
+RETURNS boolean
+LANGUAGE plpgsql
+SECURITY INVOKER
+AS $$
+DECLARE
+    acquired boolean;
+BEGIN
+    IF current_setting('role', true) IS DISTINCT FROM 'service_role' THEN
+        RAISE EXCEPTION 'authentication required';
+    END IF;
+
+    INSERT INTO anything.leases (lease_name, holder_id, expires_at)
+    VALUES (p_lease_name, p_holder_id, now() + make_interval(secs => p_ttl_seconds))
+    ON CONFLICT (lease_name) DO UPDATE
+        SET holder_id = EXCLUDED.holder_id,
+            expires_at = EXCLUDED.expires_at
+        WHERE anything.leases.holder_id = EXCLUDED.holder_id
+           OR anything.leases.expires_at < now()
+    RETURNING true INTO acquired;
+
+    RETURN coalesce(acquired, false);
+END;
+$$;
+
+-- Gives up a lease on shutdown so another replica can take over without waiting for it to expire
+CREATE OR REPLACE FUNCTION anything.release_lease(p_lease_name TEXT, p_holder_id uuid)
+RETURNS void
+LANGUAGE plpgsql
+SECURITY INVOKER
+AS $$
+BEGIN
+    IF current_setting('role', true) IS DISTINCT FROM 'service_role' THEN
+        RAISE EXCEPTION 'authentication required';
+    END IF;
+
+    DELETE FROM anything.leases
+    WHERE lease_name = p_lease_name
+      AND holder_id = p_holder_id;
+END;
+$$;
+
+-- One row per scheduled trigger run that was fired.
+-- The idempotency key is unique per (trigger, scheduled time), so a new leader taking over
+-- can't run a tick the previous leader already fired.
+CREATE TABLE IF NOT EXISTS anything.trigger_fires
+(
+    idempotency_key TEXT NOT NULL primary key,
+    flow_id uuid not null references anything.flows(flow_id) ON DELETE CASCADE,
+    action_id TEXT NOT NULL,
+    account_id uuid not null references basejump.accounts(id),
+    scheduled_time timestamp with time zone NOT NULL,
+    fired_by uuid NOT NULL, -- the server instance that fired the run
+
+    -- timestamps are useful for auditing
+    -- Basejump has some convenience functions defined below for automatically handling these
+    updated_at timestamp with time zone,
+    created_at timestamp with time zone
+);
+
+-- protect the timestamps by setting created_at and updated_at to be read-only and managed by a trigger
+CREATE TRIGGER set_trigger_fires_timestamp
+    BEFORE INSERT OR UPDATE ON anything.trigger_fires
+    FOR EACH ROW
+EXECUTE PROCEDURE basejump.trigger_set_timestamps();
+
+-- enable RLS on the table
+ALTER TABLE anything.trigger_fires ENABLE ROW LEVEL SECURITY;
@@ -0,0 +1,7 @@
+-- Runs started by a Call Workflow action record the run and task that called them
+ALTER TABLE anything.run_queue
+ADD COLUMN parent_flow_session_id uuid,
+ADD COLUMN parent_task_id uuid,
+ADD COLUMN call_stack uuid[] NOT NULL DEFAULT '{}'; -- flow ids of the calling workflows, outermost first
+
+CREATE INDEX IF NOT EXISTS run_queue_parent_flow_session_id_idx ON anything.run_queue (parent_flow_session_id);
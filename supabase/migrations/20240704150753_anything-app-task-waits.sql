@@ -0,0 +1,38 @@
+-- Tasks that parked their flow session until someone or something resumes them.
+-- The flow session's run is marked waiting while a task here is waiting, and is queued
+-- again with the task's resolution as its result once the wait is resumed or times out.
+CREATE TABLE IF NOT EXISTS anything.task_waits
+(
+    task_id uuid unique NOT NULL primary key,
+    account_id uuid not null references basejump.accounts(id),
+    flow_id uuid not null references anything.flows(flow_id),
+    flow_version_id uuid not null references anything.flow_versions(flow_version_id),
+    flow_session_id uuid NOT NULL,
+    action_id TEXT NOT NULL,
+    wait_type TEXT NOT NULL DEFAULT 'approval',
+    wait_status TEXT NOT NULL DEFAULT 'waiting', -- waiting until resumed, then resumed
+    expires_at timestamp with time zone, -- when the timeout outcome is applied, null waits forever
+    timeout_decision TEXT, -- approved or rejected
+    timeout_data jsonb,
+    details jsonb, -- what the approver is shown
+    resolution jsonb, -- the task result the wait resumed with
+    resolved_at timestamp with time zone,
+
+    -- timestamps are useful for auditing
+    -- Basejump has some convenience functions defined below for automatically handling these
+    updated_at timestamp with time zone,
+    created_at timestamp with time zone
+);
+
+CREATE INDEX IF NOT EXISTS task_waits_wait_status_expires_at_idx ON anything.task_waits (wait_status, expires_at);
+CREATE INDEX IF NOT EXISTS task_waits_flow_session_id_idx ON anything.task_waits (flow_session_id);
+
+-- protect the timestamps by setting created_at and updated_at to be read-only and managed by a trigger
+CREATE TRIGGER set_task_waits_timestamp
+    BEFORE INSERT OR UPDATE ON anything.task_waits
+    FOR EACH ROW
+EXECUTE PROCEDURE basejump.trigger_set_timestamps();
+
+-- enable RLS on the table
+-- Waits are only read and written by the server with the service role, so no policies are added
+ALTER TABLE anything.task_waits ENABLE ROW LEVEL SECURITY;
@@ -0,0 +1,6 @@
+-- Replayed runs record the session they replay and the action they started again from
+ALTER TABLE anything.run_queue
+ADD COLUMN replay_of_flow_session_id uuid,
+ADD COLUMN replay_from_action_id TEXT;
+
+CREATE INDEX IF NOT EXISTS run_queue_replay_of_flow_session_id_idx ON anything.run_queue (replay_of_flow_session_id);
@@ -0,0 +1,5 @@
+-- Set when a session is canceled through a server that is not running it, the owning server stops it
+ALTER TABLE anything.run_queue
+ADD COLUMN cancel_requested boolean NOT NULL DEFAULT false;
+
+CREATE INDEX IF NOT EXISTS run_queue_cancel_requested_idx ON anything.run_queue (claimed_by) WHERE cancel_requested;
@@ -0,0 +1,4 @@
+-- Trigger plugin events are recorded in trigger_fires too, keyed by their event id,
+-- so an event a plugin reports twice answers with the flow session it started the first time
+ALTER TABLE anything.trigger_fires
+ADD COLUMN flow_session_id uuid;